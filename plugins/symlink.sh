@@ -0,0 +1,63 @@
+#!/bin/sh
+# A switchy plugin pointing a symlink at one of the entries of a dir, e.g. for switching JDKs:
+#
+#   [[items]]
+#   type = "ExternalItem"
+#   name = "java"
+#   current = "17"
+#   plugin = "plugins/symlink.sh"
+#   settings = { dir = "/opt/jdks", link = "~/.local/jdk" }
+#
+# Entry names are listed as they are, so they shouldn't contain quotes or backslashes.
+
+set -eu
+
+request=$(cat)
+setting() {
+    printf '%s' "$request" | sed -n "s/.*\"$1\":\"\([^\"]*\)\".*/\1/p"
+}
+expand() {
+    case $1 in
+        "~"/*) printf '%s' "$HOME/${1#"~/"}" ;;
+        *) printf '%s' "$1" ;;
+    esac
+}
+
+dir=$(expand "$(setting dir)")
+link=$(expand "$(setting link)")
+if [ -z "$dir" ] || [ -z "$link" ]; then
+    printf '{"protocol":1,"error":"settings dir and link are required"}'
+    exit 1
+fi
+
+case ${1:-} in
+    states)
+        printf '{"protocol":1,"states":['
+        separator=
+        for entry in "$dir"/*; do
+            [ -e "$entry" ] || continue
+            printf '%s{"name":"%s"}' "$separator" "${entry##*/}"
+            separator=,
+        done
+        printf ']}'
+        ;;
+    current)
+        target=$(readlink "$link" 2>/dev/null || true)
+        if [ -n "$target" ] && [ "${target%/*}" = "$dir" ]; then
+            printf '{"protocol":1,"current":"%s"}' "${target##*/}"
+        else
+            printf '{"protocol":1,"current":null}'
+        fi
+        ;;
+    switch)
+        ln -sfn "$dir/$2" "$link"
+        printf '{"protocol":1}'
+        ;;
+    render)
+        printf '{"protocol":1,"lines":["%s"]}' "$dir/$2"
+        ;;
+    *)
+        printf '{"protocol":1,"error":"unknown subcommand %s"}' "${1:-}"
+        exit 1
+        ;;
+esac
@@ -0,0 +1,59 @@
+use anyhow::{bail, Result};
+
+use crate::i18n::t;
+
+pub fn get_marker_lines(comment: &str, marker: &str) -> (String, String) {
+    (
+        format!("{} switchy:{} begin", comment, marker),
+        format!("{} switchy:{} end", comment, marker)
+    )
+}
+
+/// Replaces the lines between the begin and end marker lines with the block, keeping everything else as is.
+/// Adds the markers with the block at the end if they are missing. Markers appearing more than once are refused
+/// unless `force` is set, in which case every block is replaced. Returns the new content.
+pub fn replace_block(content: &str, begin: &str, end: &str, block: &str, force: bool) -> Result<String> {
+    let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    let block_lines: Vec<&str> = block.lines().collect();
+
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let is_marker = |line: &str, marker: &str| line.trim() == marker;
+
+    let begin_count = lines.iter().filter(|line| is_marker(line, begin)).count();
+    if begin_count > 1 && ! force {
+        bail!(t!(MarkerRepeated, begin, begin_count));
+    }
+
+    let mut new_lines: Vec<String> = vec![];
+    let mut found = false;
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        index += 1;
+        new_lines.push(line.to_string());
+        if ! is_marker(line, begin) {
+            continue;
+        }
+        if ! line.ends_with('\n') {
+            new_lines.last_mut().unwrap().push_str(eol);
+        }
+
+        let Some(end_offset) = lines[index ..].iter().position(|line| is_marker(line, end)) else {
+            bail!(t!(MarkerUnmatched, begin, end));
+        };
+        found = true;
+        new_lines.extend(block_lines.iter().map(|line| format!("{}{}", line, eol)));
+        index += end_offset;
+    }
+
+    if ! found {
+        if new_lines.last().is_some_and(|line| ! line.ends_with('\n')) {
+            new_lines.last_mut().unwrap().push_str(eol);
+        }
+        new_lines.push(format!("{}{}", begin, eol));
+        new_lines.extend(block_lines.iter().map(|line| format!("{}{}", line, eol)));
+        new_lines.push(format!("{}{}", end, eol));
+    }
+
+    Ok(new_lines.concat())
+}
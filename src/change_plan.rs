@@ -0,0 +1,71 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// What a dry run would change, collected for `--dry-run --format json`, so tooling can check a switch before it's
+/// made.
+#[derive(Default)]
+pub struct ChangePlan {
+    switches: RefCell<Vec<PlannedChanges>>
+}
+
+/// The changes switching an item would make.
+#[derive(Serialize)]
+pub struct PlannedChanges {
+    pub item: String,
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<Change>
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    /// A command run, as shown, so encrypted commands stay placeholders.
+    Command { command: String },
+    /// A file written. `diff` is a unified diff, or `None` if nothing changes or either content is binary.
+    /// `old` is `None` if the file doesn't exist yet.
+    File { path: String, changed: bool, diff: Option<String>, old: Option<FileSummary>, new: FileSummary },
+    GitConfig { scope: String, key: String, value: String }
+}
+
+#[derive(Serialize)]
+pub struct FileSummary {
+    pub size: usize,
+    /// The 64-bit FNV-1a hash of the content, in hex.
+    pub hash: String,
+    pub binary: bool
+}
+
+impl FileSummary {
+    pub fn new(content: &[u8]) -> Self {
+        Self {
+            size: content.len(),
+            hash: crate::fs_util::fingerprint(content),
+            binary: std::str::from_utf8(content).is_err()
+        }
+    }
+}
+
+impl ChangePlan {
+    pub fn add_switch(&self, item: &str, from: &str, to: &str) {
+        self.switches.borrow_mut().push(PlannedChanges {
+            item: item.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            changes: vec![]
+        });
+    }
+
+    /// Adds a change of the item last added.
+    pub fn add_change(&self, change: Change) {
+        if let Some(switch) = self.switches.borrow_mut().last_mut() {
+            switch.changes.push(change);
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&*self.switches.borrow())?)
+    }
+}
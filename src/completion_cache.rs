@@ -0,0 +1,99 @@
+use std::{fs, path::{Path, PathBuf}, time::UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::ConfigManager, fs_util};
+
+/// The modification time and size of a file the names are read from, to tell if it changed.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct FileStamp {
+    path: PathBuf,
+    modified_nanos: u128,
+    size: u64
+}
+
+impl FileStamp {
+    /// Stamps the path. A missing path is stamped too, so creating it invalidates the cache.
+    fn new(path: &Path) -> FileStamp {
+        let metadata = fs::metadata(path).ok();
+        FileStamp {
+            path: path.to_path_buf(),
+            modified_nanos: metadata
+                .as_ref()
+                .and_then(|metadata| metadata.modified().ok())
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |duration| duration.as_nanos()),
+            size: metadata.map_or(0, |metadata| metadata.len())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedItem {
+    name: String,
+    states: Vec<String>
+}
+
+/// The item and state names for shell completions, valid while none of the stamped files changes.
+#[derive(Serialize, Deserialize)]
+pub struct CompletionCache {
+    stamps: Vec<FileStamp>,
+    items: Vec<CachedItem>
+}
+
+impl CompletionCache {
+    /// Reads the cache, or returns `None` if it's missing, broken, or any file it's read from changed.
+    pub fn read(cache_path: &Path) -> Option<CompletionCache> {
+        let cache: CompletionCache = serde_json::from_str(&fs::read_to_string(cache_path).ok()?).ok()?;
+        cache.stamps
+            .iter()
+            .all(|stamp| FileStamp::new(&stamp.path) == *stamp)
+            .then_some(cache)
+    }
+
+    /// Builds the cache from the loaded config.
+    pub fn new(cm: &ConfigManager) -> CompletionCache {
+        CompletionCache {
+            stamps: cm.get_source_paths().iter().map(|path| FileStamp::new(path)).collect(),
+            items: cm.config.items
+                .iter()
+                .map(|item| CachedItem {
+                    name: item.get_name().to_string(),
+                    states: item.get_state_names().into_iter().map(str::to_string).collect()
+                })
+                .collect()
+        }
+    }
+
+    /// Writes the cache. Concurrent completions may write at once, so each writes its own temp file and renames it.
+    /// A cache that can't be written is only slower, so failures are ignored.
+    pub fn write(&self, cache_path: &Path) {
+        let Ok(content) = serde_json::to_string(self) else {
+            return;
+        };
+        let temp_path = fs_util::with_suffix(cache_path, &format!(".{}.tmp", std::process::id()));
+        let written = cache_path.parent().is_some_and(|dir| fs::create_dir_all(dir).is_ok())
+            && fs::write(&temp_path, content).is_ok()
+            && fs::rename(&temp_path, cache_path).is_ok();
+        if ! written {
+            let _ = fs::remove_file(&temp_path);
+        }
+    }
+
+    /// Whether the cache is of the config file, since the config dir can be changed by `SWITCHY_CONFIG_DIR`.
+    pub fn is_for(&self, config_file_path: &Path) -> bool {
+        self.stamps.first().is_some_and(|stamp| stamp.path == config_file_path)
+    }
+
+    /// Returns the item names, or the state names of the item. An unknown item has none.
+    pub fn get_names(&self, item_name: Option<&str>) -> Vec<&str> {
+        match item_name {
+            None => self.items.iter().map(|item| item.name.as_str()).collect(),
+            Some(item_name) => self.items
+                .iter()
+                .find(|item| item.name == item_name)
+                .map(|item| item.states.iter().map(String::as_str).collect())
+                .unwrap_or_default()
+        }
+    }
+}
@@ -1,118 +1,1828 @@
-use std::{fs, io::{stdout, stderr}, path::{Path, PathBuf}, process};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeMap,
+    env, fmt, fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{self, stderr, stdin, IsTerminal, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering}, thread, time::Duration};
 
-use anyhow::Result;
-use colored::Colorize;
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use dialoguer::Confirm;
 use serde::{Serialize, Deserialize};
-use toml;
+
+use crate::{
+    block, change_plan::{Change, ChangePlan, FileSummary}, diff, fs_util, git_config, i18n::t, interrupt,
+    key_value::{self, KeyValueFormat},
+    machine, overlay::SettingsOverlay, plugin::{Plugin, PluginState}, run_log, secret,
+    shell::{self, CommandShell, ExecutionPlan},
+    snapshot, ssh::{self, SshTarget}, template, text, theme, unknown_keys
+};
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
+    /// Files with more items, relative to the config dir. `~` is expanded, and `*` and `?` match in file names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub defaults: ConfigDefaults,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sync: ConfigSync,
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub theme: ConfigTheme,
+    /// Preset name => item name => state, saved by `--save-preset` and switched to by `--preset`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, BTreeMap<String, String>>,
     pub items: Vec<ConfigItem>
 }
 
+/// A file included by the config, holding only items.
+#[derive(Serialize, Deserialize)]
+struct IncludedConfig {
+    items: Vec<ConfigItem>
+}
+
+/// Parses the items of a config or included file, ignoring the rest. Fails on items without states, like loading does.
+pub fn parse_items(content: &str) -> Result<Vec<ConfigItem>> {
+    let items = toml::from_str::<IncludedConfig>(content)?.items;
+    if let Some(item) = find_item_without_states(&items) {
+        bail!(t!(ItemWithoutStates, item.get_name().cyan()));
+    }
+    Ok(items)
+}
+
+/// Finds an item without states, except external ones whose states are listed by their plugin later.
+fn find_item_without_states(items: &[ConfigItem]) -> Option<&ConfigItem> {
+    items
+        .iter()
+        .find(|item| ! matches!(item, ConfigItem::ExternalItem(_)) && item.get_state_names().is_empty())
+}
+
+/// The config file when writing, with the items hidden on this machine put back and those from included files left out.
+#[derive(Serialize)]
+struct ConfigRef<'a> {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    include: &'a Vec<String>,
+    #[serde(skip_serializing_if = "is_default_ref")]
+    defaults: &'a ConfigDefaults,
+    #[serde(skip_serializing_if = "is_default_ref")]
+    sync: &'a ConfigSync,
+    #[serde(skip_serializing_if = "is_default_ref")]
+    theme: &'a ConfigTheme,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    presets: &'a BTreeMap<String, BTreeMap<String, String>>,
+    items: Vec<&'a ConfigItem>
+}
+
+fn is_default_ref<T: Default + PartialEq>(value: &&T) -> bool {
+    is_default(*value)
+}
+
+/// An included file when writing, with the items from it.
+#[derive(Serialize)]
+struct IncludedConfigRef<'a> {
+    items: Vec<&'a ConfigItem>
+}
+
+/// Where items of the config come from, for writing them back there.
+struct IncludedFile {
+    path: PathBuf,
+    /// The file as read, for diffing the changes against.
+    file_str: String,
+    item_names: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+pub struct ConfigDefaults {
+    /// The item to switch when no item is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_item: Option<String>,
+    /// The command to run after successful switches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_change: Option<String>,
+    /// Whether to log command output of items which don't set `log`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub log: bool,
+    /// Whether to post desktop notifications of switches for items which don't set `notify`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub notify: bool,
+    /// The shell to run commands with for items which don't set `shell`, instead of the system one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<CommandShell>,
+    /// What an interrupted switch does to the current state.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub on_interrupt: InterruptPolicy,
+    /// The language of messages, e.g. `zh-CN`. Defaults to the locale env vars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Whether to show the diff of the config file and confirm before changes like adding or removing items.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub confirm_writes: bool,
+    /// The command printing the passphrase of encrypted commands, instead of prompting for it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub askpass: Option<String>,
+    /// Whether to show each command and confirm before running it.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub confirm_run: ConfirmRun,
+    /// The order of items in `--list` and the item picker.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub sort: ItemSort,
+    /// Whether to show the item with its current state above the state picker. Defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_header: Option<bool>,
+    /// How typed text filters the entries of the item and state pickers.
+    #[serde(default, rename = "match", skip_serializing_if = "is_default")]
+    pub match_mode: MatchMode,
+    /// Whether names differing only in case are different, so adding `Proxy` next to `proxy` is allowed.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub case_sensitive_names: bool,
+    /// Whether to confirm the item when the name given on the command line isn't exactly its name, e.g. picked in the
+    /// prompt filtered by it.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub confirm_fuzzy: bool,
+    /// How many days items removed by `--remove` stay in the trash. Defaults to 30.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_days: Option<u32>,
+    /// How much of the command the `Running $ ...` banner shows, for items which don't set `show_command`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub show_command: ShowCommand
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShowCommand {
+    #[default]
+    Full,
+    /// Only the program, like `kubectl`.
+    FirstWord,
+    /// Nothing of the command.
+    None
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Ranks the entries by how well the typed chars match in order.
+    #[default]
+    Fuzzy,
+    /// Keeps the entries containing the typed text, ranked by where it starts.
+    Substring,
+    /// Keeps the entries starting with the typed text.
+    Prefix
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq, Clone, Copy, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemSort {
+    /// The order in the config.
+    #[default]
+    Manual,
+    /// By name, ignoring case.
+    Name,
+    /// By type, then in the order in the config.
+    Type,
+    /// The most recently switched first.
+    Recent
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmRun {
+    #[default]
+    Never,
+    /// Show each command with where it runs, and run it only if confirmed.
+    Always
+}
+
+/// The error of a command declined at its confirmation. The switch records nothing, and switchy exits successfully.
+#[derive(Debug)]
+pub struct RunDeclined;
+
+impl fmt::Display for RunDeclined {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", t!(RunCancelled))
+    }
+}
+
+impl std::error::Error for RunDeclined {}
+
+/// The error of writing a config file something else changed since it was read, which writing would lose.
+#[derive(Debug)]
+pub struct ConfigChangedExternally {
+    pub path: PathBuf
+}
+
+impl fmt::Display for ConfigChangedExternally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", t!(ConfigChangedExternally, self.path.display()))
+    }
+}
+
+impl std::error::Error for ConfigChangedExternally {}
+
+/// How `--sync` syncs the config dir, when it's a git repo.
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+pub struct ConfigSync {
+    /// The command to run in the config dir instead of committing, pulling and pushing with git.
+    /// The commit message is in `SWITCHY_SYNC_MESSAGE`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Whether to sync after every change of the config, including switches.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub auto: bool
+}
+
+/// How listings look.
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+pub struct ConfigTheme {
+    /// The marker of current states, instead of `*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_marker: Option<String>
+}
+
+#[derive(Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum InterruptPolicy {
+    /// Fail the switch, keeping the previous state.
+    #[default]
+    Fail,
+    /// Fail the switch, but record the new state, since the command may have partly applied it.
+    Record
+}
+
+/// Whether names differing only in case are different, from `case_sensitive_names` in `[defaults]`.
+static CASE_SENSITIVE_NAMES: AtomicBool = AtomicBool::new(false);
+
+pub fn set_case_sensitive_names(case_sensitive: bool) {
+    CASE_SENSITIVE_NAMES.store(case_sensitive, Ordering::Relaxed);
+}
+
+/// Whether a new name collides with an existing one. Only ASCII letters are compared case-insensitively, since folding
+/// the case of other letters depends on the language, e.g. of the Turkish dotless `ı`.
+pub fn is_same_name(name: &str, other: &str) -> bool {
+    if CASE_SENSITIVE_NAMES.load(Ordering::Relaxed) {
+        name == other
+    }
+    else {
+        name.eq_ignore_ascii_case(other)
+    }
+}
+
+/// Matches a name given on the command line against `names`: the exact name first, then the names differing only in
+/// ASCII case. Returns the indexes matched by the first step matching any, so several mean the name is ambiguous.
+fn match_name(names: &[&str], name: &str) -> Vec<usize> {
+    let exact: Vec<usize> = (0 .. names.len()).filter(|&index| names[index] == name).collect();
+    if ! exact.is_empty() {
+        return exact;
+    }
+    (0 .. names.len()).filter(|&index| names[index].eq_ignore_ascii_case(name)).collect()
+}
+
+/// Appends the names like the unmatched one to the message, if any: those containing it or contained in it ignoring
+/// case, and those within an edit of it, or two for longer names.
+fn with_suggestions(message: String, names: &[&str], name: &str) -> String {
+    let lowercase_name = name.to_lowercase();
+    let max_distance = if lowercase_name.chars().count() >= 6 { 2 } else { 1 };
+    let suggested: Vec<String> = names
+        .iter()
+        .filter(|candidate| {
+            let candidate = candidate.to_lowercase();
+            let distance = text::get_edit_distance(&candidate, &lowercase_name);
+            candidate.contains(&lowercase_name) || lowercase_name.contains(&candidate)
+                || (distance <= max_distance && distance < candidate.chars().count())
+        })
+        .map(|candidate| candidate.to_string())
+        .collect();
+    if suggested.is_empty() {
+        message
+    }
+    else {
+        format!("{}. {}", message, t!(DidYouMean, suggested.join(", ")))
+    }
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(tag = "type")]
+// The variant names are the `type` values in the config
+#[allow(clippy::enum_variant_names)]
 pub enum ConfigItem {
-    CommandItem(ConfigCommandItem)
+    CommandItem(ConfigCommandItem),
+    KeyValueItem(ConfigKeyValueItem),
+    BlockItem(ConfigBlockItem),
+    GitConfigItem(ConfigGitConfigItem),
+    DirItem(ConfigDirItem),
+    TemplateItem(ConfigTemplateItem),
+    ExternalItem(ConfigExternalItem)
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ConfigCommandItem {
     pub name: String,
     pub current: String,
+    /// `[user@]host[:port]` to run the commands on over SSH, instead of locally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_host: Option<String>,
+    /// Extra args of ssh, like `["-i", "~/.ssh/box"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ssh_options: Vec<String>,
+    /// Whether to rerun the command of the previous state when `verify_command` of the new one fails.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub rollback_on_failure: bool,
+    #[serde(flatten)]
+    pub options: ItemOptions,
     pub states: Vec<ConfigCommandItemState>
 }
 
+impl ConfigCommandItem {
+    /// Returns where the commands run over SSH, or `None` if they run locally.
+    pub fn get_ssh_target(&self) -> Option<SshTarget<'_>> {
+        self.ssh_host.as_deref().map(|ssh_host| SshTarget::new(ssh_host, &self.ssh_options))
+    }
+
+    /// Runs `verify_command` of the new state once its command has run, if it has one. When it fails, the command of
+    /// the previous state is rerun if `rollback_on_failure` is set, and the switch fails either way.
+    fn verify_switch(&self, previous_state: &str, new_state: &str, options: &SwitchOptions) -> Result<()> {
+        let state = self.states.iter().find(|state| state.name == new_state).unwrap();
+        let Some(verify_command) = &state.verify_command else {
+            return Ok(());
+        };
+        if run_verify_command(self, state, verify_command, options)? {
+            return Ok(());
+        }
+
+        let previous = self.states.iter().find(|state| state.name == previous_state);
+        let Some(previous) = previous.filter(|_| self.rollback_on_failure && previous_state != new_state) else {
+            bail!(t!(VerificationFailed, verify_command));
+        };
+        if ! options.quiet {
+            eprintln!("{}", t!(RollingBack, self.name.cyan(), previous_state.yellow()));
+        }
+        if ! previous.command.is_empty() {
+            let shown_command = previous.with_value(&previous.command.to_string());
+            let command = previous.with_value(&previous.command.resolve(options.askpass.as_deref())?);
+            run_state_command(&self.name, previous_state, &command, &shown_command, &self.options, self.get_ssh_target().as_ref(), options)
+                .with_context(|| t!(RollbackFailed, verify_command, self.name.cyan(), previous_state.yellow()))?;
+        }
+        bail!(t!(VerificationFailedRolledBack, verify_command, self.name.cyan(), previous_state.yellow()))
+    }
+}
+
+/// Options shared by all item types.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ItemOptions {
+    /// Whether the item appears in `--prompt-status`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub prompt: bool,
+    /// A short label used instead of the name in `--prompt-status`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Whether to log command output into `logs/<item>.log` under the config dir.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<bool>,
+    /// Whether to post a desktop notification when the item is switched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notify: Option<bool>,
+    /// The shell to run commands with, instead of the system one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shell: Option<CommandShell>,
+    /// The machines the item is for. It's hidden on others.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only_on: Option<OnlyOn>,
+    /// Tags for grouping items with `--list --group-by tag`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// A file `--watch` watches, reapplying the current state when something else changes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch: Option<String>,
+    /// Whether to check that the program of a command is on PATH before running it. Defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preflight: Option<bool>,
+    /// The known-safe state `--reset` and `--reset-all` switch back to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_state: Option<String>,
+    /// How much of the command the `Running $ ...` banner shows. `--dry-run`, `--confirm`, `--debug-exec` and the
+    /// logs still show all of it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_command: Option<ShowCommand>
+}
+
+/// The machines an item is for. Each non-empty list must contain this machine.
+#[derive(Serialize, Deserialize, Default)]
+pub struct OnlyOn {
+    /// OS names like `linux`, `macos` and `windows`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub os: Vec<String>,
+    /// Host names, matched case-insensitively.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hostname: Vec<String>
+}
+
+impl OnlyOn {
+    pub fn matches(&self, os: &str, hostname: Option<&str>) -> bool {
+        (self.os.is_empty() || self.os.iter().any(|only_os| only_os == os))
+            && (self.hostname.is_empty() || hostname.is_some_and(|hostname| {
+                self.hostname.iter().any(|only_hostname| only_hostname.eq_ignore_ascii_case(hostname))
+            }))
+    }
+}
+
+impl fmt::Display for OnlyOn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = vec![];
+        if ! self.os.is_empty() {
+            parts.push(t!(OnlyOnOs, self.os.join(&t!(OrSeparator))));
+        }
+        if ! self.hostname.is_empty() {
+            parts.push(t!(OnlyOnHostname, self.hostname.join(&t!(OrSeparator))));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct ConfigCommandItemState {
     pub name: String,
-    pub command: String
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// A value of the state for scripts, like the proxy URL, printed by `--value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// A command checking that the switch took effect, run after `command`. The switch fails if it does.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_command: Option<String>,
+    /// How many more times `verify_command` runs while it fails, for changes taking a while like network ones.
+    /// Defaults to 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_retries: Option<u32>,
+    /// How long to wait before each retry of `verify_command`, in milliseconds. Defaults to 1000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_delay_ms: Option<u64>,
+    /// An empty or missing command only records the state when switching to it.
+    #[serde(default, skip_serializing_if = "StateCommand::is_empty")]
+    pub command: StateCommand
+}
+
+/// The command of a state, in plain text or encrypted by `--encrypt-state`.
+/// An encrypted command is only decrypted to run it, and shows as `[encrypted]`.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StateCommand {
+    Plain(String),
+    Encrypted {
+        /// The command encrypted with a passphrase, in base64.
+        encrypted: String
+    }
+}
+
+impl Default for StateCommand {
+    fn default() -> Self {
+        StateCommand::Plain(String::new())
+    }
+}
+
+impl StateCommand {
+    /// Whether the command is plain and blank, so there's nothing to run.
+    pub fn is_empty(&self) -> bool {
+        self.get_plain().is_some_and(|command| command.trim().is_empty())
+    }
+
+    /// Returns the command if it's not encrypted.
+    pub fn get_plain(&self) -> Option<&str> {
+        match self {
+            StateCommand::Plain(command) => Some(command),
+            StateCommand::Encrypted { .. } => None
+        }
+    }
+
+    /// Returns the command to run, decrypting it if needed.
+    pub fn resolve(&self, askpass: Option<&str>) -> Result<String> {
+        match self {
+            StateCommand::Plain(command) => Ok(command.clone()),
+            StateCommand::Encrypted { encrypted } => secret::decrypt(encrypted, askpass)
+        }
+    }
+}
+
+impl ConfigCommandItemState {
+    /// Substitutes the `{{value}}` placeholders of the command with the value of the state, if it has one.
+    pub fn with_value(&self, command: &str) -> String {
+        match &self.value {
+            Some(value) => template::substitute(command, "value", value),
+            None => command.to_string()
+        }
+    }
+}
+
+impl fmt::Display for StateCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateCommand::Plain(command) => write!(f, "{}", command),
+            StateCommand::Encrypted { .. } => write!(f, "[encrypted]")
+        }
+    }
+}
+
+/// An item setting a key in an INI, TOML or env file to the value of the state.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigKeyValueItem {
+    pub name: String,
+    pub current: String,
+    pub file: String,
+    pub format: KeyValueFormat,
+    /// The key, prefixed by its section like `section.key` for INI and TOML files.
+    pub key: String,
+    /// Whether to add the key if it's missing, instead of failing.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub create_missing: bool,
+    /// The permission bits of the file, kept as it is if not set, in octal like `0600`. Unix only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    pub states: Vec<ConfigKeyValueItemState>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfigKeyValueItemState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    pub value: String
+}
+
+/// An item replacing the block between `# switchy:<marker> begin` and `# switchy:<marker> end` lines in a file
+/// with the content of the state.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigBlockItem {
+    pub name: String,
+    pub current: String,
+    pub file: String,
+    pub marker: String,
+    /// The line comment prefix of the marker lines.
+    #[serde(default = "default_block_comment", skip_serializing_if = "is_default_block_comment")]
+    pub comment: String,
+    /// The permission bits of the file, kept as it is if not set, in octal like `0600`. Unix only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    pub states: Vec<ConfigBlockItemState>
+}
+
+fn default_block_comment() -> String {
+    "#".to_string()
+}
+
+fn is_default_block_comment(comment: &str) -> bool {
+    comment == "#"
+}
+
+/// A block state, whose content is either inline or read from a snippet file.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigBlockItemState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// A value of the state for scripts, like the proxy URL, printed by `--value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_file: Option<String>
+}
+
+impl ConfigBlockItemState {
+    pub fn read_content(&self) -> Result<String> {
+        match (&self.content, &self.content_file) {
+            (Some(content), None) => Ok(content.clone()),
+            (None, Some(content_file)) => {
+                let path = fs_util::expand_home(content_file);
+                fs::read_to_string(&path).with_context(|| t!(FailedToRead, path.display()))
+            },
+            _ => bail!(t!(BlockStateContent, self.name.yellow()))
+        }
+    }
+}
+
+/// An item setting git config keys, e.g. `user.name` and `user.email` for switching git identities.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigGitConfigItem {
+    pub name: String,
+    pub current: String,
+    /// `global`, or the path of a repo for its local config.
+    #[serde(default = "default_git_scope", skip_serializing_if = "is_default_git_scope")]
+    pub scope: String,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    pub states: Vec<ConfigGitConfigItemState>
+}
+
+fn default_git_scope() -> String {
+    git_config::GLOBAL_SCOPE.to_string()
+}
+
+fn is_default_git_scope(scope: &str) -> bool {
+    scope == git_config::GLOBAL_SCOPE
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfigGitConfigItemState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// A value of the state for scripts, like the proxy URL, printed by `--value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Git config key => value.
+    pub values: BTreeMap<String, String>
+}
+
+impl ConfigGitConfigItem {
+    /// Finds the state whose values all match the git config. Keys set by no state are ignored.
+    pub fn detect_state(&self) -> Result<Option<&str>> {
+        git_config::ensure_git()?;
+
+        let mut current_values: BTreeMap<&str, Option<String>> = BTreeMap::new();
+        for key in self.states.iter().flat_map(|state| state.values.keys()) {
+            if ! current_values.contains_key(key.as_str()) {
+                current_values.insert(key, git_config::get_value(&self.scope, key)?);
+            }
+        }
+
+        Ok(self.states
+            .iter()
+            .find(|state| state.values
+                .iter()
+                .all(|(key, value)| current_values[key.as_str()].as_ref() == Some(value))
+            )
+            .map(|state| state.name.as_str())
+        )
+    }
+}
+
+/// An item whose states are directories, for `cd`ing there with `--print-path` in a shell function.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigDirItem {
+    pub name: String,
+    pub current: String,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    pub states: Vec<ConfigDirItemState>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfigDirItemState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// A value of the state for scripts, like the proxy URL, printed by `--value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    pub path: String
+}
+
+impl ConfigDirItem {
+    /// Resolves the path of the state, warning on stderr if it isn't a directory.
+    pub fn resolve_path(&self, state_name: &str) -> Option<PathBuf> {
+        let state = self.states.iter().find(|state| state.name == state_name)?;
+        let path = fs_util::expand_home(&state.path);
+        if ! path.is_dir() {
+            eprintln!("{} {}", t!(Warning).yellow(), t!(NotADirectory, path.display()));
+        }
+        Some(path)
+    }
+}
+
+/// An item rendering a template file with the vars of the state into the output file.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigTemplateItem {
+    pub name: String,
+    pub current: String,
+    pub template: String,
+    pub output: String,
+    /// Run after the output is written, e.g. to reload the program reading it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_command: Option<String>,
+    /// The permission bits of the output, `0644` if not set, in octal like `0600`. Unix only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    pub states: Vec<ConfigTemplateItemState>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ConfigTemplateItemState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// A command checking whether the state can be switched to now, which makes it unavailable when it fails.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available_if: Option<String>,
+    /// How long the state may stay current, like `2h`, before listings flag the item as overdue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<String>,
+    /// A value of the state for scripts, like the proxy URL, printed by `--value`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Placeholder name => value.
+    pub vars: BTreeMap<String, String>
+}
+
+/// An item whose states come from a plugin program, speaking the protocol in `plugin` over stdin and stdout.
+#[derive(Serialize, Deserialize)]
+pub struct ConfigExternalItem {
+    pub name: String,
+    pub current: String,
+    /// The plugin, a program on PATH or a path relative to the config dir. `~` is expanded.
+    pub plugin: String,
+    /// Passed to the plugin as is.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub settings: toml::Table,
+    #[serde(flatten)]
+    pub options: ItemOptions,
+    /// The states listed by the plugin, filled by `ConfigManager::load_plugin_states`.
+    #[serde(skip)]
+    pub states: Vec<PluginState>,
+    /// The plugin resolved against the config dir.
+    #[serde(skip)]
+    program: PathBuf
+}
+
+impl ConfigExternalItem {
+    pub fn get_plugin(&self) -> Plugin<'_> {
+        Plugin {
+            name: &self.plugin,
+            program: &self.program,
+            item: &self.name,
+            settings: &self.settings
+        }
+    }
+
+    /// Asks the plugin which state matches the system.
+    pub fn detect_state(&self) -> Result<Option<&str>> {
+        let current = self.get_plugin().get_current()?;
+        Ok(current
+            .and_then(|current| self.states.iter().find(|state| state.name == current))
+            .map(|state| state.name.as_str())
+        )
+    }
+}
+
+/// How long an `available_if` command may run before the state counts as unavailable.
+const AVAILABLE_IF_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long to wait before each retry of a `verify_command` without `verify_delay_ms`.
+const DEFAULT_VERIFY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+pub struct SwitchOptions {
+    /// Only print what would be done, without running commands or changing the current state.
+    pub dry_run: bool,
+    /// The dir holding per-item logs of command output.
+    pub log_dir: PathBuf,
+    /// Whether to log command output of items which don't set `log`.
+    pub log_by_default: bool,
+    /// Whether to post desktop notifications for items which don't set `notify`.
+    pub notify_by_default: bool,
+    /// The shell for items which don't set `shell`.
+    pub shell_by_default: Option<CommandShell>,
+    /// How much of the command the banner shows for items which don't set `show_command`.
+    pub show_command_by_default: ShowCommand,
+    /// Don't print the switching banners.
+    pub quiet: bool,
+    /// Override safety checks, e.g. of repeated block markers.
+    pub force: bool,
+    /// Whether an interrupted switch still records the new state.
+    pub record_interrupted: bool,
+    /// The command printing the passphrase of encrypted commands.
+    pub askpass: Option<String>,
+    /// The command to run instead of the stored one of the state, from `--with`.
+    pub command_override: Option<String>,
+    /// Whether to show each command and confirm before running it.
+    pub confirm_run: bool,
+    /// Whether to check that the programs of commands are on PATH before running them, for items not turning it off.
+    pub preflight: bool,
+    /// Whether to print the program, args, dir and env vars of each command run.
+    pub debug_exec: bool,
+    /// Whether to wrap long commands instead of truncating them.
+    pub full: bool,
+    /// The settings given by `--set`.
+    pub overlay: SettingsOverlay,
+    /// The changes of dry runs, collected for `--dry-run --format json`.
+    pub change_plan: Option<ChangePlan>
+}
+
+/// The settings a switch of an item runs with.
+pub struct ItemSettings {
+    pub log: bool,
+    pub notify: bool,
+    pub shell: Option<CommandShell>,
+    pub show_command: ShowCommand
+}
+
+impl SwitchOptions {
+    /// Adds the change to the plan of the dry run, if one is collected.
+    pub fn plan_change(&self, change: Change) {
+        if let Some(change_plan) = &self.change_plan {
+            change_plan.add_change(change);
+        }
+    }
+
+    /// Resolves the settings of the item. `--set` wins over the item, which wins over the defaults.
+    pub fn resolve(&self, item_options: &ItemOptions) -> ItemSettings {
+        ItemSettings {
+            log: self.overlay.log.or(item_options.log).unwrap_or(self.log_by_default),
+            notify: self.overlay.notify.or(item_options.notify).unwrap_or(self.notify_by_default),
+            shell: self.overlay.shell.unwrap_or(item_options.shell.or(self.shell_by_default)),
+            show_command: item_options.show_command.unwrap_or(self.show_command_by_default)
+        }
+    }
 }
 
 impl ConfigItem {
     pub fn get_name(&self) -> &str {
         match self {
-            ConfigItem::CommandItem(item) => &item.name
+            ConfigItem::CommandItem(item) => &item.name,
+            ConfigItem::KeyValueItem(item) => &item.name,
+            ConfigItem::BlockItem(item) => &item.name,
+            ConfigItem::GitConfigItem(item) => &item.name,
+            ConfigItem::DirItem(item) => &item.name,
+            ConfigItem::TemplateItem(item) => &item.name,
+            ConfigItem::ExternalItem(item) => &item.name
+        }
+    }
+
+    /// The `mode` of the file written by the item, for items writing files.
+    pub fn get_mode(&self) -> Option<&str> {
+        match self {
+            ConfigItem::KeyValueItem(item) => item.mode.as_deref(),
+            ConfigItem::BlockItem(item) => item.mode.as_deref(),
+            ConfigItem::TemplateItem(item) => item.mode.as_deref(),
+            _ => None
+        }
+    }
+
+    /// Returns `default_state` if it isn't one of the states, which fails resetting the item.
+    pub fn find_missing_default_state(&self) -> Option<&str> {
+        let default_state = self.get_options().default_state.as_deref()?;
+        (! self.has_state(default_state)).then_some(default_state)
+    }
+
+    /// Returns the problems of the item that only fail switching it, so the rest of the config still works.
+    /// `--doctor` reports them.
+    pub fn find_problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = vec![];
+        if let Some(mode) = self.get_mode().filter(|mode| fs_util::parse_mode(mode).is_none()) {
+            problems.push(t!(InvalidMode, self.get_name().cyan(), mode));
+        }
+        let only_os = self.get_options().only_on.iter().flat_map(|only_on| &only_on.os);
+        for os in only_os.filter(|os| ! machine::KNOWN_OS.contains(&os.as_str())) {
+            problems.push(t!(UnknownOs, self.get_name().cyan(), os, machine::KNOWN_OS.join(", ")));
+        }
+        // The states of external items are only known once their plugins list them, which is checked on its own
+        let is_external = matches!(self, ConfigItem::ExternalItem(_));
+        if let Some(default_state) = self.find_missing_default_state().filter(|_| ! is_external) {
+            problems.push(t!(DefaultStateNotFound, self.get_name().cyan(), default_state.yellow()));
+        }
+        problems
+    }
+
+    pub fn get_options(&self) -> &ItemOptions {
+        match self {
+            ConfigItem::CommandItem(item) => &item.options,
+            ConfigItem::KeyValueItem(item) => &item.options,
+            ConfigItem::BlockItem(item) => &item.options,
+            ConfigItem::GitConfigItem(item) => &item.options,
+            ConfigItem::DirItem(item) => &item.options,
+            ConfigItem::TemplateItem(item) => &item.options,
+            ConfigItem::ExternalItem(item) => &item.options
+        }
+    }
+
+    pub fn get_options_mut(&mut self) -> &mut ItemOptions {
+        match self {
+            ConfigItem::CommandItem(item) => &mut item.options,
+            ConfigItem::KeyValueItem(item) => &mut item.options,
+            ConfigItem::BlockItem(item) => &mut item.options,
+            ConfigItem::GitConfigItem(item) => &mut item.options,
+            ConfigItem::DirItem(item) => &mut item.options,
+            ConfigItem::TemplateItem(item) => &mut item.options,
+            ConfigItem::ExternalItem(item) => &mut item.options
+        }
+    }
+
+    pub fn get_current_state(&self) -> &str {
+        match self {
+            ConfigItem::CommandItem(item) => &item.current,
+            ConfigItem::KeyValueItem(item) => &item.current,
+            ConfigItem::BlockItem(item) => &item.current,
+            ConfigItem::GitConfigItem(item) => &item.current,
+            ConfigItem::DirItem(item) => &item.current,
+            ConfigItem::TemplateItem(item) => &item.current,
+            ConfigItem::ExternalItem(item) => &item.current
+        }
+    }
+
+    pub fn get_current_state_mut(&mut self) -> &mut String {
+        match self {
+            ConfigItem::CommandItem(item) => &mut item.current,
+            ConfigItem::KeyValueItem(item) => &mut item.current,
+            ConfigItem::BlockItem(item) => &mut item.current,
+            ConfigItem::GitConfigItem(item) => &mut item.current,
+            ConfigItem::DirItem(item) => &mut item.current,
+            ConfigItem::TemplateItem(item) => &mut item.current,
+            ConfigItem::ExternalItem(item) => &mut item.current
+        }
+    }
+
+    pub fn get_state_names(&self) -> Vec<&str> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::KeyValueItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::BlockItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::GitConfigItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::DirItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::TemplateItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect(),
+            ConfigItem::ExternalItem(item) => item.states
+                .iter()
+                .map(|state| state.name.as_str())
+                .collect()
+        }
+    }
+
+    /// Makes a command item with the states `on` and `off`, starting off.
+    pub fn new_boolean(name: String, on_command: String, off_command: String) -> ConfigItem {
+        let make_state = |name: &str, command: String| ConfigCommandItemState {
+            name: name.to_string(),
+            color: None,
+            available_if: None,
+            max_age: None,
+            value: None,
+            verify_command: None,
+            verify_retries: None,
+            verify_delay_ms: None,
+            command: StateCommand::Plain(command)
+        };
+        ConfigItem::CommandItem(ConfigCommandItem {
+            name,
+            current: "off".to_string(),
+            ssh_host: None,
+            ssh_options: vec![],
+            rollback_on_failure: false,
+            options: ItemOptions::default(),
+            states: vec![make_state("on", on_command), make_state("off", off_command)]
+        })
+    }
+
+    /// Returns why the state at the index can't be switched to on this machine, or `None` if it can.
+    /// `available_if` is run with the shell resolved for the item.
+    pub fn get_state_unavailable_reason(&self, index: usize, options: &SwitchOptions) -> Option<String> {
+        self.check_available_if(index, options).or_else(|| match self {
+            // Remote commands run on the host, so only ssh has to be here
+            ConfigItem::CommandItem(item) if item.ssh_host.is_some() => {
+                shell::find_missing_program("ssh").map(|program| t!(ProgramNotFound, program))
+            },
+            ConfigItem::CommandItem(item) => {
+                let program = shell::find_missing_program(item.states[index].command.get_plain()?)?;
+                Some(t!(ProgramNotFound, program))
+            },
+            ConfigItem::DirItem(item) => {
+                let path = fs_util::expand_home(&item.states[index].path);
+                (! path.is_dir()).then(|| t!(NotADirectory, path.display()))
+            },
+            _ => None
+        })
+    }
+
+    pub fn get_state_available_if(&self, index: usize) -> Option<&str> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::KeyValueItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::BlockItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::GitConfigItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::DirItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::TemplateItem(item) => item.states[index].available_if.as_deref(),
+            ConfigItem::ExternalItem(_) => None
+        }
+    }
+
+    pub fn get_state_max_age(&self, index: usize) -> Option<&str> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::KeyValueItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::BlockItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::GitConfigItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::DirItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::TemplateItem(item) => item.states[index].max_age.as_deref(),
+            ConfigItem::ExternalItem(_) => None
+        }
+    }
+
+    /// Runs the `available_if` command of the state at the index, if it has one. Returns why the state is unavailable,
+    /// or `None` if it's available. It's run on every call, since it checks how things are right now.
+    pub fn check_available_if(&self, index: usize, options: &SwitchOptions) -> Option<String> {
+        let command = self.get_state_available_if(index)?;
+        let shell = options.resolve(self.get_options()).shell;
+        let result = shell::run_command_with_timeout(command, shell, AVAILABLE_IF_TIMEOUT);
+        log::debug!("Checked the availability of state #{} of {} with `{}`: {:?}", index + 1, self.get_name(), command, result);
+        match result {
+            Ok(Some(status)) if status.success() => None,
+            Ok(Some(_)) => Some(t!(AvailableIfFailed, command)),
+            Ok(None) => Some(t!(AvailableIfTimedOut, command, AVAILABLE_IF_TIMEOUT.as_secs())),
+            Err(err) => Some(t!(AvailableIfNotRun, command, err))
+        }
+    }
+
+    /// Returns the value of the state at the index for scripts, if it has one. A key-value state has the value it
+    /// sets.
+    pub fn get_value_of_state(&self, index: usize) -> Option<&str> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states[index].value.as_deref(),
+            ConfigItem::KeyValueItem(item) => Some(&item.states[index].value),
+            ConfigItem::BlockItem(item) => item.states[index].value.as_deref(),
+            ConfigItem::GitConfigItem(item) => item.states[index].value.as_deref(),
+            ConfigItem::DirItem(item) => item.states[index].value.as_deref(),
+            ConfigItem::TemplateItem(item) => item.states[index].value.as_deref(),
+            ConfigItem::ExternalItem(_) => None
+        }
+    }
+
+    /// Returns the color of the state at the index, if set.
+    pub fn get_state_color(&self, index: usize) -> Option<&str> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::KeyValueItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::BlockItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::GitConfigItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::DirItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::TemplateItem(item) => item.states[index].color.as_deref(),
+            ConfigItem::ExternalItem(item) => item.states[index].color.as_deref()
+        }
+    }
+
+    /// Colors the current state name in its color, for showing it outside listings.
+    pub fn color_current_state(&self) -> ColoredString {
+        let current_state = self.get_current_state();
+        let color = self.get_state_names()
+            .iter()
+            .position(|name| *name == current_state)
+            .and_then(|index| self.get_state_color(index));
+        theme::color_state(current_state, color)
+    }
+
+    /// Moves the state at index `from` to index `to`. `current` names the state, so it follows the move.
+    pub fn move_state(&mut self, from: usize, to: usize) {
+        fn move_element<T>(elements: &mut Vec<T>, from: usize, to: usize) {
+            let element = elements.remove(from);
+            elements.insert(to, element);
+        }
+        match self {
+            ConfigItem::CommandItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::KeyValueItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::BlockItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::GitConfigItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::DirItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::TemplateItem(item) => move_element(&mut item.states, from, to),
+            ConfigItem::ExternalItem(item) => move_element(&mut item.states, from, to)
+        }
+    }
+
+    /// Returns the state with all its fields as a TOML table, for copying it to another item of the same type.
+    pub fn get_state_value(&self, state_name: &str) -> Result<toml::Value> {
+        fn to_value<S: Serialize>(states: &[S], index: usize) -> Result<toml::Value> {
+            Ok(toml::Value::try_from(&states[index])?)
+        }
+        let Some(index) = self.get_state_names().iter().position(|name| *name == state_name) else {
+            bail!(t!(NoSuchState, self.get_name().cyan(), state_name.yellow()));
+        };
+        match self {
+            ConfigItem::CommandItem(item) => to_value(&item.states, index),
+            ConfigItem::KeyValueItem(item) => to_value(&item.states, index),
+            ConfigItem::BlockItem(item) => to_value(&item.states, index),
+            ConfigItem::GitConfigItem(item) => to_value(&item.states, index),
+            ConfigItem::DirItem(item) => to_value(&item.states, index),
+            ConfigItem::TemplateItem(item) => to_value(&item.states, index),
+            ConfigItem::ExternalItem(item) => to_value(&item.states, index)
+        }
+    }
+
+    /// Appends a state given as a TOML table, as returned by `get_state_value` of an item of the same type.
+    pub fn add_state_value(&mut self, value: toml::Value) -> Result<()> {
+        match self {
+            ConfigItem::CommandItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::KeyValueItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::BlockItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::GitConfigItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::DirItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::TemplateItem(item) => item.states.push(value.try_into()?),
+            ConfigItem::ExternalItem(item) => item.states.push(value.try_into()?)
+        }
+        Ok(())
+    }
+
+    /// Removes the state. The caller makes sure it isn't the current one.
+    pub fn remove_state(&mut self, state_name: &str) {
+        match self {
+            ConfigItem::CommandItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::KeyValueItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::BlockItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::GitConfigItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::DirItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::TemplateItem(item) => item.states.retain(|state| state.name != state_name),
+            ConfigItem::ExternalItem(item) => item.states.retain(|state| state.name != state_name)
         }
     }
 
-    pub fn get_current_state(&self) -> &str {
-        match self {
-            ConfigItem::CommandItem(item) => &item.current
+    /// Renames the states of the name, so the current state and `default_state` follow. The states of external items
+    /// come from their plugins, so they're left as they are.
+    pub fn rename_state(&mut self, old_name: &str, new_name: &str) {
+        fn rename<S>(states: &mut [S], get_name: fn(&mut S) -> &mut String, old_name: &str, new_name: &str) {
+            for name in states.iter_mut().map(get_name).filter(|name| *name == old_name) {
+                *name = new_name.to_string();
+            }
+        }
+        match self {
+            ConfigItem::CommandItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::KeyValueItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::BlockItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::GitConfigItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::DirItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::TemplateItem(item) => rename(&mut item.states, |state| &mut state.name, old_name, new_name),
+            ConfigItem::ExternalItem(_) => return
+        }
+        if self.get_current_state() == old_name {
+            *self.get_current_state_mut() = new_name.to_string();
+        }
+        let options = self.get_options_mut();
+        if options.default_state.as_deref() == Some(old_name) {
+            options.default_state = Some(new_name.to_string());
+        }
+    }
+
+    /// Returns the first available state after the current one, wrapping around to the first. States whose
+    /// `available_if` fails are skipped, and `None` is returned if no state is available.
+    pub fn get_next_state(&self, options: &SwitchOptions) -> Option<&str> {
+        let state_names = self.get_state_names();
+        let current_state = self.get_current_state();
+        let next_index = state_names
+            .iter()
+            .position(|name| *name == current_state)
+            .map_or(0, |index| index + 1);
+
+        (next_index .. next_index + state_names.len())
+            .map(|index| index % state_names.len())
+            .find(|&index| self.check_available_if(index, options).is_none())
+            .map(|index| state_names[index])
+    }
+
+    /// Whether the item has exactly the states `on` and `off`, so it's toggled when no state is given.
+    pub fn is_boolean(&self) -> bool {
+        let mut state_names = self.get_state_names();
+        state_names.sort_unstable();
+        state_names == ["off", "on"]
+    }
+
+    /// Resolves a state name given on the command line, see `match_name`. Fails if it's ambiguous, or if no state
+    /// matches, suggesting similar names.
+    pub fn resolve_state(&self, state_name: &str) -> Result<usize> {
+        let state_names = self.get_state_names();
+        let matched = match_name(&state_names, state_name);
+        match matched[..] {
+            [index] => Ok(index),
+            [] => bail!(with_suggestions(t!(NoSuchState, self.get_name().cyan(), state_name.yellow()), &state_names, state_name)),
+            _ => {
+                let described: Vec<String> = matched.iter().map(|index| format!("#{}", index + 1)).collect();
+                bail!(t!(AmbiguousState, state_name.yellow(), self.get_name().cyan(), described.join(", ")))
+            }
+        }
+    }
+
+    /// Fails if several states have the name, since switching picks states by name.
+    pub fn check_unique_state(&self, state_name: &str) -> Result<()> {
+        let matched: Vec<String> = self.get_state_names()
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| **name == state_name)
+            .map(|(index, _)| format!("#{}", index + 1))
+            .collect();
+        if matched.len() > 1 {
+            bail!(t!(AmbiguousState, state_name.yellow(), self.get_name().cyan(), matched.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Finds the states of a command item running the same command as an earlier state, after trimming, as
+    /// (earlier state, state). Encrypted commands aren't compared, since that needs the passphrase, and neither are
+    /// empty ones, which are meant to only record the state.
+    pub fn find_identical_commands(&self) -> Vec<(&str, &str)> {
+        let ConfigItem::CommandItem(item) = self else {
+            return vec![];
+        };
+        item.states
+            .iter()
+            .enumerate()
+            .filter_map(|(index, state)| {
+                let command = state.command.get_plain()?.trim();
+                if command.is_empty() {
+                    return None;
+                }
+                let earlier = item.states[.. index]
+                    .iter()
+                    .find(|earlier| earlier.command.get_plain().is_some_and(|earlier| earlier.trim() == command))?;
+                Some((earlier.name.as_str(), state.name.as_str()))
+            })
+            .collect()
+    }
+
+    /// Fails for external items, since their states come from the plugin and editing them wouldn't be kept.
+    pub fn check_states_editable(&self) -> Result<()> {
+        if let ConfigItem::ExternalItem(item) = self {
+            bail!(t!(ExternalStates, item.name.cyan(), item.plugin));
+        }
+        Ok(())
+    }
+
+    pub fn has_state(&self, state_name: &str) -> bool {
+        self.get_state_names().contains(&state_name)
+    }
+
+    pub fn set_current_state(&mut self, new_state: String, options: &SwitchOptions) -> Result<()> {
+        let item_name = self.get_name().to_string();
+        if ! self.has_state(&new_state) {
+            bail!(t!(NoSuchState, item_name.cyan(), new_state.yellow()));
+        }
+        // Failing before switching would leave the file written with the wrong mode
+        if let Some(mode) = self.get_mode().filter(|mode| fs_util::parse_mode(mode).is_none()) {
+            bail!(t!(InvalidMode, item_name.cyan(), mode));
+        }
+
+        if options.dry_run {
+            eprintln!("{}", t!(WouldSwitch, item_name.cyan(), new_state.yellow()));
+            if let Some(change_plan) = &options.change_plan {
+                change_plan.add_switch(&item_name, self.get_current_state(), &new_state);
+            }
+        }
+        else if ! options.quiet {
+            eprintln!("{}", t!(Switching, item_name.cyan(), new_state.yellow()));
+        }
+        if options.dry_run || options.debug_exec {
+            let settings = options.resolve(self.get_options());
+            eprintln!("{}", t!(EffectiveSettings, settings.log, settings.notify, shell::describe_shell(settings.shell)).dimmed());
+        }
+
+        if let Err(err) = self.apply_state(&item_name, &new_state, options) {
+            if ! interrupt::is_interrupted() {
+                return Err(err);
+            }
+            // The command may have changed things before it's interrupted
+            if options.record_interrupted && ! options.dry_run {
+                *self.get_current_state_mut() = new_state.clone();
+            }
+            return Err(err.context(t!(SwitchInterrupted, item_name.cyan(), new_state.yellow())));
         }
-    }
 
-    pub fn get_state_names(&self) -> Vec<&str> {
-        match self {
-            ConfigItem::CommandItem(item) => item.states
-                .iter()
-                .map(|state| state.name.as_str())
-                .collect()
+        if ! options.dry_run {
+            *self.get_current_state_mut() = new_state;
         }
+
+        Ok(())
     }
 
-    pub fn set_current_state(&mut self, new_state: String) -> Result<()> {
-        let item_name = self.get_name().to_string();
+    fn apply_state(&self, item_name: &str, new_state: &str, options: &SwitchOptions) -> Result<()> {
+        if options.command_override.is_some() && ! matches!(self, ConfigItem::CommandItem(_)) {
+            bail!(t!(NoCommandToOverride, item_name.cyan(), self.get_type_string()));
+        }
 
         match self {
+            ConfigItem::CommandItem(item) if options.command_override.is_some() => {
+                let command = options.command_override.as_deref().unwrap();
+                eprintln!("{}", t!(CommandOverridden, new_state.yellow()));
+                run_state_command(item_name, new_state, command, command, &item.options, item.get_ssh_target().as_ref(), options)?;
+                item.verify_switch(self.get_current_state(), new_state, options)?;
+            },
             ConfigItem::CommandItem(item) => {
-                item.current = new_state.clone();
-                println!("Switching {} => {}", item_name.cyan(), new_state.yellow());
+                let state = item.states.iter().find(|state| state.name == new_state).unwrap();
+                if state.command.is_empty() {
+                    log::debug!("State {} of {} has no command, so it's only recorded", new_state, item_name);
+                }
+                else {
+                    // An encrypted command is shown and logged as the placeholder, and only decrypted to run it
+                    let shown_command = state.with_value(&state.command.to_string());
+                    let command = if options.dry_run {
+                        shown_command.clone()
+                    } else {
+                        state.with_value(&state.command.resolve(options.askpass.as_deref())?)
+                    };
+                    run_state_command(item_name, new_state, &command, &shown_command, &item.options, item.get_ssh_target().as_ref(), options)?;
+                }
+                item.verify_switch(self.get_current_state(), new_state, options)?;
+            },
+            ConfigItem::KeyValueItem(item) => {
+                let state = item.states.iter().find(|state| state.name == new_state).unwrap();
+                let path = fs_util::expand_home(&item.file);
 
-                if let Some(state) = item.states.iter().find(|state| state.name == *new_state) {
-                    let ConfigCommandItemState { command, .. } = state;
+                let content = read_file(&path, item.create_missing)?;
+                let edit = key_value::set_value(&content, item.format, &item.key, &state.value, item.create_missing)
+                    .with_context(|| t!(FailedToSetKey, path.display()))?;
+
+                if options.dry_run {
+                    eprintln!("{}", t!(WouldSetKey, item.key.purple(), path.display()));
+                }
+                else if ! options.quiet {
+                    eprintln!("{}", t!(SettingKey, edit.after.purple(), path.display()));
+                }
+                write_edited_file(&path, &edit.content, item.mode.as_deref(), options)?;
+            },
+            ConfigItem::BlockItem(item) => {
+                let state = item.states.iter().find(|state| state.name == new_state).unwrap();
+                let path = fs_util::expand_home(&item.file);
 
-                    println!("Running {} {}", "$".purple().bold(), command.purple());
+                let content = read_file(&path, true)?;
+                let (begin, end) = block::get_marker_lines(&item.comment, &item.marker);
+                let new_content = block::replace_block(&content, &begin, &end, &state.read_content()?, options.force)
+                    .with_context(|| t!(FailedToReplaceBlock, path.display()))?;
 
-                    if cfg!(target_os = "windows") {
-                        process::Command::new("cmd")
-                            .args(["/C", command])
-                            .stdout(stdout())
-                            .stderr(stderr())
-                            .output()
-                    } else {
-                        process::Command::new("sh")
-                            .args(["-c", command])
-                            .stdout(stdout())
-                            .stderr(stderr())
-                            .output()
-                    }?;
+                if options.dry_run {
+                    eprintln!("{}", t!(WouldReplaceBlock, item.marker.purple(), path.display()));
                 }
-            }
-        }
+                else if ! options.quiet {
+                    eprintln!("{}", t!(ReplacingBlock, item.marker.purple(), path.display()));
+                }
+                write_edited_file(&path, &new_content, item.mode.as_deref(), options)?;
+            },
+            ConfigItem::GitConfigItem(item) => {
+                let state = item.states.iter().find(|state| state.name == new_state).unwrap();
+                let scope = git_config::describe_scope(&item.scope);
 
-        Ok(())
-    }
+                if options.dry_run {
+                    for (key, value) in &state.values {
+                        eprintln!("{}", t!(WouldRun, "$".purple().bold(), format!("{} {} {:?}", scope, key, value).purple()));
+                        options.plan_change(Change::GitConfig { scope: item.scope.clone(), key: key.clone(), value: value.clone() });
+                    }
+                }
+                else {
+                    git_config::ensure_git()?;
+                    if options.confirm_run {
+                        // Each key is set by a git command of its own, all confirmed at once
+                        for (key, value) in &state.values {
+                            let command = format!("{} {} {}", scope, shell::quote_arg(key), shell::quote_arg(value));
+                            eprintln!("{}", t!(CommandToRun, "$".purple().bold(), command.purple()));
+                        }
+                        let prompt = if state.values.len() == 1 { t!(RunCommand) } else { t!(RunCommands) };
+                        if ! Confirm::new().with_prompt(prompt).default(false).interact()? {
+                            bail!(RunDeclined);
+                        }
+                    }
 
-    pub fn get_type_string(&self) -> String {
-        match self {
-            ConfigItem::CommandItem(_) => {
-                "Command".to_string()
+                    let mut failed_count = 0;
+                    for (key, value) in &state.values {
+                        match git_config::set_value(&item.scope, key, value) {
+                            Ok(()) => if ! options.quiet {
+                                eprintln!("{}", t!(SetGitKey, key.purple(), value));
+                            },
+                            Err(err) => {
+                                failed_count += 1;
+                                eprintln!("{} {}: {}", t!(FailedToSet).red(), key.purple(), err);
+                            }
+                        }
+                    }
+                    if failed_count > 0 {
+                        bail!(t!(FailedToSetGitKeys, failed_count, state.values.len(), scope));
+                    }
+                }
+            },
+            ConfigItem::DirItem(item) => {
+                // Only record the state. The path is the only output on stdout, for `cd "$(...)"`
+                let path = item.resolve_path(new_state).unwrap();
+                if ! options.dry_run {
+                    println!("{}", path.display());
+                }
+            },
+            ConfigItem::TemplateItem(item) => {
+                let state = item.states.iter().find(|state| state.name == new_state).unwrap();
+                let template_path = fs_util::expand_home(&item.template);
+                let output_path = fs_util::expand_home(&item.output);
+
+                let template = read_file(&template_path, false)?;
+                // A var of the state named `value` wins over the value
+                let mut vars = state.vars.clone();
+                if let Some(value) = &state.value {
+                    vars.entry("value".to_string()).or_insert_with(|| value.clone());
+                }
+                let rendered = template::render(&template, &vars)
+                    .with_context(|| t!(FailedToRender, template_path.display()))?;
+
+                if options.dry_run {
+                    eprintln!("{}", t!(WouldRender, template_path.display(), output_path.display()));
+                    preview_file_write(&output_path, &rendered, options)?;
+                }
+                else {
+                    if ! options.quiet {
+                        eprintln!("{}", t!(Rendering, template_path.display(), output_path.display()));
+                    }
+                    let mode = item.mode.as_deref().and_then(fs_util::parse_mode).unwrap_or(0o644);
+                    fs_util::backup(&output_path)?;
+                    fs_util::write_atomic_with_mode(&output_path, &rendered, Some(mode))?;
+                }
+
+                if let Some(post_command) = &item.post_command {
+                    run_state_command(item_name, new_state, post_command, post_command, &item.options, None, options)
+                        .context(t!(PostCommandFailed))?;
+                }
+            },
+            ConfigItem::ExternalItem(item) => {
+                let shown_command = format!("{} switch {}", item.plugin, new_state);
+                if options.dry_run {
+                    eprintln!("{}", text::fit(&t!(WouldRun, "$".purple().bold(), shown_command.purple()), 0, options.full));
+                    options.plan_change(Change::Command { command: shown_command });
+                    return Ok(());
+                }
+                if options.confirm_run {
+                    eprintln!("{}", t!(CommandToRun, "$".purple().bold(), shown_command.purple()));
+                    if ! Confirm::new().with_prompt(t!(RunCommand)).default(false).interact()? {
+                        bail!(RunDeclined);
+                    }
+                }
+                else if ! options.quiet {
+                    let show_command = options.resolve(&item.options).show_command;
+                    eprintln!("{}", text::fit(&format_running_banner(&shown_command, show_command), 0, options.full));
+                }
+                item.get_plugin().switch(new_state)?;
             }
         }
+
+        Ok(())
     }
 
-    pub fn to_string(&self) -> String {
+    /// Renders the item with its states, numbered if `numbered` is set. `verbose` adds the commands of command items,
+    /// and `full` wraps long details instead of truncating them.
+    pub fn render(&self, numbered: bool, verbose: bool, full: bool) -> String {
         let name = self.get_name();
         let type_string = self.get_type_string();
         let current_state = self.get_current_state();
         let state_names = self.get_state_names();
+        let index_width = state_names.len().to_string().len();
+        let marker = theme::get_current_marker();
+        let marker_width = text::get_width(marker);
+        let default_state = self.get_options().default_state.as_deref();
+
+        let host = match self {
+            ConfigItem::CommandItem(item) => item.ssh_host
+                .as_ref()
+                .map(|ssh_host| format!(" {}", t!(RunsOnHost, ssh_host).dimmed()))
+                .unwrap_or_default(),
+            _ => String::new()
+        };
 
         format!(
-            "{} [{}]\n{}",
-            name.cyan(),
+            "{} [{}]{}\n{}",
+            text::truncate(name, text::NAME_WIDTH).cyan(),
             type_string,
+            host,
             state_names
                 .iter()
-                .map(|name| format!(
-                    "{} {}",
-                    if *name == current_state { "*".green().to_string() } else { " ".to_string() },
-                    name.yellow()
+                .enumerate()
+                .map(|(index, name)| format!(
+                    // The marker column is as wide as the marker, whatever the name starts with
+                    "{} {}{}{}{}",
+                    if *name == current_state { marker.green().to_string() } else { " ".repeat(marker_width) },
+                    if numbered { format!("{:>width$}) ", index + 1, width = index_width) } else { "".to_string() },
+                    theme::color_state(&text::truncate(name, text::NAME_WIDTH), self.get_state_color(index)),
+                    if Some(*name) == default_state { format!(" {}", theme::DEFAULT_STATE_MARKER.dimmed()) } else { "".to_string() },
+                    self.get_state_details(index, verbose)
+                        .iter()
+                        .map(|detail| format!("\n      {}", text::fit(detail, 6, full)))
+                        .collect::<String>()
                 ))
                 .collect::<Vec<String>>()
                 .join("\n")
         )
     }
+
+    /// Returns the lines shown under the state in listings, for items whose states aren't obvious from their names.
+    pub fn get_state_details(&self, index: usize, verbose: bool) -> Vec<String> {
+        let mut details = match self {
+            ConfigItem::CommandItem(item) if verbose && item.states[index].command.is_empty() => vec![
+                t!(NoCommand).dimmed().to_string()
+            ],
+            ConfigItem::CommandItem(item) if verbose => vec![
+                format!("{} {}", "$".purple().bold(), item.states[index].command.to_string().purple())
+            ],
+            ConfigItem::GitConfigItem(item) => item.states[index].values
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key.purple(), value))
+                .collect(),
+            ConfigItem::DirItem(item) => vec![ item.states[index].path.clone() ],
+            ConfigItem::TemplateItem(item) => item.states[index].vars
+                .iter()
+                .map(|(var, value)| format!("{} = {}", var.purple(), value))
+                .collect(),
+            // Each state is a run of the plugin, so only verbose listings render them
+            ConfigItem::ExternalItem(item) if verbose => item.get_plugin()
+                .render(&item.states[index].name)
+                .unwrap_or_else(|err| vec![ format!("{:#}", err).red().to_string() ]),
+            _ => vec![]
+        };
+        if let Some(value) = self.get_value_of_state(index).filter(|_| verbose) {
+            details.push(format!("{} = {}", "value".purple(), value));
+        }
+        details
+    }
+
+    /// Whether `detect_state` works on the item.
+    pub fn can_detect(&self) -> bool {
+        matches!(self, ConfigItem::GitConfigItem(_) | ConfigItem::ExternalItem(_))
+    }
+
+    /// Detects the actual state from the system, or `None` if no state matches. Fails on items without detection.
+    pub fn detect_state(&self) -> Result<Option<&str>> {
+        match self {
+            ConfigItem::GitConfigItem(item) => item.detect_state(),
+            ConfigItem::ExternalItem(item) => item.detect_state(),
+            _ => bail!(t!(NoDetection, self.get_name().cyan(), self.get_type_string()))
+        }
+    }
+
+    pub fn get_type_string(&self) -> String {
+        match self {
+            ConfigItem::CommandItem(_) => {
+                "Command".to_string()
+            },
+            ConfigItem::KeyValueItem(_) => {
+                "KeyValue".to_string()
+            },
+            ConfigItem::BlockItem(_) => {
+                "Block".to_string()
+            },
+            ConfigItem::GitConfigItem(_) => {
+                "GitConfig".to_string()
+            },
+            ConfigItem::DirItem(_) => {
+                "Dir".to_string()
+            },
+            ConfigItem::TemplateItem(_) => {
+                "Template".to_string()
+            },
+            ConfigItem::ExternalItem(_) => {
+                "External".to_string()
+            }
+        }
+    }
+}
+
+/// Formats the `Running $ ...` banner, showing as much of the command as `show_command` says.
+fn format_running_banner(shown_command: &str, show_command: ShowCommand) -> String {
+    let shown_command = match show_command {
+        ShowCommand::Full => shown_command,
+        ShowCommand::FirstWord => shell::get_program(shown_command).unwrap_or(shown_command),
+        ShowCommand::None => return t!(RunningStateCommand)
+    };
+    t!(Running, "$".purple().bold(), shown_command.purple())
+}
+
+/// Runs the command of a state, teeing its output into the item's log if `log` is set.
+fn run_state_command(
+    item_name: &str,
+    state_name: &str,
+    command: &str,
+    shown_command: &str,
+    item_options: &ItemOptions,
+    ssh: Option<&SshTarget>,
+    options: &SwitchOptions
+) -> Result<()> {
+    let settings = options.resolve(item_options);
+    let shell = settings.shell;
+    let local_command = shown_command;
+    let (plan, shown_command) = match ssh {
+        Some(ssh) => (ssh.plan(command), ssh.describe(shown_command)),
+        None => (ExecutionPlan::new(command, shell), shown_command.to_string())
+    };
+    let shown_command = shown_command.as_str();
+    if options.debug_exec {
+        eprintln!("{}", plan.to_string().dimmed());
+    }
+
+    if options.dry_run {
+        eprintln!("{}", text::fit(&t!(WouldRun, "$".purple().bold(), shown_command.purple()), 0, options.full));
+        options.plan_change(Change::Command { command: shown_command.to_string() });
+        return Ok(());
+    }
+
+    // Fails before anything runs, rather than with the shell's `command not found`
+    if options.preflight && item_options.preflight != Some(false) {
+        let missing = match ssh {
+            Some(_) => shell::find_missing_program("ssh"),
+            None => shell::find_missing_program_in(command, shell)
+        };
+        if let Some(program) = missing {
+            bail!(t!(PreflightFailed, program));
+        }
+    }
+
+    if options.confirm_run {
+        // What's confirmed is exactly what runs, so a decrypted command is shown in full here
+        match ssh {
+            Some(ssh) => {
+                eprintln!("{}", t!(CommandToRun, "$".purple().bold(), ssh.describe(command).purple()));
+                eprintln!("  {}", t!(RunsOnHost, ssh.host).dimmed());
+            },
+            None => {
+                eprintln!("{}", t!(CommandToRun, "$".purple().bold(), command.purple()));
+                eprintln!("  {}", t!(RunsIn, env::current_dir()?.display(), shell::describe_shell(shell)).dimmed());
+            }
+        }
+        if ! Confirm::new().with_prompt(t!(RunCommand)).default(false).interact()? {
+            bail!(RunDeclined);
+        }
+    }
+    else if ! options.quiet {
+        // The program of a remote command is the one run on the host, not ssh
+        let banner_command = if ssh.is_some() && settings.show_command == ShowCommand::FirstWord { local_command } else { shown_command };
+        eprintln!("{}", text::fit(&format_running_banner(banner_command, settings.show_command), 0, options.full));
+    }
+
+    let status = if settings.log {
+        run_log::run_logged(&options.log_dir, item_name, state_name, &plan, shown_command)?
+    }
+    else {
+        shell::run_plan(&plan)?
+    };
+    if ! status.success() {
+        match (status.code(), ssh) {
+            (Some(ssh::SSH_FAILURE_CODE), Some(ssh)) => bail!(t!(SshFailed, ssh.host, ssh::SSH_FAILURE_CODE)),
+            (Some(code), Some(ssh)) => bail!(t!(RemoteCommandExited, ssh.host, code)),
+            (Some(code), None) => bail!(t!(CommandExited, code)),
+            (None, _) => bail!(t!(CommandKilled))
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the `verify_command` of the state where the commands of the item run, retrying while it fails. Returns
+/// whether it passed. Dry runs only print it.
+fn run_verify_command(
+    item: &ConfigCommandItem,
+    state: &ConfigCommandItemState,
+    verify_command: &str,
+    options: &SwitchOptions
+) -> Result<bool> {
+    let ssh = item.get_ssh_target();
+    let (plan, shown_command) = match &ssh {
+        Some(ssh) => (ssh.plan(verify_command), ssh.describe(verify_command)),
+        None => (ExecutionPlan::new(verify_command, options.resolve(&item.options).shell), verify_command.to_string())
+    };
+    if options.debug_exec {
+        eprintln!("{}", plan.to_string().dimmed());
+    }
+
+    if options.dry_run {
+        eprintln!("{}", text::fit(&t!(WouldVerify, "$".purple().bold(), shown_command.purple()), 0, options.full));
+        options.plan_change(Change::Command { command: shown_command });
+        return Ok(true);
+    }
+
+    let retries = state.verify_retries.unwrap_or(0);
+    let delay = state.verify_delay_ms.map(Duration::from_millis).unwrap_or(DEFAULT_VERIFY_DELAY);
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            if ! options.quiet {
+                eprintln!("{}", t!(VerifyRetrying, delay.as_millis(), attempt, retries).dimmed());
+            }
+            thread::sleep(delay);
+        }
+        if interrupt::is_interrupted() {
+            break;
+        }
+        if attempt == 0 && ! options.quiet {
+            eprintln!("{}", text::fit(&t!(Verifying, "$".purple().bold(), shown_command.purple()), 0, options.full));
+        }
+        let status = shell::run_plan(&plan)?;
+        log::debug!("Verified state {} of {} with `{}`: {}", state.name, item.name, verify_command, status);
+        if status.success() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Reads a file of a file-based item. A missing file reads as empty if `allow_missing` is set.
+fn read_file(path: &Path, allow_missing: bool) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(err) if err.kind() == io::ErrorKind::NotFound && allow_missing => Ok(String::new()),
+        Err(err) => Err(err).with_context(|| t!(FailedToRead, path.display()))
+    }
+}
+
+/// Previews writing the content to the file on dry runs, as a unified diff against the file, or as the sizes and
+/// hashes of the contents if the file is binary. The change goes into the change plan too.
+fn preview_file_write(path: &Path, content: &str, options: &SwitchOptions) -> Result<()> {
+    let old = match fs::read(path) {
+        Ok(old) => Some(old),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err).with_context(|| t!(FailedToRead, path.display()))
+    };
+    let label = path.display().to_string();
+    let (changed, diff) = match old.as_deref().map(std::str::from_utf8) {
+        Some(Ok(old)) => (old != content, diff::render_unified(old, content, 3, &label, &label)),
+        Some(Err(_)) => (true, None),
+        None => (true, diff::render_unified("", content, 3, "/dev/null", &label))
+    };
+
+    let old = old.as_deref().map(FileSummary::new);
+    let new = FileSummary::new(content.as_bytes());
+    match (&diff, &old) {
+        (Some(diff), _) => eprintln!("{}", diff::colorize_unified(diff)),
+        (None, Some(old)) if old.binary => eprintln!("{}", t!(BinaryFileReplaced, old.size, old.hash, new.size, new.hash)),
+        _ => eprintln!("{}", t!(NoChanges).dimmed())
+    }
+    options.plan_change(Change::File { path: label, changed, diff, old, new });
+
+    Ok(())
+}
+
+/// Writes the edited content of a file-based item atomically with a backup, or previews the diff on dry runs.
+/// The file keeps its mode unless `mode` is set.
+fn write_edited_file(path: &Path, content: &str, mode: Option<&str>, options: &SwitchOptions) -> Result<()> {
+    if options.dry_run {
+        return preview_file_write(path, content, options);
+    }
+
+    // Replacing the file would otherwise reset its mode by the umask
+    let mode = mode.and_then(fs_util::parse_mode).or_else(|| fs_util::get_mode(path));
+    fs_util::backup(path)?;
+    fs_util::write_atomic_with_mode(path, content, mode)?;
+
+    Ok(())
+}
+
+impl fmt::Display for ConfigItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(false, false, false))
+    }
 }
 
 impl From<&ConfigItem> for String {
@@ -124,43 +1834,581 @@ impl From<&ConfigItem> for String {
 pub struct ConfigManager<'a> {
     path: &'a Path,
     file_path: PathBuf,
+    /// The config file as read, for diffing the changes against.
+    file_str: String,
+    /// Whether the config is read from stdin, so there's nowhere to write it.
+    ephemeral: bool,
+    /// Whether writing the config is refused.
+    read_only: bool,
+    /// Whether to write even if unknown keys are dropped.
+    force_write: bool,
+    /// The files included by the config, in include order.
+    included: Vec<IncludedFile>,
+    /// The items not for this machine by `only_on`, with their positions among all items.
+    unavailable: Vec<(usize, ConfigItem)>,
+    /// Whether the config has been written, for syncing after changes.
+    written: Cell<bool>,
+    /// Whether reading created the config file, for onboarding on the first run.
+    created: bool,
+    /// The hashes of the config files as last read or written, `None` for a missing one, to tell whether something
+    /// else changed them meanwhile.
+    file_hashes: RefCell<BTreeMap<PathBuf, Option<u64>>>,
+    /// The current states of the items as read, as (item, state), for telling which ones are switched.
+    loaded_states: Vec<(String, String)>,
 
     pub config: Config
 }
 
+impl Config {
+    pub fn find_item(&self, name: &str) -> Option<usize> {
+        self.items.iter().position(|item| item.get_name() == name)
+    }
+
+    /// Finds the item by a name given on the command line, see `match_name`. Fails if several items match, listing them.
+    pub fn match_item(&self, name: &str) -> Result<Option<usize>> {
+        let names: Vec<&str> = self.items.iter().map(|item| item.get_name()).collect();
+        let matched = match_name(&names, name);
+        if matched.len() > 1 {
+            let described: Vec<String> = matched
+                .iter()
+                .map(|&index| format!("#{} [{}]", index + 1, self.items[index].get_type_string()))
+                .collect();
+            bail!(t!(AmbiguousItem, name.cyan(), described.join(", ")));
+        }
+        Ok(matched.first().copied())
+    }
+}
+
 impl<'a> ConfigManager<'a> {
     pub fn new(path: &'a Path) -> Self {
         Self {
             path,
             file_path: path.join("config.toml"),
+            file_str: String::new(),
+            ephemeral: false,
+            read_only: false,
+            force_write: false,
+            included: vec![],
+            unavailable: vec![],
+            written: Cell::new(false),
+            created: false,
+            file_hashes: RefCell::new(BTreeMap::new()),
+            loaded_states: vec![],
             config: Self::get_default_config()
         }
     }
 
+    pub fn get_path(&self) -> &Path {
+        self.path
+    }
+
+    pub fn get_file_path(&self) -> &Path {
+        &self.file_path
+    }
+
     pub fn get_default_config() -> Config {
         Config {
+            include: vec![],
+            defaults: ConfigDefaults::default(),
+            sync: ConfigSync::default(),
+            theme: ConfigTheme::default(),
+            presets: BTreeMap::new(),
             items: vec! []
         }
     }
 
     pub fn read(&mut self) -> Result<()> {
-        fs::create_dir_all(&self.path)?;
+        if self.file_path.exists() {
+            self.read_if_exists()?;
+        }
+        // A read-only config is only created in memory
+        else if ! self.read_only {
+            fs::create_dir_all(self.path)?;
+            self.write(false)?;
+            self.file_str = self.serialize()?;
+            self.created = true;
+        }
+
+        Ok(())
+    }
 
+    /// Reads the config file if it exists, without creating the config dir or file.
+    pub fn read_if_exists(&mut self) -> Result<()> {
         if self.file_path.exists() {
             let config_str = fs::read_to_string(&self.file_path)?;
-            self.config = toml::from_str::<Config>(&config_str)?;
+            self.load(config_str, self.path.to_path_buf())?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the config from stdin. Writing it is skipped, since there's nowhere to write it.
+    pub fn read_stdin(&mut self) -> Result<()> {
+        let mut config_str = String::new();
+        io::stdin().read_to_string(&mut config_str).context(t!(FailedToReadStdinConfig))?;
+        self.load(config_str, env::current_dir()?)?;
+        self.ephemeral = true;
+
+        Ok(())
+    }
+
+    /// Parses the config, then appends the items of the included files. Relative includes are resolved in `base_dir`.
+    fn load(&mut self, config_str: String, base_dir: PathBuf) -> Result<()> {
+        self.config = toml::from_str::<Config>(&config_str)?;
+        self.file_hashes.borrow_mut().clear();
+        self.file_hashes.borrow_mut().insert(self.file_path.clone(), Some(hash_content(config_str.as_bytes())));
+        self.file_str = config_str;
+        self.included.clear();
+
+        let mut paths: Vec<PathBuf> = vec![];
+        for pattern in &self.config.include {
+            let pattern_path = base_dir.join(fs_util::expand_home(pattern));
+            let matched = fs_util::expand_wildcards(&pattern_path)
+                .with_context(|| t!(IncludeFailed, pattern))?;
+            for path in matched {
+                if path != self.file_path && ! paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+
+        for path in paths {
+            let included_str = fs::read_to_string(&path).with_context(|| t!(IncludeFailed, path.display()))?;
+            let included = toml::from_str::<IncludedConfig>(&included_str)
+                .with_context(|| t!(IncludeFailed, path.display()))?;
+            let mut item_names: Vec<String> = vec![];
+            for item in included.items {
+                let name = item.get_name().to_string();
+                if self.config.find_item(&name).is_some() {
+                    bail!(t!(DuplicateIncludedItem, name, self.get_item_file(&name).display(), path.display()));
+                }
+                item_names.push(name);
+                self.config.items.push(item);
+            }
+            log::debug!("Included {} items from {}", item_names.len(), path.display());
+            self.file_hashes.borrow_mut().insert(path.clone(), Some(hash_content(included_str.as_bytes())));
+            self.included.push(IncludedFile { path, file_str: included_str, item_names });
+        }
+        log::debug!("Loaded the config with {} items", self.config.items.len());
+        self.loaded_states = self.config.items
+            .iter()
+            .map(|item| (item.get_name().to_string(), item.get_current_state().to_string()))
+            .collect();
+
+        // Switching needs a state to switch to
+        if let Some(item) = find_item_without_states(&self.config.items) {
+            bail!(t!(ItemWithoutStates, item.get_name().cyan()));
+        }
+        for item in &mut self.config.items {
+            if let ConfigItem::ExternalItem(item) = item {
+                let plugin = fs_util::expand_home(&item.plugin);
+                // A bare name is looked up on PATH
+                item.program = if plugin.components().count() > 1 { base_dir.join(plugin) } else { plugin };
+            }
+        }
+
+        self.hide_unavailable();
+        Ok(())
+    }
+
+    /// Asks the plugins of the external items for their states. A failing plugin is warned about and its item only
+    /// has the recorded state, so the rest of the config still works.
+    pub fn load_plugin_states(&mut self) {
+        for item in &mut self.config.items {
+            let ConfigItem::ExternalItem(item) = item else {
+                continue;
+            };
+            match item.get_plugin().list_states() {
+                Ok(states) => item.states = states,
+                Err(err) => {
+                    eprintln!("{} {}", t!(Warning).yellow(), t!(PluginStatesFailed, item.name.cyan(), format!("{:#}", err)));
+                    item.states = vec![ PluginState { name: item.current.clone(), color: None } ];
+                }
+            }
+        }
+    }
+
+    /// Moves the items not for this machine out of the config, so every action only sees the available ones. An
+    /// unknown os matches no machine, so a typo hides the item instead of breaking the config on every machine.
+    fn hide_unavailable(&mut self) {
+        let os = machine::get_os();
+        let hostname = machine::get_hostname();
+        self.unavailable.clear();
+        for (index, item) in std::mem::take(&mut self.config.items).into_iter().enumerate() {
+            let is_available = item.get_options().only_on
+                .as_ref()
+                .is_none_or(|only_on| only_on.matches(os, hostname.as_deref()));
+            if is_available {
+                self.config.items.push(item);
+            }
+            else {
+                self.unavailable.push((index, item));
+            }
+        }
+    }
+
+    /// Returns the items hidden on this machine by `only_on`.
+    pub fn get_unavailable_items(&self) -> impl Iterator<Item = &ConfigItem> {
+        self.unavailable.iter().map(|(_, item)| item)
+    }
+
+    /// Returns the items hidden on this machine by `only_on`, for changes made to every item wherever it's for.
+    pub fn get_unavailable_items_mut(&mut self) -> impl Iterator<Item = &mut ConfigItem> {
+        self.unavailable.iter_mut().map(|(_, item)| item)
+    }
+
+    /// Resolves an item name given on the command line, so every flag taking one agrees on it. Fails if it's ambiguous,
+    /// or if no item matches, telling the machines of a hidden item of that name or suggesting similar names. It never
+    /// prompts, since the name is given.
+    pub fn resolve_item(&self, name: &str) -> Result<usize> {
+        if let Some(index) = self.config.match_item(name)? {
+            return Ok(index);
         }
+        self.check_available(name)?;
+        let names: Vec<&str> = self.config.items.iter().map(|item| item.get_name()).collect();
+        bail!(with_suggestions(t!(ItemNotFound, name.cyan()), &names, name));
+    }
+
+    /// Fails with why if the item is hidden on this machine, since it's not found otherwise.
+    pub fn check_available(&self, name: &str) -> Result<()> {
+        let Some(only_on) = self.get_unavailable_items()
+            .find(|item| item.get_name() == name)
+            .and_then(|item| item.get_options().only_on.as_ref())
         else {
-            self.write()?;
+            return Ok(());
+        };
+        let this_machine = OnlyOn {
+            os: vec![ machine::get_os().to_string() ],
+            hostname: machine::get_hostname().into_iter().collect()
+        };
+        bail!(t!(ItemNotForThisMachine, name.cyan(), only_on, this_machine));
+    }
+
+    /// Returns all items in their order in the config, including the hidden ones.
+    fn get_all_items(&self) -> Vec<&ConfigItem> {
+        let mut items: Vec<&ConfigItem> = self.config.items.iter().collect();
+        for (index, item) in &self.unavailable {
+            items.insert((*index).min(items.len()), item);
+        }
+        items
+    }
+
+    /// Finds the keys in the files as read which this version doesn't know, so writing would drop them.
+    /// Only writing needs them, so reading stays fast for prompts and completions.
+    fn find_unknown_keys(&self) -> Vec<String> {
+        let parse = |file_str: &str| toml::from_str::<toml::Value>(file_str).ok();
+        let mut unknown_keys = parse(&self.file_str)
+            .map(|value| unknown_keys::find::<Config>(&value))
+            .unwrap_or_default();
+        for included in &self.included {
+            for key in parse(&included.file_str).map(|value| unknown_keys::find::<IncludedConfig>(&value)).unwrap_or_default() {
+                unknown_keys.push(format!("{}: {}", included.path.display(), key));
+            }
+        }
+        unknown_keys
+    }
+
+    /// Returns the files the items are read from, with the dirs of the includes, since adding a file there may add items.
+    pub fn get_source_paths(&self) -> Vec<PathBuf> {
+        let config_dir = self.file_path.parent().unwrap_or(self.path);
+        let mut paths = vec![self.file_path.clone()];
+        paths.extend(self.included.iter().map(|included| included.path.clone()));
+        for pattern in &self.config.include {
+            if let Some(dir) = config_dir.join(fs_util::expand_home(pattern)).parent() {
+                if ! paths.iter().any(|path| path == dir) {
+                    paths.push(dir.to_path_buf());
+                }
+            }
+        }
+        paths
+    }
+
+    /// Returns the file the item comes from, the config file unless it's from an included one.
+    pub fn get_item_file(&self, name: &str) -> &Path {
+        self.included
+            .iter()
+            .find(|included| included.item_names.iter().any(|item_name| item_name == name))
+            .map_or(&self.file_path, |included| &included.path)
+    }
+
+    pub fn set_read_only(&mut self) {
+        self.read_only = true;
+    }
+
+    pub fn set_force_write(&mut self) {
+        self.force_write = true;
+    }
+
+    fn serialize(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(&ConfigRef {
+            include: &self.config.include,
+            defaults: &self.config.defaults,
+            sync: &self.config.sync,
+            theme: &self.config.theme,
+            presets: &self.config.presets,
+            items: self.get_all_items()
+                .into_iter()
+                .filter(|item| self.get_item_file(item.get_name()) == self.file_path)
+                .collect()
+        })?)
+    }
+
+    /// Serializes the items of the included file.
+    fn serialize_included(&self, included: &IncludedFile) -> Result<String> {
+        Ok(toml::to_string_pretty(&IncludedConfigRef {
+            items: self.get_all_items()
+                .into_iter()
+                .filter(|item| included.item_names.iter().any(|name| name == item.get_name()))
+                .collect()
+        })?)
+    }
+
+    /// Renders the diff of the config files as read and the config to be written, or `None` if they're the same.
+    /// The serialized texts are diffed, so formatting changes show up too.
+    /// When included files change, each diff is headed by its file.
+    pub fn get_diff(&self) -> Result<Option<String>> {
+        let main_diff = diff::render(&self.file_str, &self.serialize()?, 3);
+        if self.included.is_empty() {
+            return Ok(main_diff);
+        }
+
+        let mut diffs: Vec<String> = vec![];
+        if let Some(diff) = main_diff {
+            diffs.push(format!("{}\n{}", self.file_path.display().to_string().bold(), diff));
+        }
+        for included in &self.included {
+            if let Some(diff) = diff::render(&included.file_str, &self.serialize_included(included)?, 3) {
+                diffs.push(format!("{}\n{}", included.path.display().to_string().bold(), diff));
+            }
+        }
+        Ok((! diffs.is_empty()).then(|| diffs.join("\n\n")))
+    }
+
+    /// Fails if something else changed the file since it was last read or written, since writing would lose that.
+    fn check_unchanged(&self, path: &Path) -> Result<()> {
+        let known_hash = self.file_hashes.borrow().get(path).copied().flatten();
+        if hash_file(path)? != known_hash {
+            bail!(ConfigChangedExternally { path: path.to_path_buf() });
+        }
+        Ok(())
+    }
+
+    /// Writes the config files. Unless `force` is set, it fails with `ConfigChangedExternally` without writing
+    /// anything if something else changed one of them since it was read, e.g. an editor while a prompt was open.
+    pub fn write(&self, force: bool) -> Result<()> {
+        if self.ephemeral {
+            return Ok(());
+        }
+        if self.read_only {
+            bail!(t!(ReadOnlyConfig));
+        }
+        if ! self.force_write {
+            let unknown_keys = self.find_unknown_keys();
+            if ! unknown_keys.is_empty() {
+                bail!(t!(UnknownKeys, unknown_keys.join(", ")));
+            }
+        }
+        let config_str = self.serialize()?;
+        // Included files are only written if their items changed, so unchanged ones keep their formatting
+        let mut included_strs: Vec<(&Path, String)> = vec![];
+        for included in &self.included {
+            let included_str = self.serialize_included(included)?;
+            if included_str != included.file_str {
+                included_strs.push((&included.path, included_str));
+            }
+        }
+        if ! force {
+            self.check_unchanged(&self.file_path)?;
+            for (path, _) in &included_strs {
+                self.check_unchanged(path)?;
+            }
+        }
+
+        fs::write(&self.file_path, &config_str)?;
+        self.file_hashes.borrow_mut().insert(self.file_path.clone(), Some(hash_content(config_str.as_bytes())));
+        for (path, included_str) in included_strs {
+            fs::write(path, &included_str).with_context(|| t!(FailedToWrite, path.display()))?;
+            self.file_hashes.borrow_mut().insert(path.to_path_buf(), Some(hash_content(included_str.as_bytes())));
         }
+        snapshot::write(self.path, &self.config)?;
+        self.written.set(true);
+        log::debug!("Wrote the config to {}", self.file_path.display());
 
         Ok(())
     }
 
-    pub fn write(&self) -> Result<()> {
-        let config_str = toml::to_string_pretty(&self.config)?;
-        fs::write(&self.file_path, config_str)?;
+    pub fn is_written(&self) -> bool {
+        self.written.get()
+    }
+
+    pub fn is_created(&self) -> bool {
+        self.created
+    }
+
+    /// Writes the config after switches. A read-only config isn't written, so the new states aren't recorded.
+    /// If something else changed the config meanwhile, re-reading it and recording the new states there is offered.
+    pub fn write_switched(&self) -> Result<()> {
+        if self.read_only {
+            eprintln!("{}", t!(ReadOnlyNotRecorded).dimmed());
+            return Ok(());
+        }
+        match self.write(false) {
+            Err(err) if err.is::<ConfigChangedExternally>() => self.replay_switched(err),
+            result => result
+        }
+    }
+
+    /// Re-reads the config changed by something else and records the switched states in it, if confirmed. The
+    /// config in memory is left as it is, so a later write notices the change again rather than losing it.
+    fn replay_switched(&self, err: anyhow::Error) -> Result<()> {
+        let switched: Vec<(&str, &str)> = self.config.items
+            .iter()
+            .map(|item| (item.get_name(), item.get_current_state()))
+            .filter(|(name, state)| ! self.loaded_states.iter().any(|(loaded_name, loaded_state)| loaded_name == name && loaded_state == state))
+            .collect();
+        if switched.is_empty() || ! stdin().is_terminal() || ! stderr().is_terminal() {
+            return Err(err.context(t!(StatesNotRecorded)));
+        }
+        eprintln!("{} {}", t!(Warning).yellow(), err);
+        if ! Confirm::new().with_prompt(t!(ReplaySwitches)).default(true).interact()? {
+            bail!(t!(StatesNotRecorded));
+        }
 
+        let mut fresh = ConfigManager::new(self.path);
+        fresh.force_write = self.force_write;
+        fresh.read_if_exists()?;
+        for (name, state) in switched {
+            match fresh.config.find_item(name).map(|index| &mut fresh.config.items[index]) {
+                // The states of external items are only known once their plugins list them
+                Some(item) if item.has_state(state) || matches!(item, ConfigItem::ExternalItem(_)) => {
+                    *item.get_current_state_mut() = state.to_string();
+                },
+                _ => eprintln!("{} {}", t!(Warning).yellow(), t!(NotReplayed, name.cyan(), state.yellow()))
+            }
+        }
+        fresh.write(false)?;
+        self.written.set(true);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+fn hash_content(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hashes the content of the file, or returns `None` if it's missing.
+fn hash_file(path: &Path) -> Result<Option<u64>> {
+    match fs::read(path) {
+        Ok(content) => Ok(Some(hash_content(&content))),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| t!(FailedToRead, path.display()))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "proxy"
+states = [{ name = "on" }, { name = "off" }, { name = "Off" }, { name = "Work" }, { name = "WORK" }]
+
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "Proxy"
+states = [{ name = "on" }]
+
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "vpn"
+states = [{ name = "on" }]
+
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "VPN-eu"
+states = [{ name = "on" }]
+
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "Vpn-EU"
+states = [{ name = "on" }]
+"#;
+
+    const HIDDEN: &str = r#"
+[[items]]
+type = "CommandItem"
+current = "on"
+name = "printer"
+only_on = { os = ["plan9"] }
+states = [{ name = "on" }]
+"#;
+
+    fn get_manager(path: &Path) -> ConfigManager<'_> {
+        colored::control::set_override(false);
+        let mut manager = ConfigManager::new(path);
+        manager.config = toml::from_str(CONFIG).unwrap();
+        let mut hidden: Config = toml::from_str(HIDDEN).unwrap();
+        manager.unavailable.push((manager.config.items.len(), hidden.items.remove(0)));
+        manager
+    }
+
+    #[test]
+    fn exact_names_come_first() {
+        let path = PathBuf::new();
+        let manager = get_manager(&path);
+        assert_eq!(manager.resolve_item("proxy").unwrap(), 0);
+        assert_eq!(manager.resolve_item("Proxy").unwrap(), 1);
+        let proxy = &manager.config.items[0];
+        assert_eq!(proxy.resolve_state("off").unwrap(), 1);
+        assert_eq!(proxy.resolve_state("Off").unwrap(), 2);
+    }
+
+    #[test]
+    fn names_match_ignoring_ascii_case() {
+        let path = PathBuf::new();
+        let manager = get_manager(&path);
+        assert_eq!(manager.resolve_item("VPN").unwrap(), 2);
+        assert_eq!(manager.config.items[0].resolve_state("ON").unwrap(), 0);
+        assert_eq!(match_name(&["ß"], "SS"), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn ambiguous_names_fail() {
+        let path = PathBuf::new();
+        let manager = get_manager(&path);
+        let err = manager.resolve_item("vpn-eu").unwrap_err().to_string();
+        assert!(err.contains("#4 [Command], #5 [Command]"), "{}", err);
+        let err = manager.config.items[0].resolve_state("work").unwrap_err().to_string();
+        assert!(err.contains("#4, #5"), "{}", err);
+    }
+
+    #[test]
+    fn unmatched_names_fail_with_suggestions() {
+        let path = PathBuf::new();
+        let manager = get_manager(&path);
+        let err = manager.resolve_item("prxy").unwrap_err().to_string();
+        assert!(err.contains("prxy") && err.contains("proxy"), "{}", err);
+        assert!(! err.contains("vpn"), "{}", err);
+        let err = manager.config.items[0].resolve_state("of").unwrap_err().to_string();
+        assert!(err.contains("off"), "{}", err);
+        let err = manager.resolve_item("zzz").unwrap_err().to_string();
+        assert!(! err.contains("proxy"), "{}", err);
+    }
+
+    #[test]
+    fn hidden_items_tell_their_machines() {
+        let path = PathBuf::new();
+        let manager = get_manager(&path);
+        let err = manager.resolve_item("printer").unwrap_err().to_string();
+        assert!(err.contains("plan9") && err.contains(machine::get_os()), "{}", err);
+        assert!(manager.check_available("proxy").is_ok());
+    }
+}
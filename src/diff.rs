@@ -0,0 +1,165 @@
+use colored::Colorize;
+
+pub enum DiffLine<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str)
+}
+
+/// Diffs the lines of two texts by their longest common subsequence.
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let (old_len, new_len) = (old_lines.len(), new_lines.len());
+
+    // lcs[i][j] is the LCS length of old_lines[i ..] and new_lines[j ..]
+    let mut lcs = vec![vec![0usize; new_len + 1]; old_len + 1];
+    for i in (0 .. old_len).rev() {
+        for j in (0 .. new_len).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            }
+            else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::with_capacity(old_len.max(new_len));
+    let (mut i, mut j) = (0, 0);
+    while i < old_len && j < new_len {
+        if old_lines[i] == new_lines[j] {
+            diff.push(DiffLine::Same(old_lines[i]));
+            i += 1;
+            j += 1;
+        }
+        else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            diff.push(DiffLine::Removed(old_lines[i]));
+            i += 1;
+        }
+        else {
+            diff.push(DiffLine::Added(new_lines[j]));
+            j += 1;
+        }
+    }
+    diff.extend(old_lines[i ..].iter().map(|line| DiffLine::Removed(line)));
+    diff.extend(new_lines[j ..].iter().map(|line| DiffLine::Added(line)));
+
+    diff
+}
+
+/// Renders the changed lines with `context` unchanged lines around them, eliding the rest with `...`.
+/// Returns `None` if nothing changed.
+pub fn render(old: &str, new: &str, context: usize) -> Option<String> {
+    let diff = diff_lines(old, new);
+    let changed: Vec<bool> = diff.iter().map(|line| ! matches!(line, DiffLine::Same(_))).collect();
+    if ! changed.contains(&true) {
+        return None;
+    }
+
+    let is_near_change = |index: usize| {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(diff.len());
+        changed[start .. end].contains(&true)
+    };
+
+    let mut rendered: Vec<String> = vec![];
+    let mut elided = false;
+    for (index, line) in diff.iter().enumerate() {
+        if ! is_near_change(index) {
+            if ! elided {
+                rendered.push("...".dimmed().to_string());
+                elided = true;
+            }
+            continue;
+        }
+        elided = false;
+        rendered.push(match line {
+            DiffLine::Same(line) => format!("  {}", line),
+            DiffLine::Removed(line) => format!("- {}", line).red().to_string(),
+            DiffLine::Added(line) => format!("+ {}", line).green().to_string()
+        });
+    }
+
+    Some(rendered.join("\n"))
+}
+
+/// Renders a unified diff, with `---` and `+++` headers naming the texts and `@@ -start,len +start,len @@` hunks of
+/// the changed lines with `context` unchanged lines around them. Returns `None` if nothing changed.
+/// It's uncolored, for machine-readable output, see `colorize_unified`.
+pub fn render_unified(old: &str, new: &str, context: usize, old_label: &str, new_label: &str) -> Option<String> {
+    let diff = diff_lines(old, new);
+
+    // The old and new line numbers before each diff line, and after the last
+    let mut positions = Vec::with_capacity(diff.len() + 1);
+    let (mut old_line, mut new_line) = (0, 0);
+    for line in &diff {
+        positions.push((old_line, new_line));
+        match line {
+            DiffLine::Same(_) => (old_line, new_line) = (old_line + 1, new_line + 1),
+            DiffLine::Removed(_) => old_line += 1,
+            DiffLine::Added(_) => new_line += 1
+        }
+    }
+    positions.push((old_line, new_line));
+
+    // Hunks as ranges of diff lines, merged where their context overlaps
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for (index, line) in diff.iter().enumerate() {
+        if matches!(line, DiffLine::Same(_)) {
+            continue;
+        }
+        let (start, end) = (index.saturating_sub(context), (index + context + 1).min(diff.len()));
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end))
+        }
+    }
+    if hunks.is_empty() {
+        return None;
+    }
+
+    // A range is 1-based, except an empty one, which names the line before it
+    let get_range = |start: usize, len: usize| match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len)
+    };
+    let mut rendered = vec![format!("--- {}", old_label), format!("+++ {}", new_label)];
+    for (start, end) in hunks {
+        let ((old_start, new_start), (old_end, new_end)) = (positions[start], positions[end]);
+        rendered.push(format!("@@ -{} +{} @@", get_range(old_start, old_end - old_start), get_range(new_start, new_end - new_start)));
+        rendered.extend(diff[start .. end].iter().map(|line| match line {
+            DiffLine::Same(line) => format!(" {}", line),
+            DiffLine::Removed(line) => format!("-{}", line),
+            DiffLine::Added(line) => format!("+{}", line)
+        }));
+    }
+
+    Some(rendered.join("\n"))
+}
+
+/// Colors a unified diff like `render` colors its lines, with the headers bold and the hunk ranges cyan.
+pub fn colorize_unified(diff: &str) -> String {
+    diff
+        .lines()
+        .map(|line| {
+            if line.starts_with("--- ") || line.starts_with("+++ ") {
+                line.bold().to_string()
+            }
+            else if line.starts_with("@@") {
+                line.cyan().to_string()
+            }
+            else if line.starts_with('-') {
+                line.red().to_string()
+            }
+            else if line.starts_with('+') {
+                line.green().to_string()
+            }
+            else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
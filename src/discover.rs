@@ -0,0 +1,171 @@
+use std::{env, fs, path::{Path, PathBuf}};
+
+use directories::BaseDirs;
+
+use crate::{
+    config::{ConfigCommandItem, ConfigCommandItemState, ConfigItem, ItemOptions, StateCommand},
+    recipe,
+    shell
+};
+
+/// An item generated from a tool found on the system.
+pub struct Candidate {
+    pub description: String,
+    /// Where the states came from, shown so nothing is magic.
+    pub probe: String,
+    pub item: ConfigItem
+}
+
+/// Probes the system for switchable things. Missing tools are skipped.
+pub fn probe_all() -> Vec<Candidate> {
+    [
+        probe_kubectl_contexts,
+        probe_docker_contexts,
+        probe_pyenv_versions,
+        probe_nvm_versions,
+        probe_jdks
+    ]
+        .iter()
+        .filter_map(|probe| probe())
+        .collect()
+}
+
+/// Makes a command item with a state per name. Returns `None` if there are no names.
+pub fn make_command_item(
+    name: &str,
+    names: Vec<String>,
+    make_command: impl Fn(&str) -> String,
+    current: Option<String>
+) -> Option<ConfigItem> {
+    let first_name = names.first()?.clone();
+    let current = current.filter(|current| names.contains(current)).unwrap_or(first_name);
+
+    Some(ConfigItem::CommandItem(ConfigCommandItem {
+        name: name.to_string(),
+        current,
+        ssh_host: None,
+        ssh_options: vec![],
+        rollback_on_failure: false,
+        options: ItemOptions::default(),
+        states: names
+            .into_iter()
+            .map(|name| ConfigCommandItemState {
+                command: StateCommand::Plain(make_command(&name)),
+                name,
+                color: None,
+                available_if: None,
+                max_age: None,
+                value: None,
+                verify_command: None,
+                verify_retries: None,
+                verify_delay_ms: None
+            })
+            .collect()
+    }))
+}
+
+/// Lists the names of the subdirs, sorted.
+fn list_dirs(path: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(path) else {
+        return vec![];
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    names.sort();
+    names
+}
+
+fn probe_kubectl_contexts() -> Option<Candidate> {
+    let probe = "kubectl config get-contexts -o name";
+    let current = recipe::discover("kubectl config current-context").into_iter().next();
+    Some(Candidate {
+        description: "kubectl contexts".to_string(),
+        probe: format!("`{}`", probe),
+        item: make_command_item(
+            "kube-context",
+            recipe::discover(probe),
+            |name| format!("kubectl config use-context {}", shell::quote_arg(name)),
+            current
+        )?
+    })
+}
+
+fn probe_docker_contexts() -> Option<Candidate> {
+    let probe = "docker context ls --format {{.Name}}";
+    let current = recipe::discover("docker context show").into_iter().next();
+    Some(Candidate {
+        description: "Docker contexts".to_string(),
+        probe: format!("`{}`", probe),
+        item: make_command_item(
+            "docker-context",
+            recipe::discover(probe),
+            |name| format!("docker context use {}", shell::quote_arg(name)),
+            current
+        )?
+    })
+}
+
+fn probe_pyenv_versions() -> Option<Candidate> {
+    let probe = "pyenv versions --bare";
+    let current = recipe::discover("pyenv global").into_iter().next();
+    Some(Candidate {
+        description: "pyenv Python versions".to_string(),
+        probe: format!("`{}`", probe),
+        item: make_command_item(
+            "python",
+            recipe::discover(probe),
+            |name| format!("pyenv global {}", shell::quote_arg(name)),
+            current
+        )?
+    })
+}
+
+fn probe_nvm_versions() -> Option<Candidate> {
+    // nvm is a shell function, so the versions are read from its dir, and each command sources it first
+    let nvm_dir = env::var_os("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| BaseDirs::new().map(|base_dirs| base_dirs.home_dir().join(".nvm")))?;
+    let versions_dir = nvm_dir.join("versions").join("node");
+    let nvm_script = shell::quote_arg(&nvm_dir.join("nvm.sh").to_string_lossy());
+    let make_command = |name: &str| format!(". {} && nvm alias default {}", nvm_script, shell::quote_arg(name));
+
+    Some(Candidate {
+        description: "nvm Node.js versions".to_string(),
+        probe: format!("the dirs in {}", versions_dir.display()),
+        item: make_command_item("node", list_dirs(&versions_dir), make_command, None)?
+    })
+}
+
+fn probe_jdks() -> Option<Candidate> {
+    if cfg!(target_os = "macos") {
+        // macOS has no system-wide switch, so JAVA_HOME is set for the GUI session and new shells via launchctl
+        let jdks_dir = Path::new("/Library/Java/JavaVirtualMachines");
+        let make_command = |name: &str| format!(
+            "launchctl setenv JAVA_HOME {}",
+            shell::quote_arg(&jdks_dir.join(name).join("Contents/Home").to_string_lossy())
+        );
+        return Some(Candidate {
+            description: "JDKs".to_string(),
+            probe: format!("the dirs in {}", jdks_dir.display()),
+            item: make_command_item("jdk", list_dirs(jdks_dir), make_command, None)?
+        });
+    }
+
+    let jdks_dir = Path::new("/usr/lib/jvm");
+    let names: Vec<String> = list_dirs(jdks_dir)
+        .into_iter()
+        .filter(|name| jdks_dir.join(name).join("bin/java").is_file())
+        .collect();
+    let make_command = |name: &str| format!(
+        "sudo update-alternatives --set java {}",
+        shell::quote_arg(&jdks_dir.join(name).join("bin/java").to_string_lossy())
+    );
+    Some(Candidate {
+        description: "JDKs".to_string(),
+        probe: format!("the dirs in {} with bin/java", jdks_dir.display()),
+        item: make_command_item("jdk", names, make_command, None)?
+    })
+}
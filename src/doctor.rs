@@ -0,0 +1,307 @@
+use std::{collections::BTreeSet, env, fs, io::{stderr, stdin, IsTerminal}, process::{Command, Stdio}};
+
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::{
+    config::{ConfigItem, ConfigManager},
+    editor,
+    env_vars::EnvVars,
+    i18n::t,
+    shell::{self, CommandShell, ExecutionPlan}
+};
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Pass,
+    /// Something works worse than it could, which doesn't fail `--doctor`.
+    Warn,
+    Fail
+}
+
+/// The outcome of a check, or of one of its parts like an item.
+#[derive(Serialize)]
+pub struct CheckResult {
+    /// The id of the check, the same in every language.
+    pub check: &'static str,
+    pub title: String,
+    pub status: CheckStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>
+}
+
+impl CheckResult {
+    /// Makes a result, whose check is filled in by `run`.
+    fn new(title: String, status: CheckStatus, detail: Option<String>) -> Self {
+        Self { check: "", title, status, detail }
+    }
+}
+
+/// What checks look at. The config may have failed to read, leaving it empty.
+pub struct DoctorContext<'a> {
+    pub cm: &'a ConfigManager<'a>,
+    pub config_error: Option<String>
+}
+
+/// A check of `--doctor`. It returns a result for each part it checks, or none if there's nothing to check.
+pub struct Check {
+    pub id: &'static str,
+    pub run: fn(&DoctorContext) -> Vec<CheckResult>
+}
+
+/// The checks, in the order they're printed. Subsystems add theirs here.
+pub const CHECKS: &[Check] = &[
+    Check { id: "config_dir", run: check_config_dir },
+    Check { id: "config", run: check_config },
+    Check { id: "items", run: check_items },
+    Check { id: "env", run: check_env_vars },
+    Check { id: "shells", run: check_shells },
+    Check { id: "commands", run: check_commands },
+    Check { id: "identical_commands", run: check_identical_commands },
+    Check { id: "plugins", run: check_plugins },
+    Check { id: "sync", run: check_sync },
+    Check { id: "editor", run: check_editor },
+    Check { id: "pager", run: check_pager },
+    Check { id: "terminal", run: check_terminal },
+    Check { id: "completions", run: check_completions }
+];
+
+/// Runs every check.
+pub fn run(context: &DoctorContext) -> Vec<CheckResult> {
+    CHECKS
+        .iter()
+        .flat_map(|check| (check.run)(context).into_iter().map(|result| CheckResult { check: check.id, ..result }))
+        .collect()
+}
+
+/// Renders a result as a line, like `✓ The config file parses`.
+pub fn render(result: &CheckResult) -> String {
+    let symbol = match result.status {
+        CheckStatus::Pass => "✓".green(),
+        CheckStatus::Warn => "!".yellow(),
+        CheckStatus::Fail => "✗".red()
+    };
+    match &result.detail {
+        Some(detail) => format!("{} {}  {}", symbol, result.title, detail.dimmed()),
+        None => format!("{} {}", symbol, result.title)
+    }
+}
+
+fn pass_or_fail(title: String, problem: Option<String>) -> CheckResult {
+    match problem {
+        Some(problem) => CheckResult::new(title, CheckStatus::Fail, Some(problem)),
+        None => CheckResult::new(title, CheckStatus::Pass, None)
+    }
+}
+
+fn check_config_dir(context: &DoctorContext) -> Vec<CheckResult> {
+    let dir = context.cm.get_path();
+    let title = t!(DoctorConfigDir, dir.display());
+    if ! dir.is_dir() {
+        return vec![CheckResult::new(title, CheckStatus::Warn, Some(t!(DoctorNotCreated)))];
+    }
+    // Permissions don't tell everything, e.g. on read-only mounts, so a file is written for real
+    let probe_path = dir.join(".switchy-doctor");
+    let problem = fs::write(&probe_path, "").err().map(|err| t!(DoctorNotWritable, err));
+    let _ = fs::remove_file(&probe_path);
+    vec![pass_or_fail(title, problem)]
+}
+
+fn check_config(context: &DoctorContext) -> Vec<CheckResult> {
+    let path = context.cm.get_file_path();
+    let title = t!(DoctorConfig, path.display());
+    if ! path.exists() {
+        return vec![CheckResult::new(title, CheckStatus::Warn, Some(t!(DoctorNotCreated)))];
+    }
+    vec![pass_or_fail(title, context.config_error.clone())]
+}
+
+/// Reports the problems of the items that only fail switching them, also of the items hidden on this machine.
+fn check_items(context: &DoctorContext) -> Vec<CheckResult> {
+    context.cm.config.items
+        .iter()
+        .chain(context.cm.get_unavailable_items())
+        .filter_map(|item| {
+            let problems = item.find_problems();
+            (! problems.is_empty()).then(|| {
+                CheckResult::new(t!(DoctorItem, item.get_name()), CheckStatus::Fail, Some(problems.join("; ")))
+            })
+        })
+        .collect()
+}
+
+/// Reports the `SWITCHY_*` vars in effect, and the ones ignored.
+fn check_env_vars(_context: &DoctorContext) -> Vec<CheckResult> {
+    let env_vars = EnvVars::read();
+    let set = env_vars.set
+        .into_iter()
+        .map(|(name, value)| CheckResult::new(t!(DoctorEnvVar, format!("{}={}", name, value)), CheckStatus::Pass, None));
+    let ignored = env_vars.warnings
+        .into_iter()
+        .map(|warning| CheckResult::new(t!(DoctorEnvVarIgnored), CheckStatus::Warn, Some(warning)));
+    set.chain(ignored).collect()
+}
+
+/// Returns the shell for items which don't set one, from `SWITCHY_SHELL` or the config.
+fn get_default_shell(context: &DoctorContext) -> Option<CommandShell> {
+    EnvVars::read().shell.unwrap_or(context.cm.config.defaults.shell)
+}
+
+/// Returns the program the shell runs commands with, e.g. `sh` or the one in `COMSPEC`.
+fn get_shell_program(shell: Option<CommandShell>) -> String {
+    ExecutionPlan::new("", shell).program.to_string_lossy().into_owned()
+}
+
+fn check_shells(context: &DoctorContext) -> Vec<CheckResult> {
+    let config = &context.cm.config;
+    let default_shell = get_default_shell(context);
+    let shells: BTreeSet<String> = config.items
+        .iter()
+        .map(|item| item.get_options().shell.or(default_shell))
+        .chain([default_shell])
+        .map(get_shell_program)
+        .collect();
+    shells
+        .into_iter()
+        .map(|program| {
+            let problem = shell::find_executable(&program).is_none().then(|| t!(ProgramNotFound, program));
+            pass_or_fail(t!(DoctorShell, program), problem)
+        })
+        .collect()
+}
+
+/// Returns the programs of the plain state commands of the item that the shell running them can't find, with their
+/// states, the same way as the check before running.
+fn find_missing_programs(item: &ConfigItem, default_shell: Option<CommandShell>) -> Vec<String> {
+    let ConfigItem::CommandItem(command_item) = item else {
+        return vec![];
+    };
+    if command_item.options.preflight == Some(false) {
+        return vec![];
+    }
+    if command_item.ssh_host.is_some() {
+        return shell::find_missing_program("ssh").map(|program| program.to_string()).into_iter().collect();
+    }
+    let shell = command_item.options.shell.or(default_shell);
+    command_item.states
+        .iter()
+        .filter_map(|state| {
+            let program = shell::find_missing_program_in(state.command.get_plain()?, shell)?;
+            Some(format!("{} ({})", program, state.name))
+        })
+        .collect()
+}
+
+fn check_commands(context: &DoctorContext) -> Vec<CheckResult> {
+    let config = &context.cm.config;
+    config.items
+        .iter()
+        .filter(|item| matches!(item, ConfigItem::CommandItem(_)))
+        .map(|item| {
+            let missing = find_missing_programs(item, get_default_shell(context));
+            let problem = (! missing.is_empty()).then(|| t!(DoctorMissingPrograms, missing.join(", ")));
+            pass_or_fail(t!(DoctorCommands, item.get_name()), problem)
+        })
+        .collect()
+}
+
+/// Warns about states running the same command as an earlier state of their item, which is likely a copy-paste slip.
+fn check_identical_commands(context: &DoctorContext) -> Vec<CheckResult> {
+    context.cm.config.items
+        .iter()
+        .flat_map(|item| item
+            .find_identical_commands()
+            .into_iter()
+            .map(|(earlier, state)| CheckResult::new(
+                t!(DoctorIdenticalCommands, item.get_name()),
+                CheckStatus::Warn,
+                Some(t!(IdenticalCommands, item.get_name(), earlier, state))
+            ))
+            .collect::<Vec<_>>()
+        )
+        .collect()
+}
+
+fn check_plugins(context: &DoctorContext) -> Vec<CheckResult> {
+    context.cm.config.items
+        .iter()
+        .filter_map(|item| match item {
+            ConfigItem::ExternalItem(item) => Some(item),
+            _ => None
+        })
+        .map(|item| {
+            let problem = item.get_plugin().list_states().err().map(|err| format!("{:#}", err));
+            pass_or_fail(t!(DoctorPlugin, item.plugin, item.name), problem)
+        })
+        .collect()
+}
+
+fn check_sync(context: &DoctorContext) -> Vec<CheckResult> {
+    let program = match &context.cm.config.sync.command {
+        Some(command) => shell::get_program(command),
+        None if context.cm.get_path().join(".git").exists() => Some("git"),
+        None => None
+    };
+    program
+        .map(|program| {
+            let problem = shell::find_missing_program(program).map(|program| t!(ProgramNotFound, program));
+            pass_or_fail(t!(DoctorSync, program), problem)
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Checks that the program of a command like `code --wait` is found.
+fn check_program_command(title: String, command: &str) -> CheckResult {
+    let problem = match editor::split_editor(command).first() {
+        Some(program) => shell::find_executable(program).is_none().then(|| t!(ProgramNotFound, program)),
+        None => Some(t!(ProgramNotFound, command))
+    };
+    pass_or_fail(title, problem)
+}
+
+fn check_editor(_context: &DoctorContext) -> Vec<CheckResult> {
+    let editor = editor::get_editor();
+    vec![check_program_command(t!(DoctorEditor, editor), &editor)]
+}
+
+fn check_pager(_context: &DoctorContext) -> Vec<CheckResult> {
+    match env::var("PAGER").ok().filter(|pager| ! pager.trim().is_empty()) {
+        Some(pager) => vec![check_program_command(t!(DoctorPager, pager), &pager)],
+        None => vec![]
+    }
+}
+
+fn check_terminal(_context: &DoctorContext) -> Vec<CheckResult> {
+    let title = t!(DoctorTerminal);
+    let problem = if ! stdin().is_terminal() || ! stderr().is_terminal() {
+        Some(t!(DoctorNotTerminal))
+    }
+    else if env::var("TERM").is_ok_and(|term| term == "dumb") {
+        Some(t!(DoctorDumbTerminal))
+    }
+    else {
+        None
+    };
+    vec![match problem {
+        Some(problem) => CheckResult::new(title, CheckStatus::Warn, Some(problem)),
+        None => CheckResult::new(title, CheckStatus::Pass, None)
+    }]
+}
+
+/// Runs `--complete-names` like shell completions do, in a child process with the same env.
+fn check_completions(_context: &DoctorContext) -> Vec<CheckResult> {
+    let problem = match env::current_exe().and_then(|exe| Command::new(exe)
+        .arg("--complete-names")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .output())
+    {
+        Ok(output) if output.status.success() => None,
+        Ok(output) => Some(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Err(err) => Some(err.to_string())
+    };
+    vec![pass_or_fail(t!(DoctorCompletions), problem)]
+}
@@ -0,0 +1,90 @@
+use std::{env, fs, path::Path, process::Command, time::{SystemTime, UNIX_EPOCH}};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{i18n::t, shell};
+
+/// Returns the editor command, from `VISUAL`, then `EDITOR`, then the platform default.
+pub fn get_editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| ! value.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// Splits the editor command into the program and its args, like `code --wait`.
+/// Quotes group words, so a program path may contain spaces.
+pub fn split_editor(editor: &str) -> Vec<String> {
+    let mut words: Vec<String> = vec![];
+    let mut word = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_word = false;
+
+    for char in editor.chars() {
+        match (quote, char) {
+            (Some(open), _) if char == open => quote = None,
+            (Some(_), _) => word.push(char),
+            (None, '"' | '\'') => {
+                quote = Some(char);
+                in_word = true;
+            },
+            (None, _) if char.is_whitespace() => if in_word {
+                words.push(std::mem::take(&mut word));
+                in_word = false;
+            },
+            (None, _) => {
+                word.push(char);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
+
+/// Opens the file in the editor and waits for it. Returns whether the editor exited successfully.
+pub fn open(path: &Path) -> Result<bool> {
+    let editor = get_editor();
+    let words = split_editor(&editor);
+    let Some((program, args)) = words.split_first() else {
+        bail!(t!(EditorEmpty));
+    };
+    if shell::find_executable(program).is_none() {
+        bail!(t!(EditorNotFound, program));
+    }
+
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .with_context(|| t!(FailedToRunEditor, editor))?;
+
+    Ok(status.success())
+}
+
+/// Edits the text in a temp file. Returns `None` if the editor failed or the file isn't saved.
+/// `extension` picks the syntax highlighting of the editor, e.g. `sh`.
+pub fn edit_text(text: &str, extension: &str) -> Result<Option<String>> {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos()).unwrap_or(0);
+    let path = env::temp_dir().join(format!("switchy-{}-{}.{}", std::process::id(), nanos, extension));
+
+    // Windows editors like Notepad expect CRLF line endings
+    let text = if cfg!(windows) { text.replace('\n', "\r\n") } else { text.to_string() };
+    fs::write(&path, &text).with_context(|| t!(FailedToWrite, path.display()))?;
+    let modified = fs::metadata(&path)?.modified()?;
+
+    let result = open(&path).and_then(|saved| {
+        if ! saved || fs::metadata(&path)?.modified()? == modified {
+            return Ok(None);
+        }
+        let edited = fs::read_to_string(&path).with_context(|| t!(FailedToRead, path.display()))?;
+        Ok(Some(edited.replace("\r\n", "\n")))
+    });
+    let _ = fs::remove_file(&path);
+
+    result
+}
@@ -0,0 +1,187 @@
+use std::{env, ffi::OsString};
+
+use clap::ValueEnum;
+
+use crate::{i18n::t, overlay, shell::CommandShell, theme::ColorWhen};
+
+/// The `SWITCHY_*` env vars switchy reads, or passes to the commands it runs. Any other is warned about, since it's
+/// likely a typo.
+pub const KNOWN_VARS: &[&str] = &[
+    "SWITCHY_COLOR",
+    "SWITCHY_CONFIG_DIR",
+    "SWITCHY_LOG",
+    "SWITCHY_PROFILE",
+    "SWITCHY_QUIET",
+    "SWITCHY_READONLY",
+    "SWITCHY_SHELL",
+    "SWITCHY_SYNC_MESSAGE",
+    "SWITCHY_YES"
+];
+
+/// The defaults given by `SWITCHY_*` env vars, for running without flags, e.g. in scripts and CI. Each is resolved by
+/// `resolve`, losing to its flag and winning over `[defaults]` of the config.
+#[derive(Default)]
+pub struct EnvVars {
+    pub yes: Option<bool>,
+    pub quiet: Option<bool>,
+    pub color: Option<ColorWhen>,
+    pub read_only: Option<bool>,
+    /// `Some(None)` is the system shell.
+    pub shell: Option<Option<CommandShell>>,
+    /// `Some(None)` is the main config.
+    pub profile: Option<Option<String>>,
+    /// The `SWITCHY_*` vars that are set, as `(name, value)`, for `--doctor`.
+    pub set: Vec<(String, String)>,
+    /// The unknown vars and unparsable values, which are ignored.
+    pub warnings: Vec<String>
+}
+
+/// Resolves a setting: the flag wins over the env var, which wins over the config.
+pub fn resolve<T>(flag: Option<T>, env_var: Option<T>, config: T) -> T {
+    flag.or(env_var).unwrap_or(config)
+}
+
+/// Returns `Some(true)` if a bool flag is passed, or `None` for leaving it to the env var and the config.
+pub fn flag(passed: bool) -> Option<bool> {
+    passed.then_some(true)
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" | "" => Ok(false),
+        _ => Err(t!(SetInvalidValue, name, value, t!(ExpectedBool)))
+    }
+}
+
+fn parse_color(name: &str, value: &str) -> Result<ColorWhen, String> {
+    ColorWhen::from_str(value, true).map_err(|_| t!(SetInvalidValue, name, value, t!(ExpectedColorWhen)))
+}
+
+/// Parses the name of a profile, which is a dir under `profiles`, so it can't be a path. Empty is the main config.
+pub fn parse_profile(name: &str, value: &str) -> Result<Option<String>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    if value.contains(['/', '\\']) || value == "." || value == ".." {
+        return Err(t!(SetInvalidValue, name, value, t!(ExpectedProfileName)));
+    }
+    Ok(Some(value.to_string()))
+}
+
+impl EnvVars {
+    /// Reads the `SWITCHY_*` vars of the process.
+    pub fn read() -> Self {
+        Self::from_vars(env::vars_os())
+    }
+
+    /// Reads the `SWITCHY_*` vars out of the given ones.
+    pub fn from_vars(vars: impl IntoIterator<Item = (OsString, OsString)>) -> Self {
+        let mut env_vars = EnvVars::default();
+        for (name, value) in vars {
+            let (name, value) = (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned());
+            if ! name.starts_with("SWITCHY_") {
+                continue;
+            }
+            let parsed = match name.as_str() {
+                "SWITCHY_YES" => parse_bool(&name, &value).map(|yes| env_vars.yes = Some(yes)),
+                "SWITCHY_QUIET" => parse_bool(&name, &value).map(|quiet| env_vars.quiet = Some(quiet)),
+                "SWITCHY_READONLY" => parse_bool(&name, &value).map(|read_only| env_vars.read_only = Some(read_only)),
+                "SWITCHY_COLOR" => parse_color(&name, &value).map(|color| env_vars.color = Some(color)),
+                "SWITCHY_PROFILE" => parse_profile(&name, &value).map(|profile| env_vars.profile = Some(profile)),
+                "SWITCHY_SHELL" => overlay::parse_shell(&name, &value)
+                    .map(|shell| env_vars.shell = Some(shell))
+                    .map_err(|err| err.to_string()),
+                _ if KNOWN_VARS.contains(&name.as_str()) => Ok(()),
+                _ => Err(t!(UnknownEnvVar, name, KNOWN_VARS.join(", ")))
+            };
+            match parsed {
+                Ok(()) => env_vars.set.push((name, value)),
+                Err(warning) => env_vars.warnings.push(warning)
+            }
+        }
+        env_vars.set.sort();
+        env_vars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_vars(vars: &[(&str, &str)]) -> EnvVars {
+        EnvVars::from_vars(vars.iter().map(|(name, value)| (OsString::from(name), OsString::from(value))))
+    }
+
+    #[test]
+    fn flags_win_over_env_vars_over_the_config() {
+        // (flag, env var, config) => resolved
+        let matrix = [
+            ((None, None, 1), 1),
+            ((None, Some(2), 1), 2),
+            ((Some(3), None, 1), 3),
+            ((Some(3), Some(2), 1), 3)
+        ];
+        for ((flag, env_var, config), resolved) in matrix {
+            assert_eq!(resolve(flag, env_var, config), resolved, "{:?}", (flag, env_var, config));
+        }
+    }
+
+    #[test]
+    fn unpassed_bool_flags_leave_it_to_the_env_var() {
+        assert!(resolve(flag(false), Some(true), false));
+        assert!(resolve(flag(true), Some(false), false));
+        assert!(! resolve(flag(false), None, false));
+    }
+
+    #[test]
+    fn an_empty_profile_is_the_main_config() {
+        assert_eq!(resolve(None, Some(Some("work".to_string())), None), Some("work".to_string()));
+        assert_eq!(resolve(Some(None), Some(Some("work".to_string())), None), None);
+        assert_eq!(resolve(Some(Some("home".to_string())), Some(Some("work".to_string())), None), Some("home".into()));
+    }
+
+    #[test]
+    fn reads_the_known_vars() {
+        let env_vars = from_vars(&[
+            ("SWITCHY_YES", "1"),
+            ("SWITCHY_QUIET", "off"),
+            ("SWITCHY_COLOR", "NEVER"),
+            ("SWITCHY_SHELL", "pwsh"),
+            ("SWITCHY_PROFILE", "work"),
+            ("SWITCHY_CONFIG_DIR", "/tmp/switchy"),
+            ("HOME", "/home/a")
+        ]);
+        assert_eq!(env_vars.yes, Some(true));
+        assert_eq!(env_vars.quiet, Some(false));
+        assert!(matches!(env_vars.color, Some(ColorWhen::Never)));
+        assert!(matches!(env_vars.shell, Some(Some(CommandShell::Pwsh))));
+        assert_eq!(env_vars.profile, Some(Some("work".to_string())));
+        assert_eq!(env_vars.read_only, None);
+        assert_eq!(env_vars.set.len(), 6);
+        assert!(env_vars.warnings.is_empty());
+    }
+
+    #[test]
+    fn ignores_unknown_vars_and_bad_values_with_warnings() {
+        let env_vars = from_vars(&[
+            ("SWITCHY_YSE", "1"),
+            ("SWITCHY_YES", "maybe"),
+            ("SWITCHY_PROFILE", "../work")
+        ]);
+        assert_eq!(env_vars.yes, None);
+        assert_eq!(env_vars.profile, None);
+        assert!(env_vars.set.is_empty());
+        assert_eq!(env_vars.warnings.len(), 3);
+        assert!(env_vars.warnings[0].contains("SWITCHY_YSE"), "{}", env_vars.warnings[0]);
+    }
+
+    #[test]
+    fn profiles_are_names() {
+        assert_eq!(parse_profile("--profile", "work"), Ok(Some("work".to_string())));
+        assert_eq!(parse_profile("--profile", ""), Ok(None));
+        for path in ["a/b", "a\\b", ".", ".."] {
+            assert!(parse_profile("--profile", path).is_err(), "{}", path);
+        }
+    }
+}
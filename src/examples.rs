@@ -0,0 +1,245 @@
+use colored::Colorize;
+
+/// An example command with what it does.
+pub struct Example {
+    pub command: &'static str,
+    pub description: &'static str
+}
+
+/// The examples of a task.
+pub struct ExampleGroup {
+    pub task: &'static str,
+    pub examples: &'static [Example]
+}
+
+const fn example(command: &'static str, description: &'static str) -> Example {
+    Example { command, description }
+}
+
+/// The examples printed by `switchy examples`, grouped by task. Every flag should appear in at least one of them.
+pub const EXAMPLES: &[ExampleGroup] = &[
+    ExampleGroup {
+        task: "Getting started",
+        examples: &[
+            example(
+                "switchy --add proxy --bool --on 'export ALL_PROXY=socks5://127.0.0.1:1080' --off 'unset ALL_PROXY'",
+                "Add a proxy item with the states `on` and `off`"
+            ),
+            example("switchy proxy on", "Switch it on, without any prompt"),
+            example("switchy proxy", "Toggle it, since it only has `on` and `off`"),
+            example("switchy", "Pick an item, then a state"),
+            example("switchy --list --verbose", "List the items with their commands")
+        ]
+    },
+    ExampleGroup {
+        task: "Adding items",
+        examples: &[
+            example("switchy --add proxy", "Walk through adding an item of any type"),
+            example("switchy --templates", "List the built-in recipes"),
+            example("switchy --add identity --template git-identity", "Add an item from a recipe"),
+            example("switchy --add proxy --template shell-proxy --replace", "Replace an existing item, keeping its history"),
+            example("switchy --add proxy --template shell-proxy --rename-to proxy-work", "Add it under another name if the name is taken"),
+            example("switchy --discover", "Find switchable things on this machine and add some of them"),
+            example("switchy --import-from makefile ./Makefile", "Propose items from targets like `proxy-on` and `proxy-off`"),
+            example("switchy --import-from script-dir ~/bin/toggles --yes", "Add items from scripts like `proxy_on.sh` and `proxy_off.sh`"),
+            example("switchy --import-from toggle-json ~/toggle.json", "Propose items from a JSON of names to the commands of their states"),
+            example("switchy --import ~/dotfiles/switchy.toml --yes", "Import items from another config, replacing existing ones"),
+            example("switchy --edit-config", "Edit the config file, and check it afterwards"),
+            example("switchy --schema > switchy.schema.json", "Write the JSON Schema of the config, for editors")
+        ]
+    },
+    ExampleGroup {
+        task: "Switching",
+        examples: &[
+            example("switchy --pick", "Always pick the item, even with a default item"),
+            example("switchy --numbers --no-header", "Pick with numbered entries, without the current state above"),
+            example("switchy --last", "Switch the last switched item again"),
+            example("switchy --toggle proxy", "Toggle the item between its two states"),
+            example("switchy --dry-run proxy on", "Show what switching would do"),
+            example("switchy --confirm proxy on", "Confirm each command before it runs"),
+            example("switchy --no-preflight nvm 20", "Switch without checking the program is on PATH, e.g. for a shell function"),
+            example("switchy proxy on --with 'export ALL_PROXY=http://127.0.0.1:8080'", "Run another command just this time"),
+            example("switchy proxy on --set shell=pwsh --set log=true", "Change settings just this time"),
+            example("switchy --quiet --force proxy on", "Switch without banners, even to a state unavailable now"),
+            example("switchy --batch", "Pick several items and their states, then switch them all"),
+            example("switchy --tui", "Switch items in a full-screen dashboard, built with the `tui` feature"),
+            example("switchy --all off --fail-fast", "Switch every item with an `off` state, stopping at the first failure"),
+            example("switchy --set-default proxy", "Switch `proxy` when no item is given"),
+            example("switchy --reset firewall", "Switch an item back to its `default_state`"),
+            example("switchy --reset-all", "Switch every item with a `default_state` back to it")
+        ]
+    },
+    ExampleGroup {
+        task: "Scheduling",
+        examples: &[
+            example("switchy proxy off --at 18:00", "Switch at 18:00, waiting in the terminal"),
+            example("switchy proxy off --in '2h 30m' --detach", "Switch in 2.5 hours, in a background process"),
+            example("switchy firewall off --for 10m", "Switch the firewall off, and back to its previous state after 10 minutes"),
+            example("switchy firewall off --for 1h --detach", "Switch back in a background process, unless it's switched again by then"),
+            example("switchy --watch", "Reapply the items whose `watch` file is changed, until Ctrl-C, built with the `watch` feature"),
+            example("*/10 * * * * switchy --watch --once --quiet", "Check the `watch` files every 10 minutes from cron")
+        ]
+    },
+    ExampleGroup {
+        task: "Snapshots and presets",
+        examples: &[
+            example("switchy --push before-demo", "Push the current states of all items"),
+            example("switchy --stack", "List the pushed snapshots"),
+            example("switchy --pop", "Switch back to the last pushed snapshot"),
+            example("switchy --pop --no-run", "Only record the states of the last snapshot"),
+            example("switchy --save-preset work --items proxy,identity", "Save the current states of some items as a preset"),
+            example("switchy --preset work", "Switch to a preset"),
+            example("switchy --list --presets", "List the presets"),
+            example("switchy --rename-preset work office", "Rename a preset"),
+            example("switchy --remove-preset office", "Remove a preset")
+        ]
+    },
+    ExampleGroup {
+        task: "Shells, keybindings and prompts",
+        examples: &[
+            example("eval \"$(switchy --shell-init bash)\"", "Define a shell function for each item, in the shell rc file"),
+            example("bind -x '\"\\C-xp\": switchy proxy'", "Toggle the proxy with Ctrl-X P in bash"),
+            example("PS1='$(switchy --prompt-status) $ '", "Show the items with `prompt = true` in the prompt"),
+            example("cd \"$(switchy --print-path projects work)\"", "Switch a dir item and change to its dir"),
+            example("switchy --current proxy", "Print the current state of an item"),
+            example("export JAVA_HOME=\"$(switchy --value jdk --require-value)\"", "Use the `value` of the current state, failing with code 2 if it has none"),
+            example("switchy --export-script --items proxy --shell pwsh", "Print a script applying the current states"),
+            example("switchy --escape printf '%s\\n' \"$HOME\"", "Quote args into one command for the shell commands run with")
+        ]
+    },
+    ExampleGroup {
+        task: "Editing states",
+        examples: &[
+            example("switchy --edit proxy", "Reorder the states of an item"),
+            example("switchy --edit proxy on --to 1", "Move a state to the top"),
+            example("switchy --copy-state proxy on proxy-work on", "Copy a state into another item"),
+            example("switchy --move-state proxy on proxy on-socks", "Rename a state"),
+            example("switchy --rename-state-all office work --dry-run", "List the items which renaming a state in every item would change"),
+            example("switchy --encrypt-state vpn on", "Encrypt the command of a state"),
+            example("switchy --detect identity", "Check the recorded state against the system"),
+            example("switchy --dry-run box-profile quiet", "Show the ssh invocation of an item with `ssh_host`")
+        ]
+    },
+    ExampleGroup {
+        task: "Listing and cleaning up",
+        examples: &[
+            example("switchy --list --sort recent", "List the recently switched items first"),
+            example("switchy --list proxy --verbose", "List only one item, with its commands"),
+            example("switchy --list proxy --format json", "Print an item as it is in the config, as JSON"),
+            example("switchy --sort name --save", "Sort the items by name in the config file"),
+            example("switchy --list --group-by tag --first-tag", "List the items under their first tag"),
+            example("switchy --list --all-machines --full", "Also list the items for other machines, without truncating"),
+            example("switchy --list --type command --tag net --in-state on", "List the command items tagged `net` which are `on`"),
+            example("switchy --has-state work", "Pick among the items with a `work` state"),
+            example("switchy --stats proxy --format json", "Print the switch stats of an item as JSON"),
+            example("switchy --log proxy", "Print the logged output of the last run of an item"),
+            example("switchy --prune --stale-days 30 --dry-run", "List the items and states which look unused"),
+            example("switchy --remove proxy --confirm-writes", "Remove an item into the trash, confirming the change of the config"),
+            example("switchy --trash", "List the removed items, kept for `trash_days` in `[defaults]`"),
+            example("switchy --restore-item proxy", "Put a removed item back"),
+            example("switchy --remove proxy --purge", "Remove an item outright, without the trash")
+        ]
+    },
+    ExampleGroup {
+        task: "Config files",
+        examples: &[
+            example("switchy --sync", "Commit, pull and push the config dir"),
+            example("switchy --snapshot", "Rewrite `state.json` for status bars"),
+            example("echo \"$CONFIG\" | switchy --config - proxy on", "Switch with a config from stdin, persisting nothing"),
+            example("switchy --read-only proxy on", "Switch without recording the new state"),
+            example("SWITCHY_YES=1 SWITCHY_QUIET=1 switchy --reset-all", "Answer yes and skip the banners for a whole CI job"),
+            example("switchy --profile work --list", "List the items of the `work` profile, which has a config of its own"),
+            example("switchy --force-write --set-default proxy", "Write the config even if it has unknown keys"),
+            example("switchy --no-onboarding --list", "List the items of a fresh config without the first-run onboarding"),
+            example("switchy --mangen /usr/local/share/man/man1", "Write the man page")
+        ]
+    },
+    ExampleGroup {
+        task: "Debugging",
+        examples: &[
+            example("switchy --doctor", "Check the config, the programs of the commands, the editor and the terminal"),
+            example("switchy --doctor --format json", "Print the checks as JSON, failing if any fails"),
+            example("switchy --debug --log-file proxy on", "Print the debug log, also into `switchy.log`"),
+            example("switchy --debug-exec --color never proxy on", "Print how each command is run, without colors"),
+            example("CLICOLOR_FORCE=1 switchy --examples | less -R", "Page through these examples, in color")
+        ]
+    }
+];
+
+/// Renders the examples for `switchy examples`, colorized.
+pub fn render() -> String {
+    EXAMPLES
+        .iter()
+        .map(|group| {
+            let examples: Vec<String> = group.examples
+                .iter()
+                .map(|example| format!("  {}\n  {} {}", format!("# {}", example.description).dimmed(), "$".purple().bold(), example.command))
+                .collect();
+            format!("{}\n{}", group.task.bold(), examples.join("\n\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Returns the examples shown after `--help`, which are those of the first task in plain text.
+pub fn get_help_examples() -> String {
+    let examples: Vec<String> = EXAMPLES[0].examples
+        .iter()
+        .map(|example| format!("  # {}\n  $ {}", example.description, example.command))
+        .collect();
+    format!("Examples:\n{}\n\nRun `switchy examples` for more.", examples.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, Parser};
+
+    use super::*;
+
+    /// Whether an example passes the flag, alone or with `=VALUE`, also within quotes or `$(...)`.
+    fn passes(example: &Example, flag: &str) -> bool {
+        example.command
+            .split(|char: char| char.is_whitespace() || "()\"'".contains(char))
+            .any(|word| word == flag || word.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+    }
+
+    #[test]
+    fn every_flag_has_an_example() {
+        let command = crate::Cli::command();
+        let missing: Vec<String> = command
+            .get_arguments()
+            .filter(|arg| ! arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .filter(|long| ! ["help", "version"].contains(long))
+            .map(|long| format!("--{}", long))
+            .filter(|flag| ! EXAMPLES.iter().flat_map(|group| group.examples).any(|example| passes(example, flag)))
+            .collect();
+        assert!(missing.is_empty(), "no example passes {}", missing.join(", "));
+    }
+
+    #[test]
+    fn every_example_parses() {
+        // Those in pipes, redirections or substitutions are left out, since they need a shell
+        let examples = EXAMPLES
+            .iter()
+            .flat_map(|group| group.examples)
+            .filter(|example| example.command.starts_with("switchy") && ! example.command.contains(['|', '>', '$']));
+        for example in examples {
+            let words = crate::editor::split_editor(example.command);
+            if let Err(err) = crate::Cli::try_parse_from(&words) {
+                panic!("`{}` doesn't parse: {}", example.command, err);
+            }
+        }
+    }
+
+    #[test]
+    fn renders_every_group() {
+        colored::control::set_override(false);
+        let rendered = render();
+        for group in EXAMPLES {
+            assert!(rendered.contains(group.task));
+        }
+        assert!(rendered.contains("  $ switchy proxy on"));
+        assert!(get_help_examples().ends_with("Run `switchy examples` for more."));
+    }
+}
@@ -0,0 +1,103 @@
+use clap::ValueEnum;
+
+use crate::{config::{ConfigItem, StateCommand}, git_config, shell::CommandShell};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ScriptShell {
+    Sh,
+    Pwsh
+}
+
+fn quote(shell: ScriptShell, text: &str) -> String {
+    match shell {
+        ScriptShell::Sh => format!("'{}'", text.replace('\'', r"'\''")),
+        ScriptShell::Pwsh => format!("'{}'", text.replace('\'', "''"))
+    }
+}
+
+/// A block of the script bringing an item to its current state. Commands which can't run in the script shell are
+/// kept for reference, but commented out with why.
+struct Block {
+    commands: Vec<String>,
+    skipped_because: Option<String>
+}
+
+fn run(commands: Vec<String>) -> Block {
+    Block { commands, skipped_because: None }
+}
+
+fn skip(commands: Vec<String>, reason: &str) -> Block {
+    Block { commands, skipped_because: Some(reason.to_string()) }
+}
+
+fn get_block(item: &ConfigItem, shell: ScriptShell) -> Block {
+    let current_state = item.get_current_state();
+    match item {
+        ConfigItem::CommandItem(command_item) => {
+            let Some(state) = command_item.states.iter().find(|state| state.name == current_state) else {
+                return skip(vec![], "the current state is missing");
+            };
+            let StateCommand::Plain(command) = &state.command else {
+                return skip(vec![], "the command is encrypted");
+            };
+            if command.trim().is_empty() {
+                return skip(vec![], "the state has no command");
+            }
+            // The login shell on the host runs the command, whatever the script shell is
+            if let Some(ssh) = command_item.get_ssh_target() {
+                let args: Vec<String> = ssh.get_args(command, false).iter().map(|arg| quote(shell, arg)).collect();
+                return run(vec![format!("ssh {}", args.join(" "))]);
+            }
+            // A command only runs in the kind of shell it's written for
+            match (command_item.options.shell, shell) {
+                (None, ScriptShell::Sh) | (Some(CommandShell::Powershell | CommandShell::Pwsh), ScriptShell::Pwsh) => {
+                    run(vec![command.clone()])
+                },
+                (None, ScriptShell::Pwsh) => skip(vec![command.clone()], "the command is for the system shell"),
+                (Some(_), ScriptShell::Sh) => skip(vec![command.clone()], "the command is for PowerShell")
+            }
+        },
+        ConfigItem::GitConfigItem(git_item) => {
+            let Some(state) = git_item.states.iter().find(|state| state.name == current_state) else {
+                return skip(vec![], "the current state is missing");
+            };
+            let git = if git_item.scope == git_config::GLOBAL_SCOPE {
+                "git config --global".to_string()
+            } else {
+                format!("git -C {} config", quote(shell, &git_item.scope))
+            };
+            run(state.values
+                .iter()
+                .map(|(key, value)| format!("{} {} {}", git, quote(shell, key), quote(shell, value)))
+                .collect())
+        },
+        ConfigItem::ExternalItem(_) => skip(vec![], "the item is switched by its plugin"),
+        _ => skip(vec![], "the item edits files instead of running commands")
+    }
+}
+
+/// Generates the script running the commands of the current state of each item, in order.
+pub fn generate(shell: ScriptShell, items: &[&ConfigItem]) -> String {
+    let mut lines = vec![];
+    match shell {
+        ScriptShell::Sh => lines.extend(["#!/bin/sh".to_string(), "set -e".to_string()]),
+        ScriptShell::Pwsh => lines.push("$ErrorActionPreference = 'Stop'".to_string())
+    }
+    lines.push("# Generated by `switchy --export-script`, bringing the items to their current states".to_string());
+
+    for item in items {
+        let block = get_block(item, shell);
+        lines.push(String::new());
+        lines.push(format!("# {} => {}", item.get_name(), item.get_current_state()));
+        match block.skipped_because {
+            Some(reason) => {
+                lines.push(format!("# Skipped, since {}", reason));
+                lines.extend(block.commands.iter().flat_map(|command| command.lines()).map(|line| format!("# {}", line)));
+            },
+            None => lines.extend(block.commands)
+        }
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
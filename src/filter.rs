@@ -0,0 +1,139 @@
+use crate::config::ConfigItem;
+
+/// The filters of `--type`, `--tag`, `--in-state` and `--has-state`, restricting the listed and picked items. An item
+/// matches if it matches every given filter.
+pub struct ItemFilter {
+    pub item_type: Option<String>,
+    pub tag: Option<String>,
+    pub in_state: Option<String>,
+    pub has_state: Option<String>
+}
+
+/// Folds a type name, so `key-value`, `key_value`, `KeyValue` and `KeyValueItem` are all the same type.
+fn fold_type(name: &str) -> String {
+    let folded = name.chars().filter(|char| ! matches!(char, '-' | '_')).collect::<String>().to_lowercase();
+    folded.strip_suffix("item").map(str::to_string).unwrap_or(folded)
+}
+
+impl ItemFilter {
+    /// Whether any filter is given.
+    pub fn is_active(&self) -> bool {
+        self.item_type.is_some() || self.tag.is_some() || self.in_state.is_some() || self.has_state.is_some()
+    }
+
+    /// Whether the item matches every given filter. Only the common accessors of items are used, so every item type
+    /// is filtered alike.
+    pub fn matches(&self, item: &ConfigItem) -> bool {
+        self.item_type.as_ref().is_none_or(|item_type| fold_type(item_type) == fold_type(&item.get_type_string()))
+            && self.tag.as_ref().is_none_or(|tag| item.get_options().tags.contains(tag))
+            && self.in_state.as_ref().is_none_or(|state| item.get_current_state() == state)
+            && self.has_state.as_ref().is_none_or(|state| item.get_state_names().contains(&state.as_str()))
+    }
+
+    /// Keeps the indexes of the matching items in the order.
+    pub fn apply(&self, items: &[ConfigItem], order: &[usize]) -> Vec<usize> {
+        order.iter().copied().filter(|&index| self.matches(&items[index])).collect()
+    }
+
+    /// Describes the given filters as flags, for telling which ones matched nothing.
+    pub fn describe(&self) -> String {
+        [
+            ("--type", &self.item_type),
+            ("--tag", &self.tag),
+            ("--in-state", &self.in_state),
+            ("--has-state", &self.has_state)
+        ]
+            .iter()
+            .filter_map(|(flag, value)| Some(format!("{} {}", flag, value.as_ref()?)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "on"
+tags = ["net"]
+states = [{ name = "on" }, { name = "off" }]
+
+[[items]]
+type = "KeyValueItem"
+name = "editor"
+current = "work"
+file = "editor.toml"
+format = "toml"
+key = "profile"
+tags = ["dev", "net"]
+states = [{ name = "work", value = "work" }, { name = "home", value = "home" }]
+
+[[items]]
+type = "DirItem"
+name = "project"
+current = "work"
+states = [{ name = "work", path = "~/work" }, { name = "on", path = "~/on" }]
+"#;
+
+    fn get_items() -> Vec<ConfigItem> {
+        toml::from_str::<Config>(CONFIG).unwrap().items
+    }
+
+    fn no_filters() -> ItemFilter {
+        ItemFilter { item_type: None, tag: None, in_state: None, has_state: None }
+    }
+
+    #[test]
+    fn no_filters_match_all() {
+        let items = get_items();
+        assert!(! no_filters().is_active());
+        assert_eq!(no_filters().apply(&items, &[2, 0, 1]), vec![2, 0, 1]);
+        assert_eq!(no_filters().describe(), "");
+    }
+
+    #[test]
+    fn types_are_folded() {
+        let items = get_items();
+        for item_type in ["key-value", "key_value", "KeyValue", "KeyValueItem", "keyvalueitem"] {
+            let filter = ItemFilter { item_type: Some(item_type.into()), ..no_filters() };
+            assert_eq!(filter.apply(&items, &[0, 1, 2]), vec![1], "{}", item_type);
+        }
+        let filter = ItemFilter { item_type: Some("git-config".into()), ..no_filters() };
+        assert!(filter.apply(&items, &[0, 1, 2]).is_empty());
+    }
+
+    #[test]
+    fn each_filter_checks_its_own_field() {
+        let items = get_items();
+        let by_tag = ItemFilter { tag: Some("net".into()), ..no_filters() };
+        assert_eq!(by_tag.apply(&items, &[0, 1, 2]), vec![0, 1]);
+        let in_state = ItemFilter { in_state: Some("work".into()), ..no_filters() };
+        assert_eq!(in_state.apply(&items, &[0, 1, 2]), vec![1, 2]);
+        let has_state = ItemFilter { has_state: Some("on".into()), ..no_filters() };
+        assert_eq!(has_state.apply(&items, &[0, 1, 2]), vec![0, 2]);
+    }
+
+    #[test]
+    fn filters_and_together() {
+        let items = get_items();
+        let filter = ItemFilter { tag: Some("net".into()), in_state: Some("work".into()), ..no_filters() };
+        assert_eq!(filter.apply(&items, &[0, 1, 2]), vec![1]);
+        let filter = ItemFilter { has_state: Some("on".into()), in_state: Some("work".into()), ..filter };
+        assert!(filter.apply(&items, &[0, 1, 2]).is_empty());
+        assert_eq!(filter.describe(), "--tag net --in-state work --has-state on");
+    }
+
+    #[test]
+    fn values_are_matched_exactly() {
+        let items = get_items();
+        let filter = ItemFilter { tag: Some("Net".into()), ..no_filters() };
+        assert!(filter.apply(&items, &[0, 1, 2]).is_empty());
+        let filter = ItemFilter { in_state: Some("wor".into()), ..no_filters() };
+        assert!(filter.apply(&items, &[0, 1, 2]).is_empty());
+    }
+}
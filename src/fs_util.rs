@@ -0,0 +1,139 @@
+use std::{ffi::OsString, fs, path::{Path, PathBuf}};
+
+use anyhow::{bail, Context, Result};
+use directories::BaseDirs;
+
+use crate::i18n::t;
+
+/// Expands a leading `~` to the home dir.
+pub fn expand_home(path: &str) -> PathBuf {
+    let home_dir = || BaseDirs::new().map(|base_dirs| base_dirs.home_dir().to_path_buf());
+
+    if path == "~" {
+        if let Some(home_dir) = home_dir() {
+            return home_dir;
+        }
+    }
+    else if let Some(rest) = path.strip_prefix("~/").or_else(|| path.strip_prefix("~\\")) {
+        if let Some(home_dir) = home_dir() {
+            return home_dir.join(rest);
+        }
+    }
+
+    PathBuf::from(path)
+}
+
+/// Appends a suffix to the file name, e.g. `config.ini` => `config.ini.bak`.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(suffix);
+    path.with_file_name(file_name)
+}
+
+/// Writes the file via a temporary file beside it, so readers never see partial content.
+pub fn write_atomic(path: &Path, content: &str) -> Result<()> {
+    write_atomic_with_mode(path, content, None)
+}
+
+/// Writes the file like `write_atomic`, with the permission bits set before it replaces the file, so it's never
+/// readable by more than the mode allows. Modes are ignored on Windows.
+pub fn write_atomic_with_mode(path: &Path, content: &str, mode: Option<u32>) -> Result<()> {
+    let temp_path = with_suffix(path, ".switchy.tmp");
+    fs::write(&temp_path, content)
+        .with_context(|| t!(FailedToWrite, temp_path.display()))?;
+    if let Some(mode) = mode {
+        set_mode(&temp_path, mode).with_context(|| t!(FailedToSetMode, temp_path.display()))?;
+    }
+    fs::rename(&temp_path, path)
+        .with_context(|| t!(FailedToReplace, path.display()))?;
+
+    Ok(())
+}
+
+/// Hashes the content with 64-bit FNV-1a into hex, which stays the same across builds unlike the std hasher.
+pub fn fingerprint(content: &[u8]) -> String {
+    let hash = content.iter().fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+    format!("{:016x}", hash)
+}
+
+/// Parses a mode like `0644` or `600`, in octal.
+pub fn parse_mode(mode: &str) -> Option<u32> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    if digits.is_empty() || digits.len() > 4 || ! digits.chars().all(|c| ('0' ..= '7').contains(&c)) {
+        return None;
+    }
+    u32::from_str_radix(digits, 8).ok()
+}
+
+/// Returns the permission bits of the file, or `None` if it's missing or on Windows.
+pub fn get_mode(path: &Path) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).ok().map(|metadata| metadata.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        None
+    }
+}
+
+fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    #[cfg(not(unix))]
+    log::debug!("Ignoring mode {:04o} of {}, since modes are Unix only", mode, path.display());
+    Ok(())
+}
+
+/// Copies the file to `<file>.bak` if it exists.
+pub fn backup(path: &Path) -> Result<()> {
+    if path.exists() {
+        let backup_path = with_suffix(path, ".bak");
+        fs::copy(path, &backup_path)
+            .with_context(|| t!(FailedToBackUp, path.display(), backup_path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Matches the name against a pattern where `*` matches any run of chars and `?` any one char.
+fn matches_wildcard(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0 ..= name.len()).any(|skipped| matches_wildcard(rest, &name[skipped ..])),
+        Some(('?', rest)) => ! name.is_empty() && matches_wildcard(rest, &name[1 ..]),
+        Some((char, rest)) => name.first() == Some(char) && matches_wildcard(rest, &name[1 ..])
+    }
+}
+
+/// Expands wildcards in the file name of the path to the matching files, sorted.
+/// A path without wildcards must exist.
+pub fn expand_wildcards(path: &Path) -> Result<Vec<PathBuf>> {
+    let file_name = path.file_name().map(|file_name| file_name.to_string_lossy().to_string()).unwrap_or_default();
+    if ! file_name.contains(['*', '?']) {
+        if ! path.is_file() {
+            bail!(t!(PathNotFound, path.display()));
+        }
+        return Ok(vec![ path.to_path_buf() ]);
+    }
+
+    let dir = path.parent().filter(|dir| ! dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let pattern: Vec<char> = file_name.chars().collect();
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().is_file())
+            .filter(|entry| matches_wildcard(&pattern, &entry.file_name().to_string_lossy().chars().collect::<Vec<_>>()))
+            .map(|entry| entry.path())
+            .collect(),
+        Err(_) => vec![]
+    };
+    paths.sort();
+
+    Ok(paths)
+}
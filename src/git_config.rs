@@ -0,0 +1,68 @@
+use std::process;
+
+use anyhow::{bail, Context, Result};
+
+use crate::{fs_util, i18n::t, shell};
+
+/// The scope meaning the global git config. Any other scope is the path of a repo.
+pub const GLOBAL_SCOPE: &str = "global";
+
+pub fn ensure_git() -> Result<()> {
+    if shell::find_executable("git").is_none() {
+        bail!(t!(ProgramNotFound, "git"));
+    }
+    Ok(())
+}
+
+fn git_config_command(scope: &str) -> process::Command {
+    let mut command = process::Command::new("git");
+    if scope == GLOBAL_SCOPE {
+        command.args(["config", "--global"]);
+    }
+    else {
+        command.arg("-C").arg(fs_util::expand_home(scope)).args(["config", "--local"]);
+    }
+    command
+}
+
+/// Describes the `git config` invocation of the scope, e.g. `git -C ~/work config --local`.
+pub fn describe_scope(scope: &str) -> String {
+    if scope == GLOBAL_SCOPE {
+        "git config --global".to_string()
+    }
+    else {
+        format!("git -C {} config --local", scope)
+    }
+}
+
+/// Reads the key from the git config of the scope, or `None` if it's unset.
+pub fn get_value(scope: &str, key: &str) -> Result<Option<String>> {
+    let output = git_config_command(scope)
+        .args(["--get", key])
+        .stderr(process::Stdio::null())
+        .output()
+        .context(t!(FailedToRunGit))?;
+
+    match output.status.code() {
+        Some(0) => Ok(Some(String::from_utf8_lossy(&output.stdout).trim_end_matches(['\r', '\n']).to_string())),
+        // Git exits with 1 when the key is unset
+        Some(1) => Ok(None),
+        _ => bail!(t!(GitGetFailed, describe_scope(scope), key))
+    }
+}
+
+pub fn set_value(scope: &str, key: &str, value: &str) -> Result<()> {
+    let output = git_config_command(scope)
+        .args([key, value])
+        .output()
+        .context(t!(FailedToRunGit))?;
+
+    if ! output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.trim() {
+            "" => bail!(t!(GitExited, output.status)),
+            message => bail!("{}", message)
+        }
+    }
+    Ok(())
+}
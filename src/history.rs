@@ -0,0 +1,74 @@
+use std::{fs::{self, OpenOptions}, io::Write, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+
+use crate::i18n::t;
+
+/// One switch recorded in the history log.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub time: DateTime<Local>,
+    pub item: String,
+    pub from: String,
+    pub to: String,
+    /// The command run instead of the stored one, by `--with`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_override: Option<String>,
+    /// Whether the current state is applied again, e.g. to repair what's drifted from it, instead of switched from.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub reapply: bool
+}
+
+fn is_false(value: &bool) -> bool {
+    ! value
+}
+
+/// The history log, stored as JSON lines in `history.jsonl` under the config dir.
+pub struct History {
+    /// `None` for ephemeral configs, whose switches aren't recorded.
+    file_path: Option<PathBuf>
+}
+
+impl History {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            file_path: Some(path.join("history.jsonl"))
+        }
+    }
+
+    /// A history recording nothing and reading as empty.
+    pub fn disabled() -> Self {
+        Self {
+            file_path: None
+        }
+    }
+
+    pub fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .context(t!(FailedToOpenHistory))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+
+        Ok(())
+    }
+
+    /// Reads all entries, oldest first. A missing log has no entries, and malformed lines are skipped.
+    pub fn read(&self) -> Result<Vec<HistoryEntry>> {
+        let Some(file_path) = self.file_path.as_ref().filter(|file_path| file_path.exists()) else {
+            return Ok(vec![]);
+        };
+
+        let history_str = fs::read_to_string(file_path).context(t!(FailedToReadHistory))?;
+        Ok(history_str
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+}
@@ -0,0 +1,1300 @@
+use std::{env, fmt::Display, sync::atomic::{AtomicU8, Ordering}};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Lang {
+    En,
+    ZhCn
+}
+
+impl Lang {
+    /// Parses a language tag or locale like `zh-CN` or `zh_CN.UTF-8`. Unsupported languages fall back to English.
+    pub fn parse(tag: &str) -> Lang {
+        if tag.to_lowercase().starts_with("zh") {
+            Lang::ZhCn
+        }
+        else {
+            Lang::En
+        }
+    }
+
+    /// The language of the locale env vars, in their POSIX precedence.
+    fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| ! value.is_empty())
+            .map(|value| Lang::parse(&value))
+            .unwrap_or(Lang::En)
+    }
+}
+
+/// 0 until set, then 1 + the index of the language.
+static LANG: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the language from the `lang` setting, or the locale env vars if it's unset.
+pub fn set_lang(tag: Option<&str>) {
+    let lang = tag.map(Lang::parse).unwrap_or_else(Lang::from_env);
+    LANG.store(lang as u8 + 1, Ordering::SeqCst);
+}
+
+pub fn get_lang() -> Lang {
+    match LANG.load(Ordering::SeqCst) {
+        0 => Lang::from_env(),
+        1 => Lang::En,
+        _ => Lang::ZhCn
+    }
+}
+
+/// A user-facing message. Its texts have `{}` placeholders filled in order, or `{0}`, `{1}`... by index,
+/// since languages order the parameters differently.
+#[derive(Clone, Copy)]
+pub enum Message {
+    Warning,
+    FailedToGetConfigDir,
+    EditorFailed,
+    ConfigInvalidAfterEdit,
+    OnlyStdinConfig,
+    NeedsConfigFile,
+    EphemeralConfig,
+    EphemeralNeedsItem,
+    ReadOnlyConfig,
+    ReadOnlyNotRecorded,
+    UnknownKeys,
+    IncludeFailed,
+    DuplicateIncludedItem,
+    ItemSource,
+    AlsoTagged,
+    Untagged,
+    UnknownOs,
+    UnknownColor,
+    InvalidMaxAge,
+    OverdueState,
+    OverdueItems,
+    ProgramNotFound,
+    UnavailableSuffix,
+    StateUnavailable,
+    StateUnavailableNow,
+    AvailableIfFailed,
+    AvailableIfTimedOut,
+    AvailableIfNotRun,
+    AvailableIfPassed,
+    NoAvailableState,
+    StateHeader,
+    AmbiguousItem,
+    AmbiguousState,
+    ItemNotForThisMachine,
+    NotForThisMachine,
+    NotGitRepo,
+    FailedToRunSync,
+    SyncStepFailed,
+    SyncFailed,
+    #[cfg_attr(feature = "encrypt", allow(dead_code))]
+    EncryptionUnsupported,
+    NotEncryptable,
+    NoCommandToOverride,
+    ItemWithoutStates,
+    InvalidMode,
+    SavedSort,
+    CurrentStateMissing,
+    Crashed,
+    CrashedCheckConfig,
+    CrashedDebugLog,
+    CrashedDebug,
+    CommandOverridden,
+    CommandToRun,
+    RunsIn,
+    RunCommand,
+    RunCommands,
+    RunCancelled,
+    AlreadyEncrypted,
+    EncryptedState,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    InvalidEncrypted,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    DecryptFailed,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    AskpassFailed,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    Passphrase,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    RepeatPassphrase,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    PassphrasesDiffer,
+    #[cfg_attr(not(feature = "encrypt"), allow(dead_code))]
+    EmptyPassphrase,
+    FailedToHandleCtrlC,
+    FunctionNameCollides,
+
+    ItemExists,
+    DefaultStateNotFound,
+    NoDefaultState,
+    SkippingAtDefault,
+    NothingToReset,
+    DoctorConfigDir,
+    DoctorNotCreated,
+    DoctorNotWritable,
+    DoctorConfig,
+    DoctorShell,
+    DoctorCommands,
+    DoctorMissingPrograms,
+    DoctorIdenticalCommands,
+    DoctorItem,
+    DoctorPlugin,
+    DoctorSync,
+    DoctorEditor,
+    DoctorPager,
+    DoctorTerminal,
+    DoctorNotTerminal,
+    DoctorDumbTerminal,
+    DoctorCompletions,
+    MatchedItem,
+    ContinueWithItem,
+    DoctorEnvVar,
+    SkippingStateExists,
+    RenamingStateIn,
+    IsCurrentState,
+    RenameStates,
+    RenamedStateIn,
+    NoValue,
+    ConfigChangedExternally,
+    StatesNotRecorded,
+    ReplaySwitches,
+    NotReplayed,
+    Verifying,
+    WouldVerify,
+    VerifyRetrying,
+    VerificationFailed,
+    VerificationFailedRolledBack,
+    RollingBack,
+    RollbackFailed,
+    DoctorEnvVarIgnored,
+    UnknownEnvVar,
+    DoctorFailed,
+    PreflightFailed,
+    FailedToReadTrash,
+    FailedToWriteTrash,
+    MovedToTrash,
+    TrashEmpty,
+    RemovedAt,
+    NotInTrash,
+    RemoveBeforeRestore,
+    RestoringItem,
+    ChooseOnConflict,
+    EditExisting,
+    AddUnderOtherName,
+    ReplaceExisting,
+    Abort,
+    NewItemName,
+    ConfirmReplaceItem,
+    ReplacingItem,
+    ItemExistsInOtherCase,
+    IdenticalCommands,
+    DidYouMean,
+    SetNotPair,
+    SetUnknownKey,
+    SetInvalidValue,
+    EffectiveSettings,
+    #[cfg_attr(feature = "watch", allow(dead_code))]
+    WatchUnsupported,
+    NoWatchedItems,
+    #[cfg_attr(not(feature = "watch"), allow(dead_code))]
+    Watching,
+    #[cfg_attr(not(feature = "watch"), allow(dead_code))]
+    FailedToWatch,
+    #[cfg_attr(not(feature = "watch"), allow(dead_code))]
+    FailedToWatchDir,
+    #[cfg_attr(not(feature = "watch"), allow(dead_code))]
+    WatchError,
+    WatchedFileChanged,
+    ReapplyFailed,
+    FailedToWriteFingerprints,
+    #[cfg_attr(feature = "tui", allow(dead_code))]
+    TuiUnsupported,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiNeedsTerminal,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiItems,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiStatesOf,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiHistoryOf,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiNoHistory,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiSwitched,
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    TuiKeys,
+    EmptyItemName,
+    EmptyStateName,
+    FetchNeedsProgram,
+    FetchFailed,
+    FetchFailedWith,
+    #[cfg_attr(feature = "http", allow(dead_code))]
+    HttpUnsupported,
+    ImportInvalid,
+    ImportDuplicate,
+    ImportedNothing,
+    PluginRunFailed,
+    PluginError,
+    PluginExited,
+    PluginKilled,
+    PluginInvalidReply,
+    PluginProtocol,
+    PluginNoStates,
+    PluginStatesFailed,
+    ExternalStates,
+    SkippedHiddenImport,
+    Importing,
+    WouldImport,
+    ImportConflict,
+    KeepExisting,
+    ReplaceWithImported,
+    ReplacingImported,
+    WouldReplaceImported,
+    ItemNotFound,
+    NoSuchState,
+    NoItemsYet,
+    NoItemsYetListed,
+    NoItemsMatched,
+    NoItemsYetHelp,
+    WelcomeWhat,
+    WelcomeConfig,
+    WelcomeSwitch,
+    OnboardingFirstItem,
+    OnboardingWizard,
+    OnboardingRecipe,
+    OnboardingLater,
+    OnboardingPickRecipe,
+    OnboardingItemName,
+    OnboardingShellInit,
+    OnboardingAddLine,
+    OnboardingDone,
+
+    RecipeNotFound,
+    AddingItem,
+    DiscardedItem,
+    RemovingItem,
+    ClearingDefault,
+    SettingDefault,
+    DefaultItemNotFound,
+
+    Probing,
+    SkippedCandidate,
+    FoundNothing,
+    MakefileTargets,
+    ScriptFiles,
+    ToggleJsonEntry,
+    InvalidToggleJson,
+    UnknownMigrationKind,
+    ItemsToAdd,
+    CandidateLabel,
+    AddedNothing,
+
+    NothingLogged,
+    NoSwitchesRecorded,
+    DetectedRecorded,
+    DetectedDiffering,
+    DetectedNone,
+    NoDetection,
+    DetectionFailed,
+    LastApplied,
+    NeverApplied,
+    NoPath,
+    WroteSnapshot,
+    WroteManPage,
+    ListingItems,
+    ListingMatchingItems,
+
+    PositionOutOfRange,
+    MovedState,
+    MissingPosition,
+    MissingStateToMove,
+    DiscardedOrder,
+    ReorderedStates,
+    StateTypesDiffer,
+    StateExists,
+    MovingOnlyState,
+    MovingCurrentState,
+    MovedStateTo,
+    CopiedState,
+
+    NothingToPrune,
+    PruneCandidates,
+    ToRemove,
+    RemovedNothing,
+    KeptCurrentState,
+    RemovingState,
+
+    ConfigChanges,
+    WriteChanges,
+    DiscardedChanges,
+
+    ItemsToSwitch,
+    Skipped,
+    NothingToSwitch,
+    PlannedSwitches,
+    Proceed,
+    BatchStopped,
+    SkippingNoState,
+    SkippedNoState,
+    SkippedAlreadyCurrent,
+    SummarySwitched,
+    SummaryWouldSwitch,
+    SummarySkipped,
+    SummaryNotRun,
+    SummaryRecorded,
+    NoItemHasState,
+    SwitchesFailed,
+    PushedSnapshot,
+    ItemsForPreset,
+    PresetWithoutItems,
+    ReplacePreset,
+    SavedPreset,
+    PresetNotFound,
+    PresetItemGone,
+    PresetStateGone,
+    PresetNothingToSwitch,
+    RemovedPreset,
+    PresetExists,
+    RenamedPreset,
+    NoPresetsYet,
+    ListingPresets,
+    MissingItem,
+    MissingState,
+    NoCommand,
+    StackEmpty,
+    RestoringSnapshot,
+    SnapshotItemGone,
+    SnapshotItemNew,
+    SnapshotItemCount,
+
+    NoLastItem,
+    LastItemsGone,
+    OnlyOneItem,
+    ScheduledInBackground,
+    CancelledSchedule,
+    SwitchingBack,
+    SwitchBackInBackground,
+    ConfirmCancelSwitchBack,
+    CancelledSwitchBack,
+    NothingToSwitchBack,
+    SwitchedMeanwhile,
+    RemovedMeanwhile,
+    ResetCurrent,
+
+    RunningHook,
+    HookFailed,
+    FailedToRunHook,
+
+    WouldSwitch,
+    Switching,
+    SwitchInterrupted,
+    WouldRun,
+    Running,
+    RunningStateCommand,
+    CommandExited,
+    CommandKilled,
+    RemoteCommandExited,
+    SshFailed,
+    RunsOnHost,
+    FailedToRead,
+    FailedToWrite,
+    NotADirectory,
+    BlockStateContent,
+    FailedToSetKey,
+    WouldSetKey,
+    SettingKey,
+    FailedToReplaceBlock,
+    WouldReplaceBlock,
+    ReplacingBlock,
+    SetGitKey,
+    FailedToSet,
+    FailedToSetGitKeys,
+    FailedToRender,
+    WouldRender,
+    Rendering,
+    NoChanges,
+    BinaryFileReplaced,
+    PostCommandFailed,
+
+    WizardItemType,
+    ItemTypeCommand,
+    ItemTypeKeyValue,
+    ItemTypeBlock,
+    ItemTypeGitConfig,
+    ItemTypeDir,
+    ItemTypeTemplate,
+    WizardStateNames,
+    WizardCurrentState,
+    WizardApplyNow,
+    WizardNoDefault,
+    WizardDefaultState,
+    WizardParamOfState,
+    WizardSaveOrder,
+    WizardStateToMove,
+    WizardMoving,
+    WizardMoveUp,
+    WizardMoveDown,
+    WizardDone,
+    WizardFilePath,
+    WizardFileFormat,
+    WizardKey,
+    WizardSectionKey,
+    WizardCreateMissing,
+    WizardStateValue,
+    WizardMarker,
+    WizardMarkerName,
+    WizardBlockContent,
+    WizardTypeInEditor,
+    WizardReadSnippet,
+    WizardSnippetPath,
+    WizardContentNotSaved,
+    WizardGitScope,
+    WizardSshCommand,
+    WizardDirPath,
+    WizardTemplatePath,
+    WizardOutputPath,
+    WizardPostCommand,
+    WizardVar,
+    WizardVarMissingEquals,
+    WizardAddingDefaultState,
+    WizardAddAnotherState,
+    WizardStateName,
+    WizardStateNameUsed,
+    WizardEmpty,
+    WizardStateCommand,
+    WizardStateCommandName,
+    WizardCommandNotSaved,
+    WizardNoCommand,
+    WizardWillRun,
+    WizardQuotingProblem,
+    QuotingEndsWithEscape,
+    QuotingUnclosed,
+    WizardTestCommand,
+    WizardEditFailedCommand,
+    WizardDiscardItem,
+
+    MarkerRepeated,
+    MarkerUnmatched,
+    KeyNotFoundInSection,
+    KeyNotFound,
+    EditorEmpty,
+    EditorNotFound,
+    FailedToRunEditor,
+    FailedToRunGit,
+    GitGetFailed,
+    GitExited,
+    TimeNotInTimezone,
+    InvalidTime,
+    InvalidDuration,
+    FailedToFindExe,
+    FailedToSpawnSwitch,
+    FailedToWriteSnapshot,
+    FailedToWriteCurrentSnapshot,
+    FailedToCreateLogDir,
+    FailedToRotateLog,
+    FailedToOpenLog,
+    FailedToReadLog,
+    FailedToOpenHistory,
+    FailedToReadHistory,
+    FailedToReadStack,
+    FailedToWriteStack,
+    RecipeNoStates,
+    RecipeInvalidItem,
+    FailedToSetMode,
+    FailedToReplace,
+    FailedToBackUp,
+    PathNotFound,
+    FailedToReadStdinConfig,
+    FailedToOpenLogFile,
+    FailedToSetUpLogging,
+    PlaceholderNotClosed,
+    NoValueForPlaceholders,
+    PruneItem,
+    PruneState,
+    LastSwitchedOn,
+    NeverSwitched,
+    NeverSwitchedTo,
+    ExpectedBool,
+    ExpectedTrueOrFalse,
+    ExpectedColorWhen,
+    ExpectedProfileName,
+    OnlyOnOs,
+    OnlyOnHostname,
+    OrSeparator
+}
+
+impl Message {
+    /// The English and Chinese texts.
+    fn get_texts(self) -> (&'static str, &'static str) {
+        match self {
+            Message::Warning => ("Warning:", "警告："),
+            Message::FailedToGetConfigDir => ("Failed to get config dir", "无法获取配置目录"),
+            Message::EditorFailed => ("The editor failed", "编辑器运行失败"),
+            Message::OnlyStdinConfig => (
+                "Only `--config -` is supported, reading the config from stdin",
+                "只支持 `--config -`，即从标准输入读取配置"
+            ),
+            Message::NeedsConfigFile => (
+                "This needs a config file, but `--config -` reads an ephemeral one from stdin",
+                "此操作需要配置文件，但 `--config -` 从标准输入读取的是临时配置"
+            ),
+            Message::EphemeralConfig => ("Ephemeral config, state not persisted", "临时配置，状态不会保存"),
+            Message::ReadOnlyConfig => (
+                "The config is read-only, because of `--read-only` or `SWITCHY_READONLY`",
+                "配置是只读的，因为使用了 `--read-only` 或 `SWITCHY_READONLY`"
+            ),
+            Message::ReadOnlyNotRecorded => ("Read-only config, state not recorded", "只读配置，状态未记录"),
+            Message::UnknownKeys => (
+                "The config has keys this version doesn't know, which writing would drop: {}. \
+                Upgrade switchy, or use `--force-write` to drop them",
+                "配置中有此版本不认识的键，写入会丢失它们：{}。请升级 switchy，或使用 `--force-write` 丢弃它们"
+            ),
+            Message::IncludeFailed => ("Failed to include {}", "无法包含 {}"),
+            Message::DuplicateIncludedItem => (
+                "Item {0} is in both {1} and {2}. Item names must be unique across included files",
+                "配置项 {0} 同时存在于 {1} 和 {2} 中。配置项名称在所有包含的文件中必须唯一"
+            ),
+            Message::ItemSource => ("from {}", "来自 {}"),
+            Message::AlsoTagged => ("also tagged {}", "也有标签 {}"),
+            Message::Untagged => ("(untagged)", "(无标签)"),
+            Message::UnknownOs => (
+                "Item {0} has unknown os `{1}` in only_on. Known ones are {2}",
+                "配置项 {0} 的 only_on 中有未知的 os `{1}`。已知的有 {2}"
+            ),
+            Message::UnknownColor => (
+                "State {1} of {0} has unknown color `{2}`, so it's shown in the default color",
+                "{0} 的状态 {1} 的颜色 `{2}` 未知，将以默认颜色显示"
+            ),
+            Message::InvalidMaxAge => (
+                "State {1} of {0} has max_age `{2}`, which isn't a duration like `2h`, so it's ignored",
+                "{0} 的状态 {1} 的 max_age `{2}` 不是 `2h` 这样的时长，将被忽略"
+            ),
+            Message::OverdueState => (
+                "Overdue: {0} has been current for {1}, longer than its max_age of {2}",
+                "已超时：{0} 已持续 {1}，超过了其 max_age {2}"
+            ),
+            Message::OverdueItems => (
+                "Overdue, current for longer than their max_age: {}",
+                "已超时，持续时间超过 max_age：{}"
+            ),
+            Message::ProgramNotFound => ("`{}` is not found on PATH", "在 PATH 中找不到 `{}`"),
+            Message::UnavailableSuffix => ("(unavailable: {})", "(不可用：{})"),
+            Message::StateUnavailable => (
+                "State {0} is unavailable: {1}. Pick another one",
+                "状态 {0} 不可用：{1}。请选择其他状态"
+            ),
+            Message::StateUnavailableNow => (
+                "State {0} of {1} is unavailable: {2}. Use --force to switch to it anyway",
+                "{1} 的状态 {0} 不可用：{2}。使用 --force 以强制切换"
+            ),
+            Message::AvailableIfFailed => ("`{}` failed", "`{}` 失败"),
+            Message::AvailableIfTimedOut => ("`{}` took over {} s", "`{}` 超过了 {} 秒"),
+            Message::AvailableIfNotRun => ("`{}` couldn't be run: {}", "无法运行 `{}`：{}"),
+            Message::AvailableIfPassed => ("State {} is available, since `{}` succeeded", "状态 {} 可用，因为 `{}` 成功了"),
+            Message::NoAvailableState => ("No state of {} is available now", "{} 当前没有可用的状态"),
+            Message::StateHeader => ("{0} [{1}] — currently: {2}", "{0} [{1}] — 当前：{2}"),
+            Message::AmbiguousItem => (
+                "Ambiguous name {0}, matching items {1}. Rename all but one with `--edit-config`",
+                "名称 {0} 有歧义，匹配配置项 {1}。请用 `--edit-config` 只保留一个该名称"
+            ),
+            Message::AmbiguousState => (
+                "Ambiguous state {0} of {1}, matching states {2}. Rename all but one with `--edit-config`",
+                "{1} 的状态 {0} 有歧义，匹配状态 {2}。请用 `--edit-config` 只保留一个该名称"
+            ),
+            Message::ItemNotForThisMachine => (
+                "Item {0} is only for {1}, but this machine has {2}",
+                "配置项 {0} 仅适用于 {1}，但本机为 {2}"
+            ),
+            Message::NotForThisMachine => ("only for {}", "仅适用于 {}"),
+            Message::NotGitRepo => (
+                "The config dir {} is not a git repo. Run `git init` there to sync it",
+                "配置目录 {} 不是 git 仓库。请在其中运行 `git init` 以同步"
+            ),
+            Message::FailedToRunSync => ("Failed to run `{}`", "无法运行 `{}`"),
+            Message::SyncStepFailed => ("`{}` failed: {}", "`{}` 失败：{}"),
+            Message::SyncFailed => ("Failed to sync the config dir: {}", "同步配置目录失败：{}"),
+            Message::EncryptionUnsupported => (
+                "Encrypted commands need switchy built with the `encrypt` feature",
+                "加密命令需要使用 `encrypt` 特性构建的 switchy"
+            ),
+            Message::NotEncryptable => (
+                "Config item {} of type {} has no commands to encrypt",
+                "{1} 类型的配置项 {0} 没有可加密的命令"
+            ),
+            Message::SavedSort => ("Reordering the items in the config", "正在重新排列配置中的配置项"),
+            Message::ItemWithoutStates => (
+                "Config item {} has no states. Add one, or remove the item",
+                "配置项 {} 没有状态。请添加一个，或删除该配置项"
+            ),
+            Message::InvalidMode => (
+                "Config item {} has an invalid mode `{}`, expected octal permission bits like `0644`",
+                "配置项 {} 的 mode `{}` 无效，应为八进制权限位，例如 `0644`"
+            ),
+            Message::CurrentStateMissing => (
+                "The current state {1} of {0} is not one of its states. Switch it to one first",
+                "{0} 的当前状态 {1} 不在其状态中。请先将其切换到某个状态"
+            ),
+            Message::Crashed => (
+                "switchy crashed. This is a bug, likely set off by something in the config",
+                "switchy 崩溃了。这是一个 bug，很可能由配置中的内容触发"
+            ),
+            Message::CrashedCheckConfig => (
+                "Check the config {} with `switchy --edit-config`, which validates it after editing",
+                "请使用 `switchy --edit-config` 检查配置 {}，编辑后会验证它"
+            ),
+            Message::CrashedDebugLog => (
+                "Run again with `--debug --log-file` to log what happens into {}, and report it with the log",
+                "使用 `--debug --log-file` 重新运行以将过程记录到 {}，并附上日志报告问题"
+            ),
+            Message::CrashedDebug => (
+                "Run again with `--debug` to see what happens, and report it",
+                "使用 `--debug` 重新运行以查看过程，并报告问题"
+            ),
+            Message::NoCommandToOverride => (
+                "Config item {} of type {} has no command to override with `--with`",
+                "{1} 类型的配置项 {0} 没有可用 `--with` 覆盖的命令"
+            ),
+            Message::CommandOverridden => (
+                "Running the command from `--with` instead of the one of {}",
+                "正在运行 `--with` 给出的命令，而非 {} 的命令"
+            ),
+            Message::CommandToRun => ("Command to run {} {}", "将要运行的命令 {} {}"),
+            Message::RunsIn => ("in {}, with {}", "在 {} 中，使用 {}"),
+            Message::RunCommand => ("Run it?", "是否运行？"),
+            Message::RunCommands => ("Run them?", "是否运行这些命令？"),
+            Message::RunCancelled => ("Cancelled, nothing is switched", "已取消，未切换任何内容"),
+            Message::AlreadyEncrypted => ("State {} of {} is already encrypted", "配置项 {1} 的状态 {0} 已加密"),
+            Message::EncryptedState => ("Encrypted state {} of {}", "已加密配置项 {1} 的状态 {0}"),
+            Message::InvalidEncrypted => ("The encrypted command is malformed", "加密命令格式错误"),
+            Message::DecryptFailed => (
+                "Failed to decrypt the command. Is the passphrase right?",
+                "无法解密命令。密码是否正确？"
+            ),
+            Message::AskpassFailed => ("The askpass command `{}` failed: {}", "askpass 命令 `{}` 失败：{}"),
+            Message::Passphrase => ("Passphrase", "密码"),
+            Message::RepeatPassphrase => ("Repeat the passphrase", "再次输入密码"),
+            Message::PassphrasesDiffer => ("The passphrases don't match", "两次输入的密码不一致"),
+            Message::EmptyPassphrase => ("The passphrase is empty", "密码为空"),
+            Message::EphemeralNeedsItem => (
+                "Give the item and the state with `--config -`, since stdin is taken by the config",
+                "使用 `--config -` 时请给出配置项和状态，因为标准输入已被配置占用"
+            ),
+            Message::ConfigInvalidAfterEdit => (
+                "The config is invalid. Run `--edit-config` again to fix it",
+                "配置无效。请再次运行 `--edit-config` 修复"
+            ),
+            Message::FailedToHandleCtrlC => ("Failed to handle Ctrl-C: {}", "无法处理 Ctrl-C：{}"),
+            Message::FunctionNameCollides => (
+                "Skipped config item '{}', whose function name collides with '{}'",
+                "已跳过配置项 '{}'，它的函数名与 '{}' 冲突"
+            ),
+
+            Message::ItemExists => ("Config item {} already exists", "配置项 {} 已存在"),
+            Message::DefaultStateNotFound => ("The default state {1} of {0} isn't one of its states", "{0} 的默认状态 {1} 不是它的状态之一"),
+            Message::NoDefaultState => ("Config item {} has no `default_state` to reset to", "配置项 {} 没有可重置到的 `default_state`"),
+            Message::SkippingAtDefault => ("Skipping {}, which is already in its default state {}", "跳过 {}，它已处于默认状态 {}"),
+            Message::NothingToReset => ("Every config item with a `default_state` is already in it", "所有设置了 `default_state` 的配置项都已处于该状态"),
+            Message::DoctorConfigDir => ("The config dir {} is writable", "配置目录 {} 可写"),
+            Message::DoctorNotCreated => ("It doesn't exist yet, and is created on the first run", "它还不存在，会在首次运行时创建"),
+            Message::DoctorNotWritable => ("Failed to write into it: {}", "无法写入：{}"),
+            Message::DoctorConfig => ("The config file {} is valid", "配置文件 {} 有效"),
+            Message::DoctorShell => ("The shell `{}` is found", "找到了 shell `{}`"),
+            Message::DoctorCommands => ("The programs of {} are found", "找到了 {} 的程序"),
+            Message::DoctorMissingPrograms => ("Not found on PATH: {}", "在 PATH 中找不到：{}"),
+            Message::DoctorItem => ("The config item {} is valid", "配置项 {} 有效"),
+            Message::DoctorIdenticalCommands => ("The states of {} run different commands", "{} 的各状态运行不同的命令"),
+            Message::DoctorPlugin => ("The plugin {0} of {1} lists its states", "{1} 的插件 {0} 可以列出其状态"),
+            Message::DoctorSync => ("The sync program `{}` is found", "找到了同步程序 `{}`"),
+            Message::DoctorEditor => ("The editor `{}` is found", "找到了编辑器 `{}`"),
+            Message::DoctorPager => ("The pager `{}` is found", "找到了分页器 `{}`"),
+            Message::DoctorTerminal => ("The terminal supports prompts", "终端支持交互提示"),
+            Message::DoctorNotTerminal => ("stdin or stderr isn't a terminal, so nothing can be prompted for", "stdin 或 stderr 不是终端，因此无法进行交互提示"),
+            Message::DoctorDumbTerminal => ("`TERM` is `dumb`, so prompts may look broken", "`TERM` 为 `dumb`，交互提示可能显示异常"),
+            Message::DoctorCompletions => ("Shell completions respond", "shell 补全可以响应"),
+            Message::Verifying => ("Verifying with {} {}", "正在验证 {} {}"),
+            Message::WouldVerify => ("Would verify with {} {}", "将验证 {} {}"),
+            Message::VerifyRetrying => ("Verification failed, retrying in {}ms ({}/{})", "验证失败，{} 毫秒后重试（{}/{}）"),
+            Message::VerificationFailed => (
+                "The switch command succeeded but the verification `{}` failed",
+                "切换命令已成功，但验证 `{}` 失败"
+            ),
+            Message::VerificationFailedRolledBack => (
+                "The switch command succeeded but the verification `{0}` failed, so {1} is rolled back to {2}",
+                "切换命令已成功，但验证 `{0}` 失败，因此 {1} 已回滚到 {2}"
+            ),
+            Message::RollingBack => ("Rolling back {} to {}", "正在将 {} 回滚到 {}"),
+            Message::RollbackFailed => (
+                "The verification `{0}` failed, and rolling {1} back to {2} failed too",
+                "验证 `{0}` 失败，并且将 {1} 回滚到 {2} 也失败了"
+            ),
+            Message::ConfigChangedExternally => (
+                "{} was changed by something else since switchy read it, so nothing is written to keep that change",
+                "{} 在 switchy 读取后被其他程序修改，为保留该修改，未写入任何内容"
+            ),
+            Message::StatesNotRecorded => ("The new states are not recorded", "新状态未被记录"),
+            Message::ReplaySwitches => ("Re-read the config and record the new states in it?", "重新读取配置并在其中记录新状态？"),
+            Message::NotReplayed => (
+                "Not recording {} => {}, since the re-read config no longer has that item or state",
+                "未记录 {} => {}，因为重新读取的配置中已没有该配置项或状态"
+            ),
+            Message::NoValue => ("The state {1} of {0} has no value", "{0} 的状态 {1} 没有值"),
+            Message::SkippingStateExists => ("Skipping {}, which already has a state {}", "跳过 {}，它已有状态 {}"),
+            Message::RenamingStateIn => ("Renaming state {} to {} in {} config item(s):", "将在 {2} 个配置项中把状态 {0} 重命名为 {1}："),
+            Message::IsCurrentState => ("(current)", "（当前）"),
+            Message::RenameStates => ("Rename the state in these items?", "在这些配置项中重命名该状态？"),
+            Message::RenamedStateIn => ("Renamed state {} to {} in {} config item(s)", "已在 {2} 个配置项中把状态 {0} 重命名为 {1}"),
+            Message::MatchedItem => ("Matched item: {}", "匹配到配置项：{}"),
+            Message::ContinueWithItem => ("Continue with it?", "继续使用此配置项？"),
+            Message::DoctorEnvVar => ("The env var `{}` is in effect", "环境变量 `{}` 已生效"),
+            Message::DoctorEnvVarIgnored => ("An env var is ignored", "有环境变量被忽略"),
+            Message::UnknownEnvVar => (
+                "Unknown env var `{}`, which is ignored. The known ones are {}",
+                "未知的环境变量 `{}`，已忽略。已知的环境变量有 {}"
+            ),
+            Message::DoctorFailed => ("{} check(s) failed", "{} 项检查失败"),
+            Message::PreflightFailed => (
+                "`{}` is not found on PATH. Install it or edit the state command, or pass `--no-preflight` if it's a shell function",
+                "在 PATH 中找不到 `{}`。请安装它或编辑状态命令；如果它是 shell 函数，请传入 `--no-preflight`"
+            ),
+            Message::FailedToReadTrash => ("Failed to read the trash", "读取回收站失败"),
+            Message::FailedToWriteTrash => ("Failed to write the trash", "写入回收站失败"),
+            Message::MovedToTrash => (
+                "Moved {0} to the trash. Run `switchy --restore-item {0}` to put it back",
+                "已将 {0} 移至回收站。运行 `switchy --restore-item {0}` 以恢复它"
+            ),
+            Message::TrashEmpty => ("The trash is empty", "回收站为空"),
+            Message::RemovedAt => ("removed at {}", "移除于 {}"),
+            Message::NotInTrash => ("Config item {} isn't in the trash", "配置项 {} 不在回收站中"),
+            Message::RemoveBeforeRestore => ("Remove or rename it before restoring", "请在恢复前移除或重命名它"),
+            Message::RestoringItem => ("Restoring config item {}", "正在恢复配置项 {}"),
+            Message::ChooseOnConflict => (
+                "Pass `--replace` to replace it, or `--rename-to NAME` to add it as NAME",
+                "传入 `--replace` 以替换它，或传入 `--rename-to NAME` 以将其添加为 NAME"
+            ),
+            Message::EditExisting => ("Edit the existing one", "编辑现有的"),
+            Message::AddUnderOtherName => ("Add under another name", "以其他名称添加"),
+            Message::ReplaceExisting => ("Replace the existing one", "替换现有的"),
+            Message::Abort => ("Abort", "中止"),
+            Message::NewItemName => ("Name of the new item", "新配置项的名称"),
+            Message::ConfirmReplaceItem => ("Replace {} with a new item?", "用新配置项替换 {}？"),
+            Message::ReplacingItem => ("Replacing config item {}", "正在替换配置项 {}"),
+            Message::SetNotPair => (
+                "`--set {}` is not `key=value`. The keys are {}",
+                "`--set {}` 不是 `key=value` 的形式。可用的键有 {}"
+            ),
+            Message::SetUnknownKey => (
+                "`{}` is not a key `--set` accepts. The keys are {}",
+                "`--set` 不接受键 `{}`。可用的键有 {}"
+            ),
+            Message::SetInvalidValue => ("Invalid value `{1}` of `{0}`: {2}", "`{0}` 的值 `{1}` 无效：{2}"),
+            Message::EffectiveSettings => (
+                "Settings: log = {}, notify = {}, shell = {}",
+                "设置：log = {}，notify = {}，shell = {}"
+            ),
+            Message::WatchUnsupported => (
+                "`--watch` needs switchy built with the `watch` feature, unless with `--once`",
+                "`--watch` 需要使用 `watch` 特性构建的 switchy，除非使用 `--once`"
+            ),
+            Message::NoWatchedItems => ("No item has `watch`", "没有配置项设置了 `watch`"),
+            Message::Watching => ("Watching {} file(s), press Ctrl-C to stop", "正在监视 {} 个文件，按 Ctrl-C 停止"),
+            Message::FailedToWatch => ("Failed to watch files", "无法监视文件"),
+            Message::FailedToWatchDir => ("Failed to watch {}", "无法监视 {}"),
+            Message::WatchError => ("Watching failed: {}", "监视失败：{}"),
+            Message::WatchedFileChanged => ("{0} changed, reapplying {1} => {2}", "{0} 已变化，正在重新应用 {1} => {2}"),
+            Message::ReapplyFailed => ("Failed to reapply {}: {}", "无法重新应用 {}：{}"),
+            Message::FailedToWriteFingerprints => ("Failed to write the fingerprints of watched files", "无法写入被监视文件的指纹"),
+            Message::TuiUnsupported => ("`--tui` needs switchy built with the `tui` feature", "`--tui` 需要使用 `tui` 特性构建的 switchy"),
+            Message::TuiNeedsTerminal => ("`--tui` needs stdout to be a terminal", "`--tui` 需要 stdout 是终端"),
+            Message::TuiItems => ("Items", "配置项"),
+            Message::TuiStatesOf => ("States of {}", "{} 的状态"),
+            Message::TuiHistoryOf => ("History of {}", "{} 的历史"),
+            Message::TuiNoHistory => ("Not switched yet", "尚未切换过"),
+            Message::TuiSwitched => ("Switched {} => {}", "已切换 {} => {}"),
+            Message::TuiKeys => (
+                "Enter switch  t toggle  / filter  h history  Tab pane  q quit",
+                "Enter 切换  t 轮换  / 筛选  h 历史  Tab 切换窗格  q 退出"
+            ),
+            Message::DidYouMean => ("Did you mean {}?", "你是不是想要 {}？"),
+            Message::IdenticalCommands => (
+                "States {1} and {2} of {0} run the same command",
+                "{0} 的状态 {1} 和 {2} 运行相同的命令"
+            ),
+            Message::ItemExistsInOtherCase => (
+                "Config item {} collides with {}. Set `case_sensitive_names = true` in [defaults] to have both",
+                "配置项 {} 与 {} 冲突。在 [defaults] 中设置 `case_sensitive_names = true` 以同时保留两者"
+            ),
+            Message::EmptyItemName => ("The item name is empty", "配置项名称为空"),
+            Message::EmptyStateName => ("The state name is empty", "状态名称为空"),
+            Message::FetchNeedsProgram => ("`{0}` is needed to fetch {1}, but it's not found on PATH", "获取 {1} 需要 `{0}`，但在 PATH 中找不到它"),
+            Message::FetchFailed => ("Failed to fetch {}", "获取 {} 失败"),
+            Message::FetchFailedWith => ("Failed to fetch {0}: {1}", "获取 {0} 失败：{1}"),
+            Message::HttpUnsupported => (
+                "Fetching {} needs switchy built with the `http` feature",
+                "获取 {} 需要使用 `http` 特性构建的 switchy"
+            ),
+            Message::ImportInvalid => ("{} is not a valid config to import from", "{} 不是可导入的有效配置"),
+            Message::ImportDuplicate => ("{0} has several items named {1}", "{0} 中有多个名为 {1} 的配置项"),
+            Message::ImportedNothing => ("{} has no items to import", "{} 中没有可导入的配置项"),
+            Message::PluginRunFailed => ("Failed to run plugin `{}`", "运行插件 `{}` 失败"),
+            Message::PluginError => ("Plugin `{}` failed at `{}`: {}", "插件 `{}` 执行 `{}` 失败：{}"),
+            Message::PluginExited => ("Plugin `{}` exited with code {2} at `{1}`", "插件 `{}` 执行 `{}` 时以代码 {} 退出"),
+            Message::PluginKilled => ("Plugin `{}` was killed at `{}`", "插件 `{}` 执行 `{}` 时被终止"),
+            Message::PluginInvalidReply => ("Plugin `{}` replied to `{}` with invalid JSON", "插件 `{}` 对 `{}` 的回复不是有效的 JSON"),
+            Message::PluginProtocol => (
+                "Plugin `{}` speaks protocol version {}, but this switchy speaks {}",
+                "插件 `{}` 使用协议版本 {}，但此 switchy 使用 {}"
+            ),
+            Message::PluginNoStates => ("Plugin `{}` listed no states", "插件 `{}` 没有列出任何状态"),
+            Message::PluginStatesFailed => (
+                "Failed to list the states of {} with its plugin, so only the recorded one is available: {}",
+                "无法通过插件列出 {} 的状态，因此只有记录的状态可用：{}"
+            ),
+            Message::ExternalStates => (
+                "The states of {} come from plugin `{}`, so they can't be edited in the config",
+                "{} 的状态来自插件 `{}`，因此无法在配置中编辑"
+            ),
+            Message::SkippedHiddenImport => (
+                "Skipped {}, since an item for other machines has the name",
+                "已跳过 {}，因为一个适用于其他机器的配置项使用了该名称"
+            ),
+            Message::Importing => ("Importing config item {}", "正在导入配置项 {}"),
+            Message::WouldImport => ("Would import config item {}", "将导入配置项 {}"),
+            Message::ImportConflict => ("What to do with {}?", "如何处理 {}？"),
+            Message::KeepExisting => ("Keep the existing one", "保留现有的"),
+            Message::ReplaceWithImported => ("Replace it with the imported one", "替换为导入的"),
+            Message::ReplacingImported => ("Replacing config item {} with the imported one", "正在将配置项 {} 替换为导入的"),
+            Message::WouldReplaceImported => ("Would replace config item {} with the imported one", "将把配置项 {} 替换为导入的"),
+            Message::ItemNotFound => ("Config item {} doesn't exist", "配置项 {} 不存在"),
+            Message::NoSuchState => ("Config item {} has no state {}", "配置项 {} 没有状态 {}"),
+            Message::NoItemsYet => ("No config items yet. Use `--add` to add one", "还没有配置项。使用 `--add` 添加一个"),
+            Message::NoItemsYetListed => ("No config items yet.", "还没有配置项。"),
+            Message::NoItemsMatched => ("No items matched {}.", "没有配置项匹配 {}。"),
+            Message::NoItemsYetHelp => (
+                "No config items yet. Use `--add` to add one. Use `--help` for more information",
+                "还没有配置项。使用 `--add` 添加一个。使用 `--help` 查看更多信息"
+            ),
+            Message::WelcomeWhat => (
+                "switchy switches things between states, like a proxy between `on` and `off`, by running the commands of the state.",
+                "switchy 通过运行状态的命令，在状态之间切换各种东西，例如在 `on` 和 `off` 之间切换代理。"
+            ),
+            Message::WelcomeConfig => (
+                "Each thing is an item with its states, kept in {}.",
+                "每个东西都是一个带有状态的配置项，保存在 {} 中。"
+            ),
+            Message::WelcomeSwitch => (
+                "Run `switchy` to pick an item and a state, or `switchy proxy on` to switch right away.",
+                "运行 `switchy` 选择配置项和状态，或运行 `switchy proxy on` 直接切换。"
+            ),
+            Message::OnboardingFirstItem => ("Add your first item?", "添加第一个配置项？"),
+            Message::OnboardingWizard => ("Walk through adding an item", "逐步添加配置项"),
+            Message::OnboardingRecipe => ("Start from a built-in recipe", "从内置模板开始"),
+            Message::OnboardingLater => ("Not now", "以后再说"),
+            Message::OnboardingPickRecipe => ("The recipe", "模板"),
+            Message::OnboardingItemName => ("The name of the item", "配置项的名称"),
+            Message::OnboardingShellInit => (
+                "Show the line defining shell functions like `proxy on` in {}?",
+                "显示在 {} 中定义 `proxy on` 等 shell 函数的那一行？"
+            ),
+            Message::OnboardingAddLine => ("Add this line to {}:", "将这一行添加到 {}："),
+            Message::OnboardingDone => (
+                "All set. Run `switchy --add <name>` to add more items, or `switchy --examples` for more.",
+                "准备就绪。运行 `switchy --add <name>` 添加更多配置项，或运行 `switchy --examples` 查看更多用法。"
+            ),
+
+            Message::RecipeNotFound => (
+                "Recipe {} doesn't exist. Use `--templates` to list the recipes",
+                "配方 {} 不存在。使用 `--templates` 列出所有配方"
+            ),
+            Message::AddingItem => ("Adding config item {}", "正在添加配置项 {}"),
+            Message::DiscardedItem => ("Discarded config item {}", "已放弃配置项 {}"),
+            Message::RemovingItem => ("Removing config item {}", "正在移除配置项 {}"),
+            Message::ClearingDefault => ("Clearing the default item", "正在清除默认配置项"),
+            Message::SettingDefault => ("Setting the default item to {}", "正在将默认配置项设为 {}"),
+            Message::DefaultItemNotFound => ("Default item {} doesn't exist", "默认配置项 {} 不存在"),
+
+            Message::Probing => ("Probing the system for switchable tools...", "正在探测系统中可切换的工具……"),
+            Message::SkippedCandidate => ("Skipped {}, since config item {} exists", "已跳过{}，因为配置项 {} 已存在"),
+            Message::FoundNothing => ("Found nothing to add.", "没有找到可添加的内容。"),
+            Message::MakefileTargets => ("Makefile targets {}", "Makefile 目标 {}"),
+            Message::ScriptFiles => ("scripts {}", "脚本 {}"),
+            Message::ToggleJsonEntry => ("toggle {}", "开关 {}"),
+            Message::InvalidToggleJson => ("{} is not valid JSON", "{} 不是有效的 JSON"),
+            Message::UnknownMigrationKind => (
+                "Unknown kind {}, expected `makefile`, `script-dir` or `toggle-json`",
+                "未知的类型 {}，应为 `makefile`、`script-dir` 或 `toggle-json`"
+            ),
+            Message::ItemsToAdd => ("Items to add", "要添加的配置项"),
+            Message::CandidateLabel => ("{} as {}, with {} state(s) from {}", "{}，作为 {}，含 {} 个状态，来自 {}"),
+            Message::AddedNothing => ("Added nothing", "未添加任何配置项"),
+
+            Message::NothingLogged => (
+                "Nothing is logged for {} yet. Set `log = true` on the item to enable logging.",
+                "{} 还没有日志。在配置项上设置 `log = true` 以启用日志。"
+            ),
+            Message::NoSwitchesRecorded => (
+                "No switches are recorded yet. Stats start accumulating from now.",
+                "还没有切换记录。统计将从现在开始累积。"
+            ),
+            Message::DetectedRecorded => ("Detected {} => {}, the recorded state", "检测到 {} => {}，与记录的状态一致"),
+            Message::DetectedDiffering => (
+                "Detected {} => {}, differing from the recorded state {}",
+                "检测到 {} => {}，与记录的状态 {} 不同"
+            ),
+            Message::DetectedNone => (
+                "No state of {} matches, the recorded state is {}",
+                "{} 没有匹配的状态，记录的状态是 {}"
+            ),
+            Message::NoDetection => (
+                "Config item {} of type {} doesn't support detection",
+                "{1} 类型的配置项 {0} 不支持检测"
+            ),
+            Message::DetectionFailed => ("Failed to detect the state: {}", "检测状态失败：{}"),
+            Message::LastApplied => ("{} was last applied on {}", "{} 上次应用于 {}"),
+            Message::NeverApplied => ("{} was never applied according to the history", "根据历史记录，{} 从未被应用过"),
+            Message::NoPath => ("Config item {} of type {} has no path", "{1} 类型的配置项 {0} 没有路径"),
+            Message::WroteSnapshot => ("Wrote the state snapshot", "已写入状态快照"),
+            Message::WroteManPage => ("Wrote {}", "已写入 {}"),
+            Message::ListingItems => ("Listing all {} config item(s):", "列出全部 {} 个配置项："),
+            Message::ListingMatchingItems => ("Listing {} of {} config item(s):", "列出 {1} 个配置项中的 {0} 个："),
+
+            Message::PositionOutOfRange => ("Position {} is not between 1 and {}", "位置 {} 不在 1 到 {} 之间"),
+            Message::MovedState => ("Moved state {1} of {0} to position {2}", "已将 {0} 的状态 {1} 移到位置 {2}"),
+            Message::MissingPosition => ("Use `--to POSITION` with the state to move", "请用 `--to POSITION` 指定状态要移到的位置"),
+            Message::MissingStateToMove => ("Use `--edit ITEM STATE` with `--to`", "`--to` 需要与 `--edit ITEM STATE` 一起使用"),
+            Message::DiscardedOrder => ("Discarded the new order", "已放弃新的顺序"),
+            Message::ReorderedStates => ("Reordered the states of {}", "已重新排列 {} 的状态"),
+            Message::StateTypesDiffer => (
+                "Config item {} is of type {}, but {} is of type {}, so their states differ",
+                "配置项 {} 的类型是 {}，而 {} 的类型是 {}，它们的状态不通用"
+            ),
+            Message::StateExists => (
+                "Config item {} already has state {}. Give a new name to copy it as",
+                "配置项 {} 已有状态 {}。请指定一个新名称"
+            ),
+            Message::MovingOnlyState => (
+                "Config item {} would have no states without {}",
+                "移走 {1} 后配置项 {0} 将没有状态"
+            ),
+            Message::MovingCurrentState => (
+                "{} is the current state of {}. Use `--force` to move it anyway",
+                "{} 是 {} 的当前状态。使用 `--force` 仍然移动它"
+            ),
+            Message::MovedStateTo => ("Moved {}.{} to {}.{}", "已将 {}.{} 移到 {}.{}"),
+            Message::CopiedState => ("Copied {}.{} to {}.{}", "已将 {}.{} 复制到 {}.{}"),
+
+            Message::NothingToPrune => ("Found nothing to prune.", "没有找到可清理的内容。"),
+            Message::PruneCandidates => ("Candidates to prune:", "可清理的内容："),
+            Message::ToRemove => ("To remove", "要移除的内容"),
+            Message::RemovedNothing => ("Removed nothing", "未移除任何内容"),
+            Message::KeptCurrentState => ("Kept {}, the current state of {}", "保留了 {1} 的当前状态 {0}"),
+            Message::RemovingState => ("Removing state {} of {}", "正在移除 {1} 的状态 {0}"),
+
+            Message::ConfigChanges => ("Changes to the config file:", "配置文件的变更："),
+            Message::WriteChanges => ("Write the changes?", "写入这些变更？"),
+            Message::DiscardedChanges => ("Discarded the changes", "已放弃这些变更"),
+
+            Message::ItemsToSwitch => ("Items to switch", "要切换的配置项"),
+            Message::Skipped => ("Skipped {}", "已跳过 {}"),
+            Message::NothingToSwitch => ("Nothing to switch.", "没有要切换的配置项。"),
+            Message::PlannedSwitches => ("Planned switches:", "计划的切换："),
+            Message::Proceed => ("Proceed?", "继续？"),
+            Message::BatchStopped => ("Batch switching stopped at a failure", "批量切换因失败而停止"),
+            Message::SkippingNoState => ("Skipping {}, which has no state {}", "跳过 {}，它没有状态 {}"),
+            Message::SkippedNoState => ("no such state", "没有此状态"),
+            Message::SkippedAlreadyCurrent => ("already current", "已是当前状态"),
+            Message::SummarySwitched => ("switched", "已切换"),
+            Message::SummaryWouldSwitch => ("would switch", "将切换"),
+            Message::SummarySkipped => ("skipped: {}", "已跳过：{}"),
+            Message::SummaryNotRun => ("not run", "未运行"),
+            Message::SummaryRecorded => ("recorded", "已记录"),
+            Message::NoItemHasState => ("No config item has state {}", "没有配置项有状态 {}"),
+            Message::PushedSnapshot => (
+                "Pushed a snapshot of {} item(s), {} on the stack now",
+                "已压入 {} 个配置项的快照，栈中现有 {} 个"
+            ),
+            Message::ItemsForPreset => ("Items to save in preset {}", "要保存到预设 {} 的配置项"),
+            Message::PresetWithoutItems => ("A preset needs at least one item", "预设至少需要一个配置项"),
+            Message::ReplacePreset => ("Preset {} exists. Replace it?", "预设 {} 已存在。是否替换？"),
+            Message::SavedPreset => ("Saving preset {} of {} item(s)", "正在保存包含 {1} 个配置项的预设 {0}"),
+            Message::PresetNotFound => ("Preset {} is not found", "找不到预设 {}"),
+            Message::PresetItemGone => ("Skipping {}, which is in preset {} but no longer in the config", "跳过 {}，它在预设 {} 中但已不在配置中"),
+            Message::PresetStateGone => (
+                "Skipping {}, since its state {} in preset {} no longer exists",
+                "跳过 {}，因为它在预设 {2} 中的状态 {1} 已不存在"
+            ),
+            Message::PresetNothingToSwitch => ("No item of preset {} can be switched", "预设 {} 中没有可切换的配置项"),
+            Message::RemovedPreset => ("Removing preset {}", "正在删除预设 {}"),
+            Message::PresetExists => ("Preset {} already exists", "预设 {} 已存在"),
+            Message::RenamedPreset => ("Renaming preset {} to {}", "正在将预设 {} 重命名为 {}"),
+            Message::NoPresetsYet => ("No presets yet. Save one with --save-preset NAME", "还没有预设。请使用 --save-preset NAME 保存"),
+            Message::ListingPresets => ("Listing all {} preset(s):", "列出全部 {} 个预设："),
+            Message::MissingItem => ("(no such item)", "(配置项不存在)"),
+            Message::MissingState => ("(no such state)", "(状态不存在)"),
+            Message::NoCommand => ("(no command)", "(无命令)"),
+            Message::StackEmpty => ("No snapshots are pushed. Push one with --push", "没有已压入的快照。请使用 --push 压入"),
+            Message::RestoringSnapshot => ("Restoring the snapshot of {}", "正在恢复快照 {}"),
+            Message::SnapshotItemGone => ("Skipping {}, which is no longer in the config", "跳过 {}，它已不在配置中"),
+            Message::SnapshotItemNew => ("Leaving {} as is, since it's not in the snapshot", "保持 {} 不变，因为它不在快照中"),
+            Message::SnapshotItemCount => ("{} item(s)", "{} 个配置项"),
+            Message::SwitchesFailed => ("{} switch(es) failed", "{} 个切换失败"),
+
+            Message::NoLastItem => (
+                "Nothing has been switched yet, so there is no last item",
+                "还没有切换过，所以没有上一个配置项"
+            ),
+            Message::LastItemsGone => ("None of the switched items exists any more", "切换过的配置项都已不存在"),
+            Message::OnlyOneItem => ("Only one config item, switching {}", "只有一个配置项，切换 {}"),
+            Message::ScheduledInBackground => (
+                "Scheduled switching {} => {} at {} in background process {}",
+                "已安排后台进程 {3} 在 {2} 切换 {0} => {1}"
+            ),
+            Message::CancelledSchedule => ("Cancelled the scheduled switch", "已取消计划的切换"),
+            Message::SwitchingBack => ("Switching {} back to {}", "将 {} 切换回 {}"),
+            Message::SwitchBackInBackground => (
+                "Scheduled switching {} back to {} at {} in background process {}",
+                "已安排后台进程 {3} 在 {2} 将 {0} 切换回 {1}"
+            ),
+            Message::ConfirmCancelSwitchBack => ("Cancel switching {} back to {}?", "取消将 {} 切换回 {}？"),
+            Message::CancelledSwitchBack => ("Cancelled switching back, {} stays {}", "已取消切换回去，{} 保持为 {}"),
+            Message::NothingToSwitchBack => (
+                "{} was already {}, so there's nothing to switch back to",
+                "{} 本来就是 {}，无需切换回去"
+            ),
+            Message::SwitchedMeanwhile => (
+                "{0} was switched to {1} in the meantime, so it's not switched from {2}",
+                "{0} 在此期间已被切换到 {1}，因此不再从 {2} 切换"
+            ),
+            Message::RemovedMeanwhile => ("{} was removed in the meantime, so it's not switched", "{} 在此期间已被移除，因此不再切换"),
+            Message::ResetCurrent => ("{} is the current state. Reset?", "{} 是当前状态。要重置吗？"),
+
+            Message::RunningHook => ("Running on_change hook {} {}", "正在运行 on_change 钩子 {} {}"),
+            Message::HookFailed => ("The on_change hook failed with {}", "on_change 钩子失败：{}"),
+            Message::FailedToRunHook => ("Failed to run the on_change hook: {}", "无法运行 on_change 钩子：{}"),
+
+            Message::WouldSwitch => ("Would switch {} => {}", "将切换 {} => {}"),
+            Message::Switching => ("Switching {} => {}", "正在切换 {} => {}"),
+            Message::SwitchInterrupted => ("Switching {} => {} was interrupted", "切换 {} => {} 被中断"),
+            Message::WouldRun => ("Would run {} {}", "将运行 {} {}"),
+            Message::Running => ("Running {} {}", "正在运行 {} {}"),
+            Message::RunningStateCommand => ("Running the state command…", "正在运行状态命令…"),
+            Message::CommandExited => ("Command exited with code {}", "命令退出，退出码为 {}"),
+            Message::CommandKilled => ("Command was terminated by signal", "命令被信号终止"),
+            Message::RemoteCommandExited => ("Command on {} exited with code {}", "{} 上的命令退出，退出码为 {}"),
+            Message::SshFailed => (
+                "ssh to {0} failed with code {1}, e.g. to connect or to authenticate, unless the command exited with it",
+                "到 {0} 的 ssh 失败，退出码为 {1}，例如无法连接或认证，除非是命令本身以此退出"
+            ),
+            Message::RunsOnHost => ("on {} over SSH", "通过 SSH 在 {} 上"),
+            Message::FailedToRead => ("Failed to read {}", "无法读取 {}"),
+            Message::FailedToWrite => ("Failed to write {}", "无法写入 {}"),
+            Message::NotADirectory => ("{} is not a directory", "{} 不是目录"),
+            Message::BlockStateContent => (
+                "State {} needs exactly one of `content` and `content_file`",
+                "状态 {} 需要 `content` 和 `content_file` 中的恰好一个"
+            ),
+            Message::FailedToSetKey => ("Failed to set the key in {}", "无法设置 {} 中的键"),
+            Message::WouldSetKey => ("Would set {} in {}", "将设置 {1} 中的 {0}"),
+            Message::SettingKey => ("Setting {} in {}", "正在设置 {1} 中的 {0}"),
+            Message::FailedToReplaceBlock => ("Failed to replace the block in {}", "无法替换 {} 中的块"),
+            Message::WouldReplaceBlock => ("Would replace block {} in {}", "将替换 {1} 中的块 {0}"),
+            Message::ReplacingBlock => ("Replacing block {} in {}", "正在替换 {1} 中的块 {0}"),
+            Message::SetGitKey => ("Set {} = {}", "已设置 {} = {}"),
+            Message::FailedToSet => ("Failed to set", "无法设置"),
+            Message::FailedToSetGitKeys => (
+                "Failed to set {} of {} key(s) with `{}`",
+                "用 `{2}` 设置的 {1} 个键中有 {0} 个失败"
+            ),
+            Message::FailedToRender => ("Failed to render {}", "无法渲染 {}"),
+            Message::WouldRender => ("Would render {} to {}", "将把 {} 渲染到 {}"),
+            Message::Rendering => ("Rendering {} to {}", "正在把 {} 渲染到 {}"),
+            Message::NoChanges => ("(no changes)", "（无变化）"),
+            Message::BinaryFileReplaced => (
+                "Binary file of {} bytes (hash {}) => {} bytes (hash {})",
+                "二进制文件 {} 字节（哈希 {}）=> {} 字节（哈希 {}）"
+            ),
+            Message::PostCommandFailed => ("The post_command failed", "post_command 失败"),
+            Message::WizardItemType => ("The type of the item", "配置项的类型"),
+            Message::ItemTypeCommand => ("Command item", "命令配置项"),
+            Message::ItemTypeKeyValue => ("Key-value item", "键值配置项"),
+            Message::ItemTypeBlock => ("Block item", "块配置项"),
+            Message::ItemTypeGitConfig => ("Git config item", "Git 配置项"),
+            Message::ItemTypeDir => ("Dir item", "目录配置项"),
+            Message::ItemTypeTemplate => ("Template item", "模板配置项"),
+            Message::WizardStateNames => ("State names", "状态名"),
+            Message::WizardCurrentState => ("Which state is currently active?", "当前处于哪个状态？"),
+            Message::WizardApplyNow => ("Apply {} now?", "现在应用 {} 吗？"),
+            Message::WizardNoDefault => ("None", "无"),
+            Message::WizardDefaultState => (
+                "Which state is the default, switched back to by `--reset`?",
+                "哪个是默认状态，即 `--reset` 切换回的状态？"
+            ),
+            Message::WizardParamOfState => ("{} of {}", "{1} 的{0}"),
+            Message::WizardSaveOrder => ("Save the order", "保存顺序"),
+            Message::WizardStateToMove => ("State to move, Esc to discard the new order", "要移动的状态，按 Esc 放弃新顺序"),
+            Message::WizardMoving => ("Moving {}", "正在移动 {}"),
+            Message::WizardMoveUp => ("Move up", "上移"),
+            Message::WizardMoveDown => ("Move down", "下移"),
+            Message::WizardDone => ("Done", "完成"),
+            Message::WizardFilePath => ("File path", "文件路径"),
+            Message::WizardFileFormat => ("The format of the file", "文件的格式"),
+            Message::WizardKey => ("Key", "键"),
+            Message::WizardSectionKey => (
+                "Key, prefixed by its section like `section.key`",
+                "键，以所在的节为前缀，例如 `section.key`"
+            ),
+            Message::WizardCreateMissing => ("Add the key if it's missing?", "键不存在时添加它吗？"),
+            Message::WizardStateValue => ("State value", "状态值"),
+            Message::WizardMarker => (
+                "Marker, as in `# switchy:<marker> begin`",
+                "标记，即 `# switchy:<marker> begin` 中的 marker"
+            ),
+            Message::WizardMarkerName => ("Marker", "标记"),
+            Message::WizardBlockContent => ("The content of the block", "块的内容"),
+            Message::WizardTypeInEditor => ("Type it in the editor", "在编辑器中输入"),
+            Message::WizardReadSnippet => ("Read it from a snippet file", "从片段文件读取"),
+            Message::WizardSnippetPath => ("Snippet file path", "片段文件路径"),
+            Message::WizardContentNotSaved => (
+                "The content is not saved, edit it again or discard the item",
+                "内容未保存，请重新编辑或丢弃此配置项"
+            ),
+            Message::WizardGitScope => ("Scope, `global` or the path of a repo", "作用域，`global` 或仓库的路径"),
+            Message::WizardSshCommand => ("core.sshCommand, empty to leave it as is", "core.sshCommand，留空则保持不变"),
+            Message::WizardDirPath => ("Directory path", "目录路径"),
+            Message::WizardTemplatePath => ("Template file path", "模板文件路径"),
+            Message::WizardOutputPath => ("Output file path", "输出文件路径"),
+            Message::WizardPostCommand => ("Command to run after rendering, empty for none", "渲染后运行的命令，留空则不运行"),
+            Message::WizardVar => ("Var like `key=value`, empty to finish", "形如 `key=value` 的变量，留空结束"),
+            Message::WizardVarMissingEquals => ("Missing `=` between the key and the value", "键和值之间缺少 `=`"),
+            Message::WizardAddingDefaultState => ("Adding default state", "正在添加默认状态"),
+            Message::WizardAddAnotherState => ("To add another state?", "要添加另一个状态吗？"),
+            Message::WizardStateName => ("State name", "状态名"),
+            Message::WizardStateNameUsed => ("State name '{}' is used", "状态名 '{}' 已被使用"),
+            Message::WizardEmpty => ("{} is empty, enter one or discard the item", "{}为空，请输入或丢弃此配置项"),
+            Message::WizardStateCommand => ("State command, or `!e` to edit it in the editor", "状态命令，或输入 `!e` 在编辑器中编辑"),
+            Message::WizardStateCommandName => ("State command", "状态命令"),
+            Message::WizardCommandNotSaved => ("The command is not saved", "命令未保存"),
+            Message::WizardNoCommand => (
+                "No command, so switching will only record the state. OK?",
+                "没有命令，切换时只会记录状态。确定吗？"
+            ),
+            Message::WizardWillRun => ("Will run:", "将运行："),
+            Message::WizardQuotingProblem => ("The command {}, so it may not run as meant", "该命令{}，可能不会按预期运行"),
+            Message::QuotingEndsWithEscape => ("ends with `{}`, which escapes nothing", "以 `{}` 结尾，但它没有转义任何字符"),
+            Message::QuotingUnclosed => ("has an unclosed `{}`", "有未闭合的 `{}`"),
+            Message::WizardTestCommand => ("Test this command now?", "现在测试此命令吗？"),
+            Message::WizardEditFailedCommand => ("The command failed. Edit it?", "命令失败。要编辑它吗？"),
+            Message::WizardDiscardItem => ("Discard this item?", "丢弃此配置项吗？"),
+            Message::MarkerRepeated => (
+                "Marker `{}` appears {} times, use `--force` to replace every block",
+                "标记 `{}` 出现了 {} 次，使用 `--force` 替换每个块"
+            ),
+            Message::MarkerUnmatched => ("Marker `{}` has no matching `{}`", "标记 `{}` 没有匹配的 `{}`"),
+            Message::KeyNotFoundInSection => ("Key `{}` is not found in section [{}]", "在节 [{1}] 中找不到键 `{0}`"),
+            Message::KeyNotFound => ("Key `{}` is not found", "找不到键 `{}`"),
+            Message::EditorEmpty => (
+                "The editor command is empty. Set VISUAL or EDITOR",
+                "编辑器命令为空。请设置 VISUAL 或 EDITOR"
+            ),
+            Message::EditorNotFound => (
+                "Editor `{}` is not found. Set VISUAL or EDITOR to an installed editor",
+                "找不到编辑器 `{}`。请将 VISUAL 或 EDITOR 设置为已安装的编辑器"
+            ),
+            Message::FailedToRunEditor => ("Failed to run editor `{}`", "无法运行编辑器 `{}`"),
+            Message::FailedToRunGit => ("Failed to run git", "无法运行 git"),
+            Message::GitGetFailed => ("`{} --get {}` failed", "`{} --get {}` 失败"),
+            Message::GitExited => ("git exited with {}", "git 已退出：{}"),
+            Message::TimeNotInTimezone => ("The time doesn't exist today in the local timezone", "该时间今天在本地时区中不存在"),
+            Message::InvalidTime => (
+                "Invalid time '{}', expected e.g. `18:00`, an RFC 3339 date time or `2h`",
+                "无效的时间 '{}'，应为例如 `18:00`、RFC 3339 日期时间或 `2h`"
+            ),
+            Message::InvalidDuration => ("Invalid duration '{}', expected e.g. `2h 30m`", "无效的时长 '{}'，应为例如 `2h 30m`"),
+            Message::FailedToFindExe => ("Failed to find the switchy executable", "找不到 switchy 可执行文件"),
+            Message::FailedToSpawnSwitch => ("Failed to spawn the background switch", "无法启动后台切换"),
+            Message::FailedToWriteSnapshot => ("Failed to write the state snapshot", "无法写入状态快照"),
+            Message::FailedToWriteCurrentSnapshot => ("Failed to write the current state snapshot", "无法写入当前状态快照"),
+            Message::FailedToCreateLogDir => ("Failed to create the log dir", "无法创建日志目录"),
+            Message::FailedToRotateLog => ("Failed to rotate the log", "无法轮转日志"),
+            Message::FailedToOpenLog => ("Failed to open the log", "无法打开日志"),
+            Message::FailedToReadLog => ("Failed to read the log", "无法读取日志"),
+            Message::FailedToOpenHistory => ("Failed to open the history log", "无法打开历史日志"),
+            Message::FailedToReadHistory => ("Failed to read the history log", "无法读取历史日志"),
+            Message::FailedToReadStack => ("Failed to read the snapshot stack", "无法读取快照栈"),
+            Message::FailedToWriteStack => ("Failed to write the snapshot stack", "无法写入快照栈"),
+            Message::RecipeNoStates => ("Recipe {} generated no states", "配方 {} 没有生成任何状态"),
+            Message::RecipeInvalidItem => ("Recipe {} generated an invalid item", "配方 {} 生成了无效的配置项"),
+            Message::FailedToSetMode => ("Failed to set the mode of {}", "无法设置 {} 的权限"),
+            Message::FailedToReplace => ("Failed to replace {}", "无法替换 {}"),
+            Message::FailedToBackUp => ("Failed to back up {} to {}", "无法将 {} 备份到 {}"),
+            Message::PathNotFound => ("{} doesn't exist", "{} 不存在"),
+            Message::FailedToReadStdinConfig => ("Failed to read the config from stdin", "无法从 stdin 读取配置"),
+            Message::FailedToOpenLogFile => ("Failed to open the log file {}", "无法打开日志文件 {}"),
+            Message::FailedToSetUpLogging => ("Failed to set up logging", "无法设置日志"),
+            Message::PlaceholderNotClosed => ("Placeholder `{}` is not closed by `}}`", "占位符 `{}` 没有用 `}}` 闭合"),
+            Message::NoValueForPlaceholders => ("No value for placeholder(s): {}", "这些占位符没有值：{}"),
+            Message::PruneItem => ("item {}: {}", "配置项 {}：{}"),
+            Message::PruneState => ("state {} of {}: {}", "{1} 的状态 {0}：{2}"),
+            Message::LastSwitchedOn => ("last switched on {}", "上次切换于 {}"),
+            Message::NeverSwitched => ("never switched", "从未切换"),
+            Message::NeverSwitchedTo => ("never switched to", "从未切换到"),
+            Message::ExpectedBool => ("expected `1` or `0`", "应为 `1` 或 `0`"),
+            Message::ExpectedTrueOrFalse => ("expected `true` or `false`", "应为 `true` 或 `false`"),
+            Message::ExpectedColorWhen => ("expected `auto`, `always` or `never`", "应为 `auto`、`always` 或 `never`"),
+            Message::ExpectedProfileName => ("expected a name, not a path", "应为名称，而不是路径"),
+            Message::OnlyOnOs => ("os {}", "系统 {}"),
+            Message::OnlyOnHostname => ("hostname {}", "主机名 {}"),
+            Message::OrSeparator => (" or ", " 或 ")
+        }
+    }
+
+    pub fn get_text(self, lang: Lang) -> &'static str {
+        let (en, zh_cn) = self.get_texts();
+        match lang {
+            Lang::En => en,
+            Lang::ZhCn => zh_cn
+        }
+    }
+}
+
+/// Fills the placeholders of the message text in the current language with the args.
+/// Each arg is formatted on its own, so its color styling stays on it.
+pub fn format_message(message: Message, args: &[&dyn Display]) -> String {
+    let text = message.get_text(get_lang());
+    let mut formatted = String::with_capacity(text.len());
+    let mut next_index = 0;
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        formatted.push_str(&rest[.. start]);
+        let after_start = &rest[start + 1 ..];
+        let index = after_start.find('}').and_then(|end| match &after_start[.. end] {
+            "" => Some((next_index, end)),
+            index => index.parse().ok().map(|index| (index, end))
+        });
+        match index {
+            Some((index, end)) => {
+                if let Some(arg) = args.get(index) {
+                    formatted.push_str(&arg.to_string());
+                }
+                next_index = index + 1;
+                rest = &after_start[end + 1 ..];
+            },
+            None => {
+                formatted.push('{');
+                rest = after_start;
+            }
+        }
+    }
+    formatted.push_str(rest);
+
+    formatted
+}
+
+/// Formats a message in the current language, like `t!(NoSuchState, item_name.cyan(), state.yellow())`.
+macro_rules! t {
+    ($message:ident $(, $arg:expr)* $(,)?) => {
+        $crate::i18n::format_message($crate::i18n::Message::$message, &[$(&$arg as &dyn std::fmt::Display),*])
+    };
+}
+pub(crate) use t;
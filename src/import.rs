@@ -0,0 +1,145 @@
+use std::{fs, process::{Command, Stdio}};
+
+use anyhow::{bail, Context, Result};
+
+use crate::{config::{self, ConfigItem}, fs_util, i18n::t, shell};
+
+/// Where `--import` fetches items from.
+pub enum ImportSource<'a> {
+    /// An http or https URL, fetched with the `http` feature.
+    Url(&'a str),
+    /// A `[user@]host:path` file, read with `ssh host cat path`.
+    Ssh { host: &'a str, path: &'a str },
+    File(&'a str)
+}
+
+impl<'a> ImportSource<'a> {
+    pub fn parse(source: &'a str) -> ImportSource<'a> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            return ImportSource::Url(source);
+        }
+        // A colon before any slash, like scp, except in Windows drive letters like `C:\`
+        match source.split_once(':') {
+            Some((host, path)) if host.len() > 1 && ! host.contains(['/', '\\']) => ImportSource::Ssh { host, path },
+            _ => ImportSource::File(source)
+        }
+    }
+}
+
+/// Runs the program and returns its stdout, failing with its stderr.
+fn run_fetch(program: &str, args: &[&str], source: &str) -> Result<String> {
+    if shell::find_executable(program).is_none() {
+        bail!(t!(FetchNeedsProgram, program, source));
+    }
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| t!(FetchFailed, source))?;
+    if ! output.status.success() {
+        bail!(t!(FetchFailedWith, source, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    String::from_utf8(output.stdout).with_context(|| t!(FetchFailed, source))
+}
+
+/// Fetches the URL, following redirects. HTTP errors fail instead of returning the error page.
+#[cfg(feature = "http")]
+fn fetch_url(url: &str) -> Result<String> {
+    let response = reqwest::blocking::get(url).with_context(|| t!(FetchFailed, url))?;
+    let status = response.status();
+    if ! status.is_success() {
+        bail!(t!(FetchFailedWith, url, status));
+    }
+    response.text().with_context(|| t!(FetchFailed, url))
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_url(url: &str) -> Result<String> {
+    bail!(t!(HttpUnsupported, url));
+}
+
+/// Fetches the TOML from the source.
+pub fn fetch(source: &str) -> Result<String> {
+    match ImportSource::parse(source) {
+        ImportSource::Url(url) => fetch_url(url),
+        // The path is expanded by the remote shell, so `~` works
+        ImportSource::Ssh { host, path } => run_fetch("ssh", &["-o", "BatchMode=yes", "--", host, "cat", path], source),
+        ImportSource::File(path) => {
+            let path = fs_util::expand_home(path);
+            fs::read_to_string(&path).with_context(|| t!(FetchFailed, path.display()))
+        }
+    }
+}
+
+/// Fetches and parses the items of a config or included file. Fails unless every item is valid, before anything is
+/// imported.
+pub fn fetch_items(source: &str) -> Result<Vec<ConfigItem>> {
+    let content = fetch(source)?;
+    let items = config::parse_items(&content).with_context(|| t!(ImportInvalid, source))?;
+    for (index, item) in items.iter().enumerate() {
+        if items[.. index].iter().any(|other| other.get_name() == item.get_name()) {
+            bail!(t!(ImportDuplicate, source, item.get_name()));
+        }
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        assert!(matches!(ImportSource::parse("https://example.com/a.toml"), ImportSource::Url(_)));
+        assert!(matches!(ImportSource::parse("http://example.com/a.toml"), ImportSource::Url(_)));
+        assert!(matches!(
+            ImportSource::parse("me@box:~/.config/switchy/config.toml"),
+            ImportSource::Ssh { host: "me@box", path: "~/.config/switchy/config.toml" }
+        ));
+        assert!(matches!(ImportSource::parse("C:\\config.toml"), ImportSource::File(_)));
+        assert!(matches!(ImportSource::parse("./a:b.toml"), ImportSource::File(_)));
+        assert!(matches!(ImportSource::parse("config.toml"), ImportSource::File(_)));
+    }
+
+    #[cfg(not(feature = "http"))]
+    #[test]
+    fn urls_need_the_http_feature() {
+        let err = fetch("https://example.com/a.toml").unwrap_err().to_string();
+        assert!(err.contains("`http`"), "{}", err);
+    }
+
+    /// Serves one request with the response on a local port, returning the URL.
+    #[cfg(feature = "http")]
+    fn serve_once(response: String) -> String {
+        use std::{io::{Read, Write}, net::TcpListener, thread};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/config.toml", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        url
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn fetches_urls() {
+        let body = "[[items]]\ntype = \"CommandItem\"\nname = \"proxy\"\ncurrent = \"on\"\nstates = [{ name = \"on\" }]\n";
+        let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+        let url = serve_once(response);
+        let items = fetch_items(&url).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].get_name(), "proxy");
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn http_errors_fail() {
+        let url = serve_once("HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\nConnection: close\r\n\r\nnot found".into());
+        let err = fetch(&url).unwrap_err().to_string();
+        assert!(err.contains("404"), "{}", err);
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// The PID of the running child leading its own process group, or 0.
+static CHILD_GROUP: AtomicU32 = AtomicU32::new(0);
+
+/// Records a Ctrl-C or termination, for long waits and running commands to notice.
+pub fn set_interrupted() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Forgets a Ctrl-C which was handled, e.g. by asking whether to stop waiting.
+pub fn clear_interrupted() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+pub fn set_child_group(pid: Option<u32>) {
+    CHILD_GROUP.store(pid.unwrap_or(0), Ordering::SeqCst);
+}
+
+/// Sends SIGINT to the process group of the running child, which doesn't get the Ctrl-C of the terminal itself.
+/// Children sharing the terminal get it directly, as do all processes of the console on Windows.
+pub fn forward_to_child() {
+    #[cfg(unix)]
+    {
+        let pid = CHILD_GROUP.load(Ordering::SeqCst);
+        if pid != 0 {
+            // SAFETY: kill is async-signal-safe and has no memory effects
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGINT);
+            }
+        }
+    }
+}
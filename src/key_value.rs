@@ -0,0 +1,174 @@
+use anyhow::{bail, Result};
+use serde::{Serialize, Deserialize};
+
+use crate::i18n::t;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyValueFormat {
+    Ini,
+    Toml,
+    Env
+}
+
+impl KeyValueFormat {
+    pub const ALL: [KeyValueFormat; 3] = [KeyValueFormat::Ini, KeyValueFormat::Toml, KeyValueFormat::Env];
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            KeyValueFormat::Ini => "ini",
+            KeyValueFormat::Toml => "toml",
+            KeyValueFormat::Env => "env"
+        }
+    }
+}
+
+/// The result of setting a key, with the changed line after the edit for messages.
+pub struct KeyValueEdit {
+    pub content: String,
+    pub after: String
+}
+
+/// Splits `section.key` into the section and the key. Env files have no sections.
+fn split_key_path(format: KeyValueFormat, key_path: &str) -> (Option<&str>, &str) {
+    match format {
+        KeyValueFormat::Env => (None, key_path),
+        _ => match key_path.rsplit_once('.') {
+            Some((section, key)) => (Some(section), key),
+            None => (None, key_path)
+        }
+    }
+}
+
+/// Returns the section name if the line is a section header. TOML array tables never match a section.
+fn parse_section_header(format: KeyValueFormat, line: &str) -> Option<String> {
+    let line = line.trim();
+    if format == KeyValueFormat::Env || ! line.starts_with('[') || ! line.ends_with(']') {
+        return None;
+    }
+    if line.starts_with("[[") {
+        return Some(line.to_string());
+    }
+    Some(line[1 .. line.len() - 1].trim().to_string())
+}
+
+/// Returns the byte offset where the value starts if the line assigns the key.
+fn find_value_start(format: KeyValueFormat, line: &str, key: &str) -> Option<usize> {
+    let indent = line.len() - line.trim_start().len();
+    let mut rest = &line[indent ..];
+    if rest.starts_with('#') || rest.starts_with(';') {
+        return None;
+    }
+    if format == KeyValueFormat::Env {
+        rest = rest.strip_prefix("export ").unwrap_or(rest);
+    }
+
+    let separator = match format {
+        KeyValueFormat::Ini => rest.find(['=', ':'])?,
+        _ => rest.find('=')?
+    };
+    if rest[.. separator].trim() != key {
+        return None;
+    }
+
+    let after_separator = &rest[separator + 1 ..];
+    let spaces = after_separator.len() - after_separator.trim_start_matches([' ', '\t']).len();
+    Some(line.len() - after_separator.len() + spaces)
+}
+
+/// Formats the value for the file. TOML values which aren't valid TOML are written as strings.
+fn format_value(format: KeyValueFormat, value: &str) -> String {
+    match format {
+        KeyValueFormat::Toml if toml::from_str::<toml::Table>(&format!("value = {}", value)).is_err() =>
+            toml::Value::String(value.to_string()).to_string(),
+        _ => value.to_string()
+    }
+}
+
+fn format_assignment(format: KeyValueFormat, key: &str, value: &str) -> String {
+    match format {
+        KeyValueFormat::Env => format!("{}={}", key, value),
+        _ => format!("{} = {}", key, value)
+    }
+}
+
+fn split_line_ending(line: &str) -> (&str, &str) {
+    let body = line.trim_end_matches(['\r', '\n']);
+    (body, &line[body.len() ..])
+}
+
+/// Sets the key to the value, keeping every other byte of the content as is.
+/// A missing key is added to its section (created at the end if missing too) if `create_missing` is set.
+pub fn set_value(
+    content: &str,
+    format: KeyValueFormat,
+    key_path: &str,
+    value: &str,
+    create_missing: bool
+) -> Result<KeyValueEdit> {
+    let (section, key) = split_key_path(format, key_path);
+    let value = format_value(format, value);
+    let eol = if content.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let mut lines: Vec<String> = content.split_inclusive('\n').map(String::from).collect();
+    let mut current_section: Option<String> = None;
+    // The index after the last non-blank line of the target section, where a missing key is inserted
+    let mut insert_at: Option<usize> = section.is_none().then_some(0);
+
+    for index in 0 .. lines.len() {
+        let (body, line_ending) = split_line_ending(&lines[index]);
+
+        if let Some(header) = parse_section_header(format, body) {
+            current_section = Some(header);
+            if current_section.as_deref() == section {
+                insert_at = Some(index + 1);
+            }
+            continue;
+        }
+        if current_section.as_deref() != section {
+            continue;
+        }
+
+        if let Some(value_start) = find_value_start(format, body, key) {
+            let after = format!("{}{}", &body[.. value_start], value);
+            lines[index] = after.clone() + line_ending;
+            return Ok(KeyValueEdit { content: lines.concat(), after });
+        }
+        if ! body.trim().is_empty() {
+            insert_at = Some(index + 1);
+        }
+    }
+
+    if ! create_missing {
+        match section {
+            Some(section) => bail!(t!(KeyNotFoundInSection, key, section)),
+            None => bail!(t!(KeyNotFound, key))
+        }
+    }
+
+    let after = format_assignment(format, key, &value);
+    let insert_at = match insert_at {
+        Some(insert_at) => insert_at,
+        None => {
+            // Add the missing section at the end, separated by a blank line
+            if lines.last().is_some_and(|line| ! line.ends_with('\n')) {
+                lines.last_mut().unwrap().push_str(eol);
+            }
+            if ! lines.is_empty() {
+                lines.push(eol.to_string());
+            }
+            lines.push(format!("[{}]{}", section.unwrap_or_default(), eol));
+            lines.len()
+        }
+    };
+
+    if insert_at > 0 && ! lines[insert_at - 1].ends_with('\n') {
+        lines[insert_at - 1].push_str(eol);
+    }
+    lines.insert(insert_at, format!("{}{}", after, eol));
+
+    Ok(KeyValueEdit {
+        content: lines.concat(),
+        after
+    })
+}
@@ -0,0 +1,123 @@
+use clap::ValueEnum;
+use colored::Colorize;
+
+use crate::{config::{Config, ConfigItem, ConfigManager}, i18n::t, stale::Overdue};
+
+/// What `--list` groups items by.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum GroupBy {
+    Tag,
+    Type
+}
+
+pub struct ListOptions {
+    pub numbered: bool,
+    /// Whether to show the source files of items and the commands of states.
+    pub verbose: bool,
+    /// Whether to wrap long details instead of truncating them.
+    pub full: bool,
+    pub group_by: Option<GroupBy>,
+    /// With `group_by` tag, lists an item with several tags only under its first tag.
+    pub first_tag_only: bool,
+    /// The overdue items, flagged under their states.
+    pub overdue: Vec<Overdue>
+}
+
+/// Renders the items in `order`. Numbers are the positions in `order`, so they stay the same when grouped.
+pub fn render(cm: &ConfigManager, order: &[usize], options: &ListOptions) -> String {
+    let index_width = order.len().to_string().len();
+    let render_item = |position: usize, item: &ConfigItem| {
+        let rendered = if options.numbered {
+            format!("{:>width$}) {}", position + 1, item.render(true, options.verbose, options.full), width = index_width)
+        } else {
+            item.render(false, options.verbose, options.full)
+        };
+        let rendered = match options.overdue.iter().find(|overdue| overdue.item == item.get_name()) {
+            Some(overdue) => format!("{}\n  {}", rendered, overdue.to_string().red()),
+            None => rendered
+        };
+        if options.verbose {
+            let source = t!(ItemSource, cm.get_item_file(item.get_name()).display()).dimmed();
+            format!("{}\n  {}", rendered, source)
+        } else {
+            rendered
+        }
+    };
+    let items = order.iter().map(|&index| &cm.config.items[index]).enumerate();
+
+    let Some(group_by) = options.group_by else {
+        return items
+            .map(|(position, item)| render_item(position, item))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    };
+
+    // Groups in the order they first appear in, with the untagged last
+    let mut groups: Vec<(String, Vec<String>)> = vec![];
+    let mut untagged: Vec<String> = vec![];
+    for (position, item) in items {
+        let group_names: Vec<String> = match group_by {
+            GroupBy::Type => vec![item.get_type_string()],
+            GroupBy::Tag if options.first_tag_only => item.get_options().tags.iter().take(1).cloned().collect(),
+            GroupBy::Tag => item.get_options().tags.clone()
+        };
+        if group_names.is_empty() {
+            untagged.push(render_item(position, item));
+            continue;
+        }
+        for group_name in &group_names {
+            let mut rendered = render_item(position, item);
+            let other_names: Vec<&str> = group_names
+                .iter()
+                .filter(|name| *name != group_name)
+                .map(String::as_str)
+                .collect();
+            if ! other_names.is_empty() {
+                rendered = format!("{}\n  {}", rendered, t!(AlsoTagged, other_names.join(", ")).dimmed());
+            }
+            match groups.iter_mut().find(|(name, _)| name == group_name) {
+                Some((_, group)) => group.push(rendered),
+                None => groups.push((group_name.clone(), vec![rendered]))
+            }
+        }
+    }
+    if ! untagged.is_empty() {
+        groups.push((t!(Untagged), untagged));
+    }
+
+    groups
+        .into_iter()
+        .map(|(name, group)| {
+            let header = format!("== {} ({}) ==", name, group.len()).bold();
+            format!("{}\n\n{}", header, group.join("\n\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Renders the presets with their item states. Entries naming missing items or states are dimmed with why.
+pub fn render_presets(config: &Config) -> String {
+    config.presets
+        .iter()
+        .map(|(name, states)| {
+            let entries = states
+                .iter()
+                .map(|(item_name, state)| {
+                    let entry = format!("  {} => {}", item_name.cyan(), state.yellow());
+                    let missing = match config.find_item(item_name) {
+                        None => Some(t!(MissingItem)),
+                        Some(item_index) if ! config.items[item_index].has_state(state) => Some(t!(MissingState)),
+                        Some(_) => None
+                    };
+                    match missing {
+                        Some(missing) => format!("{}  {}", entry, missing).dimmed().to_string(),
+                        None => entry
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            format!("{}\n{}", name.bold(), entries)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
@@ -0,0 +1,79 @@
+use std::{env, fs::{self, File, OpenOptions}, io::Write, path::Path, str::FromStr, sync::Mutex};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::i18n::t;
+
+/// Writes timestamped events to stderr and, if given, a log file.
+struct Logger {
+    level: LevelFilter,
+    to_stderr: bool,
+    file: Option<Mutex<File>>
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if ! self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {}: {}",
+            Local::now().format("%Y-%m-%dT%H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if self.to_stderr {
+            eprintln!("{}", line);
+        }
+        if let Some(file) = &self.file {
+            let _ = writeln!(file.lock().unwrap(), "{}", line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Returns the level of `SWITCHY_LOG`, like `debug` or `trace`, if it's set to one.
+fn get_env_level() -> Option<LevelFilter> {
+    env::var("SWITCHY_LOG").ok().and_then(|level| LevelFilter::from_str(&level).ok())
+}
+
+/// Sets up logging. `--debug` or `SWITCHY_LOG` logs to stderr, and `log_file` to the file, at the debug level
+/// unless `SWITCHY_LOG` gives another. Without any, nothing is logged.
+pub fn init(debug: bool, log_file: Option<&Path>) -> Result<()> {
+    let env_level = get_env_level();
+    let to_stderr = debug || env_level.is_some();
+    if ! to_stderr && log_file.is_none() {
+        return Ok(());
+    }
+
+    let file = match log_file {
+        Some(path) => Some(Mutex::new({
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| t!(FailedToOpenLogFile, path.display()))?
+        })),
+        None => None
+    };
+    let level = env_level.unwrap_or(LevelFilter::Debug);
+    log::set_boxed_logger(Box::new(Logger { level, to_stderr, file })).context(t!(FailedToSetUpLogging))?;
+    log::set_max_level(level);
+
+    Ok(())
+}
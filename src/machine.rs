@@ -0,0 +1,29 @@
+use std::env;
+
+/// The OS names `only_on.os` accepts, as Rust names them.
+pub const KNOWN_OS: &[&str] = &[
+    "linux", "macos", "windows", "freebsd", "openbsd", "netbsd", "dragonfly", "android", "ios", "solaris", "illumos"
+];
+
+pub fn get_os() -> &'static str {
+    env::consts::OS
+}
+
+/// Returns the host name of this machine, or `None` if it can't be got.
+#[cfg(unix)]
+pub fn get_hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer outlives the call, and its length is passed along
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|&byte| byte == 0).unwrap_or(buffer.len());
+    String::from_utf8(buffer[.. len].to_vec()).ok()
+}
+
+/// Returns the host name of this machine, or `None` if it can't be got.
+#[cfg(not(unix))]
+pub fn get_hostname() -> Option<String> {
+    env::var("COMPUTERNAME").ok()
+}
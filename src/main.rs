@@ -1,191 +1,2510 @@
+use std::{collections::BTreeMap, env, fmt, io::{stdin, IsTerminal}, panic, path::PathBuf, process::ExitCode, sync::OnceLock};
+
 use anyhow::{anyhow, bail, Context, Result};
-use clap::Parser;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
-use ctrlc;
 use dialoguer::{
     self, console::{Style, Term}, theme::ColorfulTheme,
-    FuzzySelect, Select, Input, Confirm
+    Input, MultiSelect, Confirm, Select
 };
 use directories::ProjectDirs;
 
-mod config;
-use config::{ConfigCommandItem, ConfigCommandItemState, ConfigItem, ConfigManager};
+mod block;
+mod change_plan;
+mod completion_cache;
+mod config;
+mod diff;
+mod discover;
+mod doctor;
+mod editor;
+mod env_vars;
+mod examples;
+mod export_script;
+mod filter;
+mod fs_util;
+mod git_config;
+mod history;
+mod i18n;
+mod import;
+mod interrupt;
+mod key_value;
+mod list;
+mod logging;
+mod machine;
+mod mangen;
+mod migrate;
+mod notify;
+mod onboarding;
+mod overlay;
+mod picker;
+mod plugin;
+mod prune;
+mod recipe;
+mod run_log;
+mod schedule;
+mod schema;
+mod secret;
+mod shell;
+mod shell_init;
+mod snapshot;
+mod sort;
+mod ssh;
+mod stale;
+mod stack;
+mod stats;
+mod switch;
+mod sync;
+mod template;
+mod text;
+mod theme;
+mod trash;
+#[cfg(feature = "tui")]
+mod tui;
+mod unknown_keys;
+mod watch;
+mod wizard;
+use picker::{FilterSelect, NumberSelect};
+use change_plan::ChangePlan;
+use completion_cache::CompletionCache;
+use config::{ConfigItem, ConfigManager, ConfirmRun, InterruptPolicy, ItemSort, MatchMode, RunDeclined, StateCommand, SwitchOptions};
+use history::History;
+use i18n::t;
+use list::{GroupBy, ListOptions};
+use migrate::MigrationKind;
+use overlay::SettingsOverlay;
+use export_script::ScriptShell;
+use env_vars::EnvVars;
+use filter::ItemFilter;
+use shell_init::InitShell;
+use stack::{Stack, StackEntry};
+use theme::ColorWhen;
+use trash::Trash;
+use switch::{PlannedSwitch, SwitchOutcome, SwitchResult};
+
+#[derive(Parser)]
+#[command(version)]
+#[command(group(ArgGroup::new("action")))]
+#[command(group(ArgGroup::new("schedule")))]
+#[command(group(ArgGroup::new("with_items").multiple(true)))]
+#[command(group(ArgGroup::new("switching_all").multiple(true)))]
+#[command(about = "Easily switch your config items in terminal")]
+#[command(long_about = "\
+Easily switch your config items in terminal.
+
+A config item has named states, and switching it to a state applies the state: running its command, setting a key \
+in a file, replacing a marked block in a file, setting git config keys, rendering a template or recording a dir. \
+The current state of each item is recorded in the config file.
+
+Run `switchy ITEM STATE` to switch, or leave out the state or the item to pick them. Add items with `--add`, \
+`--discover` or a recipe from `--templates`.")]
+#[command(after_long_help = examples::get_help_examples())]
+// `switchy help` would hide an item named help
+#[command(disable_help_subcommand = true)]
+struct Cli {
+    /// Add a config item
+    ///
+    /// Without `--bool` or `--template`, the item is added in a wizard asking for its type and states.
+    /// `switchy --add proxy --bool --on 'export ALL_PROXY=socks5://127.0.0.1:1080' --off 'unset ALL_PROXY'`
+    /// adds a boolean item without any prompt.
+    ///
+    /// If an item of the name exists, it asks whether to edit it, add the new one under another name, or replace it.
+    #[arg(short, long, name = "ADD_NAME", group = "action")]
+    add: Option<String>,
+
+    /// Generate the added item from a built-in recipe, see `--templates`
+    #[arg(long, name = "RECIPE", requires = "ADD_NAME")]
+    template: Option<String>,
+
+    /// With `--add`, replace the item if it exists, instead of asking what to do
+    #[arg(long, requires = "ADD_NAME", conflicts_with = "NEW_ITEM_NAME")]
+    replace: bool,
+
+    /// With `--add`, add the item under this name if the given one exists, instead of asking what to do
+    #[arg(long, requires = "ADD_NAME", name = "NEW_ITEM_NAME")]
+    rename_to: Option<String>,
+
+    /// Add a boolean item with the states `on` and `off`, running the commands of `--on` and `--off`
+    #[arg(long = "bool", requires_all = ["ADD_NAME", "ON_COMMAND", "OFF_COMMAND"], conflicts_with = "RECIPE")]
+    boolean: bool,
+
+    /// The command of the `on` state of a `--bool` item
+    #[arg(long, name = "ON_COMMAND", requires = "boolean")]
+    on: Option<String>,
+
+    /// The command of the `off` state of a `--bool` item
+    #[arg(long, name = "OFF_COMMAND", requires = "boolean")]
+    off: Option<String>,
+
+    /// List the built-in recipes for `--add NAME --template RECIPE`
+    #[arg(long, group = "action")]
+    templates: bool,
+
+    /// Print examples of common tasks, like `switchy examples`
+    #[arg(long, group = "action")]
+    examples: bool,
+
+    /// Import the items of a config file from a path, an http(s) URL or `[user@]host:path` over SSH.
+    /// Existing items are kept or replaced as picked, or replaced with `--yes`
+    #[arg(long, name = "SOURCE", group = "action")]
+    import: Option<String>,
+
+    /// Probe the system for switchable tools, and add items for the picked ones
+    #[arg(long, group = "action")]
+    discover: bool,
+
+    /// Propose items from a Makefile with targets like `proxy-on` and `proxy-off`, from a dir of scripts like
+    /// `proxy_on.sh` and `proxy_off.sh`, or from a JSON file like `{ "proxy": { "on": "...", "off": "..." } }`, and
+    /// add the picked ones, or all of them with `--yes`. Nothing is run
+    #[arg(long, value_names = ["KIND", "PATH"], num_args = 2, group = "action")]
+    import_from: Option<Vec<String>>,
+
+    /// Remove a config item, moving it into the trash, see `--trash`
+    #[arg(short, long, name = "REMOVE_NAME", group = "action")]
+    remove: Option<String>,
+
+    /// With `--remove`, delete the item outright instead of moving it into the trash
+    #[arg(long, requires = "REMOVE_NAME")]
+    purge: bool,
+
+    /// List the removed items in the trash, which are purged after `trash_days` in `[defaults]`, 30 by default
+    #[arg(long, group = "action")]
+    trash: bool,
+
+    /// Put the removed item back from the trash
+    #[arg(long, name = "RESTORE_NAME", group = "action")]
+    restore_item: Option<String>,
+
+    /// List all config items, or only the given one
+    #[arg(short, long, name = "LIST_NAME", num_args = 0..=1, group = "action")]
+    list: Option<Option<String>>,
+
+    /// The order of items in `--list` and the item picker, instead of `sort` in `[defaults]`
+    #[arg(long, value_enum, name = "SORT")]
+    sort: Option<ItemSort>,
+
+    /// Reorder the items in the config file by `--sort` for good
+    #[arg(long, requires = "SORT", group = "action")]
+    save: bool,
+
+    /// Don't show the item with its current state above the state picker, instead of `show_header` in `[defaults]`
+    #[arg(long)]
+    no_header: bool,
+
+    /// Don't walk through adding a first item when bare `switchy` creates the config file
+    ///
+    /// Onboarding only runs in a terminal without any other argument, so this is only needed to get the plain error
+    /// about having no items.
+    #[arg(long)]
+    no_onboarding: bool,
+
+    /// With `--list`, group the items under headers by their tags or types
+    #[arg(long, value_enum, requires = "LIST_NAME", name = "GROUP")]
+    group_by: Option<GroupBy>,
+
+    /// With `--group-by tag`, list an item with several tags only under its first tag
+    #[arg(long, requires = "GROUP")]
+    first_tag: bool,
+
+    /// With `--list`, also list the items for other machines by `only_on`, dimmed
+    #[arg(long, requires = "LIST_NAME")]
+    all_machines: bool,
+
+    /// Only list or pick the items of the type, like `command` or `key-value`
+    #[arg(long = "type", name = "TYPE", conflicts_with = "save")]
+    item_type: Option<String>,
+
+    /// Only list or pick the items with the tag
+    #[arg(long, conflicts_with = "save")]
+    tag: Option<String>,
+
+    /// Only list or pick the items whose current state is the state
+    #[arg(long, name = "IN_STATE", conflicts_with = "save")]
+    in_state: Option<String>,
+
+    /// Only list or pick the items defining the state
+    #[arg(long, name = "HAS_STATE", conflicts_with = "save")]
+    has_state: Option<String>,
+
+    /// Switch several config items at once
+    #[arg(long, group = "action")]
+    batch: bool,
+
+    /// Push a snapshot of the current states of all items onto the stack, optionally labeled
+    #[arg(long, name = "PUSH_LABEL", num_args = 0..=1, group = "action")]
+    push: Option<Option<String>>,
+
+    /// Switch every item back to its state in the most recently pushed snapshot, and drop the snapshot
+    #[arg(long, group = "action")]
+    pop: bool,
+
+    /// With `--pop`, only record the states of the snapshot without running anything
+    #[arg(long, requires = "pop")]
+    no_run: bool,
+
+    /// List the pushed snapshots, the most recent last
+    #[arg(long, group = "action")]
+    stack: bool,
+
+    /// Save the current states of the picked items, or of `--items`, as a preset in the config
+    #[arg(long, name = "SAVE_PRESET", groups = ["action", "with_items"])]
+    save_preset: Option<String>,
+
+    /// Switch the items of a preset to their saved states
+    #[arg(long, name = "PRESET", group = "action")]
+    preset: Option<String>,
+
+    /// Remove a preset
+    #[arg(long, name = "REMOVE_PRESET", group = "action")]
+    remove_preset: Option<String>,
+
+    /// Rename a preset
+    #[arg(long, value_names = ["PRESET", "NEW_NAME"], num_args = 2, group = "action")]
+    rename_preset: Option<Vec<String>>,
+
+    /// With `--list`, list the presets instead of the items
+    #[arg(long, requires = "LIST_NAME")]
+    presets: bool,
+
+    /// Switch every config item which has the given state
+    #[arg(long, name = "ALL_STATE", groups = ["action", "switching_all"])]
+    all: Option<String>,
+
+    /// Switch the item back to its `default_state`
+    #[arg(long, name = "RESET_ITEM", group = "action")]
+    reset: Option<String>,
+
+    /// Switch every config item with a `default_state` back to it, skipping those already in it
+    #[arg(long, groups = ["action", "switching_all"])]
+    reset_all: bool,
+
+    /// Set the item to switch when no item is given, or clear it with an empty name
+    #[arg(long, name = "DEFAULT_NAME", group = "action")]
+    set_default: Option<String>,
+
+    /// Print shell functions for each config item, to be `eval`ed in the shell rc file
+    ///
+    /// Add `eval "$(switchy --shell-init bash)"` to `~/.bashrc`, and `proxy on` runs `switchy proxy on`. `sw` runs
+    /// `switchy`.
+    #[arg(long, name = "SHELL", group = "action")]
+    shell_init: Option<InitShell>,
+
+    /// Print the args quoted into one command for the shell commands run with, which `--set shell=...` changes
+    ///
+    /// E.g. `switchy --escape printf '%s\n' "$HOME"` prints a command fit for `--on` or a state command, however the
+    /// args are quoted. It takes every arg after it, so other flags go before it.
+    #[arg(long, name = "ARGS", group = "action", num_args = 1.., allow_hyphen_values = true)]
+    escape: Option<Vec<String>>,
+
+    /// Print a script running the commands of the current states, to set up another machine without switchy
+    #[arg(long, groups = ["action", "with_items"])]
+    export_script: bool,
+
+    /// With `--export-script` or `--save-preset`, only take these items, separated by commas
+    #[arg(long, value_delimiter = ',', requires = "with_items", name = "ITEM_NAMES")]
+    items: Vec<String>,
+
+    /// The shell of the `--export-script` script
+    #[arg(long, value_enum, default_value = "sh", requires = "export_script", name = "SCRIPT_SHELL")]
+    shell: ScriptShell,
+
+    /// Print the current state of the item, for scripts
+    #[arg(long, name = "CURRENT_ITEM", group = "action")]
+    current: Option<String>,
+
+    /// Print the `value` of the current state of the item, for scripts like `export JAVA_HOME="$(switchy --value jdk)"`.
+    /// Prints nothing if the state has none
+    #[arg(long, name = "VALUE_ITEM", group = "action")]
+    value: Option<String>,
+
+    /// With `--value`, fail with exit code 2 if the state has no value
+    #[arg(long, requires = "VALUE_ITEM")]
+    require_value: bool,
+
+    /// Check the config, the programs of the commands, the editor, the terminal and the completions
+    ///
+    /// Each check prints a line, passing, failing, or warning about something working worse than it could. Exits with
+    /// a failure if any check fails. `--format json` prints the results as JSON instead.
+    #[arg(long, group = "action")]
+    doctor: bool,
+
+    /// Print the item names, or the state names of the item, one per line, for shell completions
+    #[arg(long, name = "COMPLETE_ITEM", hide = true)]
+    complete_names: Option<Option<String>>,
+
+    /// Print a short status string of the items with `prompt = true`, without a trailing newline
+    #[arg(long, group = "action")]
+    prompt_status: bool,
+
+    /// Regenerate the state snapshots `state.json` and `current.json` in the config dir
+    #[arg(long, group = "action")]
+    snapshot: bool,
+
+    /// Print the logged output of the last run of an item
+    #[arg(long, name = "LOG_ITEM", group = "action")]
+    log: Option<String>,
+
+    /// Show switch statistics from the history, of all items or the given one
+    #[arg(long, name = "STATS_ITEM", num_args = 0..=1, group = "action")]
+    stats: Option<Option<String>>,
+
+    /// Detect the actual state of an item and compare it with the recorded one
+    #[arg(long, name = "DETECT_ITEM", group = "action")]
+    detect: Option<String>,
+
+    /// Print the directory of a dir item, after switching it to the state if given. Nothing else is printed on stdout
+    #[arg(long, value_names = ["ITEM", "STATE"], num_args = 1..=2, group = "action")]
+    print_path: Option<Vec<String>>,
+
+    /// Reorder the states of an item interactively, or move the given state to the position given by `--to`
+    #[arg(long, value_names = ["ITEM", "STATE"], num_args = 1..=2, group = "action")]
+    edit: Option<Vec<String>>,
+
+    /// The 1-based position to move the state given by `--edit ITEM STATE` to
+    #[arg(long, name = "POSITION", requires = "edit")]
+    to: Option<usize>,
+
+    /// Copy a state with all its fields to another item of the same type, optionally renaming it
+    #[arg(long, value_names = ["FROM_ITEM", "STATE", "TO_ITEM", "NEW_NAME"], num_args = 3..=4, group = "action")]
+    copy_state: Option<Vec<String>>,
+
+    /// Like `--copy-state`, but remove the state from the source item. Moving its current state needs `--force`
+    #[arg(long, value_names = ["FROM_ITEM", "STATE", "TO_ITEM", "NEW_NAME"], num_args = 3..=4, group = "action")]
+    move_state: Option<Vec<String>>,
+
+    /// Rename the state in every item having it, after confirming the items. Items already having the new name are
+    /// skipped
+    #[arg(long, value_names = ["STATE", "NEW_NAME"], num_args = 2, group = "action")]
+    rename_state_all: Option<Vec<String>>,
+
+    /// Commit the config dir to git, then pull and push it, or run the sync command from `[sync]`
+    #[arg(long, group = "action")]
+    sync: bool,
+
+    /// Encrypt the command of a state in place, to be decrypted with a passphrase when switching.
+    /// Needs the `encrypt` feature
+    #[arg(long, value_names = ["ITEM", "STATE"], num_args = 2, group = "action")]
+    encrypt_state: Option<Vec<String>>,
+
+    /// Find broken or unused items and states, and pick the ones to remove. Only reports them with `--dry-run`
+    #[arg(long, group = "action")]
+    prune: bool,
+
+    /// How many days without a switch make an item unused for `--prune`
+    #[arg(long, name = "DAYS", default_value_t = 90)]
+    stale_days: u32,
+
+    /// Print the JSON Schema of the config file, for validation and completion in editors
+    #[arg(long, group = "action")]
+    schema: bool,
+
+    /// Write the man page `switchy.1` into the dir
+    #[arg(long, name = "MAN_DIR", group = "action")]
+    mangen: Option<PathBuf>,
+
+    /// Open the config file in the editor from VISUAL or EDITOR, and check it afterwards
+    #[arg(long, group = "action")]
+    edit_config: bool,
+
+    /// Refuse to change the config. Switching runs the commands, without recording the new states. Also set by `SWITCHY_READONLY=1`
+    #[arg(long)]
+    read_only: bool,
+
+    /// Write the config even if it has keys this version doesn't know, dropping them
+    #[arg(long)]
+    force_write: bool,
+
+    /// Read the config from stdin with `-`, for one-shot switches. Nothing is persisted
+    #[arg(long, name = "CONFIG")]
+    config: Option<String>,
+
+    /// Use the profile, a config dir of its own under `profiles` in the config dir, with its own config, history and
+    /// trash. An empty name is the main config. Also set by `SWITCHY_PROFILE`
+    #[arg(long, name = "PROFILE")]
+    profile: Option<String>,
+
+    /// Name of the config item to switch, matched exactly, then ignoring ASCII case. Without STATE, an unmatched name
+    /// filters the item picker instead
+    #[arg(name = "ITEM", conflicts_with = "action")]
+    item: Option<String>,
+
+    /// Name of the state to switch to, prompted for if omitted. Items with only `on` and `off` are toggled instead
+    ///
+    /// With both ITEM and STATE nothing is prompted for, so `switchy proxy on` fits scripts. Bind `switchy proxy` to
+    /// a key to toggle a boolean item, e.g. `bind -x '"\C-xp": switchy proxy'` in bash or
+    /// `bind P run-shell "switchy proxy"` in tmux.
+    #[arg(name = "STATE", requires = "ITEM")]
+    state: Option<String>,
+
+    /// Run the command instead of the stored one of the state, just this time. The switch is still recorded
+    #[arg(long, name = "COMMAND", requires = "STATE")]
+    with: Option<String>,
+
+    /// Change a setting just this time, e.g. `--set shell=pwsh`. The keys are log, notify, shell (`powershell`, `pwsh` or
+    /// `system`), on_interrupt, confirm_run and askpass. They win over what the items and the defaults set
+    #[arg(long, name = "KEY=VALUE")]
+    set: Vec<String>,
+
+    /// Switch at the given time, e.g. `18:00`, an RFC 3339 date time or `2h`
+    #[arg(long, name = "TIME", group = "schedule")]
+    at: Option<String>,
+
+    /// Switch after the given duration, e.g. `2h 30m`
+    #[arg(long = "in", name = "DURATION", group = "schedule")]
+    in_duration: Option<String>,
+
+    /// Switch after the duration, e.g. `10m`, then switch back to the previous state, counting down until then.
+    /// Ctrl-C asks whether to cancel switching back
+    #[arg(long = "for", name = "FOR_DURATION", group = "schedule")]
+    for_duration: Option<String>,
+
+    /// Switch at the scheduled time in a background process, or switch back in one with `--for`
+    ///
+    /// With `--for`, the background process leaves the item alone if it's been switched again by then, noting so in
+    /// `switchy.log`.
+    #[arg(long, requires = "schedule")]
+    detach: bool,
+
+    /// Only switch if the item is still in this state right before switching, for switching back in the background
+    #[arg(long, name = "IF_STATE", hide = true, requires = "STATE")]
+    if_current: Option<String>,
+
+    /// Switch the most recently switched item again
+    #[arg(long, group = "action")]
+    last: bool,
+
+    /// Open a full-screen dashboard of the items, which stays open for switching several of them.
+    /// Needs switchy built with the `tui` feature
+    #[arg(long, group = "action", conflicts_with = "CONFIG")]
+    tui: bool,
+
+    /// Watch the files of the items with `watch`, and reapply the current state of an item when its file changes.
+    /// Needs switchy built with the `watch` feature, unless with `--once`
+    #[arg(long, group = "action", conflicts_with = "CONFIG")]
+    watch: bool,
+
+    /// With `--watch`, check the files once and exit, e.g. from cron
+    #[arg(long, requires = "watch")]
+    once: bool,
+
+    /// Switch to the next state instead of prompting for one
+    ///
+    /// `switchy -t theme` cycles through the states of `theme`, which suits a keybinding.
+    #[arg(short, long)]
+    toggle: bool,
+
+    /// Always show the item picker, even if there is a default item
+    #[arg(long, conflicts_with = "ITEM")]
+    pick: bool,
+
+    /// Show what would be switched without running commands or writing the config
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Stop switching at the first failure when using `--all` or `--reset-all`
+    #[arg(long, requires = "switching_all")]
+    fail_fast: bool,
+
+    /// Use numbered pickers, where typing a number jumps to the entry
+    #[arg(long)]
+    numbers: bool,
+
+    /// Output format of reports
+    ///
+    /// With `--dry-run`, `json` prints the plan of what the switches would change on stdout: the commands run, the
+    /// files written with unified diffs, and the git config keys set. With `--list`, it prints the items as they are in
+    /// the config, or the given item alone.
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Override safety checks, e.g. of block markers appearing more than once
+    #[arg(long)]
+    force: bool,
+
+    /// Show the diff of the config file and confirm before changes like adding or removing items
+    #[arg(long)]
+    confirm_writes: bool,
+
+    /// Show each command and confirm before running it
+    #[arg(long)]
+    confirm: bool,
+
+    /// Don't check that the program of each command is on PATH before running it
+    ///
+    /// The check fails on shell functions, which aren't on PATH. Set `preflight = false` on an item to skip it for
+    /// that item only.
+    #[arg(long)]
+    no_preflight: bool,
+
+    /// Answer yes to confirmations, of config changes and of commands by `--confirm`. Also set by `SWITCHY_YES=1`
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Print more details, like the diff of config changes confirmed by `--yes`, or commands in `--list`
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Wrap long commands and details instead of truncating them to the terminal width
+    #[arg(long)]
+    full: bool,
+
+    /// Don't print the switching banners. Also set by `SWITCHY_QUIET=1`
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// When to use colors
+    ///
+    /// `auto` colors a terminal, unless `NO_COLOR` is set or `CLICOLOR=0`, and `CLICOLOR_FORCE=1` colors even a pipe,
+    /// e.g. into `less -R`. `always` and `never` win over the env vars. Without it, `SWITCHY_COLOR` gives when, or
+    /// else `auto`.
+    #[arg(long, value_enum, name = "WHEN")]
+    color: Option<ColorWhen>,
+
+    /// Debug mode, logging what happens to stderr and printing errors in full. Also set by `SWITCHY_LOG=debug`
+    #[arg(short, long)]
+    debug: bool,
+
+    /// Also write the debug log to `switchy.log` in the config dir
+    #[arg(long)]
+    log_file: bool,
+
+    /// Print the exact program, args, dir and env vars of each command run, on stderr
+    #[arg(long)]
+    debug_exec: bool,
+
+    #[command(subcommand)]
+    command: Option<CliCommand>
+}
+
+/// The subcommands, taking the place of ITEM. An item of the same name is switched with `switchy -- NAME`.
+#[derive(Subcommand)]
+enum CliCommand {
+    /// Print examples of common tasks, grouped by task. Same as `--examples`
+    Examples
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json
+}
+
+/// Returns the config dir, `SWITCHY_CONFIG_DIR` or else the platform one, or the dir of the profile under it.
+fn get_config_dir(profile: Option<&str>) -> Result<PathBuf> {
+    let dir = match env::var_os("SWITCHY_CONFIG_DIR") {
+        Some(dir) if ! dir.is_empty() => PathBuf::from(dir),
+        _ => ProjectDirs::from("top", "IceLava", "switchy")
+            .context(t!(FailedToGetConfigDir))?
+            .config_dir()
+            .to_path_buf()
+    };
+    Ok(match profile {
+        Some(profile) => dir.join("profiles").join(profile),
+        None => dir
+    })
+}
+
+fn main_wrapper(mut cli: Cli) -> Result<()> {
+    let env_vars = EnvVars::read();
+    let color = env_vars::resolve(cli.color, env_vars.color, ColorWhen::Auto);
+    let profile_flag = cli.profile
+        .as_deref()
+        .map(|profile| env_vars::parse_profile("--profile", profile))
+        .transpose()
+        .map_err(|err| anyhow!(err))?;
+    let profile = env_vars::resolve(profile_flag, env_vars.profile.clone(), None);
+
+    if cli.prompt_status {
+        // The status is embedded in shell prompts, so it never fails, prints nothing else and is only colored on
+        // request. It's printed before warning about env vars or setting up logging, which could do either
+        colored::control::set_override(theme::should_color(color, false));
+        if let Ok(config_dir) = get_config_dir(profile.as_deref()) {
+            let mut cm = ConfigManager::new(&config_dir);
+            if cm.read_if_exists().is_ok() {
+                print!("{}", cm.config.items
+                    .iter()
+                    .filter(|item| item.get_options().prompt)
+                    .map(|item| format!(
+                        "{}:{}",
+                        item.get_options().label.as_deref().unwrap_or(item.get_name()).cyan(),
+                        item.color_current_state()
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                );
+            }
+        }
+        return Ok(());
+    }
+
+    cli.yes = env_vars::resolve(env_vars::flag(cli.yes), env_vars.yes, false);
+    cli.quiet = env_vars::resolve(env_vars::flag(cli.quiet), env_vars.quiet, false);
+    cli.read_only = env_vars::resolve(env_vars::flag(cli.read_only), env_vars.read_only, false);
+    theme::set_colors(color);
+    // Completions run on every tab, so they stay quiet about typos, which `--doctor` reports anyway
+    if cli.complete_names.is_none() {
+        for warning in &env_vars.warnings {
+            eprintln!("{} {}", t!(Warning).yellow(), warning);
+        }
+    }
+
+    if let Some(dir) = cli.mangen {
+        return mangen::write(Cli::command(), &dir);
+    }
+
+    let config_dir = get_config_dir(profile.as_deref())?;
+    logging::init(cli.debug, cli.log_file.then(|| config_dir.join("switchy.log")).as_deref())?;
+    log::debug!("Config dir resolved to {}", config_dir.display());
+    let mut cm = ConfigManager::new(&config_dir);
+    let _ = CONFIG_FILE_PATH.set(cm.get_file_path().to_path_buf());
+
+    // The paths run from prompts and scripts only read the config, and skip building anything for prompting
+    if let Some(item_name) = &cli.current {
+        cm.read_if_exists()?;
+        let item_index = cm.resolve_item(item_name)?;
+        let state = cm.config.items[item_index].get_current_state();
+        println!("{}", state);
+        // The config is the source of truth, which the snapshots follow, e.g. after editing the config by hand
+        if cm.get_file_path().exists() {
+            if let Err(err) = snapshot::sync_item(cm.get_path(), &cm.config, cm.config.items[item_index].get_name(), state) {
+                log::debug!("Failed to sync the snapshots: {:#}", err);
+            }
+        }
+        return Ok(());
+    }
+
+    if let Some(item_name) = &cli.value {
+        cm.read_if_exists()?;
+        let item = &cm.config.items[cm.resolve_item(item_name)?];
+        let value = item.get_state_names()
+            .iter()
+            .position(|name| *name == item.get_current_state())
+            .and_then(|index| item.get_value_of_state(index));
+        match value {
+            Some(value) => println!("{}", value),
+            None if cli.require_value => bail!(NoValue(t!(NoValue, item.get_name().cyan(), item.get_current_state().yellow()))),
+            None => {}
+        }
+        return Ok(());
+    }
+
+    if let Some(item_name) = &cli.complete_names {
+        // Completions run on every Tab, so the names are served from a cache while the config is unchanged
+        let cache_path = ProjectDirs::from("top", "IceLava", "switchy")
+            .map(|dirs| dirs.cache_dir().join("completion.json"));
+        let cache = match cache_path.as_deref().and_then(CompletionCache::read) {
+            Some(cache) if cache.is_for(cm.get_file_path()) => cache,
+            _ => {
+                cm.read_if_exists()?;
+                cm.load_plugin_states();
+                let cache = CompletionCache::new(&cm);
+                if let Some(cache_path) = &cache_path {
+                    cache.write(cache_path);
+                }
+                cache
+            }
+        };
+        for name in cache.get_names(item_name.as_deref()) {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    if cli.examples || matches!(cli.command, Some(CliCommand::Examples)) {
+        println!("{}", examples::render());
+        return Ok(());
+    }
+
+    if cli.schema {
+        println!("{}", serde_json::to_string_pretty(&schema::get_schema())?);
+        return Ok(());
+    }
+
+    if let Some(shell) = cli.shell_init {
+        cm.read_if_exists()?;
+        let (snippet, collisions) = shell_init::generate(shell, &cm.config.items);
+        print!("{}", snippet);
+        for (name, existing) in collisions {
+            eprintln!("{}", t!(FunctionNameCollides, name, existing));
+        }
+        return Ok(());
+    }
+
+    if let Some(args) = &cli.escape {
+        cm.read_if_exists()?;
+        let overlay = SettingsOverlay::parse(&cli.set)?;
+        println!("{}", shell::join_args(args, env_vars::resolve(overlay.shell, env_vars.shell, cm.config.defaults.shell)));
+        return Ok(());
+    }
+
+    if cli.doctor {
+        // A config failing to read is a failed check, not an error
+        let config_error = cm.read_if_exists().err().map(|err| format!("{:#}", err));
+        i18n::set_lang(cm.config.defaults.lang.as_deref());
+        let results = doctor::run(&doctor::DoctorContext { cm: &cm, config_error });
+        if cli.format == Format::Json {
+            println!("{}", serde_json::to_string_pretty(&results)?);
+        }
+        else {
+            for result in &results {
+                println!("{}", doctor::render(result));
+            }
+        }
+        let failed = results.iter().filter(|result| result.status == doctor::CheckStatus::Fail).count();
+        if failed > 0 {
+            bail!(t!(DoctorFailed, failed));
+        }
+        return Ok(());
+    }
+
+    let ephemeral = match cli.config.as_deref() {
+        Some("-") => true,
+        Some(_) => bail!(t!(OnlyStdinConfig)),
+        None => false
+    };
+    if ephemeral {
+        // Only reading and switching work without a config file, and stdin is taken by the config so nothing can be prompted
+        let needs_config_file = cli.add.is_some() || cli.discover || cli.remove.is_some() || cli.batch
+            || cli.set_default.is_some() || cli.edit.is_some() || cli.copy_state.is_some() || cli.move_state.is_some()
+            || cli.prune || cli.edit_config || cli.snapshot || cli.detach || cli.sync || cli.encrypt_state.is_some()
+            || cli.save || cli.import.is_some() || cli.push.is_some() || cli.pop || cli.stack || cli.save_preset.is_some()
+            || cli.remove_preset.is_some() || cli.rename_preset.is_some() || cli.trash || cli.restore_item.is_some()
+            || cli.import_from.is_some() || cli.rename_state_all.is_some();
+        if needs_config_file {
+            bail!(t!(NeedsConfigFile));
+        }
+    }
+
+    let read_only = cli.read_only;
+    if read_only {
+        // Refuse before any prompt, rather than at the write after a wizard
+        let mutates_config = cli.add.is_some() || cli.discover || cli.remove.is_some() || cli.set_default.is_some()
+            || cli.edit.is_some() || cli.copy_state.is_some() || cli.move_state.is_some() || (cli.prune && ! cli.dry_run)
+            || cli.edit_config || cli.snapshot || cli.sync || cli.encrypt_state.is_some() || cli.save
+            || (cli.import.is_some() && ! cli.dry_run) || (cli.pop && ! cli.dry_run) || cli.save_preset.is_some()
+            || cli.remove_preset.is_some() || cli.rename_preset.is_some() || cli.restore_item.is_some()
+            || cli.import_from.is_some() || (cli.rename_state_all.is_some() && ! cli.dry_run);
+        if mutates_config {
+            bail!(t!(ReadOnlyConfig));
+        }
+        cm.set_read_only();
+    }
+
+    if cli.force_write {
+        cm.set_force_write();
+    }
+
+    if cli.edit_config {
+        // Only create the config, since an invalid one is to be fixed in the editor
+        if ! cm.get_file_path().exists() {
+            cm.read()?;
+        }
+        if ! editor::open(cm.get_file_path())? {
+            bail!(t!(EditorFailed));
+        }
+        cm.read_if_exists().context(t!(ConfigInvalidAfterEdit))?;
+        cm.config.items.iter().for_each(warn_identical_commands);
+        return Ok(());
+    }
+
+    if ephemeral {
+        cm.read_stdin()?;
+    }
+    else {
+        cm.read()?;
+    }
+    i18n::set_lang(cm.config.defaults.lang.as_deref());
+    theme::set_current_marker(cm.config.theme.current_marker.as_deref());
+    config::set_case_sensitive_names(cm.config.defaults.case_sensitive_names);
+    cm.load_plugin_states();
+    // An unknown color only falls back to the default one, so it's not worth failing on
+    for (item, state, color) in theme::find_unknown_colors(&cm.config.items) {
+        eprintln!("{} {}", t!(Warning).yellow(), t!(UnknownColor, item.cyan(), state.yellow(), color));
+    }
+    for (item, state, max_age) in stale::find_invalid_max_ages(&cm.config.items) {
+        eprintln!("{} {}", t!(Warning).yellow(), t!(InvalidMaxAge, item.cyan(), state.yellow(), max_age));
+    }
+    if ephemeral {
+        eprintln!("{}", t!(EphemeralConfig).dimmed());
+    }
+    else if ! read_only {
+        let trash_days = cm.config.defaults.trash_days.unwrap_or(trash::DEFAULT_TRASH_DAYS);
+        match Trash::new(cm.get_path()).purge(trash_days, chrono::Local::now()) {
+            Ok(0) => {},
+            Ok(purged) => log::info!("Purged {} item(s) from the trash", purged),
+            Err(err) => eprintln!("{} {:#}", t!(Warning).yellow(), err)
+        }
+    }
+
+    let history = if ephemeral || read_only { History::disabled() } else { History::new(cm.get_path()) };
+    let overlay = SettingsOverlay::parse(&cli.set)?;
+    let mut switch_options = SwitchOptions {
+        dry_run: cli.dry_run,
+        log_dir: cm.get_path().join("logs"),
+        log_by_default: cm.config.defaults.log,
+        notify_by_default: cm.config.defaults.notify,
+        shell_by_default: env_vars.shell.unwrap_or(cm.config.defaults.shell),
+        show_command_by_default: cm.config.defaults.show_command,
+        quiet: cli.quiet,
+        force: cli.force,
+        record_interrupted: *overlay.on_interrupt.as_ref().unwrap_or(&cm.config.defaults.on_interrupt) == InterruptPolicy::Record,
+        askpass: overlay.askpass.clone().or_else(|| cm.config.defaults.askpass.clone()),
+        command_override: cli.with,
+        confirm_run: (cli.confirm || *overlay.confirm_run.as_ref().unwrap_or(&cm.config.defaults.confirm_run) == ConfirmRun::Always)
+            && ! cli.yes,
+        preflight: ! cli.no_preflight,
+        debug_exec: cli.debug_exec,
+        full: cli.full,
+        overlay,
+        change_plan: (cli.dry_run && cli.format == Format::Json).then(ChangePlan::default)
+    };
+
+    let write_policy = WritePolicy {
+        confirm: cli.confirm_writes || cm.config.defaults.confirm_writes,
+        yes: cli.yes,
+        verbose: cli.verbose
+    };
+
+    let sort = cli.sort.unwrap_or(cm.config.defaults.sort);
+    let item_order = sort::get_order(
+        &cm.config.items,
+        sort,
+        &if sort == ItemSort::Recent { history.read()? } else { vec![] }
+    );
+    let item_filter = ItemFilter {
+        item_type: cli.item_type.clone(),
+        tag: cli.tag.clone(),
+        in_state: cli.in_state.clone(),
+        has_state: cli.has_state.clone()
+    };
+    let item_order = item_filter.apply(&cm.config.items, &item_order);
+
+    let colorful_theme = ColorfulTheme {
+        prompt_style: Style::new().for_stderr().cyan(),
+        ..ColorfulTheme::default()
+    };
+    let picker = Picker {
+        theme: &colorful_theme,
+        numbered: cli.numbers,
+        match_mode: cm.config.defaults.match_mode,
+        show_header: ! cli.no_header && cm.config.defaults.show_header.unwrap_or(true)
+    };
+
+    // Bare `switchy` on a just created config walks through a first item, rather than failing for having none
+    let onboarding = ! cli.no_onboarding && onboarding::is_first_run(&cm);
+    if onboarding {
+        match onboarding::welcome(&colorful_theme, cm.get_file_path())? {
+            Some(first_item) => {
+                cli.add = Some(first_item.name);
+                cli.template = first_item.recipe_id;
+            },
+            None => return onboarding::finish(&colorful_theme)
+        }
+    }
+
+    if let Some(name) = cli.add {
+        // A name with spaces around can't be told apart from the trimmed one when typed
+        let mut name = name.trim().to_string();
+        if name.is_empty() {
+            bail!(t!(EmptyItemName));
+        }
+        let mut replaced_index = None;
+        if find_same_name(&cm, &name).is_some() {
+            match resolve_add_conflict(&colorful_theme, &cm, &name, cli.replace, cli.rename_to.as_deref())? {
+                AddConflict::Edit(item_index) => {
+                    let item = &mut cm.config.items[item_index];
+                    item.check_states_editable()?;
+                    if ! wizard::reorder_states(&colorful_theme, item)? {
+                        eprintln!("{}", t!(DiscardedOrder));
+                        return Ok(());
+                    }
+                    eprintln!("{}", t!(ReorderedStates, item.get_name().cyan()));
+                    eprintln!("{}", cm.config.items[item_index]);
+                    write_config(&cm, &colorful_theme, &write_policy)?;
+                    return Ok(());
+                },
+                AddConflict::Rename(new_name) => name = new_name,
+                // The replacement keeps the existing name, so the history and presets of the item still apply
+                AddConflict::Replace(item_index) => {
+                    name = cm.config.items[item_index].get_name().to_string();
+                    replaced_index = Some(item_index);
+                },
+                AddConflict::Abort => {
+                    eprintln!("{}", t!(DiscardedItem, name.cyan()));
+                    return Ok(());
+                }
+            }
+        }
+        let recipe = match &cli.template {
+            Some(id) => match recipe::find_recipe(id) {
+                Some(recipe) => Some(recipe),
+                None => bail!(t!(RecipeNotFound, id.yellow()))
+            },
+            None => None
+        };
+
+        eprintln!("{}", if replaced_index.is_some() { t!(ReplacingItem, name.cyan()) } else { t!(AddingItem, name.cyan()) });
+
+        let from_wizard = cli.on.is_none();
+        let item = match (recipe, cli.on, cli.off) {
+            (_, Some(on), Some(off)) => Some(ConfigItem::new_boolean(name.clone(), on, off)),
+            (Some(recipe), _, _) => wizard::add_item_from_recipe(&colorful_theme, name.clone(), &recipe)?,
+            _ => wizard::add_item(&colorful_theme, name.clone(), cm.config.defaults.shell)?
+        };
+        let Some(mut item) = item else {
+            eprintln!("{}", t!(DiscardedItem, name.cyan()));
+            return Ok(());
+        };
+        warn_identical_commands(&item);
+        if from_wizard {
+            wizard::prompt_default_state(&colorful_theme, &mut item)?;
+        }
+        let apply = from_wizard && wizard::prompt_current_state(&colorful_theme, &mut item)?;
+
+        let item_index = match replaced_index {
+            Some(item_index) => {
+                cm.config.items[item_index] = item;
+                item_index
+            },
+            None => {
+                cm.config.items.push(item);
+                cm.config.items.len() - 1
+            }
+        };
+        write_config(&cm, &colorful_theme, &write_policy)?;
+
+        // The item is applied only once it's saved, so a failing state still leaves it added
+        if apply && cm.is_written() {
+            let item = &mut cm.config.items[item_index];
+            let state = item.get_current_state().to_string();
+            switch::switch(item, state, &switch_options, &history)?;
+            if ! cli.dry_run {
+                cm.write_switched()?;
+                run_on_change_hook(&cm, cli.quiet);
+            }
+        }
+    }
+
+    else if let Some(source) = &cli.import {
+        // Everything is fetched and validated before anything changes
+        let items = import::fetch_items(source)?;
+        if items.is_empty() {
+            eprintln!("{}", t!(ImportedNothing, source));
+            return Ok(());
+        }
+
+        let mut changed = false;
+        for item in items {
+            let name = item.get_name().to_string();
+            if cm.get_unavailable_items().any(|hidden| hidden.get_name() == name) {
+                eprintln!("{}", t!(SkippedHiddenImport, name.cyan()));
+                continue;
+            }
+            let Some(item_index) = cm.config.find_item(&name) else {
+                eprintln!("{}", if cli.dry_run { t!(WouldImport, name.cyan()) } else { t!(Importing, name.cyan()) });
+                cm.config.items.push(item);
+                changed = true;
+                continue;
+            };
+
+            let replace = cli.yes || {
+                eprintln!("{}", t!(ItemExists, name.cyan()));
+                let choices = [t!(KeepExisting), t!(ReplaceWithImported)];
+                let Some(choice) = Select::with_theme(&colorful_theme)
+                    .with_prompt(t!(ImportConflict, name.cyan()))
+                    .items(&choices)
+                    .default(0)
+                    .interact_opt()?
+                else {
+                    return Ok(());
+                };
+                choice == 1
+            };
+            if replace {
+                eprintln!(
+                    "{}",
+                    if cli.dry_run { t!(WouldReplaceImported, name.cyan()) } else { t!(ReplacingImported, name.cyan()) }
+                );
+                cm.config.items[item_index] = item;
+                changed = true;
+            }
+        }
+
+        if changed && ! cli.dry_run {
+            write_config(&cm, &colorful_theme, &write_policy)?;
+        }
+    }
+
+    else if cli.discover {
+        eprintln!("{}", t!(Probing));
+        add_candidates(&mut cm, discover::probe_all(), &colorful_theme, &write_policy, false)?;
+    }
+
+    else if let Some(args) = cli.import_from {
+        let kind = MigrationKind::from_str(&args[0], true).map_err(|_| anyhow!(t!(UnknownMigrationKind, args[0])))?;
+        let candidates = migrate::find_candidates(kind, &fs_util::expand_home(&args[1]))?;
+        add_candidates(&mut cm, candidates, &colorful_theme, &write_policy, cli.yes)?;
+    }
+
+    else if cli.templates {
+        let recipes = recipe::get_recipes();
+        let id_width = recipes.iter().map(|recipe| text::get_width(recipe.id)).max().unwrap_or(0);
+        for recipe in recipes {
+            println!("{}{}  {}", recipe.id.yellow(), text::get_padding(recipe.id, id_width), recipe.description);
+        }
+    }
+
+    else if let Some(name) = cli.set_default {
+        if name.is_empty() {
+            eprintln!("{}", t!(ClearingDefault));
+            cm.config.defaults.default_item = None;
+        }
+        else {
+            let name = cm.config.items[cm.resolve_item(&name)?].get_name().to_string();
+            eprintln!("{}", t!(SettingDefault, name.cyan()));
+            cm.config.defaults.default_item = Some(name);
+        }
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if let Some(name) = cli.log {
+        let name = cm.config.items[cm.resolve_item(&name)?].get_name();
+        match run_log::read_last_run(&switch_options.log_dir, name)? {
+            Some(last_run) => println!("{}", last_run),
+            None => eprintln!("{}", t!(NothingLogged, name.cyan()))
+        }
+    }
+
+    else if let Some(name) = cli.stats {
+        let items: Vec<&ConfigItem> = match &name {
+            Some(name) => vec![&cm.config.items[cm.resolve_item(name)?]],
+            None => cm.config.items.iter().collect()
+        };
+
+        let entries = history.read()?;
+        let item_stats: Vec<stats::ItemStats> = items
+            .iter()
+            .map(|item| stats::compute(&entries, item.get_name(), item.get_current_state()))
+            .collect();
+
+        if cli.format == Format::Json {
+            println!("{}", serde_json::to_string_pretty(&item_stats)?);
+        }
+        else if entries.is_empty() {
+            eprintln!("{}", t!(NoSwitchesRecorded));
+        }
+        else {
+            let now = chrono::Local::now();
+            println!(
+                "{}",
+                item_stats
+                    .iter()
+                    .map(|item_stats| item_stats.render(now))
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            );
+        }
+    }
+
+    else if let Some(name) = cli.detect {
+        let item = &cm.config.items[cm.resolve_item(&name)?];
+        let name = item.get_name();
+        let current_state = item.get_current_state();
+
+        match item.detect_state()? {
+            Some(state) if state == current_state =>
+                println!("{}", t!(DetectedRecorded, name.cyan(), state.yellow())),
+            Some(state) => println!(
+                "{} {}",
+                t!(Warning).yellow(), t!(DetectedDiffering, name.cyan(), state.yellow(), current_state.yellow())
+            ),
+            None => println!(
+                "{} {}",
+                t!(Warning).yellow(), t!(DetectedNone, name.cyan(), current_state.yellow())
+            )
+        }
+    }
+
+    else if let Some(args) = cli.print_path {
+        let item_index = cm.resolve_item(&args[0])?;
+        let item = &mut cm.config.items[item_index];
+        let name = &item.get_name().to_string();
+        let ConfigItem::DirItem(dir_item) = item else {
+            bail!(t!(NoPath, name.cyan(), item.get_type_string()));
+        };
+
+        match args.get(1) {
+            None => {
+                let path = dir_item
+                    .resolve_path(&dir_item.current)
+                    .with_context(|| t!(CurrentStateMissing, name.cyan(), dir_item.current.yellow()))?;
+                println!("{}", path.display());
+            },
+            Some(state) => {
+                let state = item.get_state_names()[item.resolve_state(state)?].to_string();
+                // Only the path is printed
+                switch_options.quiet = true;
+                switch::switch(item, state, &switch_options, &history)?;
+                if ! switch_options.dry_run {
+                    cm.write_switched()?;
+                    run_on_change_hook(&cm, true);
+                }
+            }
+        }
+    }
+
+    else if let Some(args) = cli.edit {
+        let item_index = cm.resolve_item(&args[0])?;
+        let item = &mut cm.config.items[item_index];
+        let name = &item.get_name().to_string();
+        item.check_states_editable()?;
+
+        match (args.get(1), cli.to) {
+            (Some(state), Some(position)) => {
+                let from = item.resolve_state(state)?;
+                let state = &item.get_state_names()[from].to_string();
+                let state_count = item.get_state_names().len();
+                if ! (1 ..= state_count).contains(&position) {
+                    bail!(t!(PositionOutOfRange, position, state_count));
+                }
+                item.move_state(from, position - 1);
+                eprintln!("{}", t!(MovedState, name.cyan(), state.yellow(), position));
+            },
+            (Some(_), None) => bail!(t!(MissingPosition)),
+            (None, Some(_)) => bail!(t!(MissingStateToMove)),
+            (None, None) => {
+                if ! wizard::reorder_states(&colorful_theme, item)? {
+                    eprintln!("{}", t!(DiscardedOrder));
+                    return Ok(());
+                }
+                eprintln!("{}", t!(ReorderedStates, name.cyan()));
+            }
+        }
+        eprintln!("{}", cm.config.items[item_index]);
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if let Some((args, moving)) = cli.copy_state.map(|args| (args, false)).or(cli.move_state.map(|args| (args, true))) {
+        let (from_name, state, to_name) = (&args[0], &args[1], &args[2]);
+        let new_name = &args.get(3).unwrap_or(state).trim().to_string();
+        if new_name.is_empty() {
+            bail!(t!(EmptyStateName));
+        }
+        let from_index = cm.resolve_item(from_name)?;
+        let to_index = cm.resolve_item(to_name)?;
+
+        let from_item = &cm.config.items[from_index];
+        let to_item = &cm.config.items[to_index];
+        let (from_name, to_name) = (&from_item.get_name().to_string(), &to_item.get_name().to_string());
+        let state = &from_item.get_state_names()[from_item.resolve_state(state)?].to_string();
+        if moving {
+            from_item.check_states_editable()?;
+        }
+        to_item.check_states_editable()?;
+        let mut value = from_item.get_state_value(state)?;
+        if from_item.get_type_string() != to_item.get_type_string() {
+            bail!(t!(StateTypesDiffer, from_name.cyan(), from_item.get_type_string(), to_name.cyan(), to_item.get_type_string()));
+        }
+        // A state moved within its item may keep its name, or change its case
+        let collides = to_item.get_state_names()
+            .iter()
+            .any(|name| config::is_same_name(name, new_name) && ! (moving && from_index == to_index && name == state));
+        if collides {
+            bail!(t!(StateExists, to_name.cyan(), new_name.yellow()));
+        }
+        let is_current = from_item.get_current_state() == state;
+        if moving && is_current && from_index != to_index {
+            if from_item.get_state_names().len() == 1 {
+                bail!(t!(MovingOnlyState, from_name.cyan(), state.yellow()));
+            }
+            if ! cli.force {
+                bail!(t!(MovingCurrentState, state.yellow(), from_name.cyan()));
+            }
+        }
+
+        value.as_table_mut().unwrap().insert("name".to_string(), toml::Value::String(new_name.clone()));
+        if moving {
+            let from_item = &mut cm.config.items[from_index];
+            from_item.remove_state(state);
+            if is_current {
+                // The current state of the same item is renamed, otherwise the first remaining one is recorded without switching
+                *from_item.get_current_state_mut() = if from_index == to_index {
+                    new_name.clone()
+                }
+                else {
+                    from_item.get_state_names()[0].to_string()
+                };
+            }
+        }
+        cm.config.items[to_index].add_state_value(value)?;
+
+        if moving {
+            eprintln!("{}", t!(MovedStateTo, from_name.cyan(), state.yellow(), to_name.cyan(), new_name.yellow()));
+        }
+        else {
+            eprintln!("{}", t!(CopiedState, from_name.cyan(), state.yellow(), to_name.cyan(), new_name.yellow()));
+        }
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if let Some(args) = &cli.rename_state_all {
+        let (state, new_name) = (&args[0], args[1].trim());
+        if new_name.is_empty() {
+            bail!(t!(EmptyStateName));
+        }
+        // The items for other machines are in the same files, so they're renamed too
+        let mut renamed: Vec<(String, bool)> = vec![];
+        for item in cm.config.items.iter().chain(cm.get_unavailable_items()) {
+            if ! item.has_state(state) || item.check_states_editable().is_err() {
+                continue;
+            }
+            if item.get_state_names().iter().any(|name| config::is_same_name(name, new_name)) {
+                eprintln!("{} {}", t!(Warning).yellow(), t!(SkippingStateExists, item.get_name().cyan(), new_name.yellow()));
+                continue;
+            }
+            renamed.push((item.get_name().to_string(), item.get_current_state() == state));
+        }
+        if renamed.is_empty() {
+            bail!(t!(NoItemHasState, state.yellow()));
+        }
+
+        eprintln!("{}", t!(RenamingStateIn, state.yellow(), new_name.yellow(), renamed.len()));
+        for (name, is_current) in &renamed {
+            let current = if *is_current { format!(" {}", t!(IsCurrentState).dimmed()) } else { String::new() };
+            eprintln!("  {}{}", name.cyan(), current);
+        }
+        if cli.dry_run {
+            return Ok(());
+        }
+        if ! cli.yes && ! Confirm::with_theme(&colorful_theme)
+            .with_prompt(t!(RenameStates))
+            .default(false)
+            .interact()?
+        {
+            eprintln!("{}", t!(DiscardedChanges));
+            return Ok(());
+        }
+
+        let is_renamed = |name: &str| renamed.iter().any(|(renamed_name, _)| renamed_name == name);
+        for item in cm.config.items.iter_mut().filter(|item| is_renamed(item.get_name())) {
+            item.rename_state(state, new_name);
+        }
+        for item in cm.get_unavailable_items_mut().filter(|item| is_renamed(item.get_name())) {
+            item.rename_state(state, new_name);
+        }
+        for preset in cm.config.presets.values_mut() {
+            for (item_name, preset_state) in preset.iter_mut() {
+                if is_renamed(item_name) && preset_state == state {
+                    *preset_state = new_name.to_string();
+                }
+            }
+        }
+        eprintln!("{}", t!(RenamedStateIn, state.yellow(), new_name.yellow(), renamed.len()));
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if let Some(args) = cli.encrypt_state {
+        let item_index = cm.resolve_item(&args[0])?;
+        let item = &mut cm.config.items[item_index];
+        let state_index = item.resolve_state(&args[1])?;
+        let ConfigItem::CommandItem(command_item) = item else {
+            bail!(t!(NotEncryptable, item.get_name().cyan(), item.get_type_string()));
+        };
+        let name = &command_item.name;
+        let state = &mut command_item.states[state_index];
+        let state_name = &state.name;
+        let StateCommand::Plain(command) = &state.command else {
+            bail!(t!(AlreadyEncrypted, state_name.yellow(), name.cyan()));
+        };
+
+        let encrypted = secret::encrypt(command, cm.config.defaults.askpass.as_deref())?;
+        state.command = StateCommand::Encrypted { encrypted };
+        eprintln!("{}", t!(EncryptedState, state_name.yellow(), name.cyan()));
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if cli.save {
+        let mut items: Vec<Option<ConfigItem>> = std::mem::take(&mut cm.config.items).into_iter().map(Some).collect();
+        cm.config.items = item_order.iter().filter_map(|&index| items[index].take()).collect();
+        eprintln!("{}", t!(SavedSort));
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if cli.sync {
+        sync::run(cm.get_path(), &cm.config.sync, cm.config.defaults.shell, &history.read()?, false)?;
+    }
+
+    else if cli.prune {
+        let candidates = prune::analyze(&cm.config.items, &history.read()?, chrono::Local::now(), cli.stale_days);
+        if candidates.is_empty() {
+            eprintln!("{}", t!(NothingToPrune));
+            return Ok(());
+        }
+        if cli.dry_run {
+            println!("{}\n{}", t!(PruneCandidates), candidates
+                .iter()
+                .map(|candidate| format!("  {}", candidate))
+                .collect::<Vec<_>>()
+                .join("\n")
+            );
+            return Ok(());
+        }
+
+        let Some(picked) = MultiSelect::with_theme(&colorful_theme)
+            .with_prompt(t!(ToRemove))
+            .items(&candidates)
+            .interact_opt()?
+        else {
+            return Ok(());
+        };
+        if picked.is_empty() {
+            eprintln!("{}", t!(RemovedNothing));
+            return Ok(());
+        }
+
+        let mut pruned: Vec<ConfigItem> = vec![];
+        for candidate in picked.into_iter().map(|index| &candidates[index]) {
+            match &candidate.target {
+                prune::PruneTarget::Item(name) => {
+                    let Some(item_index) = cm.config.find_item(name) else {
+                        continue;
+                    };
+                    eprintln!("{}", t!(RemovingItem, name.cyan()));
+                    pruned.push(cm.config.items.remove(item_index));
+                    if cm.config.defaults.default_item.as_ref() == Some(name) {
+                        cm.config.defaults.default_item = None;
+                    }
+                },
+                prune::PruneTarget::State(name, state) => {
+                    // The item may be removed too
+                    let Some(item_index) = cm.config.find_item(name) else {
+                        continue;
+                    };
+                    let item = &mut cm.config.items[item_index];
+                    if item.get_current_state() == state {
+                        eprintln!("{}", t!(KeptCurrentState, state.yellow(), name.cyan()));
+                        continue;
+                    }
+                    eprintln!("{}", t!(RemovingState, state.yellow(), name.cyan()));
+                    item.remove_state(state);
+                }
+            }
+        }
+        // Into the trash before the config is written, like `--remove`
+        let trash = Trash::new(cm.get_path());
+        let names: Vec<String> = pruned.iter().map(|item| item.get_name().to_string()).collect();
+        for item in pruned {
+            trash.add(item)?;
+        }
+        let written = write_config(&cm, &colorful_theme, &write_policy);
+        for name in &names {
+            if cm.is_written() {
+                eprintln!("{}", t!(MovedToTrash, name.cyan()));
+            }
+            else if let Some((_, rest)) = trash.take(name)? {
+                trash.finish_take(rest)?;
+            }
+        }
+        written?;
+    }
+
+    else if cli.snapshot {
+        snapshot::write(cm.get_path(), &cm.config)?;
+        eprintln!("{}", t!(WroteSnapshot));
+    }
+
+    else if let Some(name) = cli.remove {
+        let index = cm.resolve_item(&name)?;
+        let name = cm.config.items[index].get_name().to_string();
+        eprintln!("{}", t!(RemovingItem, name.cyan()));
+        let item = cm.config.items.swap_remove(index);
+        if cm.config.defaults.default_item.as_ref() == Some(&name) {
+            cm.config.defaults.default_item = None;
+        }
+        // Into the trash before the config is written, so failing to add it there can't lose the item
+        let trash = Trash::new(cm.get_path());
+        if ! cli.purge {
+            trash.add(item)?;
+        }
+        let written = write_config(&cm, &colorful_theme, &write_policy);
+        if ! cli.purge && cm.is_written() {
+            eprintln!("{}", t!(MovedToTrash, name.cyan()));
+        }
+        else if ! cli.purge {
+            // The item is still in the config, so it's taken back out of the trash
+            if let Some((_, rest)) = trash.take(&name)? {
+                trash.finish_take(rest)?;
+            }
+        }
+        written?;
+    }
+
+    else if cli.trash {
+        let entries = Trash::new(cm.get_path()).read()?;
+        if entries.is_empty() {
+            eprintln!("{}", t!(TrashEmpty));
+        }
+        for entry in entries.iter().rev() {
+            println!(
+                "{} [{}]  {}",
+                entry.item.get_name(),
+                entry.item.get_type_string(),
+                t!(RemovedAt, entry.removed.format("%Y-%m-%d %H:%M:%S")).dimmed()
+            );
+        }
+    }
+
+    else if let Some(name) = cli.restore_item {
+        let trash = Trash::new(cm.get_path());
+        let Some((item, rest)) = trash.take(&name)? else {
+            bail!(t!(NotInTrash, name.cyan()));
+        };
+        let name = item.get_name().to_string();
+        match find_same_name(&cm, &name).map(|existing| existing.get_name()) {
+            Some(existing) if existing == name => bail!("{}. {}", t!(ItemExists, name.cyan()), t!(RemoveBeforeRestore)),
+            Some(existing) => bail!(t!(ItemExistsInOtherCase, name.cyan(), existing.cyan())),
+            None => {}
+        }
+        eprintln!("{}", t!(RestoringItem, name.cyan()));
+        cm.config.items.push(item);
+        write_config(&cm, &colorful_theme, &write_policy)?;
+        if cm.is_written() {
+            trash.finish_take(rest)?;
+        }
+    }
+
+    else if cli.batch {
+        if cm.config.items.is_empty() {
+            bail!(t!(NoItemsYet));
+        }
+
+        let item_names: Vec<&str> = cm.config.items
+            .iter()
+            .map(|item| item.get_name())
+            .collect();
+
+        let Some(item_indices) = MultiSelect::with_theme(&colorful_theme)
+            .with_prompt(t!(ItemsToSwitch))
+            .items(&item_names)
+            .interact_opt()?
+        else {
+            return Ok(());
+        };
+
+        let mut plan: Vec<PlannedSwitch> = vec![];
+        let total = item_indices.len();
+        for (nth, item_index) in item_indices.into_iter().enumerate() {
+            let item = &cm.config.items[item_index];
+            eprintln!("{}/{}: {}", nth + 1, total, item.get_name().cyan());
+
+            match pick_state(&picker, item, &switch_options, &history)? {
+                Some(state) => plan.push(PlannedSwitch { item_index, state }),
+                None => eprintln!("{}", t!(Skipped, item.get_name().cyan()))
+            }
+        }
+
+        if plan.is_empty() {
+            eprintln!("{}", t!(NothingToSwitch));
+            return Ok(());
+        }
+
+        eprintln!("{}\n{}", t!(PlannedSwitches), switch::format_plan(&cm.config.items, &plan));
+        if ! cli.yes && ! Confirm::with_theme(&colorful_theme)
+            .with_prompt(t!(Proceed))
+            .default(true)
+            .interact()?
+        {
+            return Ok(());
+        }
+
+        let results = switch::execute(&mut cm.config.items, plan, &switch_options, &history, true);
+        eprintln!("\n{}", switch::format_summary(&cm.config.items, &results));
+
+        if write_switch_results(&cm, &results, cli.dry_run, cli.quiet)? > 0 {
+            bail!(t!(BatchStopped));
+        }
+    }
+
+    else if let Some(state) = cli.all {
+        let mut plan: Vec<PlannedSwitch> = vec![];
+        let mut skipped: Vec<SwitchResult> = vec![];
+        for (item_index, item) in cm.config.items.iter().enumerate() {
+            if item.has_state(&state) {
+                plan.push(PlannedSwitch { item_index, state: state.clone() });
+            }
+            else {
+                eprintln!("{}", t!(SkippingNoState, item.get_name().cyan(), state.yellow()));
+                skipped.push((
+                    PlannedSwitch { item_index, state: state.clone() },
+                    SwitchOutcome::Skipped(t!(SkippedNoState))
+                ));
+            }
+        }
+
+        if plan.is_empty() {
+            bail!(t!(NoItemHasState, state.yellow()));
+        }
+
+        let mut results = switch::execute(&mut cm.config.items, plan, &switch_options, &history, cli.fail_fast);
+        results.append(&mut skipped);
+        results.sort_by_key(|(switch, _)| switch.item_index);
+        eprintln!("\n{}", switch::format_summary(&cm.config.items, &results));
 
-#[derive(Parser)]
-#[command(version)]
-#[command(about = "Easily switch your config items in terminal", long_about = None)]
-struct Cli {
-    /// Add a config item
-    #[arg(short, long, name = "ADD_NAME", conflicts_with_all = vec!["REMOVE_NAME", "LIST_NAME", "ITEM"])]
-    add: Option<String>,
+        let failed_count = write_switch_results(&cm, &results, cli.dry_run, cli.quiet)?;
+        if failed_count > 0 {
+            bail!(t!(SwitchesFailed, failed_count));
+        }
+    }
 
-    /// Remove a config item
-    #[arg(short, long, name = "REMOVE_NAME", conflicts_with_all = vec!["LIST_NAME", "ITEM"])]
-    remove: Option<String>,
+    else if cli.reset.is_some() || cli.reset_all {
+        let mut plan: Vec<PlannedSwitch> = vec![];
+        if let Some(name) = &cli.reset {
+            let item_index = cm.resolve_item(name)?;
+            let item = &cm.config.items[item_index];
+            let Some(state) = &item.get_options().default_state else {
+                bail!(t!(NoDefaultState, item.get_name().cyan()));
+            };
+            if item.find_missing_default_state().is_some() {
+                bail!(t!(DefaultStateNotFound, item.get_name().cyan(), state.yellow()));
+            }
+            plan.push(PlannedSwitch { item_index, state: state.clone() });
+        }
+        else {
+            for (item_index, item) in cm.config.items.iter().enumerate() {
+                match &item.get_options().default_state {
+                    Some(state) if state == item.get_current_state() => {
+                        eprintln!("{}", t!(SkippingAtDefault, item.get_name().cyan(), state.yellow()));
+                    },
+                    Some(state) => plan.push(PlannedSwitch { item_index, state: state.clone() }),
+                    None => {}
+                }
+            }
+            if plan.is_empty() {
+                eprintln!("{}", t!(NothingToReset));
+                return Ok(());
+            }
+        }
 
-    /// List all config items
-    #[arg(short, long, name = "LIST_NAME", conflicts_with_all = vec!["ITEM"])]
-    list: bool,
+        // An item whose default state is missing fails on its own, without keeping the others from being reset
+        let (plan, missing): (Vec<PlannedSwitch>, Vec<PlannedSwitch>) = plan
+            .into_iter()
+            .partition(|planned| cm.config.items[planned.item_index].find_missing_default_state().is_none());
+        let mut results = switch::execute(&mut cm.config.items, plan, &switch_options, &history, cli.fail_fast);
+        results.extend(missing.into_iter().map(|planned| {
+            let item = &cm.config.items[planned.item_index];
+            let err = anyhow!(t!(DefaultStateNotFound, item.get_name().cyan(), planned.state.yellow()));
+            (planned, SwitchOutcome::Failed(err))
+        }));
+        eprintln!("\n{}", switch::format_summary(&cm.config.items, &results));
 
-    /// Name of the config item to switch, fuzzy
-    #[arg(name = "ITEM")]
-    item: Option<String>,
+        let failed_count = write_switch_results(&cm, &results, cli.dry_run, cli.quiet)?;
+        if failed_count > 0 {
+            bail!(t!(SwitchesFailed, failed_count));
+        }
+    }
 
-    /// Debug mode
-    #[arg(short, long)]
-    debug: bool
-}
+    else if let Some(label) = cli.push {
+        let entry = StackEntry::new(&cm.config.items, label);
+        let item_count = entry.states.len();
+        let depth = Stack::new(cm.get_path()).push(entry)?;
+        eprintln!("{}", t!(PushedSnapshot, item_count, depth));
+    }
 
-fn main_wrapper(cli: Cli) -> Result<()> {
-    let project_dirs = ProjectDirs::from("top", "IceLava", "switchy")
-        .context("Failed to get config dir")?;
-    let mut cm = ConfigManager::new(project_dirs.config_dir());
-    cm.read()?;
+    else if cli.pop {
+        let stack = Stack::new(cm.get_path());
+        let Some(entry) = stack.read()?.pop() else {
+            bail!(t!(StackEmpty));
+        };
+        eprintln!("{}", t!(RestoringSnapshot, describe_stack_entry(&entry)));
 
-    let mut colorful_theme = ColorfulTheme::default();
-    colorful_theme.prompt_style = Style::new().for_stderr().cyan();
+        let mut plan: Vec<PlannedSwitch> = vec![];
+        let mut skipped: Vec<SwitchResult> = vec![];
+        for (name, state) in &entry.states {
+            let Some(item_index) = cm.config.find_item(name) else {
+                eprintln!("{}", t!(SnapshotItemGone, name.cyan()));
+                continue;
+            };
+            let item = &cm.config.items[item_index];
+            let planned = PlannedSwitch { item_index, state: state.clone() };
+            if ! item.has_state(state) {
+                skipped.push((planned, SwitchOutcome::Skipped(t!(SkippedNoState))));
+            }
+            else if item.get_current_state() == state {
+                skipped.push((planned, SwitchOutcome::Skipped(t!(SkippedAlreadyCurrent))));
+            }
+            else {
+                plan.push(planned);
+            }
+        }
+        for item in &cm.config.items {
+            if ! entry.states.contains_key(item.get_name()) {
+                eprintln!("{}", t!(SnapshotItemNew, item.get_name().cyan()));
+            }
+        }
 
-    if let Some(name) = cli.add {
-        if cm.config.items.iter().any(|item| item.get_name() == name) {
-            bail!("Config item {} already exists", name.cyan());
-        }
-
-        println!("Adding config item {}", name.cyan());
-
-        let item_type = Select::with_theme(&colorful_theme)
-            .with_prompt("The type of the item")
-            .default(0)
-            .items(&vec![
-                "Command item"
-            ])
-            .interact()?;
-
-        let item = match item_type {
-            0 => {
-                let mut states: Vec<ConfigCommandItemState> = vec![];
-                loop {
-                    if states.is_empty() {
-                        println!("Adding default state");
-                    }
-                    else if ! Confirm::with_theme(&colorful_theme)
-                        .with_prompt("To add another state?")
-                        .interact()?
-                    {
-                        break;
-                    }
-                    
-                    let name = Input::<String>::with_theme(&colorful_theme)
-                        .with_prompt("State name")
-                        .interact_text()?
-                        .trim()
-                        .to_string();
-
-                    if name.is_empty() {
-                        bail!("State name is empty");
-                    }
-                    if states.iter().any(|state| state.name == name) {
-                        bail!("State name '{}' is used", name);
+        let mut results = if cli.no_run {
+            plan
+                .into_iter()
+                .map(|planned| {
+                    if switch_options.dry_run {
+                        return (planned, SwitchOutcome::DryRun);
                     }
+                    *cm.config.items[planned.item_index].get_current_state_mut() = planned.state.clone();
+                    (planned, SwitchOutcome::Recorded)
+                })
+                .collect()
+        }
+        else {
+            switch::execute(&mut cm.config.items, plan, &switch_options, &history, false)
+        };
+        results.append(&mut skipped);
+        results.sort_by_key(|(switch, _)| switch.item_index);
+        eprintln!("\n{}", switch::format_summary(&cm.config.items, &results));
+
+        let failed_count = write_switch_results(&cm, &results, cli.dry_run, cli.quiet)?;
+        // The snapshot is dropped even if some switches failed, since they're reported above
+        if ! switch_options.dry_run && ! interrupt::is_interrupted() {
+            stack.drop_last()?;
+        }
+        if failed_count > 0 {
+            bail!(t!(SwitchesFailed, failed_count));
+        }
+    }
 
-                    let command = Input::<String>::with_theme(&colorful_theme)
-                        .with_prompt("State command")
-                        .interact_text()?
-                        .trim()
-                        .to_string();
+    else if cli.stack {
+        let entries = Stack::new(cm.get_path()).read()?;
+        if entries.is_empty() {
+            eprintln!("{}", t!(StackEmpty));
+        }
+        for (index, entry) in entries.iter().enumerate() {
+            println!("#{} {}", index + 1, describe_stack_entry(entry));
+        }
+    }
 
-                    states.push(ConfigCommandItemState { name, command });
-                }
-                
-                ConfigItem::CommandItem(ConfigCommandItem {
-                    name,
-                    current: states[0].name.clone(),
-                    states
-                })
-            },
-            _ => unreachable!()
+    else if let Some(name) = cli.save_preset {
+        let item_indices: Vec<usize> = if cli.items.is_empty() {
+            if cm.config.items.is_empty() {
+                bail!(t!(NoItemsYet));
+            }
+            let item_names: Vec<&str> = cm.config.items
+                .iter()
+                .map(|item| item.get_name())
+                .collect();
+            let Some(item_indices) = MultiSelect::with_theme(&colorful_theme)
+                .with_prompt(t!(ItemsForPreset, name.cyan()))
+                .items(&item_names)
+                .interact_opt()?
+            else {
+                return Ok(());
+            };
+            item_indices
+        }
+        else {
+            cli.items.iter().map(|item_name| cm.resolve_item(item_name)).collect::<Result<Vec<usize>>>()?
         };
+        if item_indices.is_empty() {
+            bail!(t!(PresetWithoutItems));
+        }
 
-        cm.config.items.push(item);
-        cm.write()?;
+        let exists = cm.config.presets.contains_key(&name);
+        if exists && ! cli.yes && ! Confirm::with_theme(&colorful_theme)
+            .with_prompt(t!(ReplacePreset, name.cyan()))
+            .default(false)
+            .interact()?
+        {
+            return Ok(());
+        }
+        let states: BTreeMap<String, String> = item_indices
+            .iter()
+            .map(|&index| &cm.config.items[index])
+            .map(|item| (item.get_name().to_string(), item.get_current_state().to_string()))
+            .collect();
+        eprintln!("{}", t!(SavedPreset, name.cyan(), states.len()));
+        cm.config.presets.insert(name, states);
+        write_config(&cm, &colorful_theme, &write_policy)?;
     }
 
-    else if let Some(name) = cli.remove {
-        if let Some(index) = cm.config.items.iter().position(|item| item.get_name() == name) {
-            println!("Removing config item {}", name.cyan());
-            cm.config.items.swap_remove(index);
-            cm.write()?;
+    else if let Some(name) = cli.preset {
+        let Some(preset) = cm.config.presets.get(&name) else {
+            bail!(t!(PresetNotFound, name.cyan()));
+        };
+        // A preset outlives the items and states it names, so those are only warned about
+        let mut plan: Vec<PlannedSwitch> = vec![];
+        for (item_name, state) in preset {
+            match cm.config.find_item(item_name) {
+                None => eprintln!("{} {}", t!(Warning).yellow(), t!(PresetItemGone, item_name.cyan(), name.cyan())),
+                Some(item_index) if ! cm.config.items[item_index].has_state(state) => eprintln!(
+                    "{} {}",
+                    t!(Warning).yellow(),
+                    t!(PresetStateGone, item_name.cyan(), state.yellow(), name.cyan())
+                ),
+                Some(item_index) => plan.push(PlannedSwitch { item_index, state: state.clone() })
+            }
+        }
+        if plan.is_empty() {
+            bail!(t!(PresetNothingToSwitch, name.cyan()));
+        }
+
+        let mut results = switch::execute(&mut cm.config.items, plan, &switch_options, &history, false);
+        results.sort_by_key(|(switch, _)| switch.item_index);
+        eprintln!("\n{}", switch::format_summary(&cm.config.items, &results));
+
+        let failed_count = write_switch_results(&cm, &results, cli.dry_run, cli.quiet)?;
+        if failed_count > 0 {
+            bail!(t!(SwitchesFailed, failed_count));
+        }
+    }
+
+    else if let Some(name) = cli.remove_preset {
+        if cm.config.presets.remove(&name).is_none() {
+            bail!(t!(PresetNotFound, name.cyan()));
+        }
+        eprintln!("{}", t!(RemovedPreset, name.cyan()));
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if let Some(args) = cli.rename_preset {
+        let (name, new_name) = (&args[0], &args[1]);
+        if cm.config.presets.contains_key(new_name) {
+            bail!(t!(PresetExists, new_name.cyan()));
+        }
+        let Some(preset) = cm.config.presets.remove(name) else {
+            bail!(t!(PresetNotFound, name.cyan()));
+        };
+        cm.config.presets.insert(new_name.clone(), preset);
+        eprintln!("{}", t!(RenamedPreset, name.cyan(), new_name.cyan()));
+        write_config(&cm, &colorful_theme, &write_policy)?;
+    }
+
+    else if cli.list.is_some() && cli.presets {
+        if cm.config.presets.is_empty() {
+            eprintln!("{}", t!(NoPresetsYet));
+        }
+        else {
+            println!("{}\n\n{}", t!(ListingPresets, cm.config.presets.len()), list::render_presets(&cm.config));
+        }
+    }
+
+    else if cli.export_script {
+        let item_indices = cli.items.iter().map(|name| cm.resolve_item(name)).collect::<Result<Vec<usize>>>()?;
+        let items: Vec<&ConfigItem> = cm.config.items
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| item_indices.is_empty() || item_indices.contains(index))
+            .map(|(_, item)| item)
+            .collect();
+        print!("{}", export_script::generate(cli.shell, &items));
+    }
+
+    else if let Some(Some(name)) = &cli.list {
+        let item = &cm.config.items[cm.resolve_item(name)?];
+        if cli.format == Format::Json {
+            println!("{}", serde_json::to_string_pretty(item)?);
         }
         else {
-            bail!("Config item {} doesn't exist", name.cyan());
+            println!("{}", item.render(cli.numbers, cli.verbose, cli.full));
         }
     }
 
-    else if cli.list {
+    else if cli.list.is_some() && cli.format == Format::Json {
+        let items: Vec<&ConfigItem> = item_order.iter().map(|&index| &cm.config.items[index]).collect();
+        println!("{}", serde_json::to_string_pretty(&items)?);
+    }
+
+    else if cli.list.is_some() {
         let len = cm.config.items.len();
         if len == 0 {
-            println!("No config items yet.");
+            eprintln!("{}", t!(NoItemsYetListed));
+        }
+        else if item_order.is_empty() {
+            bail!(t!(NoItemsMatched, item_filter.describe()));
         }
         else {
-            println!(
-                "Listing all {} config item(s):\n\n{}",
-                len,
-                cm.config.items
-                    .iter()
-                    .map(String::from)
-                    .collect::<Vec<_>>()
-                    .join("\n\n")
-            );
+            let list_options = ListOptions {
+                numbered: cli.numbers,
+                verbose: cli.verbose,
+                full: cli.full,
+                group_by: cli.group_by,
+                first_tag_only: cli.first_tag,
+                overdue: stale::find_overdue(&cm.config.items, &history.read()?, chrono::Local::now())
+            };
+            let header = if item_filter.is_active() {
+                t!(ListingMatchingItems, item_order.len(), len)
+            }
+            else {
+                t!(ListingItems, len)
+            };
+            println!("{}\n\n{}", header, list::render(&cm, &item_order, &list_options));
+        }
+        if cli.all_machines {
+            for item in cm.get_unavailable_items() {
+                let only_on = item.get_options().only_on.as_ref().map(ToString::to_string).unwrap_or_default();
+                let line = format!("{} [{}]  {}", item.get_name(), item.get_type_string(), t!(NotForThisMachine, only_on));
+                println!("\n{}", line.dimmed());
+            }
+        }
+    }
+
+    else if cli.watch {
+        let watched = watch::find_watched(&cm.config.items);
+        if watched.is_empty() {
+            bail!(t!(NoWatchedItems));
+        }
+        let mut fingerprints = watch::Fingerprints::read(cm.get_path());
+        if cli.once {
+            let failed = watch::check(
+                &mut cm.config.items,
+                &watched.iter().collect::<Vec<_>>(),
+                &mut fingerprints,
+                &switch_options,
+                &history
+            )?;
+            if failed > 0 {
+                bail!(t!(SwitchesFailed, failed));
+            }
+        }
+        else {
+            watch::run(&mut cm.config.items, &watched, &mut fingerprints, &switch_options, &history)?;
+        }
+    }
+
+    else if cli.tui {
+        if cm.config.items.is_empty() {
+            bail!(t!(NoItemsYetHelp));
         }
+        #[cfg(feature = "tui")]
+        tui::run(
+            &mut cm,
+            &switch_options,
+            &history,
+            item_order,
+            &|cm| run_on_change_hook(cm, cli.quiet)
+        )?;
+        #[cfg(not(feature = "tui"))]
+        bail!(t!(TuiUnsupported));
     }
 
     else {
         if cm.config.items.is_empty() {
-            Err(anyhow!("No config items yet. Use `--add` to add one. Use `--help` for more information"))?;
+            Err(anyhow!(t!(NoItemsYetHelp)))?;
         }
         else {
-            let item_names: Vec<&str> = cm.config.items
-                .iter()
-                .map(|item| item.get_name())
-                .collect();
+            // Parse the schedule before any prompt, to fail early on invalid times
+            let scheduled_time = match (&cli.at, &cli.in_duration) {
+                (Some(time), _) => Some(schedule::parse_at(time)?),
+                (_, Some(duration)) => Some(schedule::parse_in(duration)?),
+                _ => None
+            };
+            let switch_back_after = cli.for_duration.as_deref().map(schedule::parse_duration).transpose()?;
 
-            let item_name = cli.item.unwrap_or("".to_string());
-            let item_index = item_names
-                .iter()
-                .position(|name| *name == item_name)
-                .map_or_else(
-                    || FuzzySelect::with_theme(&colorful_theme)
-                        .with_initial_text(item_name)
-                        .default(0)
-                        .items(&item_names)
-                        .interact(),
-                    Ok
-                )?;
+            if cli.item.is_none() && ! ephemeral {
+                let overdue = stale::find_overdue(&cm.config.items, &history.read().unwrap_or_default(), chrono::Local::now());
+                if ! overdue.is_empty() {
+                    let listed: Vec<String> = overdue
+                        .iter()
+                        .map(|overdue| format!("{} ({})", overdue.item.cyan(), overdue.state.yellow()))
+                        .collect();
+                    eprintln!("{} {}", t!(Warning).yellow(), t!(OverdueItems, listed.join(", ")));
+                }
+            }
+
+            // Esc in the state picker goes back to the item picker, if the item was picked there, with the text typed
+            let mut last_picked: Option<usize> = None;
+            let mut last_text: Option<String> = None;
+            let (item_index, new_state) = loop {
+                let mut from_picker = false;
+                let mut pick = |initial_text: &str| {
+                    from_picker = true;
+                    if item_order.is_empty() {
+                        bail!(t!(NoItemsMatched, item_filter.describe()));
+                    }
+                    let initial_text = last_text.take().unwrap_or_else(|| initial_text.to_string());
+                    let (picked, text) = pick_item(&picker, &cm.config.items, &item_order, &initial_text, last_picked)?;
+                    last_text = Some(text);
+                    Ok(picked)
+                };
+                let item_index = match &cli.item {
+                    None if ephemeral => bail!(t!(EphemeralNeedsItem)),
+                    None if cli.last => {
+                        let entries = history.read()?;
+                        let last_item = entries
+                            .iter()
+                            .rev()
+                            .find_map(|entry| cm.config.find_item(&entry.item));
+                        match last_item {
+                            Some(item_index) => Some(item_index),
+                            None if entries.is_empty() => bail!(t!(NoLastItem)),
+                            None => bail!(t!(LastItemsGone))
+                        }
+                    },
+                    // With the state given too, nothing is prompted for, so the item must match
+                    Some(item_name) if ephemeral || cli.state.is_some() => Some(cm.resolve_item(item_name)?),
+                    Some(item_name) => match cm.config.match_item(item_name)? {
+                        Some(item_index) => Some(item_index),
+                        None => {
+                            cm.check_available(item_name)?;
+                            pick(item_name)?
+                        }
+                    },
+                    None if cli.pick || item_filter.is_active() => pick("")?,
+                    None => match &cm.config.defaults.default_item {
+                        Some(default_item) => match cm.config.find_item(default_item) {
+                            Some(item_index) => Some(item_index),
+                            None => {
+                                eprintln!("{} {}", t!(Warning).yellow(), t!(DefaultItemNotFound, default_item.cyan()));
+                                pick("")?
+                            }
+                        },
+                        None if cm.config.items.len() == 1 => {
+                            eprintln!("{}", t!(OnlyOneItem, cm.config.items[0].get_name().cyan()));
+                            Some(0)
+                        },
+                        None => pick("")?
+                    }
+                };
+                let Some(item_index) = item_index else {
+                    return Ok(());
+                };
+
+                let item = &cm.config.items[item_index];
+
+                // A wrong item matched by a typo would be switched as well as the right one
+                if let Some(item_name) = &cli.item {
+                    if cm.config.defaults.confirm_fuzzy && item.get_name() != item_name && ! cli.yes {
+                        eprintln!("{}", t!(MatchedItem, item.get_name().cyan()));
+                        if Confirm::with_theme(&colorful_theme)
+                            .with_prompt(t!(ContinueWithItem))
+                            .default(true)
+                            .interact_opt()? != Some(true)
+                        {
+                            bail!(RunDeclined);
+                        }
+                    }
+                }
+
+                let new_state = if let Some(state) = &cli.state {
+                    let index = item.resolve_state(state)?;
+                    let state = &item.get_state_names()[index].to_string();
+                    match item.check_available_if(index, &switch_options) {
+                        Some(reason) if ! cli.force => {
+                            bail!(t!(StateUnavailableNow, state.yellow(), item.get_name().cyan(), reason));
+                        },
+                        None if cli.dry_run && item.get_state_available_if(index).is_some() => {
+                            eprintln!("{}", t!(AvailableIfPassed, state.yellow(), item.get_state_available_if(index).unwrap()));
+                        },
+                        _ => {}
+                    }
+                    Some(state.clone())
+                }
+                else if cli.toggle || item.is_boolean() {
+                    let Some(next_state) = item.get_next_state(&switch_options) else {
+                        bail!(t!(NoAvailableState, item.get_name().cyan()));
+                    };
+                    let next_index = item.get_state_names().iter().position(|name| *name == next_state).unwrap();
+                    if let (true, Some(command)) = (cli.dry_run, item.get_state_available_if(next_index)) {
+                        eprintln!("{}", t!(AvailableIfPassed, next_state.yellow(), command));
+                    }
+                    Some(next_state.to_string())
+                }
+                else if ephemeral {
+                    bail!(t!(EphemeralNeedsItem));
+                }
+                else {
+                    pick_state(&picker, item, &switch_options, &history)?
+                };
+
+                match new_state {
+                    Some(new_state) => break (item_index, new_state),
+                    None if from_picker => last_picked = Some(item_index),
+                    None => return Ok(())
+                }
+            };
+
+            let mut item_index = item_index;
+            let item = &cm.config.items[item_index];
+            log::debug!("Resolved item {} and state {}", item.get_name(), new_state);
+            if let Some(time) = scheduled_time {
+                if cli.detach {
+                    let pid = schedule::detach(item.get_name(), &new_state, time, None)?;
+                    eprintln!(
+                        "{}",
+                        t!(ScheduledInBackground, item.get_name().cyan(), new_state.yellow(), time.format("%Y-%m-%d %H:%M:%S"), pid)
+                    );
+                    return Ok(());
+                }
+
+                let label = t!(Switching, item.get_name(), new_state);
+                if ! schedule::wait_until(time, &label)? {
+                    eprintln!("{}", t!(CancelledSchedule));
+                    return Ok(());
+                }
+                if let Some(expected) = &cli.if_current {
+                    let item_name = item.get_name().to_string();
+                    let Some(index) = find_if_current(&mut cm, &item_name, expected, ephemeral)? else {
+                        return Ok(());
+                    };
+                    item_index = index;
+                }
+            }
 
             let item = &mut cm.config.items[item_index];
+            let previous_state = item.get_current_state().to_string();
+            let result = switch::switch(item, new_state.clone(), &switch_options, &history);
+            if ! switch_options.dry_run && (result.is_ok() || interrupt::is_interrupted()) {
+                // An interrupted switch may still record the new state
+                cm.write_switched()?;
+            }
+            result?;
+            if ! switch_options.dry_run {
+                run_on_change_hook(&cm, cli.quiet);
+            }
 
-            let state_names = item.get_state_names();
-            let current_state = item.get_current_state();
-            let current_state_index = state_names.iter().position(|name| *name == current_state).unwrap();
-            let new_state_index = FuzzySelect::with_theme(&colorful_theme)
-                .default(current_state_index)
-                .items(&state_names)
-                .interact()?;
-            let new_state = state_names[new_state_index].to_string();
-
-            if  new_state_index != current_state_index ||
-                Confirm::with_theme(&colorful_theme)
-                    .with_prompt(format!("{} is the current state. Reset?", new_state.yellow()))
-                    .interact()?
-            {
-                item.set_current_state(new_state)?;
-                cm.write()?;
+            if let Some(duration) = switch_back_after.filter(|_| ! switch_options.dry_run && ! interrupt::is_interrupted()) {
+                let item_name = cm.config.items[item_index].get_name().to_string();
+                if previous_state == new_state {
+                    eprintln!("{}", t!(NothingToSwitchBack, item_name.cyan(), new_state.yellow()));
+                    return Ok(());
+                }
+                let time = chrono::Local::now() + duration;
+                if cli.detach {
+                    let pid = schedule::detach(&item_name, &previous_state, time, Some(&new_state))?;
+                    eprintln!(
+                        "{}",
+                        t!(
+                            SwitchBackInBackground,
+                            item_name.cyan(),
+                            previous_state.yellow(),
+                            time.format("%Y-%m-%d %H:%M:%S"),
+                            pid
+                        )
+                    );
+                    return Ok(());
+                }
+
+                let label = t!(SwitchingBack, item_name, previous_state);
+                if ! schedule::wait_to_switch_back(time, &label, &colorful_theme, &item_name, &previous_state)? {
+                    eprintln!("{}", t!(CancelledSwitchBack, item_name.cyan(), new_state.yellow()));
+                    return Ok(());
+                }
+                let Some(item_index) = find_if_current(&mut cm, &item_name, &new_state, ephemeral)? else {
+                    return Ok(());
+                };
+                let item = &mut cm.config.items[item_index];
+                let result = switch::switch(item, previous_state, &switch_options, &history);
+                if result.is_ok() || interrupt::is_interrupted() {
+                    cm.write_switched()?;
+                }
+                result?;
+                run_on_change_hook(&cm, cli.quiet);
             }
         }
     }
 
+    if onboarding {
+        onboarding::finish(&colorful_theme)?;
+    }
+
+    if let Some(change_plan) = &switch_options.change_plan {
+        println!("{}", change_plan.to_json()?);
+    }
+
+    if cm.config.sync.auto && cm.is_written() {
+        // Syncing after a change is a side effect, so it never fails the change
+        let entries = history.read().unwrap_or_default();
+        if let Err(err) = sync::run(cm.get_path(), &cm.config.sync, cm.config.defaults.shell, &entries, true) {
+            eprintln!("{} {}", t!(Warning).yellow(), t!(SyncFailed, format!("{:#}", err)));
+        }
+    }
+
     Ok(())
 }
 
+/// Writes the config after switching several items if any new state is recorded, and runs `on_change` if any item is
+/// switched. Returns how many switches failed.
+fn write_switch_results(cm: &ConfigManager, results: &[SwitchResult], dry_run: bool, quiet: bool) -> Result<usize> {
+    if results.iter().any(|(_, outcome)| outcome.is_recorded()) {
+        cm.write_switched()?;
+        run_on_change_hook(cm, quiet);
+    }
+    // An interrupted switch may still record the new state
+    else if interrupt::is_interrupted() && ! dry_run {
+        cm.write_switched()?;
+    }
+    Ok(results.iter().filter(|(_, outcome)| outcome.is_failed()).count())
+}
+
+/// Describes a snapshot by its time, label and item count.
+fn describe_stack_entry(entry: &StackEntry) -> String {
+    format!(
+        "{}{}  {}",
+        entry.time.format("%Y-%m-%d %H:%M:%S"),
+        entry.label.as_ref().map(|label| format!("  {}", label.cyan())).unwrap_or_default(),
+        t!(SnapshotItemCount, entry.states.len()).dimmed()
+    )
+}
+
+/// The item and state pickers, numbered or filtered by typing.
+struct Picker<'a> {
+    theme: &'a ColorfulTheme,
+    numbered: bool,
+    match_mode: MatchMode,
+    /// Whether to show the item above the state picker.
+    show_header: bool
+}
+
+impl Picker<'_> {
+    /// Prompts for one of the entries, with `default` selected and the filter text initialized.
+    /// Returns the index of the entry, or `None` if the prompt is cancelled, with the filter text as last typed.
+    fn select<T: ToString>(&self, entries: &[T], default: usize, initial_text: &str) -> Result<(Option<usize>, String)> {
+        if self.numbered {
+            let picked = NumberSelect::with_theme(self.theme)
+                .default(default)
+                .items(entries)
+                .interact_opt()?;
+            return Ok((picked, initial_text.to_string()));
+        }
+        Ok(FilterSelect::with_theme(self.theme, self.match_mode)
+            .with_initial_text(initial_text)
+            .default(default)
+            .items(entries)
+            .interact_text()?)
+    }
+}
+
+/// Prompts for an item, listing the items in `order`, with the filter text initialized and the `default` item selected.
+/// Returns the index of the item in `items`, or `None` if the prompt is cancelled, with the filter text as last typed.
+fn pick_item(
+    picker: &Picker,
+    items: &[ConfigItem],
+    order: &[usize],
+    initial_text: &str,
+    default: Option<usize>
+) -> Result<(Option<usize>, String)> {
+    log::debug!("Prompting for an item, filtered by {:?}", initial_text);
+    let item_names: Vec<&str> = order
+        .iter()
+        .map(|&index| items[index].get_name())
+        .collect();
+    let item_labels = picker::disambiguate(&item_names, |position| {
+        let index = order[position];
+        format!("#{} {}", index + 1, items[index].get_type_string())
+    });
+    let default_position = order.iter().position(|&index| Some(index) == default).unwrap_or(0);
+
+    let (picked, text) = picker.select(&item_labels, default_position, initial_text)?;
+    Ok((picked.map(|index| order[index]), text))
+}
+
+/// Prompts for the new state of an item. Returns `None` if the prompt is cancelled.
+fn pick_state(
+    picker: &Picker,
+    item: &ConfigItem,
+    options: &SwitchOptions,
+    history: &History
+) -> Result<Option<String>> {
+    let state_names = item.get_state_names();
+    let current_state = item.get_current_state();
+    // A current state edited out of the states is stale, so any state is a change
+    let current_state_index = state_names.iter().position(|name| *name == current_state);
+    log::debug!("Prompting for a state of {}", item.get_name());
+    if picker.show_header {
+        // On stderr with the prompt, so stdout stays clean
+        eprintln!("{}", t!(StateHeader, item.get_name().cyan(), item.get_type_string(), item.color_current_state()));
+    }
+
+    // The states which can't be switched to stay listed, dimmed with why, so they don't seem to be gone
+    let unavailable_reasons: Vec<Option<String>> = (0 .. state_names.len())
+        .map(|index| item.get_state_unavailable_reason(index, options))
+        .collect();
+    let state_labels = picker::disambiguate(&state_names, |index| format!("#{}", index + 1));
+    let entries: Vec<String> = state_labels
+        .iter()
+        .zip(&unavailable_reasons)
+        .map(|(name, reason)| match reason {
+            Some(reason) => format!("{} {}", name, t!(UnavailableSuffix, reason)).dimmed().to_string(),
+            None => name.to_string()
+        })
+        .collect();
+
+    let mut default_index = current_state_index.unwrap_or(0);
+    loop {
+        let (Some(new_state_index), _) = picker.select(&entries, default_index, "")? else {
+            return Ok(None);
+        };
+        default_index = new_state_index;
+        if let Some(reason) = &unavailable_reasons[new_state_index] {
+            eprintln!("{}", t!(StateUnavailable, state_names[new_state_index].yellow(), reason));
+            continue;
+        }
+        let new_state = state_names[new_state_index].to_string();
+        // States are switched to by name, so a duplicate one can't be told apart
+        item.check_unique_state(&new_state)?;
+
+        if Some(new_state_index) == current_state_index {
+            describe_reapply(item, history);
+        }
+        // Declining to reset the current state picks again
+        if  Some(new_state_index) != current_state_index ||
+            Confirm::with_theme(picker.theme)
+                .with_prompt(t!(ResetCurrent, new_state.yellow()))
+                .interact_opt()? == Some(true)
+        {
+            return Ok(Some(new_state));
+        }
+    }
+}
+
+/// Tells whether the system still matches the current state before it's reapplied, by detecting the state if the item
+/// supports it, or by when the state was last applied otherwise. On stderr with the prompt.
+fn describe_reapply(item: &ConfigItem, history: &History) {
+    let name = item.get_name();
+    let current_state = item.get_current_state();
+    if item.can_detect() {
+        match item.detect_state() {
+            Ok(Some(state)) if state == current_state => eprintln!("{}", t!(DetectedRecorded, name.cyan(), state.yellow())),
+            Ok(Some(state)) => eprintln!(
+                "{} {}",
+                t!(Warning).yellow(), t!(DetectedDiffering, name.cyan(), state.yellow(), current_state.yellow())
+            ),
+            Ok(None) => eprintln!("{} {}", t!(Warning).yellow(), t!(DetectedNone, name.cyan(), current_state.yellow())),
+            Err(err) => eprintln!("{} {}", t!(Warning).yellow(), t!(DetectionFailed, format!("{:#}", err)))
+        }
+        return;
+    }
+
+    let last_applied = history
+        .read()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.item == name && entry.to == current_state)
+        .map(|entry| entry.time)
+        .max();
+    match last_applied {
+        Some(time) => eprintln!("{}", t!(LastApplied, current_state.yellow(), time.format("%Y-%m-%d %H:%M")).dimmed()),
+        None => eprintln!("{}", t!(NeverApplied, current_state.yellow()).dimmed())
+    }
+}
+
+/// How changes to the config, other than switching, are written.
+struct WritePolicy {
+    confirm: bool,
+    yes: bool,
+    verbose: bool
+}
+
+/// Adds the picked candidates, or all of them if `all`, skipping those named like existing items.
+fn add_candidates(
+    cm: &mut ConfigManager,
+    candidates: Vec<discover::Candidate>,
+    theme: &ColorfulTheme,
+    policy: &WritePolicy,
+    all: bool
+) -> Result<()> {
+    let candidates: Vec<discover::Candidate> = candidates
+        .into_iter()
+        .filter(|candidate| {
+            let exists = cm.config.find_item(candidate.item.get_name()).is_some();
+            if exists {
+                eprintln!("{}", t!(SkippedCandidate, candidate.description, candidate.item.get_name().cyan()));
+            }
+            ! exists
+        })
+        .collect();
+    if candidates.is_empty() {
+        eprintln!("{}", t!(FoundNothing));
+        return Ok(());
+    }
+
+    let picked: Vec<usize> = if all {
+        (0 .. candidates.len()).collect()
+    }
+    else {
+        let labels: Vec<String> = candidates
+            .iter()
+            .map(|candidate| t!(
+                CandidateLabel,
+                candidate.description,
+                candidate.item.get_name().cyan(),
+                candidate.item.get_state_names().len(),
+                candidate.probe
+            ))
+            .collect();
+        let Some(picked) = MultiSelect::with_theme(theme)
+            .with_prompt(t!(ItemsToAdd))
+            .items(&labels)
+            .interact_opt()?
+        else {
+            return Ok(());
+        };
+        picked
+    };
+    if picked.is_empty() {
+        eprintln!("{}", t!(AddedNothing));
+        return Ok(());
+    }
+
+    for (_, candidate) in candidates.into_iter().enumerate().filter(|(index, _)| picked.contains(index)) {
+        eprintln!("{}\n{}", t!(AddingItem, candidate.item.get_name().cyan()), candidate.item);
+        cm.config.items.push(candidate.item);
+    }
+    write_config(cm, theme, policy)
+}
+
+/// Finds the item, hidden or not, whose name is the same as the name, by `case_sensitive_names`.
+fn find_same_name<'a>(cm: &'a ConfigManager, name: &str) -> Option<&'a ConfigItem> {
+    cm.config.items
+        .iter()
+        .chain(cm.get_unavailable_items())
+        .find(|item| config::is_same_name(item.get_name(), name))
+}
+
+/// What `--add` does with an item whose name exists.
+enum AddConflict {
+    /// Reorder the states of the existing item instead.
+    Edit(usize),
+    /// Add the item under another name.
+    Rename(String),
+    /// Replace the existing item.
+    Replace(usize),
+    Abort
+}
+
+/// Decides what to do with the existing item of the name added, by `--replace` or `--rename-to`, or else by asking.
+/// Hidden items can't be replaced or edited here, since they're not for this machine.
+fn resolve_add_conflict(
+    theme: &ColorfulTheme,
+    cm: &ConfigManager,
+    name: &str,
+    replace: bool,
+    rename_to: Option<&str>
+) -> Result<AddConflict> {
+    let existing_name = find_same_name(cm, name).map(|item| item.get_name()).unwrap_or(name);
+    let Some(item_index) = cm.config.find_item(existing_name) else {
+        bail!(t!(ItemExists, name.cyan()));
+    };
+    let validate_name = |new_name: &str| {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(t!(EmptyItemName));
+        }
+        match find_same_name(cm, new_name).map(|item| item.get_name()) {
+            Some(existing) if existing == new_name => Err(t!(ItemExists, new_name.cyan())),
+            Some(existing) => Err(t!(ItemExistsInOtherCase, new_name.cyan(), existing.cyan())),
+            None => Ok(())
+        }
+    };
+
+    if replace {
+        return Ok(AddConflict::Replace(item_index));
+    }
+    if let Some(new_name) = rename_to {
+        validate_name(new_name).map_err(|err| anyhow!(err))?;
+        return Ok(AddConflict::Rename(new_name.trim().to_string()));
+    }
+    let exists = if existing_name == name {
+        t!(ItemExists, name.cyan())
+    }
+    else {
+        t!(ItemExistsInOtherCase, name.cyan(), existing_name.cyan())
+    };
+    if ! stdin().is_terminal() {
+        bail!("{}. {}", exists, t!(ChooseOnConflict));
+    }
+
+    eprintln!("{}", exists);
+    let choices = [t!(EditExisting), t!(AddUnderOtherName), t!(ReplaceExisting), t!(Abort)];
+    let choice = Select::with_theme(theme)
+        .with_prompt(t!(ImportConflict, existing_name.cyan()))
+        .items(&choices)
+        .default(0)
+        .interact_opt()?;
+    match choice {
+        Some(0) => Ok(AddConflict::Edit(item_index)),
+        Some(1) => {
+            let suggested = (2 ..)
+                .map(|number| format!("{}-{}", name, number))
+                .find(|new_name| find_same_name(cm, new_name).is_none())
+                .unwrap();
+            let new_name = Input::<String>::with_theme(theme)
+                .with_prompt(t!(NewItemName))
+                .with_initial_text(suggested)
+                .validate_with(|new_name: &String| validate_name(new_name))
+                .interact_text()?;
+            Ok(AddConflict::Rename(new_name.trim().to_string()))
+        },
+        Some(2) => {
+            eprintln!("{}", cm.config.items[item_index]);
+            let confirmed = Confirm::with_theme(theme)
+                .with_prompt(t!(ConfirmReplaceItem, existing_name.cyan()))
+                .default(false)
+                .interact_opt()?;
+            Ok(if confirmed == Some(true) { AddConflict::Replace(item_index) } else { AddConflict::Abort })
+        },
+        _ => Ok(AddConflict::Abort)
+    }
+}
+
+/// Warns about states running the same command, which is likely a copy-paste mistake but may be meant.
+fn warn_identical_commands(item: &ConfigItem) {
+    for (earlier, state) in item.find_identical_commands() {
+        eprintln!("{} {}", t!(Warning).yellow(), t!(IdenticalCommands, item.get_name().cyan(), earlier.yellow(), state.yellow()));
+    }
+}
+
+/// Reads the config again before a delayed switch, since the item may have been switched while waiting. Returns the
+/// item if it's still in the expected state, and notes why not otherwise, also in the log for background processes.
+fn find_if_current(cm: &mut ConfigManager, item_name: &str, expected: &str, ephemeral: bool) -> Result<Option<usize>> {
+    if ! ephemeral {
+        cm.read()?;
+        cm.load_plugin_states();
+    }
+    let note = match cm.config.find_item(item_name) {
+        Some(item_index) if cm.config.items[item_index].get_current_state() == expected => return Ok(Some(item_index)),
+        Some(item_index) => t!(SwitchedMeanwhile, item_name, cm.config.items[item_index].get_current_state(), expected),
+        None => t!(RemovedMeanwhile, item_name)
+    };
+    log::warn!("{}", note);
+    eprintln!("{} {}", t!(Warning).yellow(), note);
+    Ok(None)
+}
+
+/// Writes the config, first showing the diff of the config file and confirming if the policy says so.
+fn write_config(cm: &ConfigManager, theme: &ColorfulTheme, policy: &WritePolicy) -> Result<()> {
+    if policy.confirm {
+        if let Some(diff) = cm.get_diff()? {
+            if ! policy.yes || policy.verbose {
+                eprintln!("{}\n{}", t!(ConfigChanges), diff);
+            }
+            if ! policy.yes && ! Confirm::with_theme(theme)
+                .with_prompt(t!(WriteChanges))
+                .default(true)
+                .interact()?
+            {
+                eprintln!("{}", t!(DiscardedChanges));
+                return Ok(());
+            }
+        }
+    }
+
+    cm.write(false)
+}
+
+/// Runs the global `on_change` hook after switches. Failures are only warned about, since the switches are done.
+/// The output of the hook goes to stderr, keeping stdout for requested data.
+fn run_on_change_hook(cm: &ConfigManager, quiet: bool) {
+    let Some(hook) = &cm.config.defaults.on_change else {
+        return;
+    };
+
+    if ! quiet {
+        eprintln!("{}", t!(RunningHook, "$".purple().bold(), hook.purple()));
+    }
+    match shell::run_command_to_stderr(hook, cm.config.defaults.shell) {
+        Ok(status) if status.success() => {},
+        Ok(status) => eprintln!("{} {}", t!(Warning).yellow(), t!(HookFailed, status)),
+        Err(err) => eprintln!("{} {}", t!(Warning).yellow(), t!(FailedToRunHook, err))
+    }
+}
+
 struct CleanUp {
     pub hook: fn() -> ()
 }
@@ -202,30 +2521,112 @@ impl Drop for CleanUp {
     }
 }
 
+/// The config file, for pointing at it when panicking.
+static CONFIG_FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Explains a panic with what to do about it, since it's likely caused by a broken config.
+fn print_panic_help() {
+    eprintln!("{}", t!(Crashed).red());
+    match CONFIG_FILE_PATH.get() {
+        Some(path) => {
+            eprintln!("{}", t!(CrashedCheckConfig, path.display()));
+            let log_path = path.with_file_name("switchy.log");
+            eprintln!("{}", t!(CrashedDebugLog, log_path.display()));
+        },
+        None => eprintln!("{}", t!(CrashedDebug))
+    }
+}
+
+/// Shows the cursor hidden by prompts, which render on stderr. Nothing is written unless stderr is a terminal,
+/// so the output of e.g. `--shell-init` stays clean. Never panics, since it runs in the signal and panic handlers.
 fn show_cursor() {
-    Term::stdout().show_cursor().unwrap();
+    let term = Term::stderr();
+    if term.is_term() {
+        let _ = term.show_cursor();
+    }
 }
 
-fn main() {
+/// The conventional exit code of a process interrupted by SIGINT.
+const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+/// The exit code of `--value --require-value` when the state has no value, apart from other failures.
+const NO_VALUE_EXIT_CODE: u8 = 2;
+
+/// The error of `--require-value` when the state has no value.
+#[derive(Debug)]
+struct NoValue(String);
+
+impl fmt::Display for NoValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for NoValue {}
+
+fn main() -> ExitCode {
+    // Restores the cursor on every exit: returns via the guard, panics via the hook, and signals via the handler
     let _clean_up = CleanUp::new(show_cursor);
-    ctrlc::set_handler(show_cursor).unwrap();
+    let default_panic_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        show_cursor();
+        print_panic_help();
+        // A backtrace asked for is printed the usual way
+        if env::var_os("RUST_BACKTRACE").is_some_and(|value| value != "0") {
+            default_panic_hook(info);
+        }
+        else {
+            eprintln!("{}", info.to_string().dimmed());
+        }
+    }));
+    if let Err(err) = ctrlc::set_handler(|| {
+        interrupt::set_interrupted();
+        interrupt::forward_to_child();
+        show_cursor();
+    }) {
+        eprintln!("{} {}", t!(Warning).yellow(), t!(FailedToHandleCtrlC, err));
+    }
 
     let cli = Cli::parse();
     let debug_mode = cli.debug;
 
-    if let Err(err) = main_wrapper(cli) {
-        if err.is::<dialoguer::Error>() {
-            let dialoguer::Error::IO(err) = err.downcast_ref::<dialoguer::Error>().unwrap();
-            if err.kind() == std::io::ErrorKind::Interrupted {
-                return;
-            }
+    let result = main_wrapper(cli);
+    // A declined command cancels the switch, which is no failure
+    if let Err(err) = &result {
+        if err.is::<RunDeclined>() {
+            eprintln!("{}", err);
+            return ExitCode::SUCCESS;
         }
+    }
+    let interrupted = interrupt::is_interrupted() || result.as_ref().is_err_and(|err| matches!(
+        err.downcast_ref::<dialoguer::Error>(),
+        Some(dialoguer::Error::IO(err)) if err.kind() == std::io::ErrorKind::Interrupted
+    ));
 
-        if debug_mode {
-            eprintln!("{:#?}", err);
-        }
-        else {
-            eprintln!("{}", err.to_string().red());
+    if let Err(err) = &result {
+        // The log has the whole chain of causes, whatever the terminal shows
+        log::error!("{:#}", err);
+        // Interrupted prompts need no message
+        if ! (interrupted && err.is::<dialoguer::Error>()) {
+            if debug_mode {
+                eprintln!("{:#?}", err);
+            }
+            else {
+                eprintln!("{}", format!("{:#}", err).red());
+            }
         }
     }
-}   
+
+    if interrupted {
+        ExitCode::from(INTERRUPTED_EXIT_CODE)
+    }
+    else if result.as_ref().is_err_and(|err| err.is::<NoValue>()) {
+        ExitCode::from(NO_VALUE_EXIT_CODE)
+    }
+    else if result.is_err() {
+        ExitCode::FAILURE
+    }
+    else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fs, io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use clap_mangen::Man;
+use roff::{bold, italic, roman, Roff};
+
+use crate::i18n::t;
+
+/// Writes `switchy.1` into the dir, from the arg definitions and the sections below.
+/// Nothing depends on the time or the system, so packagers get the same page on every build.
+pub fn write(command: clap::Command, dir: &Path) -> Result<()> {
+    let man = Man::new(command);
+    let mut page: Vec<u8> = vec![];
+    man.render_title(&mut page)?;
+    man.render_name_section(&mut page)?;
+    man.render_synopsis_section(&mut page)?;
+    man.render_description_section(&mut page)?;
+    man.render_options_section(&mut page)?;
+    man.render_subcommands_section(&mut page)?;
+    get_extra_sections().to_writer(&mut page)?;
+    man.render_version_section(&mut page)?;
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(man.get_filename());
+    fs::File::create(&path)
+        .and_then(|mut file| file.write_all(&page))
+        .with_context(|| t!(FailedToWrite, path.display()))?;
+    eprintln!("{}", t!(WroteManPage, path.display()));
+
+    Ok(())
+}
+
+fn get_extra_sections() -> Roff {
+    let mut roff = Roff::new();
+    let entry = |roff: &mut Roff, term: &str, description: &str| {
+        roff.control("TP", []).text([bold(term)]).text([roman(description)]);
+    };
+
+    roff.control("SH", ["EXIT STATUS"]);
+    entry(&mut roff, "0", "Success.");
+    entry(&mut roff, "1", "Failure, e.g. a state command failed, an item doesn't exist or the config is invalid.");
+    entry(&mut roff, "2", "Invalid arguments.");
+    entry(&mut roff, "130", "Interrupted by Ctrl-C.");
+
+    roff.control("SH", ["ENVIRONMENT"]);
+    entry(&mut roff, "SWITCHY_CONFIG_DIR", "The config dir, instead of the platform one.");
+    entry(&mut roff, "SWITCHY_PROFILE", "The profile to use, unless --profile is given.");
+    entry(&mut roff, "NO_COLOR", "Disables colors when set, unless --color always is given.");
+    entry(&mut roff, "CLICOLOR_FORCE", "Enables colors when set to a value other than 0, even if the output isn't a terminal.");
+    entry(&mut roff, "VISUAL, EDITOR", "The editor for --edit-config and the add wizard, vi or notepad by default.");
+    entry(&mut roff, "LC_ALL, LC_MESSAGES, LANG", "The language of messages, unless lang is set in [defaults].");
+    entry(&mut roff, "COMSPEC", "The shell running commands on Windows, instead of cmd.");
+
+    roff.control("SH", ["FILES"]);
+    roff.text([
+        roman("The config dir holds "),
+        italic("config.toml"),
+        roman(", the state snapshot "),
+        italic("state.json"),
+        roman(", the history "),
+        italic("history.jsonl"),
+        roman(", the snapshot stack "),
+        italic("stack.jsonl"),
+        roman(" and the command logs in "),
+        italic("logs/"),
+        roman(". It is:")
+    ]);
+    entry(&mut roff, "Linux", "$XDG_CONFIG_HOME/switchy, or ~/.config/switchy");
+    entry(&mut roff, "macOS", "~/Library/Application Support/top.IceLava.switchy");
+    entry(&mut roff, "Windows", "%APPDATA%\\IceLava\\switchy\\config");
+    roff.text([roman("Run "), bold("switchy --schema"), roman(" for the JSON Schema of config.toml.")]);
+
+    roff
+}
@@ -0,0 +1,282 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+use crate::{discover::{self, Candidate}, fs_util, i18n::t, shell};
+
+/// The setups `--import-from` migrates from.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MigrationKind {
+    /// A Makefile with targets like `proxy-on` and `proxy-off`.
+    Makefile,
+    /// A dir of scripts like `proxy_on.sh` and `proxy_off.sh`.
+    ScriptDir,
+    /// A JSON file like `{ "proxy": { "on": "...", "off": "..." } }`, mapping names to the commands of their states.
+    ToggleJson
+}
+
+/// Groups the names split by `split` into item names with their states, in order of appearance. Only names with
+/// several states are kept, since one is nothing to switch between.
+fn group_states<T>(entries: Vec<T>, split: impl Fn(&T) -> Option<(String, String)>) -> Vec<(String, Vec<(String, T)>)> {
+    let mut groups: Vec<(String, Vec<(String, T)>)> = vec![];
+    for entry in entries {
+        let Some((name, state)) = split(&entry) else {
+            continue;
+        };
+        match groups.iter_mut().find(|(group_name, _)| *group_name == name) {
+            Some((_, states)) if states.iter().any(|(existing, _)| *existing == state) => {},
+            Some((_, states)) => states.push((state, entry)),
+            None => groups.push((name, vec![(state, entry)]))
+        }
+    }
+    groups.retain(|(_, states)| states.len() > 1);
+    groups
+}
+
+/// Splits `name<separator>state` at the last separator, with both sides non-empty.
+fn split_last(text: &str, separator: char) -> Option<(String, String)> {
+    let (name, state) = text.rsplit_once(separator)?;
+    (! name.is_empty() && ! state.is_empty()).then(|| (name.to_string(), state.to_string()))
+}
+
+/// Starts with `off` if there is such a state, since that's the usual state of things not set up yet.
+fn guess_current(states: &[String]) -> Option<String> {
+    states.iter().find(|state| *state == "off").cloned()
+}
+
+/// Returns the targets of the Makefile, only the phony ones if any are declared. Pattern rules, special targets and
+/// targets with variables are skipped, and anything else it can't make sense of is ignored.
+fn parse_makefile_targets(makefile: &str) -> Vec<String> {
+    let mut phony: Vec<String> = vec![];
+    let mut targets: Vec<String> = vec![];
+    for line in makefile.replace("\\\n", " ").lines() {
+        // Recipe lines run in the shell, so they're never targets
+        if line.starts_with('\t') {
+            continue;
+        }
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((before, after)) = line.split_once(':') else {
+            continue;
+        };
+        // `A := b`, `A ::= b` and `A = b:c` are assignments
+        if after.starts_with('=') || after.starts_with(":=") || before.contains('=') {
+            continue;
+        }
+        if before.trim() == ".PHONY" {
+            phony.extend(after.split_whitespace().map(String::from));
+            continue;
+        }
+        targets.extend(before
+            .split_whitespace()
+            .filter(|name| ! name.starts_with('.') && ! name.contains(['%', '$', '(']))
+            .map(String::from));
+    }
+    if phony.is_empty() { targets } else { phony }
+}
+
+fn find_in_makefile(path: &Path) -> Result<Vec<Candidate>> {
+    let makefile = fs::read_to_string(path).with_context(|| t!(FailedToRead, path.display()))?;
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    let groups = group_states(parse_makefile_targets(&makefile), |target| split_last(target, '-'));
+
+    Ok(groups
+        .into_iter()
+        .filter_map(|(name, states)| {
+            let state_names: Vec<String> = states.iter().map(|(state, _)| state.clone()).collect();
+            let make_command = |state: &str| {
+                let target = &states.iter().find(|(name, _)| name == state).unwrap().1;
+                format!(
+                    "make -C {} -f {} {}",
+                    shell::quote_arg(&dir.to_string_lossy()),
+                    shell::quote_arg(&path.to_string_lossy()),
+                    shell::quote_arg(target)
+                )
+            };
+            let current = guess_current(&state_names);
+            Some(Candidate {
+                description: t!(MakefileTargets, format!("{}-*", name)),
+                probe: path.display().to_string(),
+                item: discover::make_command_item(&name, state_names, make_command, current)?
+            })
+        })
+        .collect())
+}
+
+/// Returns the command running the script: the script itself if it's executable, or else `sh` with it.
+fn get_script_command(path: &Path) -> String {
+    let quoted = shell::quote_arg(&path.to_string_lossy());
+    match fs_util::get_mode(path) {
+        Some(mode) if mode & 0o111 == 0 => format!("sh {}", quoted),
+        _ => quoted
+    }
+}
+
+fn find_in_script_dir(dir: &Path) -> Result<Vec<Candidate>> {
+    if ! dir.is_dir() {
+        bail!(t!(NotADirectory, dir.display()));
+    }
+    let dir = dir.canonicalize().unwrap_or(dir.to_path_buf());
+    let mut scripts: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| t!(FailedToRead, dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|extension| extension == "sh"))
+        .collect();
+    scripts.sort();
+    let groups = group_states(scripts, |path| split_last(&path.file_stem()?.to_string_lossy(), '_'));
+
+    Ok(groups
+        .into_iter()
+        .filter_map(|(name, states)| {
+            let state_names: Vec<String> = states.iter().map(|(state, _)| state.clone()).collect();
+            let make_command = |state: &str| get_script_command(&states.iter().find(|(name, _)| name == state).unwrap().1);
+            let current = guess_current(&state_names);
+            Some(Candidate {
+                description: t!(ScriptFiles, format!("{}_*.sh", name)),
+                probe: dir.display().to_string(),
+                item: discover::make_command_item(&name, state_names, make_command, current)?
+            })
+        })
+        .collect())
+}
+
+/// A toggle of a toggle JSON, with the commands of its states.
+struct Toggle {
+    name: String,
+    states: Vec<(String, String)>,
+    current: Option<String>
+}
+
+/// Returns the command of a state in a toggle JSON: a string, a list of strings run one after another, or an object
+/// with such a `command`.
+fn get_toggle_command(value: &Value) -> Option<String> {
+    match value {
+        Value::String(command) if ! command.trim().is_empty() => Some(command.clone()),
+        Value::Array(commands) => {
+            let commands: Vec<&str> = commands.iter().map(Value::as_str).collect::<Option<_>>()?;
+            (! commands.is_empty()).then(|| commands.join(" && "))
+        },
+        Value::Object(state) => get_toggle_command(state.get("command")?),
+        _ => None
+    }
+}
+
+/// Parses a toggle of a toggle JSON, with the state it's in if it tells. The states are under `states`, or else every
+/// other key is a state. Anything that isn't a command is ignored.
+fn parse_toggle(name: String, toggle: &Map<String, Value>) -> Toggle {
+    let current = toggle.get("current").and_then(Value::as_str).map(String::from);
+    let states = match toggle.get("states") {
+        Some(Value::Object(states)) => states,
+        _ => toggle
+    };
+    let states = states
+        .iter()
+        .filter(|(name, _)| ! ["name", "current", "description"].contains(&name.as_str()) && ! name.is_empty())
+        .filter_map(|(name, value)| Some((name.clone(), get_toggle_command(value)?)))
+        .collect();
+    Toggle { name, states, current }
+}
+
+/// Returns the toggles of a toggle JSON, from an object of toggles by name, or from a list of toggles with a `name`
+/// each. Toggles without a name or with fewer than two states are skipped.
+fn parse_toggle_json(json: &Value) -> Vec<Toggle> {
+    let toggles: Vec<(String, &Map<String, Value>)> = match json {
+        Value::Object(toggles) => toggles
+            .iter()
+            .filter_map(|(name, toggle)| Some((name.clone(), toggle.as_object()?)))
+            .collect(),
+        Value::Array(toggles) => toggles
+            .iter()
+            .filter_map(|toggle| {
+                let toggle = toggle.as_object()?;
+                Some((toggle.get("name")?.as_str()?.to_string(), toggle))
+            })
+            .collect(),
+        _ => vec![]
+    };
+    toggles
+        .into_iter()
+        .map(|(name, toggle)| parse_toggle(name, toggle))
+        .filter(|toggle| ! toggle.name.is_empty() && toggle.states.len() > 1)
+        .collect()
+}
+
+fn find_in_toggle_json(path: &Path) -> Result<Vec<Candidate>> {
+    let content = fs::read_to_string(path).with_context(|| t!(FailedToRead, path.display()))?;
+    let json: Value = serde_json::from_str(&content).with_context(|| t!(InvalidToggleJson, path.display()))?;
+
+    Ok(parse_toggle_json(&json)
+        .into_iter()
+        .filter_map(|Toggle { name, states, current }| {
+            let state_names: Vec<String> = states.iter().map(|(state, _)| state.clone()).collect();
+            let make_command = |state: &str| states.iter().find(|(name, _)| name == state).unwrap().1.clone();
+            let current = current.or_else(|| guess_current(&state_names));
+            Some(Candidate {
+                description: t!(ToggleJsonEntry, name),
+                probe: path.display().to_string(),
+                item: discover::make_command_item(&name, state_names, make_command, current)?
+            })
+        })
+        .collect())
+}
+
+/// Proposes command items from the Makefile, script dir or toggle JSON. Nothing is run, only read.
+pub fn find_candidates(kind: MigrationKind, path: &Path) -> Result<Vec<Candidate>> {
+    match kind {
+        MigrationKind::Makefile => find_in_makefile(path),
+        MigrationKind::ScriptDir => find_in_script_dir(path),
+        MigrationKind::ToggleJson => find_in_toggle_json(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_toggle<'a>(toggles: &'a [Toggle], name: &str) -> &'a Toggle {
+        toggles.iter().find(|toggle| toggle.name == name).unwrap()
+    }
+
+    #[test]
+    fn toggle_json_maps_names_to_states() {
+        let json = serde_json::json!({
+            "proxy": { "on": "proxy-on", "off": ["unset http_proxy", "unset https_proxy"], "current": "on" },
+            "theme": { "states": { "dark": { "command": "theme dark" }, "light": "theme light" } }
+        });
+        let toggles = parse_toggle_json(&json);
+        assert_eq!(toggles.len(), 2);
+        assert_eq!(get_toggle(&toggles, "proxy").states, [
+            ("off".to_string(), "unset http_proxy && unset https_proxy".to_string()),
+            ("on".to_string(), "proxy-on".to_string())
+        ]);
+        assert_eq!(get_toggle(&toggles, "proxy").current.as_deref(), Some("on"));
+        assert_eq!(get_toggle(&toggles, "theme").states, [
+            ("dark".to_string(), "theme dark".to_string()),
+            ("light".to_string(), "theme light".to_string())
+        ]);
+    }
+
+    #[test]
+    fn toggle_json_lists_named_toggles() {
+        let json = serde_json::json!([
+            { "name": "vpn", "description": "Work VPN", "up": "wg-quick up wg0", "down": "wg-quick down wg0" },
+            { "up": "nameless", "down": "nameless" }
+        ]);
+        let toggles = parse_toggle_json(&json);
+        assert_eq!(toggles.len(), 1);
+        assert_eq!(get_toggle(&toggles, "vpn").states.len(), 2);
+    }
+
+    #[test]
+    fn toggle_json_ignores_what_it_cant_run() {
+        let json = serde_json::json!({
+            "proxy": { "on": "proxy-on", "off": "", "port": 1080, "hosts": ["a", 1] },
+            "broken": "not a toggle",
+            "version": 2
+        });
+        assert!(parse_toggle_json(&json).is_empty());
+        assert!(parse_toggle_json(&serde_json::json!("proxy")).is_empty());
+    }
+}
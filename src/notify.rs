@@ -0,0 +1,12 @@
+/// Posts a desktop notification. Does nothing without the `notify` feature, or when no notification
+/// daemon is available.
+#[cfg(feature = "notify")]
+pub fn send(body: &str) {
+    let _ = notify_rust::Notification::new()
+        .summary("switchy")
+        .body(body)
+        .show();
+}
+
+#[cfg(not(feature = "notify"))]
+pub fn send(_body: &str) {}
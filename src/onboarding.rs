@@ -0,0 +1,90 @@
+use std::{env, io::{stderr, stdin, IsTerminal}, path::Path};
+
+use anyhow::Result;
+use clap::ValueEnum;
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
+
+use crate::{config::ConfigManager, i18n::t, recipe, shell_init::InitShell};
+
+/// The first item picked in onboarding, to be added like with `--add NAME [--template ID]`.
+pub struct FirstItem {
+    pub name: String,
+    pub recipe_id: Option<String>
+}
+
+/// Whether to onboard: on bare `switchy` in a terminal, right after the config file is created. Any argument means
+/// the user already knows what to do.
+pub fn is_first_run(cm: &ConfigManager) -> bool {
+    cm.is_created() && env::args_os().len() == 1 && stdin().is_terminal() && stderr().is_terminal()
+}
+
+/// Explains switchy, then offers adding a first item. Returns `None` if the user adds none now.
+pub fn welcome(theme: &ColorfulTheme, config_file_path: &Path) -> Result<Option<FirstItem>> {
+    eprintln!("{}", t!(WelcomeWhat));
+    eprintln!("{}", t!(WelcomeConfig, config_file_path.display().to_string().cyan()));
+    eprintln!("{}", t!(WelcomeSwitch));
+    eprintln!();
+
+    let choice = Select::with_theme(theme)
+        .with_prompt(t!(OnboardingFirstItem))
+        .default(0)
+        .items(&[t!(OnboardingWizard), t!(OnboardingRecipe), t!(OnboardingLater)])
+        .interact_opt()?;
+    let recipe_id = match choice {
+        Some(0) => None,
+        Some(1) => {
+            let recipes = recipe::get_recipes();
+            let entries: Vec<String> = recipes
+                .iter()
+                .map(|recipe| format!("{}  {}", recipe.id, recipe.description.dimmed()))
+                .collect();
+            let Some(index) = Select::with_theme(theme)
+                .with_prompt(t!(OnboardingPickRecipe))
+                .default(0)
+                .items(&entries)
+                .interact_opt()?
+            else {
+                return Ok(None);
+            };
+            Some(recipes[index].id.to_string())
+        },
+        _ => return Ok(None)
+    };
+
+    let mut input = Input::<String>::with_theme(theme)
+        .with_prompt(t!(OnboardingItemName))
+        .validate_with(|name: &String| if name.trim().is_empty() { Err(t!(EmptyItemName)) } else { Ok(()) });
+    if let Some(recipe_id) = &recipe_id {
+        input = input.default(recipe_id.clone());
+    }
+    let name = input.interact_text()?;
+
+    Ok(Some(FirstItem { name, recipe_id }))
+}
+
+/// Offers showing the line enabling `--shell-init` for the shell in `$SHELL`, then where to go next.
+pub fn finish(theme: &ColorfulTheme) -> Result<()> {
+    let shell = env::var("SHELL")
+        .ok()
+        .and_then(|path| Some(Path::new(&path).file_name()?.to_string_lossy().into_owned()))
+        .and_then(|name| InitShell::from_str(&name, true).ok());
+    if let Some(shell) = shell {
+        let (name, line, rc_file) = match shell {
+            InitShell::Bash => ("bash", "eval \"$(switchy --shell-init bash)\"", "~/.bashrc"),
+            InitShell::Zsh => ("zsh", "eval \"$(switchy --shell-init zsh)\"", "~/.zshrc"),
+            InitShell::Fish => ("fish", "switchy --shell-init fish | source", "~/.config/fish/config.fish")
+        };
+        if Confirm::with_theme(theme)
+            .with_prompt(t!(OnboardingShellInit, name))
+            .default(true)
+            .interact_opt()? == Some(true)
+        {
+            eprintln!("{}", t!(OnboardingAddLine, rc_file.cyan()));
+            println!("{}", line);
+        }
+    }
+
+    eprintln!("{}", t!(OnboardingDone).dimmed());
+    Ok(())
+}
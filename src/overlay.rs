@@ -0,0 +1,62 @@
+use anyhow::{bail, Result};
+use serde::{de::{value::StrDeserializer, IntoDeserializer}, Deserialize};
+
+use crate::{config::{ConfirmRun, InterruptPolicy}, i18n::t, shell::CommandShell};
+
+/// The keys `--set` accepts.
+pub const KEYS: &[&str] = &["log", "notify", "shell", "on_interrupt", "confirm_run", "askpass"];
+
+/// Settings given by `--set key=value` for one invocation. They win over the items and the defaults, and are never
+/// written to the config.
+#[derive(Default)]
+pub struct SettingsOverlay {
+    pub log: Option<bool>,
+    pub notify: Option<bool>,
+    /// `Some(None)` is the system shell.
+    pub shell: Option<Option<CommandShell>>,
+    pub on_interrupt: Option<InterruptPolicy>,
+    pub confirm_run: Option<ConfirmRun>,
+    pub askpass: Option<String>
+}
+
+impl SettingsOverlay {
+    /// Parses the `key=value` pairs, failing on the first unknown key or unparsable value. Later pairs win.
+    pub fn parse(pairs: &[String]) -> Result<Self> {
+        let mut overlay = SettingsOverlay::default();
+        for pair in pairs {
+            let Some((key, value)) = pair.split_once('=') else {
+                bail!(t!(SetNotPair, pair, KEYS.join(", ")));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "log" => overlay.log = Some(parse_bool(key, value)?),
+                "notify" => overlay.notify = Some(parse_bool(key, value)?),
+                "shell" => overlay.shell = Some(parse_shell(key, value)?),
+                "on_interrupt" => overlay.on_interrupt = Some(parse_value(key, value)?),
+                "confirm_run" => overlay.confirm_run = Some(parse_value(key, value)?),
+                "askpass" => overlay.askpass = Some(value.to_string()),
+                _ => bail!(t!(SetUnknownKey, key, KEYS.join(", ")))
+            }
+        }
+        Ok(overlay)
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => bail!(t!(SetInvalidValue, key, value, t!(ExpectedTrueOrFalse)))
+    }
+}
+
+/// Parses a shell the way `shell` spells it, or `system` for the system shell.
+pub fn parse_shell(key: &str, value: &str) -> Result<Option<CommandShell>> {
+    if value == "system" { Ok(None) } else { Ok(Some(parse_value(key, value)?)) }
+}
+
+/// Parses the value of an enum the way the config file spells it, e.g. `record`.
+fn parse_value<T: for<'de> Deserialize<'de>>(key: &str, value: &str) -> Result<T> {
+    let deserializer: StrDeserializer<serde::de::value::Error> = value.into_deserializer();
+    T::deserialize(deserializer).or_else(|err| bail!(t!(SetInvalidValue, key, value, err)))
+}
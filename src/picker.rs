@@ -0,0 +1,273 @@
+use std::{fmt, io};
+
+use dialoguer::{console::{self, Key, Term}, theme::Theme, Result};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+
+use crate::config::MatchMode;
+
+/// A select prompt whose entries are numbered, where typing a number jumps to that entry.
+/// Unlike `FilterSelect`, typed digits never filter the entries, so names containing digits are unaffected.
+pub struct NumberSelect<'a> {
+    items: Vec<String>,
+    default: usize,
+    theme: &'a dyn Theme
+}
+
+impl<'a> NumberSelect<'a> {
+    pub fn with_theme(theme: &'a dyn Theme) -> Self {
+        Self {
+            items: vec![],
+            default: 0,
+            theme
+        }
+    }
+
+    pub fn default(mut self, default: usize) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub fn items<T: ToString>(mut self, items: &[T]) -> Self {
+        self.items.extend(items.iter().map(ToString::to_string));
+        self
+    }
+
+    /// Returns the index of the selected entry, or `None` if the user pressed Esc or `q`.
+    pub fn interact_opt(self) -> Result<Option<usize>> {
+        let term = Term::stderr();
+        if ! term.is_term() {
+            Err(io::Error::new(io::ErrorKind::NotConnected, "not a terminal"))?;
+        }
+        if self.items.is_empty() {
+            Err(io::Error::other("Empty list of items given to `NumberSelect`"))?;
+        }
+
+        let len = self.items.len();
+        let index_width = len.to_string().len();
+        let mut sel = self.default.min(len - 1);
+        let mut typed = String::new();
+
+        term.hide_cursor()?;
+        let result = loop {
+            let mut lines = vec![];
+            for (index, item) in self.items.iter().enumerate() {
+                lines.push(render(|line| self.theme.format_select_prompt_item(
+                    line,
+                    &format!("{:>width$}) {}", index + 1, item, width = index_width),
+                    index == sel
+                )));
+            }
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            term.flush()?;
+
+            let key = term.read_key()?;
+            term.clear_last_lines(lines.len())?;
+
+            match key {
+                Key::Char(digit @ '0'..='9') => {
+                    typed.push(digit);
+                    match typed.parse::<usize>() {
+                        Ok(number) if (1..=len).contains(&number) => sel = number - 1,
+                        _ => {
+                            typed = digit.to_string();
+                            if let Some(number) = digit.to_digit(10).filter(|number| (1..=len as u32).contains(number)) {
+                                sel = number as usize - 1;
+                            }
+                        }
+                    }
+                }
+                Key::ArrowDown | Key::Tab | Key::Char('j') => {
+                    typed.clear();
+                    sel = (sel + 1) % len;
+                }
+                Key::ArrowUp | Key::BackTab | Key::Char('k') => {
+                    typed.clear();
+                    sel = (sel + len - 1) % len;
+                }
+                Key::Escape | Key::Char('q') => break None,
+                Key::Enter => break Some(sel),
+                _ => {}
+            }
+        };
+        term.show_cursor()?;
+
+        Ok(result)
+    }
+}
+
+/// Appends the description of each entry whose label is shared by others, so identical labels can be told apart.
+pub fn disambiguate(labels: &[&str], describe: impl Fn(usize) -> String) -> Vec<String> {
+    labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            if labels.iter().filter(|other| *other == label).count() > 1 {
+                format!("{} ({})", label, describe(index))
+            }
+            else {
+                label.to_string()
+            }
+        })
+        .collect()
+}
+
+/// Returns the indexes of the entries matching the query, best first. Case is ignored, and so are colors of entries.
+/// A substring match ranks by where the query starts, so prefixes come first, and a fuzzy match by its score as in
+/// `FuzzySelect`. An empty query matches all.
+pub fn filter<T: AsRef<str>>(entries: &[T], query: &str, mode: MatchMode) -> Vec<usize> {
+    let query = query.to_lowercase();
+    let matcher = SkimMatcherV2::default();
+    let mut matches: Vec<(usize, i64)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| {
+            let entry = console::strip_ansi_codes(entry.as_ref()).to_lowercase();
+            let rank = match mode {
+                MatchMode::Fuzzy => matcher.fuzzy_match(&entry, &query).map(|score| -score),
+                MatchMode::Substring => entry.find(&query).map(|position| position as i64),
+                MatchMode::Prefix => entry.starts_with(&query).then_some(0)
+            };
+            rank.map(|rank| (index, rank))
+        })
+        .collect();
+    // Stable, so entries ranked the same keep their order
+    matches.sort_by_key(|(_, rank)| *rank);
+    matches.into_iter().map(|(index, _)| index).collect()
+}
+
+/// A select prompt where typed text filters the entries, see `filter`.
+pub struct FilterSelect<'a> {
+    items: Vec<String>,
+    default: usize,
+    initial_text: String,
+    mode: MatchMode,
+    theme: &'a dyn Theme
+}
+
+impl<'a> FilterSelect<'a> {
+    pub fn with_theme(theme: &'a dyn Theme, mode: MatchMode) -> Self {
+        Self {
+            items: vec![],
+            default: 0,
+            initial_text: String::new(),
+            mode,
+            theme
+        }
+    }
+
+    pub fn default(mut self, default: usize) -> Self {
+        self.default = default;
+        self
+    }
+
+    pub fn with_initial_text(mut self, initial_text: &str) -> Self {
+        self.initial_text = initial_text.to_string();
+        self
+    }
+
+    pub fn items<T: ToString>(mut self, items: &[T]) -> Self {
+        self.items.extend(items.iter().map(ToString::to_string));
+        self
+    }
+
+    /// Returns the index of the selected entry, or `None` if the user pressed Esc, with the filter text as typed, so
+    /// the prompt can be shown again with it.
+    pub fn interact_text(self) -> Result<(Option<usize>, String)> {
+        let term = Term::stderr();
+        if ! term.is_term() {
+            Err(io::Error::new(io::ErrorKind::NotConnected, "not a terminal"))?;
+        }
+        if self.items.is_empty() {
+            Err(io::Error::other("Empty list of items given to `FilterSelect`"))?;
+        }
+
+        // Leave a line for the prompt and one for the cursor
+        let page_size = (term.size().0 as usize).saturating_sub(2).max(1);
+        let mut query = self.initial_text.clone();
+        let mut matches = filter(&self.items, &query, self.mode);
+        let mut sel = matches.iter().position(|&index| index == self.default).unwrap_or(0);
+        let matcher = SkimMatcherV2::default();
+
+        term.hide_cursor()?;
+        let result = loop {
+            let mut lines = vec![render(|line| self.theme.format_fuzzy_select_prompt(line, "", &query, query.len()))];
+            let first = sel.saturating_sub(page_size - 1);
+            for (position, &index) in matches.iter().enumerate().skip(first).take(page_size) {
+                let active = position == sel;
+                lines.push(render(|line| match self.mode {
+                    MatchMode::Fuzzy => {
+                        self.theme.format_fuzzy_select_prompt_item(line, &self.items[index], active, true, &matcher, &query)
+                    },
+                    _ => self.theme.format_select_prompt_item(line, &self.items[index], active)
+                }));
+            }
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            term.flush()?;
+
+            let key = term.read_key()?;
+            term.clear_last_lines(lines.len())?;
+
+            match key {
+                Key::ArrowDown | Key::Tab if ! matches.is_empty() => sel = (sel + 1) % matches.len(),
+                Key::ArrowUp | Key::BackTab if ! matches.is_empty() => sel = (sel + matches.len() - 1) % matches.len(),
+                Key::Escape => break None,
+                Key::Enter if ! matches.is_empty() => break Some(matches[sel]),
+                Key::Backspace | Key::Char(_) => {
+                    match key {
+                        Key::Char(char) if ! char.is_control() => query.push(char),
+                        Key::Backspace => {
+                            query.pop();
+                        },
+                        _ => continue
+                    }
+                    matches = filter(&self.items, &query, self.mode);
+                    sel = 0;
+                }
+                _ => {}
+            }
+        };
+        term.show_cursor()?;
+
+        Ok((result, query))
+    }
+}
+
+/// Renders a theme formatting call into a string. Writing to a `String` can't fail.
+fn render(format: impl FnOnce(&mut String) -> fmt::Result) -> String {
+    let mut line = String::new();
+    let _ = format(&mut line);
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ENTRIES: [&str; 4] = ["proxy", "theme", "\x1b[36mtheme-dark\x1b[0m", "other"];
+
+    #[test]
+    fn empty_queries_match_all() {
+        for mode in [MatchMode::Fuzzy, MatchMode::Substring, MatchMode::Prefix] {
+            assert_eq!(filter(&ENTRIES, "", mode), vec![0, 1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn substrings_rank_by_position() {
+        assert_eq!(filter(&ENTRIES, "HE", MatchMode::Substring), vec![1, 2, 3]);
+        assert_eq!(filter(&ENTRIES, "the", MatchMode::Prefix), vec![1, 2]);
+        assert_eq!(filter(&ENTRIES, "dark", MatchMode::Prefix), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn fuzzy_matches_chars_in_order() {
+        assert_eq!(filter(&ENTRIES, "tm", MatchMode::Fuzzy), vec![1, 2]);
+        assert_eq!(filter(&ENTRIES, "pxy", MatchMode::Fuzzy), vec![0]);
+        assert_eq!(filter(&ENTRIES, "yxp", MatchMode::Fuzzy), Vec::<usize>::new());
+        assert_eq!(filter(&ENTRIES, "tdk", MatchMode::Fuzzy), vec![2]);
+    }
+}
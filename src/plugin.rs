@@ -0,0 +1,120 @@
+use std::{io::Write, path::Path, process::{Command, Stdio}};
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::i18n::t;
+
+/// The protocol version switchy speaks. The protocol is described in the readme.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// A state listed by a plugin.
+#[derive(Serialize, Deserialize)]
+pub struct PluginState {
+    pub name: String,
+    /// The color of the state name in listings, like `red` or `bright blue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    protocol: u32,
+    item: &'a str,
+    settings: &'a toml::Table
+}
+
+#[derive(Deserialize)]
+struct Reply {
+    protocol: u32,
+    #[serde(default)]
+    error: Option<String>
+}
+
+#[derive(Deserialize)]
+struct StatesReply {
+    states: Vec<PluginState>
+}
+
+#[derive(Deserialize)]
+struct CurrentReply {
+    current: Option<String>
+}
+
+#[derive(Deserialize)]
+struct RenderReply {
+    #[serde(default)]
+    lines: Vec<String>
+}
+
+/// A plugin run for an item.
+pub struct Plugin<'a> {
+    /// The plugin as written in the config, for messages.
+    pub name: &'a str,
+    pub program: &'a Path,
+    pub item: &'a str,
+    pub settings: &'a toml::Table
+}
+
+impl Plugin<'_> {
+    /// Runs the subcommand with the request on stdin, and parses the reply on stdout after checking its protocol.
+    /// stderr is left to the terminal, for the plugin's own messages.
+    fn call<T: DeserializeOwned>(&self, args: &[&str]) -> Result<T> {
+        let subcommand = args[0];
+        let request = serde_json::to_string(&Request {
+            protocol: PROTOCOL_VERSION,
+            item: self.item,
+            settings: self.settings
+        })?;
+
+        let mut child = Command::new(self.program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| t!(PluginRunFailed, self.name))?;
+        // A plugin may exit without reading the request, which isn't an error by itself
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(request.as_bytes());
+        }
+        let output = child.wait_with_output().with_context(|| t!(PluginRunFailed, self.name))?;
+
+        let reply: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        if let Ok(Reply { error: Some(error), .. }) = serde_json::from_value::<Reply>(reply.clone()) {
+            bail!(t!(PluginError, self.name, subcommand, error));
+        }
+        if ! output.status.success() {
+            match output.status.code() {
+                Some(code) => bail!(t!(PluginExited, self.name, subcommand, code)),
+                None => bail!(t!(PluginKilled, self.name, subcommand))
+            }
+        }
+
+        let Reply { protocol, .. } = serde_json::from_value(reply.clone())
+            .with_context(|| t!(PluginInvalidReply, self.name, subcommand))?;
+        if protocol != PROTOCOL_VERSION {
+            bail!(t!(PluginProtocol, self.name, protocol, PROTOCOL_VERSION));
+        }
+        serde_json::from_value(reply).with_context(|| t!(PluginInvalidReply, self.name, subcommand))
+    }
+
+    pub fn list_states(&self) -> Result<Vec<PluginState>> {
+        let states = self.call::<StatesReply>(&["states"])?.states;
+        if states.is_empty() {
+            bail!(t!(PluginNoStates, self.name));
+        }
+        Ok(states)
+    }
+
+    pub fn get_current(&self) -> Result<Option<String>> {
+        Ok(self.call::<CurrentReply>(&["current"])?.current)
+    }
+
+    pub fn switch(&self, state: &str) -> Result<()> {
+        self.call::<Reply>(&["switch", state]).map(|_| ())
+    }
+
+    pub fn render(&self, state: &str) -> Result<Vec<String>> {
+        Ok(self.call::<RenderReply>(&["render", state])?.lines)
+    }
+}
@@ -0,0 +1,312 @@
+use std::{collections::HashSet, fmt};
+
+use chrono::{DateTime, Local, TimeDelta};
+use colored::Colorize;
+
+use crate::{config::ConfigItem, fs_util, history::HistoryEntry, i18n::t, shell};
+
+/// What a prune candidate would remove.
+#[derive(PartialEq)]
+pub enum PruneTarget {
+    Item(String),
+    State(String, String)
+}
+
+/// Something that looks unused or broken, with why.
+pub struct PruneCandidate {
+    pub target: PruneTarget,
+    pub reason: String
+}
+
+impl fmt::Display for PruneCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.target {
+            PruneTarget::Item(item) => write!(f, "{}", t!(PruneItem, item.cyan(), self.reason)),
+            PruneTarget::State(item, state) => write!(f, "{}", t!(PruneState, state.yellow(), item.cyan(), self.reason))
+        }
+    }
+}
+
+/// Runs every analysis. A target found by several analyses appears once, with the first reason.
+pub fn analyze(
+    items: &[ConfigItem],
+    entries: &[HistoryEntry],
+    now: DateTime<Local>,
+    stale_days: u32
+) -> Vec<PruneCandidate> {
+    let mut candidates: Vec<PruneCandidate> = vec![];
+    for candidate in find_missing_programs(items)
+        .into_iter()
+        .chain(find_missing_paths(items))
+        .chain(find_stale_items(items, entries, now, stale_days))
+        .chain(find_unused_states(items, entries))
+    {
+        if ! candidates.iter().any(|existing| existing.target == candidate.target) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Finds the states of command items whose program isn't on PATH.
+pub fn find_missing_programs(items: &[ConfigItem]) -> Vec<PruneCandidate> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            ConfigItem::CommandItem(item) => Some(item),
+            _ => None
+        })
+        .flat_map(|item| item.states.iter().filter_map(|state| {
+            let program = shell::find_missing_program(state.command.get_plain()?)?;
+            Some(PruneCandidate {
+                target: PruneTarget::State(item.name.clone(), state.name.clone()),
+                reason: t!(ProgramNotFound, program)
+            })
+        }))
+        .collect()
+}
+
+/// Finds the file-based items whose file doesn't exist, and dir states whose dir doesn't.
+pub fn find_missing_paths(items: &[ConfigItem]) -> Vec<PruneCandidate> {
+    let missing_file = |name: &str, file: &str| (! fs_util::expand_home(file).exists()).then(|| PruneCandidate {
+        target: PruneTarget::Item(name.to_string()),
+        reason: t!(PathNotFound, file)
+    });
+
+    items
+        .iter()
+        .flat_map(|item| match item {
+            // A missing file is created when the key is added
+            ConfigItem::KeyValueItem(item) if ! item.create_missing => missing_file(&item.name, &item.file).into_iter().collect(),
+            ConfigItem::TemplateItem(item) => missing_file(&item.name, &item.template).into_iter().collect(),
+            ConfigItem::DirItem(item) => item.states
+                .iter()
+                .filter(|state| ! fs_util::expand_home(&state.path).is_dir())
+                .map(|state| PruneCandidate {
+                    target: PruneTarget::State(item.name.clone(), state.name.clone()),
+                    reason: t!(NotADirectory, state.path)
+                })
+                .collect(),
+            _ => vec![]
+        })
+        .collect()
+}
+
+/// Finds the states never switched to according to the history, except the current ones. The states of external items
+/// come from their plugin, so they aren't removable.
+pub fn find_unused_states(items: &[ConfigItem], entries: &[HistoryEntry]) -> Vec<PruneCandidate> {
+    let used: HashSet<(&str, &str)> = entries
+        .iter()
+        .map(|entry| (entry.item.as_str(), entry.to.as_str()))
+        .collect();
+
+    items
+        .iter()
+        .filter(|item| ! matches!(item, ConfigItem::ExternalItem(_)))
+        .flat_map(|item| {
+            let used = &used;
+            item.get_state_names()
+                .into_iter()
+                .filter(move |state| *state != item.get_current_state() && ! used.contains(&(item.get_name(), *state)))
+                .map(|state| PruneCandidate {
+                    target: PruneTarget::State(item.get_name().to_string(), state.to_string()),
+                    reason: t!(NeverSwitchedTo)
+                })
+        })
+        .collect()
+}
+
+/// Finds the items not switched in the last `stale_days` days, including those never switched.
+pub fn find_stale_items(
+    items: &[ConfigItem],
+    entries: &[HistoryEntry],
+    now: DateTime<Local>,
+    stale_days: u32
+) -> Vec<PruneCandidate> {
+    let threshold = now - TimeDelta::days(stale_days.into());
+    items
+        .iter()
+        .filter_map(|item| {
+            let last_time = entries
+                .iter()
+                .filter(|entry| entry.item == item.get_name())
+                .map(|entry| entry.time)
+                .max();
+            let reason = match last_time {
+                Some(time) if time >= threshold => return None,
+                Some(time) => t!(LastSwitchedOn, time.format("%Y-%m-%d")),
+                None => t!(NeverSwitched)
+            };
+            Some(PruneCandidate {
+                target: PruneTarget::Item(item.get_name().to_string()),
+                reason
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path};
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config::Config;
+
+    fn parse_items(config: &str) -> Vec<ConfigItem> {
+        toml::from_str::<Config>(config).unwrap().items
+    }
+
+    fn at(day: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap()
+    }
+
+    fn entry(day: u32, item: &str, to: &str) -> HistoryEntry {
+        HistoryEntry {
+            time: at(day),
+            item: item.to_string(),
+            from: String::new(),
+            to: to.to_string(),
+            command_override: None,
+            reapply: false
+        }
+    }
+
+    fn targets(candidates: &[PruneCandidate]) -> Vec<(&str, Option<&str>)> {
+        candidates
+            .iter()
+            .map(|candidate| match &candidate.target {
+                PruneTarget::Item(item) => (item.as_str(), None),
+                PruneTarget::State(item, state) => (item.as_str(), Some(state.as_str()))
+            })
+            .collect()
+    }
+
+    const ITEMS: &str = r#"
+        [[items]]
+        type = "CommandItem"
+        name = "proxy"
+        current = "off"
+        states = [
+            { name = "on", command = "echo on" },
+            { name = "off", command = "echo off" },
+            { name = "socks", command = "echo socks" }
+        ]
+
+        [[items]]
+        type = "CommandItem"
+        name = "theme"
+        current = "dark"
+        states = [
+            { name = "dark", command = "echo dark" },
+            { name = "light", command = "echo light" }
+        ]
+    "#;
+
+    #[test]
+    fn finds_missing_programs() {
+        // The test binary itself is an executable found everywhere
+        let exe = env::current_exe().unwrap();
+        let items = parse_items(&format!(r#"
+            [[items]]
+            type = "CommandItem"
+            name = "kube"
+            current = "dev"
+            states = [
+                {{ name = "dev", command = "switchy-missing-program --context dev" }},
+                {{ name = "prod", command = '{} --version' }},
+                {{ name = "ci", command = "FOO=1 switchy-missing-program" }}
+            ]
+        "#, exe.display()));
+        let candidates = find_missing_programs(&items);
+        assert_eq!(targets(&candidates), [("kube", Some("dev")), ("kube", Some("ci"))]);
+        assert_eq!(candidates[0].reason, "`switchy-missing-program` is not found on PATH");
+    }
+
+    #[test]
+    fn finds_missing_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let exists = dir.path().join("exists.toml");
+        fs::write(&exists, "").unwrap();
+        let missing = dir.path().join("missing.toml");
+        let path = |path: &Path| path.display().to_string();
+        let items = parse_items(&format!(r#"
+            [[items]]
+            type = "KeyValueItem"
+            name = "present"
+            current = "a"
+            file = '{exists}'
+            format = "toml"
+            key = "k"
+            states = [{{ name = "a", value = "1" }}]
+
+            [[items]]
+            type = "KeyValueItem"
+            name = "absent"
+            current = "a"
+            file = '{missing}'
+            format = "toml"
+            key = "k"
+            states = [{{ name = "a", value = "1" }}]
+
+            [[items]]
+            type = "KeyValueItem"
+            name = "created"
+            current = "a"
+            file = '{missing}'
+            format = "toml"
+            key = "k"
+            create_missing = true
+            states = [{{ name = "a", value = "1" }}]
+
+            [[items]]
+            type = "DirItem"
+            name = "project"
+            current = "here"
+            states = [
+                {{ name = "here", path = '{dir}' }},
+                {{ name = "gone", path = '{gone}' }}
+            ]
+        "#,
+            exists = path(&exists),
+            missing = path(&missing),
+            dir = path(dir.path()),
+            gone = path(&dir.path().join("gone"))
+        ));
+        let candidates = find_missing_paths(&items);
+        assert_eq!(targets(&candidates), [("absent", None), ("project", Some("gone"))]);
+    }
+
+    #[test]
+    fn finds_unused_states_except_current() {
+        let items = parse_items(ITEMS);
+        let entries = vec![entry(1, "proxy", "on"), entry(2, "theme", "light")];
+        assert_eq!(targets(&find_unused_states(&items, &entries)), [("proxy", Some("socks"))]);
+        assert_eq!(
+            targets(&find_unused_states(&items, &[])),
+            [("proxy", Some("on")), ("proxy", Some("socks")), ("theme", Some("light"))]
+        );
+    }
+
+    #[test]
+    fn finds_stale_items() {
+        let items = parse_items(ITEMS);
+        let entries = vec![entry(1, "proxy", "on"), entry(9, "proxy", "off")];
+        assert_eq!(targets(&find_stale_items(&items, &entries, at(10), 7)), [("theme", None)]);
+
+        let candidates = find_stale_items(&items, &entries, at(20), 7);
+        assert_eq!(targets(&candidates), [("proxy", None), ("theme", None)]);
+        assert_eq!(candidates[0].reason, "last switched on 2026-10-09");
+        assert_eq!(candidates[1].reason, "never switched");
+    }
+
+    #[test]
+    fn analyze_reports_each_target_once() {
+        let items = parse_items(ITEMS);
+        let entries = vec![entry(1, "proxy", "on"), entry(1, "theme", "light")];
+        let candidates = analyze(&items, &entries, at(20), 7);
+        assert_eq!(targets(&candidates), [("proxy", None), ("theme", None), ("proxy", Some("socks"))]);
+        assert_eq!(targets(&analyze(&items, &entries, at(2), 7)), [("proxy", Some("socks"))]);
+    }
+}
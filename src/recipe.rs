@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+
+use crate::{config::ConfigItem, i18n::t, shell, template};
+
+/// Built-in recipes generating whole items for common switches from a few params, as TOML embedded in the binary.
+const RECIPE_SOURCES: &[(&str, &str)] = &[
+    ("git-identity", include_str!("../recipes/git-identity.toml")),
+    ("kubectl-context", include_str!("../recipes/kubectl-context.toml")),
+    ("macos-appearance", include_str!("../recipes/macos-appearance.toml")),
+    ("npm-registry", include_str!("../recipes/npm-registry.toml")),
+    ("shell-proxy", include_str!("../recipes/shell-proxy.toml"))
+];
+
+#[derive(Deserialize)]
+pub struct Recipe {
+    #[serde(skip)]
+    pub id: &'static str,
+    pub description: String,
+    /// Params of the item, available as `{{name}}` in every string of the item and its states.
+    #[serde(default)]
+    pub params: Vec<RecipeParam>,
+    /// The item without its name and states.
+    pub item: toml::Table,
+    /// Fixed states, each with its `name`.
+    #[serde(default)]
+    pub states: Vec<toml::Table>,
+    /// States named by the user, all generated from the same state.
+    pub each_state: Option<RecipeEachState>
+}
+
+#[derive(Deserialize)]
+pub struct RecipeParam {
+    pub name: String,
+    pub prompt: String,
+    pub default: Option<String>,
+    /// A command whose first line of output is the default, if it succeeds.
+    pub discover: Option<String>
+}
+
+#[derive(Deserialize)]
+pub struct RecipeEachState {
+    pub prompt: String,
+    /// The default state names, separated by commas.
+    pub default: Option<String>,
+    /// A command listing the state names, one per line, if it succeeds.
+    pub discover: Option<String>,
+    /// Params of each state. The state name is available as `{{state}}`.
+    #[serde(default)]
+    pub params: Vec<RecipeParam>,
+    pub state: toml::Table
+}
+
+/// Returns every built-in recipe, ordered by id.
+pub fn get_recipes() -> Vec<Recipe> {
+    RECIPE_SOURCES
+        .iter()
+        .map(|(id, source)| Recipe {
+            id,
+            ..toml::from_str(source).unwrap_or_else(|err| panic!("Built-in recipe {} is invalid: {}", id, err))
+        })
+        .collect()
+}
+
+pub fn find_recipe(id: &str) -> Option<Recipe> {
+    get_recipes().into_iter().find(|recipe| recipe.id == id)
+}
+
+/// Runs the discover command, returning its output lines. Missing programs and failures discover nothing.
+pub fn discover(command: &str) -> Vec<String> {
+    if shell::find_missing_program(command).is_some() {
+        return vec![];
+    }
+    match shell::run_command_output(command, None) {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| ! line.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => vec![]
+    }
+}
+
+impl RecipeParam {
+    /// Returns the discovered value, or the default.
+    pub fn get_default(&self) -> Option<String> {
+        self.discover
+            .as_deref()
+            .and_then(|command| discover(command).into_iter().next())
+            .or_else(|| self.default.clone())
+    }
+}
+
+fn render_value(value: &mut toml::Value, vars: &BTreeMap<String, String>) -> Result<()> {
+    match value {
+        toml::Value::String(string) => *string = template::render(string, vars)?,
+        toml::Value::Array(array) => for value in array {
+            render_value(value, vars)?;
+        },
+        toml::Value::Table(table) => for (_, value) in table.iter_mut() {
+            render_value(value, vars)?;
+        },
+        _ => {}
+    }
+    Ok(())
+}
+
+fn render_table(table: &toml::Table, vars: &BTreeMap<String, String>) -> Result<toml::Table> {
+    let mut value = toml::Value::Table(table.clone());
+    render_value(&mut value, vars)?;
+    let toml::Value::Table(table) = value else { unreachable!() };
+    Ok(table)
+}
+
+impl Recipe {
+    /// Generates the item from the values of the item params, and the names and param values of each state.
+    /// `each_states` is only used by recipes with `each_state`.
+    pub fn generate(
+        &self,
+        name: String,
+        vars: &BTreeMap<String, String>,
+        each_states: &[(String, BTreeMap<String, String>)]
+    ) -> Result<ConfigItem> {
+        let mut states: Vec<toml::Value> = vec![];
+        for state in &self.states {
+            states.push(toml::Value::Table(render_table(state, vars)?));
+        }
+        if let Some(each_state) = &self.each_state {
+            for (state_name, state_vars) in each_states {
+                let mut state_vars = state_vars.clone();
+                state_vars.extend(vars.clone());
+                state_vars.insert("state".to_string(), state_name.clone());
+
+                let mut state = render_table(&each_state.state, &state_vars)?;
+                state.insert("name".to_string(), toml::Value::String(state_name.clone()));
+                states.push(toml::Value::Table(state));
+            }
+        }
+
+        let Some(current) = states.first().and_then(|state| state.get("name")).cloned() else {
+            bail!(t!(RecipeNoStates, self.id));
+        };
+
+        let mut item = render_table(&self.item, vars)?;
+        item.insert("name".to_string(), toml::Value::String(name));
+        item.insert("current".to_string(), current);
+        item.insert("states".to_string(), toml::Value::Array(states));
+
+        toml::Value::Table(item)
+            .try_into()
+            .with_context(|| t!(RecipeInvalidItem, self.id))
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    fs::{self, OpenOptions}, io::Write,
+    path::{Path, PathBuf}, process::ExitStatus, sync::Mutex
+};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+
+use crate::{i18n::t, shell::{self, ExecutionPlan, OutputStream}};
+
+/// Logs are rotated to `<item>.log.1` before a run once they exceed this size.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Every run in a log starts with a line with this prefix.
+const RUN_HEADER_PREFIX: &str = "=== ";
+
+pub fn get_log_path(log_dir: &Path, item_name: &str) -> PathBuf {
+    let file_name: String = item_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || "-_.".contains(c) { c } else { '_' })
+        .collect();
+    log_dir.join(format!("{}.log", file_name))
+}
+
+/// Runs the command of a state, teeing its output into the item's log with a header naming the state.
+/// The log shows `shown_command`, so decrypted commands stay out of it.
+pub fn run_logged(
+    log_dir: &Path,
+    item_name: &str,
+    state_name: &str,
+    plan: &ExecutionPlan,
+    shown_command: &str
+) -> Result<ExitStatus> {
+    fs::create_dir_all(log_dir).context(t!(FailedToCreateLogDir))?;
+
+    let log_path = get_log_path(log_dir, item_name);
+    if fs::metadata(&log_path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        fs::rename(&log_path, log_path.with_extension("log.1")).context(t!(FailedToRotateLog))?;
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .context(t!(FailedToOpenLog))?;
+    writeln!(file, "{}{} state: {}", RUN_HEADER_PREFIX, Local::now().to_rfc3339(), state_name)?;
+    writeln!(file, "$ {}", shown_command)?;
+
+    let file = Mutex::new(file);
+    let status = shell::run_plan_tee(plan, &|stream, line| {
+        let mut file = file.lock().unwrap();
+        let time = Local::now().format("%H:%M:%S");
+        let _ = match stream {
+            OutputStream::Stdout => writeln!(file, "[{}] {}", time, line),
+            OutputStream::Stderr => writeln!(file, "[{}] (stderr) {}", time, line)
+        };
+    })?;
+
+    let mut file = file.into_inner().unwrap();
+    writeln!(file, "{}{}", RUN_HEADER_PREFIX, status)?;
+
+    Ok(status)
+}
+
+/// Reads the last run from the item's log, or `None` if nothing is logged yet.
+pub fn read_last_run(log_dir: &Path, item_name: &str) -> Result<Option<String>> {
+    let log_path = get_log_path(log_dir, item_name);
+    if ! log_path.exists() {
+        return Ok(None);
+    }
+
+    let log_str = fs::read_to_string(&log_path).context(t!(FailedToReadLog))?;
+    let lines: Vec<&str> = log_str.lines().collect();
+    let start = lines
+        .iter()
+        .rposition(|line| line.starts_with(RUN_HEADER_PREFIX) && line.contains(" state: "))
+        .unwrap_or(0);
+
+    Ok(Some(lines[start ..].join("\n")))
+}
+
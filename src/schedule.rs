@@ -0,0 +1,142 @@
+use std::{env, io::{stderr, stdin, IsTerminal, Write}, process, thread, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local, NaiveTime, TimeDelta};
+use dialoguer::{theme::ColorfulTheme, Confirm};
+
+use crate::{i18n::t, interrupt};
+
+/// Parses `--at`: a time of day like `18:00` (today, or tomorrow if it has passed), an RFC 3339 date time,
+/// or a duration like `2h` from now.
+pub fn parse_at(time: &str) -> Result<DateTime<Local>> {
+    let now = Local::now();
+
+    for format in ["%H:%M", "%H:%M:%S"] {
+        if let Ok(time_of_day) = NaiveTime::parse_from_str(time, format) {
+            let mut date_time = now
+                .date_naive()
+                .and_time(time_of_day)
+                .and_local_timezone(Local)
+                .earliest()
+                .context(t!(TimeNotInTimezone))?;
+            if date_time <= now {
+                date_time += TimeDelta::days(1);
+            }
+            return Ok(date_time);
+        }
+    }
+
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(time) {
+        return Ok(date_time.with_timezone(&Local));
+    }
+
+    parse_in(time).map_err(|_| anyhow!(t!(InvalidTime, time)))
+}
+
+/// Parses `--in`: a duration like `2h 30m` from now.
+pub fn parse_in(duration: &str) -> Result<DateTime<Local>> {
+    Ok(Local::now() + parse_duration(duration)?)
+}
+
+/// Parses a duration like `2h 30m`, as of `--in` and `--for`.
+pub fn parse_duration(duration: &str) -> Result<TimeDelta> {
+    let parsed = humantime::parse_duration(duration)
+        .with_context(|| t!(InvalidDuration, duration))?;
+    Ok(TimeDelta::from_std(parsed)?)
+}
+
+/// Waits until the time, printing a countdown to stderr. Returns `false` if cancelled by Ctrl-C.
+pub fn wait_until(time: DateTime<Local>, label: &str) -> Result<bool> {
+    let is_terminal = stderr().is_terminal();
+    if ! is_terminal {
+        eprintln!("{} at {}", label, time.to_rfc3339());
+    }
+
+    loop {
+        if interrupt::is_interrupted() {
+            if is_terminal {
+                eprintln!();
+            }
+            return Ok(false);
+        }
+
+        let remaining = time - Local::now();
+        if remaining <= TimeDelta::zero() {
+            break;
+        }
+
+        if is_terminal {
+            let seconds = remaining.num_seconds();
+            eprint!("\r\x1b[2K{} in {:02}:{:02}:{:02}", label, seconds / 3600, seconds / 60 % 60, seconds % 60);
+            stderr().flush()?;
+        }
+        thread::sleep(Duration::from_millis(100).min(remaining.to_std()?));
+    }
+
+    if is_terminal {
+        eprintln!("\r\x1b[2K{} now", label);
+    }
+
+    Ok(true)
+}
+
+/// Waits until the time to switch back for `--for`, like `wait_until`. Ctrl-C asks whether to cancel switching back,
+/// if there's a terminal to ask in, and waiting goes on if not. Returns `false` if cancelled.
+pub fn wait_to_switch_back(time: DateTime<Local>, label: &str, theme: &ColorfulTheme, item_name: &str, state_name: &str) -> Result<bool> {
+    loop {
+        if wait_until(time, label)? {
+            return Ok(true);
+        }
+        if ! stdin().is_terminal() {
+            return Ok(false);
+        }
+        interrupt::clear_interrupted();
+        // Another Ctrl-C in the prompt cancels too
+        let cancel = Confirm::with_theme(theme)
+            .with_prompt(t!(ConfirmCancelSwitchBack, item_name, state_name))
+            .default(true)
+            .interact_opt()
+            .unwrap_or(Some(true));
+        if cancel != Some(false) {
+            interrupt::set_interrupted();
+            return Ok(false);
+        }
+    }
+}
+
+/// Spawns a background switchy doing the switch at the time, returning its PID. With `if_current`, it only switches
+/// if the item is still in that state then, and notes in `switchy.log` why if not, having no terminal to tell.
+pub fn detach(item_name: &str, state_name: &str, time: DateTime<Local>, if_current: Option<&str>) -> Result<u32> {
+    let exe = env::current_exe().context(t!(FailedToFindExe))?;
+
+    let mut command = process::Command::new(exe);
+    command
+        .args([item_name, state_name, "--at", &time.to_rfc3339(), "--quiet"])
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null());
+    if let Some(if_current) = if_current {
+        command.args(["--if-current", if_current, "--log-file"]);
+        // Only the notes, unless a level is asked for
+        if env::var_os("SWITCHY_LOG").is_none() {
+            command.env("SWITCHY_LOG", "warn");
+        }
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Leave the terminal's process group, so Ctrl-C in the terminal doesn't reach it
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const DETACHED_PROCESS: u32 = 0x00000008;
+        command.creation_flags(DETACHED_PROCESS);
+    }
+
+    let child = command.spawn().context(t!(FailedToSpawnSwitch))?;
+
+    Ok(child.id())
+}
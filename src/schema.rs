@@ -0,0 +1,558 @@
+use serde_json::{json, Map, Value};
+
+use crate::machine;
+
+/// Returns the JSON Schema of the config file, for editors validating and completing it.
+/// Keep it in sync with the structs in `config`.
+pub fn get_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "switchy config",
+        "type": "object",
+        "properties": {
+            "include": {
+                "type": "array",
+                "description": "Files with more items, relative to the config dir. `~` is expanded, and `*` and `?` match in file names.",
+                "items": { "type": "string" }
+            },
+            "defaults": get_defaults_schema(),
+            "sync": {
+                "type": "object",
+                "description": "How `--sync` syncs the config dir, when it's a git repo.",
+                "properties": {
+                    "command": string(
+                        "The command to run in the config dir instead of committing, pulling and pushing with git. \
+                        The commit message is in `SWITCHY_SYNC_MESSAGE`."
+                    ),
+                    "auto": boolean("Whether to sync after every change of the config, including switches.")
+                },
+                "additionalProperties": false
+            },
+            "theme": {
+                "type": "object",
+                "description": "How listings look.",
+                "properties": {
+                    "current_marker": string("The marker of current states, instead of `*`.")
+                },
+                "additionalProperties": false
+            },
+            "presets": {
+                "type": "object",
+                "description": "Presets saved by `--save-preset` and switched to by `--preset`.",
+                "additionalProperties": string_map("Item name => state.")
+            },
+            "items": {
+                "type": "array",
+                "items": {
+                    "oneOf": [
+                        get_item_schema("CommandItem", "An item running the command of the state.", json!({
+                            "ssh_host": string(
+                                "`[user@]host[:port]` to run the commands on over SSH with the login shell there, instead of locally. \
+                                `available_if` still runs locally."
+                            ),
+                            "ssh_options": {
+                                "type": "array",
+                                "description": "Extra args of ssh, like `[\"-i\", \"~/.ssh/box\"]`.",
+                                "items": { "type": "string" }
+                            },
+                            "rollback_on_failure": boolean(
+                                "Whether to rerun the command of the previous state when `verify_command` of the new one fails."
+                            )
+                        }), &[], json!({
+                            "verify_command": string(
+                                "A command checking that the switch took effect, run after `command` where it runs. \
+                                The switch fails if it does, recording nothing."
+                            ),
+                            "verify_retries": json!({
+                                "type": "integer",
+                                "minimum": 0,
+                                "description": "How many more times `verify_command` runs while it fails. Defaults to 0."
+                            }),
+                            "verify_delay_ms": json!({
+                                "type": "integer",
+                                "minimum": 0,
+                                "description": "How long to wait before each retry of `verify_command`, in milliseconds. Defaults to 1000."
+                            }),
+                            "command": {
+                                "description": "The command to run, with the system shell or `shell`. An empty or missing one only records the state.",
+                                "oneOf": [
+                                    { "type": "string" },
+                                    {
+                                        "type": "object",
+                                        "properties": {
+                                            "encrypted": string("The command encrypted by `--encrypt-state`, in base64.")
+                                        },
+                                        "required": ["encrypted"],
+                                        "additionalProperties": false
+                                    }
+                                ]
+                            }
+                        }), &[]),
+                        get_item_schema(
+                            "KeyValueItem",
+                            "An item setting a key in an INI, TOML or env file to the value of the state.",
+                            json!({
+                                "file": string("The file to edit. `~` is expanded."),
+                                "format": string_enum("The format of the file.", &["ini", "toml", "env"]),
+                                "key": string("The key, prefixed by its section like `section.key` for INI and TOML files."),
+                                "create_missing": boolean("Whether to add the key if it's missing, instead of failing."),
+                                "mode": string("The permission bits of the file, in octal like `0600`. Unix only. Kept as it is if not set.")
+                            }),
+                            &["file", "format", "key"],
+                            json!({
+                                "value": string("The value to set the key to.")
+                            }),
+                            &["value"]
+                        ),
+                        get_item_schema(
+                            "BlockItem",
+                            "An item replacing the block between `# switchy:<marker> begin` and `# switchy:<marker> end` lines in a file.",
+                            json!({
+                                "file": string("The file to edit. `~` is expanded."),
+                                "marker": string("The marker of the block."),
+                                "comment": string("The line comment prefix of the marker lines. Defaults to `#`."),
+                                "mode": string("The permission bits of the file, in octal like `0600`. Unix only. Kept as it is if not set.")
+                            }),
+                            &["file", "marker"],
+                            json!({
+                                "content": string("The content of the block. Exactly one of `content` and `content_file` is set."),
+                                "content_file": string("A snippet file with the content of the block. `~` is expanded.")
+                            }),
+                            &[]
+                        ),
+                        get_item_schema(
+                            "GitConfigItem",
+                            "An item setting git config keys, e.g. `user.name` and `user.email` for switching git identities.",
+                            json!({
+                                "scope": string("`global`, or the path of a repo for its local config. Defaults to `global`.")
+                            }),
+                            &[],
+                            json!({
+                                "values": string_map("Git config key => value.")
+                            }),
+                            &["values"]
+                        ),
+                        get_item_schema(
+                            "DirItem",
+                            "An item whose states are directories, for `cd`ing there with `--print-path`.",
+                            json!({}),
+                            &[],
+                            json!({
+                                "path": string("The directory. `~` is expanded.")
+                            }),
+                            &["path"]
+                        ),
+                        get_item_schema(
+                            "TemplateItem",
+                            "An item rendering a template file with the vars of the state into the output file.",
+                            json!({
+                                "template": string("The template file, with `{{var}}` placeholders. `~` is expanded."),
+                                "output": string("The file to write. `~` is expanded."),
+                                "post_command": string("Run after the output is written, e.g. to reload the program reading it."),
+                                "mode": string("The permission bits of the file, in octal like `0600`. Unix only. Defaults to `0644`.")
+                            }),
+                            &["template", "output"],
+                            json!({
+                                "vars": string_map("Placeholder name => value.")
+                            }),
+                            &["vars"]
+                        ),
+                        without_states(get_item_schema(
+                            "ExternalItem",
+                            "An item whose states are listed and switched by a plugin program.",
+                            json!({
+                                "plugin": string("The plugin, a program on PATH or a path relative to the config dir. `~` is expanded."),
+                                "settings": {
+                                    "type": "object",
+                                    "description": "Passed to the plugin as is."
+                                }
+                            }),
+                            &["plugin"],
+                            json!({}),
+                            &[]
+                        ))
+                    ]
+                }
+            }
+        },
+        "required": ["items"]
+    })
+}
+
+fn get_show_command_schema(description: &str) -> Value {
+    string_enum(
+        &format!("{} `full` shows all of it, `first-word` only the program, and `none` nothing of it.", description),
+        &["full", "first-word", "none"]
+    )
+}
+
+fn get_defaults_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Global settings.",
+        "properties": {
+            "default_item": string("The item to switch when no item is given."),
+            "on_change": string("The command to run after successful switches."),
+            "log": boolean("Whether to log command output of items which don't set `log`."),
+            "notify": boolean("Whether to post desktop notifications of switches for items which don't set `notify`."),
+            "shell": get_shell_schema("The shell to run commands with for items which don't set `shell`, instead of the system one."),
+            "on_interrupt": string_enum(
+                "What an interrupted switch does to the current state: `fail` keeps the previous one, `record` records the new one.",
+                &["fail", "record"]
+            ),
+            "lang": string("The language of messages, e.g. `zh-CN`. Defaults to the locale env vars."),
+            "askpass": string("The command printing the passphrase of encrypted commands, instead of prompting for it."),
+            "sort": string_enum(
+                "The order of items in `--list` and the item picker. `manual` is the order in the config.",
+                &["manual", "name", "type", "recent"]
+            ),
+            "show_header": boolean("Whether to show the item with its current state above the state picker. Defaults to `true`."),
+            "match": string_enum(
+                "How typed text filters the item and state pickers: `fuzzy` ranks by matched chars in order, \
+                `substring` keeps the entries containing it, and `prefix` those starting with it.",
+                &["fuzzy", "substring", "prefix"]
+            ),
+            "case_sensitive_names": boolean(
+                "Whether names differing only in ASCII case are different, so adding `Proxy` next to `proxy` is allowed."
+            ),
+            "trash_days": json!({
+                "type": "integer",
+                "minimum": 0,
+                "description": "How many days items removed by `--remove` stay in the trash. Defaults to 30."
+            }),
+            "confirm_run": string_enum(
+                "`always` shows each command with where it runs, and runs it only if confirmed.",
+                &["never", "always"]
+            ),
+            "show_command": get_show_command_schema(
+                "How much of the command the `Running $ ...` banner shows, for items which don't set `show_command`."
+            ),
+            "confirm_writes": boolean(
+                "Whether to show the diff of the config file and confirm before changes like adding or removing items."
+            ),
+            "confirm_fuzzy": boolean(
+                "Whether to confirm the item when the name given on the command line isn't exactly its name."
+            )
+        },
+        "additionalProperties": false
+    })
+}
+
+fn get_shell_schema(description: &str) -> Value {
+    string_enum(description, &["powershell", "pwsh"])
+}
+
+/// Makes the schema of an item type from the fields of the type and of its states.
+/// The fields shared by all types are added.
+fn get_item_schema(
+    item_type: &str,
+    description: &str,
+    fields: Value,
+    required_fields: &[&str],
+    state_fields: Value,
+    required_state_fields: &[&str]
+) -> Value {
+    let mut properties = Map::new();
+    properties.insert("type".to_string(), json!({ "const": item_type }));
+    properties.insert("name".to_string(), string("The name of the item."));
+    properties.insert("current".to_string(), string("The name of the current state."));
+    properties.insert("prompt".to_string(), boolean("Whether the item appears in `--prompt-status`."));
+    properties.insert("label".to_string(), string("A short label used instead of the name in `--prompt-status`."));
+    properties.insert("log".to_string(), boolean("Whether to log command output into `logs/<item>.log` under the config dir."));
+    properties.insert("notify".to_string(), boolean("Whether to post a desktop notification when the item is switched."));
+    properties.insert("shell".to_string(), get_shell_schema("The shell to run commands with, instead of the system one."));
+    properties.insert("only_on".to_string(), json!({
+        "type": "object",
+        "description": "The machines the item is for. It's hidden on others.",
+        "properties": {
+            "os": {
+                "type": "array",
+                "description": "OS names.",
+                "items": { "type": "string", "enum": machine::KNOWN_OS }
+            },
+            "hostname": {
+                "type": "array",
+                "description": "Host names, matched case-insensitively.",
+                "items": { "type": "string" }
+            }
+        },
+        "additionalProperties": false
+    }));
+    properties.insert("tags".to_string(), json!({
+        "type": "array",
+        "description": "Tags for grouping items with `--list --group-by tag`.",
+        "items": { "type": "string" }
+    }));
+    properties.insert("watch".to_string(), string(
+        "A file `--watch` watches, reapplying the current state when something else changes it. `~` is expanded."
+    ));
+    properties.insert("default_state".to_string(), string(
+        "The known-safe state `--reset` and `--reset-all` switch back to. It must be one of the states."
+    ));
+    properties.insert("show_command".to_string(), get_show_command_schema(
+        "How much of the command the `Running $ ...` banner shows. `--dry-run`, `--confirm`, `--debug-exec` and the logs \
+        still show all of it."
+    ));
+    properties.insert("preflight".to_string(), boolean(
+        "Whether to check that the program of a command is on PATH before running it. Defaults to `true`. \
+        Turn it off for commands starting with shell functions."
+    ));
+    if let Value::Object(fields) = fields {
+        properties.extend(fields);
+    }
+
+    let mut state_properties = Map::new();
+    state_properties.insert("name".to_string(), string("The name of the state."));
+    state_properties.insert("color".to_string(), string_enum("The color of the state name in listings.", &[
+        "black", "red", "green", "yellow", "blue", "magenta", "purple", "cyan", "white", "bright black", "bright red",
+        "bright green", "bright yellow", "bright blue", "bright magenta", "bright cyan", "bright white"
+    ]));
+    state_properties.insert("available_if".to_string(), string(
+        "A command checking whether the state can be switched to now, which makes it unavailable when it fails. \
+        It's run when the state is picked or toggled to, and may take up to 2 seconds."
+    ));
+    state_properties.insert("value".to_string(), string(
+        "A value of the state for scripts, like the proxy URL, printed by `--value`. Commands and templates get it as \
+        `{{value}}`."
+    ));
+    state_properties.insert("max_age".to_string(), string(
+        "How long the state may stay current, like `2h` or `1day`, before `--list` and the item picker flag the item as overdue."
+    ));
+    if let Value::Object(state_fields) = state_fields {
+        state_properties.extend(state_fields);
+    }
+    properties.insert("states".to_string(), json!({
+        "type": "array",
+        "minItems": 1,
+        "items": {
+            "type": "object",
+            "properties": state_properties,
+            "required": ([&["name"], required_state_fields].concat()),
+            "additionalProperties": false
+        }
+    }));
+
+    json!({
+        "type": "object",
+        "description": description,
+        "properties": properties,
+        "required": ([&["type", "name", "current", "states"], required_fields].concat()),
+        "additionalProperties": false
+    })
+}
+
+/// Removes `states` from an item schema, for items whose states don't come from the config.
+fn without_states(mut schema: Value) -> Value {
+    schema["properties"].as_object_mut().unwrap().remove("states");
+    schema["required"].as_array_mut().unwrap().retain(|field| field != "states");
+    schema
+}
+
+fn string(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn boolean(description: &str) -> Value {
+    json!({ "type": "boolean", "description": description })
+}
+
+fn string_enum(description: &str, values: &[&str]) -> Value {
+    json!({ "type": "string", "description": description, "enum": values })
+}
+
+fn string_map(description: &str) -> Value {
+    json!({ "type": "object", "description": description, "additionalProperties": { "type": "string" } })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{config::Config, recipe};
+
+    /// A config setting every field of the config and of every item type.
+    const FULL_CONFIG: &str = r##"
+        include = ["work/*.toml"]
+
+        [defaults]
+        default_item = "proxy"
+        on_change = "tmux refresh-client -S"
+        log = true
+        notify = true
+        shell = "pwsh"
+        on_interrupt = "record"
+        lang = "zh-CN"
+        confirm_writes = true
+        askpass = "pass show switchy"
+        confirm_run = "always"
+        sort = "recent"
+        show_header = false
+        match = "prefix"
+        case_sensitive_names = true
+        confirm_fuzzy = true
+        trash_days = 7
+        show_command = "first-word"
+
+        [sync]
+        command = "rsync -a . backup:switchy"
+        auto = true
+
+        [theme]
+        current_marker = ">"
+
+        [presets.demo]
+        proxy = "on"
+
+        [[items]]
+        type = "CommandItem"
+        name = "proxy"
+        current = "off"
+        ssh_host = "me@box:2222"
+        ssh_options = ["-i", "~/.ssh/box"]
+        rollback_on_failure = true
+        prompt = true
+        label = "px"
+        log = false
+        notify = false
+        shell = "powershell"
+        only_on = { os = ["linux", "macos"], hostname = ["box"] }
+        tags = ["net"]
+        watch = "~/.proxyrc"
+        preflight = false
+        default_state = "off"
+        show_command = "none"
+
+        [[items.states]]
+        name = "on"
+        color = "bright green"
+        available_if = "test -f ~/.proxyrc"
+        max_age = "2h"
+        value = "socks5://127.0.0.1:1080"
+        verify_command = "curl -sI https://example.com"
+        verify_retries = 3
+        verify_delay_ms = 500
+        command = "export ALL_PROXY={{value}}"
+
+        [[items.states]]
+        name = "off"
+        command = { encrypted = "c2VjcmV0" }
+
+        [[items]]
+        type = "KeyValueItem"
+        name = "registry"
+        current = "npm"
+        file = "~/.npmrc"
+        format = "ini"
+        key = "registry"
+        create_missing = true
+        mode = "600"
+        states = [{ name = "npm", value = "https://registry.npmjs.org/" }]
+
+        [[items]]
+        type = "BlockItem"
+        name = "hosts"
+        current = "off"
+        file = "/etc/hosts"
+        marker = "dev"
+        comment = "#"
+        states = [{ name = "on", content = "127.0.0.1 dev.local" }, { name = "off" }]
+
+        [[items]]
+        type = "GitConfigItem"
+        name = "identity"
+        current = "work"
+        scope = "local"
+        states = [{ name = "work", values = { "user.email" = "me@work.example" } }]
+
+        [[items]]
+        type = "DirItem"
+        name = "project"
+        current = "api"
+        states = [{ name = "api", path = "~/src/api", value = "api" }]
+
+        [[items]]
+        type = "TemplateItem"
+        name = "theme"
+        current = "dark"
+        template = "~/.config/alacritty/theme.toml.in"
+        output = "~/.config/alacritty/theme.toml"
+        post_command = "touch ~/.config/alacritty/alacritty.toml"
+        mode = "644"
+        states = [{ name = "dark", vars = { background = "#000000" } }]
+
+        [[items]]
+        type = "ExternalItem"
+        name = "java"
+        current = "17"
+        plugin = "plugins/symlink.sh"
+        settings = { dir = "/opt/jdks", link = "~/.local/jdk" }
+    "##;
+
+    /// Returns why the config doesn't match the schema, if it doesn't.
+    fn validate(config: &Value) -> Vec<String> {
+        let validator = jsonschema::validator_for(&get_schema()).expect("the schema is invalid");
+        validator
+            .iter_errors(config)
+            .map(|err| format!("{} at {}", err, err.instance_path))
+            .collect()
+    }
+
+    fn validate_toml(config: &str) -> Vec<String> {
+        validate(&serde_json::to_value(toml::from_str::<toml::Value>(config).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn full_config_is_valid() {
+        // The sample itself must fit the structs, so the schema is checked against what switchy reads
+        let config = toml::from_str::<Config>(FULL_CONFIG).unwrap();
+        assert!(config.defaults.confirm_fuzzy);
+        assert_eq!(validate_toml(FULL_CONFIG), Vec::<String>::new());
+        // And so is what switchy writes
+        assert_eq!(validate(&serde_json::to_value(&config).unwrap()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn empty_config_is_valid() {
+        assert_eq!(validate_toml("items = []"), Vec::<String>::new());
+        assert!(! validate_toml("").is_empty());
+        let config = toml::from_str::<Config>("items = []").unwrap();
+        assert_eq!(validate(&serde_json::to_value(&config).unwrap()), Vec::<String>::new());
+    }
+
+    #[test]
+    fn recipes_generate_valid_items() {
+        for recipe in recipe::get_recipes() {
+            let vars: BTreeMap<String, String> = recipe.params
+                .iter()
+                .map(|param| (param.name.clone(), param.default.clone().unwrap_or("value".to_string())))
+                .collect();
+            let each_states: Vec<(String, BTreeMap<String, String>)> = ["a", "b"]
+                .into_iter()
+                .map(|state| {
+                    let params = recipe.each_state.iter().flat_map(|each_state| &each_state.params);
+                    (state.to_string(), params.map(|param| (param.name.clone(), state.to_string())).collect())
+                })
+                .collect();
+            let item = recipe.generate("item".to_string(), &vars, &each_states).unwrap();
+            let config = json!({ "items": [serde_json::to_value(&item).unwrap()] });
+            assert_eq!(validate(&config), Vec::<String>::new(), "recipe {}", recipe.id);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        let invalid = [
+            "[defaults]\nconfrim_run = \"always\"\n",
+            "[defaults]\nsort = \"random\"\n",
+            "[[items]]\ntype = \"ShellItem\"\nname = \"a\"\ncurrent = \"b\"\nstates = [{ name = \"b\" }]\n",
+            "[[items]]\ntype = \"CommandItem\"\nname = \"a\"\ncurrent = \"b\"\nstates = []\n",
+            "[[items]]\ntype = \"CommandItem\"\nname = \"a\"\ncurrent = \"b\"\nstates = [{ name = \"b\", command = 1 }]\n",
+            "[[items]]\ntype = \"KeyValueItem\"\nname = \"a\"\ncurrent = \"b\"\nfile = \"f\"\nformat = \"yaml\"\nkey = \"k\"\n\
+             states = [{ name = \"b\", value = \"v\" }]\n"
+        ];
+        for config in invalid {
+            assert!(! validate_toml(config).is_empty(), "{}", config);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+/// Encrypts the text with a passphrase into base64, for `command = { encrypted = "..." }`.
+/// The passphrase comes from the askpass command if set, else it's prompted for twice.
+#[cfg(feature = "encrypt")]
+pub fn encrypt(text: &str, askpass: Option<&str>) -> Result<String> {
+    use base64::Engine;
+
+    let passphrase = get_passphrase(askpass, true)?;
+    let encrypted = age::encrypt(&age::scrypt::Recipient::new(passphrase), text.as_bytes())?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(encrypted))
+}
+
+/// Decrypts the base64 text from `encrypt`. The passphrase is asked for once per run.
+#[cfg(feature = "encrypt")]
+pub fn decrypt(encrypted: &str, askpass: Option<&str>) -> Result<String> {
+    use anyhow::Context;
+    use base64::Engine;
+
+    use crate::i18n::t;
+
+    let encrypted = base64::engine::general_purpose::STANDARD
+        .decode(encrypted.trim())
+        .context(t!(InvalidEncrypted))?;
+    let passphrase = get_passphrase(askpass, false)?;
+    let decrypted = age::decrypt(&age::scrypt::Identity::new(passphrase), &encrypted).context(t!(DecryptFailed))?;
+    String::from_utf8(decrypted).context(t!(InvalidEncrypted))
+}
+
+/// Gets the passphrase from the first line of the askpass output, or prompts for it.
+/// It's kept for the rest of the run, so switching several encrypted states asks once.
+#[cfg(feature = "encrypt")]
+fn get_passphrase(askpass: Option<&str>, confirm: bool) -> Result<age::secrecy::SecretString> {
+    use std::sync::Mutex;
+
+    use anyhow::bail;
+    use dialoguer::Password;
+
+    use crate::{i18n::t, shell};
+
+    static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+    let mut cached = PASSPHRASE.lock().unwrap();
+    if let Some(passphrase) = cached.as_ref().filter(|_| ! confirm) {
+        return Ok(passphrase.clone().into());
+    }
+
+    let passphrase = match askpass {
+        Some(askpass) => {
+            let output = shell::run_command_output(askpass, None)?;
+            if ! output.status.success() {
+                bail!(t!(AskpassFailed, askpass, output.status));
+            }
+            String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().to_string()
+        },
+        None if confirm => Password::new()
+            .with_prompt(t!(Passphrase))
+            .with_confirmation(t!(RepeatPassphrase), t!(PassphrasesDiffer))
+            .interact()?,
+        None => Password::new().with_prompt(t!(Passphrase)).interact()?
+    };
+    if passphrase.is_empty() {
+        bail!(t!(EmptyPassphrase));
+    }
+
+    *cached = Some(passphrase.clone());
+    Ok(passphrase.into())
+}
+
+#[cfg(not(feature = "encrypt"))]
+pub fn encrypt(_text: &str, _askpass: Option<&str>) -> Result<String> {
+    anyhow::bail!(crate::i18n::t!(EncryptionUnsupported));
+}
+
+#[cfg(not(feature = "encrypt"))]
+pub fn decrypt(_encrypted: &str, _askpass: Option<&str>) -> Result<String> {
+    anyhow::bail!(crate::i18n::t!(EncryptionUnsupported));
+}
@@ -0,0 +1,506 @@
+use std::{env, ffi::OsString, fmt, io::{stderr, stdout, Read, Write}, path::{Path, PathBuf}, process, thread, time::{Duration, Instant}};
+
+use anyhow::Result;
+use serde::{Serialize, Deserialize};
+
+use crate::{i18n::t, interrupt};
+
+/// Shell builtins which never resolve on PATH but are fine to run.
+const SHELL_BUILTINS: &[&str] = &[
+    ".", ":", "alias", "cd", "eval", "exec", "exit", "export", "set", "source", "unset"
+];
+
+/// The words starting compound commands, which aren't programs either.
+const SHELL_KEYWORDS: &[&str] = &[
+    "!", "[[", "{", "case", "for", "function", "if", "time", "until", "while"
+];
+
+/// The keywords of PowerShell, which `Get-Command` doesn't know.
+const POWERSHELL_KEYWORDS: &[&str] = &[
+    "begin", "break", "class", "continue", "do", "exit", "filter", "for", "foreach", "function", "if", "param",
+    "return", "switch", "throw", "trap", "try", "until", "using", "while"
+];
+
+#[derive(Clone, Copy)]
+pub enum OutputStream {
+    Stdout,
+    Stderr
+}
+
+/// A shell to run commands with instead of the system one, i.e. `sh`, or `COMSPEC` on Windows.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandShell {
+    Powershell,
+    Pwsh
+}
+
+/// Describes how the shell runs commands, e.g. `sh -c`.
+pub fn describe_shell(shell: Option<CommandShell>) -> &'static str {
+    match shell {
+        Some(CommandShell::Powershell) => "powershell -NoProfile -Command",
+        Some(CommandShell::Pwsh) => "pwsh -NoProfile -Command",
+        None if cfg!(windows) => "cmd /S /C",
+        None => "sh -c"
+    }
+}
+
+/// How a command is run: the program and args passed to `process::Command`, with the env vars set on top of
+/// the inherited ones. Built before running, so `--debug-exec` and `--dry-run` show exactly what runs.
+pub struct ExecutionPlan {
+    pub program: OsString,
+    pub args: Vec<String>,
+    /// Whether the args are passed raw instead of quoted, as cmd needs on Windows.
+    pub raw_args: bool,
+    /// The dir to run in, or `None` for the current one.
+    pub dir: Option<PathBuf>,
+    pub vars: Vec<(String, String)>
+}
+
+impl ExecutionPlan {
+    pub fn new(command: &str, shell: Option<CommandShell>) -> Self {
+        let plan = |program: OsString, args: Vec<String>, raw_args: bool| ExecutionPlan {
+            program, args, raw_args, dir: None, vars: vec![]
+        };
+        match shell {
+            Some(CommandShell::Powershell) => plan("powershell".into(), vec!["-NoProfile".into(), "-Command".into(), command.into()], false),
+            Some(CommandShell::Pwsh) => plan("pwsh".into(), vec!["-NoProfile".into(), "-Command".into(), command.into()], false),
+            // Pass the command raw, since cmd doesn't parse its command line by the rules Rust quotes arguments with.
+            // With `/S`, cmd only strips the outer quotes, keeping quotes, `%` and `^` in the command as they are
+            None if cfg!(windows) => plan(
+                env::var_os("COMSPEC").unwrap_or("cmd".into()),
+                vec![format!("/S /C \"{}\"", command)],
+                true
+            ),
+            None => plan("sh".into(), vec!["-c".into(), command.into()], false)
+        }
+    }
+
+    pub fn in_dir(mut self, dir: &Path) -> Self {
+        self.dir = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn with_vars(mut self, vars: &[(&str, &str)]) -> Self {
+        self.vars = vars.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect();
+        self
+    }
+
+    /// Describes the program and args as a command line, e.g. `sh -c 'echo "$HOME"'`, for showing what runs.
+    pub fn describe(&self) -> String {
+        let mut words = vec![quote_arg(&self.program.to_string_lossy())];
+        words.extend(self.args.iter().map(|arg| if self.raw_args { arg.clone() } else { quote_arg(arg) }));
+        words.join(" ")
+    }
+
+    fn to_command(&self) -> process::Command {
+        let mut command = process::Command::new(&self.program);
+        #[cfg(windows)]
+        if self.raw_args {
+            use std::os::windows::process::CommandExt;
+            for arg in &self.args {
+                command.raw_arg(arg);
+            }
+        }
+        else {
+            command.args(&self.args);
+        }
+        #[cfg(not(windows))]
+        command.args(&self.args);
+
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+        command.envs(self.vars.iter().map(|(name, value)| (name, value)));
+        command
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "program: {}", self.program.to_string_lossy())?;
+        writeln!(f, "argv{}: {:?}", if self.raw_args { " (raw)" } else { "" }, self.args)?;
+        let dir = self.dir.clone().or_else(|| env::current_dir().ok()).unwrap_or_default();
+        writeln!(f, "cwd: {}", dir.display())?;
+        let var_names: Vec<&str> = self.vars.iter().map(|(name, _)| name.as_str()).collect();
+        write!(f, "env set: {}", if var_names.is_empty() { "(none)".to_string() } else { var_names.join(", ") })
+    }
+}
+
+fn shell_command(command: &str, shell: Option<CommandShell>) -> process::Command {
+    ExecutionPlan::new(command, shell).to_command()
+}
+
+/// Spawns the command, with Ctrl-C forwarded to it. Without a terminal on stdin, e.g. when scheduled in background,
+/// the child runs in its own process group, so only the forwarded signal reaches it.
+/// Children reading the terminal must stay in its foreground group, where they get Ctrl-C directly.
+fn spawn(command: &mut process::Command) -> Result<process::Child> {
+    #[cfg(unix)]
+    let own_group = ! std::io::IsTerminal::is_terminal(&std::io::stdin());
+    #[cfg(unix)]
+    if own_group {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let child = command.spawn()?;
+    // The args aren't logged, since they may hold a decrypted command
+    log::debug!("Spawned {} as pid {}", command.get_program().to_string_lossy(), child.id());
+    #[cfg(unix)]
+    if own_group {
+        interrupt::set_child_group(Some(child.id()));
+    }
+    Ok(child)
+}
+
+fn wait(mut child: process::Child) -> Result<process::ExitStatus> {
+    let status = child.wait();
+    interrupt::set_child_group(None);
+    let status = status?;
+    log::debug!("Pid {} exited with {}", child.id(), status);
+    Ok(status)
+}
+
+pub fn run_command(command: &str, shell: Option<CommandShell>) -> Result<process::ExitStatus> {
+    run_plan(&ExecutionPlan::new(command, shell))
+}
+
+pub fn run_plan(plan: &ExecutionPlan) -> Result<process::ExitStatus> {
+    wait(spawn(&mut plan.to_command())?)
+}
+
+/// Runs the command capturing its output.
+pub fn run_command_output(command: &str, shell: Option<CommandShell>) -> Result<process::Output> {
+    Ok(shell_command(command, shell).stdin(process::Stdio::null()).output()?)
+}
+
+/// Runs the command without input or output, killing it after the timeout. Returns `None` if it timed out.
+pub fn run_command_with_timeout(
+    command: &str,
+    shell: Option<CommandShell>,
+    timeout: Duration
+) -> Result<Option<process::ExitStatus>> {
+    let mut child = shell_command(command, shell)
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .spawn()?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+/// Runs the command like `run_command`, but writes its stdout to stderr, keeping stdout clean for the caller.
+pub fn run_command_to_stderr(command: &str, shell: Option<CommandShell>) -> Result<process::ExitStatus> {
+    run_piped_to_stderr(shell_command(command, shell).stdout(process::Stdio::piped()))
+}
+
+/// Runs the command like `run_command_to_stderr`, in the dir and with the env vars set.
+pub fn run_command_to_stderr_in(
+    command: &str,
+    shell: Option<CommandShell>,
+    dir: &Path,
+    vars: &[(&str, &str)]
+) -> Result<process::ExitStatus> {
+    let mut command = ExecutionPlan::new(command, shell).in_dir(dir).with_vars(vars).to_command();
+    run_piped_to_stderr(command.stdout(process::Stdio::piped()))
+}
+
+fn run_piped_to_stderr(command: &mut process::Command) -> Result<process::ExitStatus> {
+    let mut child = spawn(command)?;
+    let child_stdout = child.stdout.take().expect("stdout is piped");
+    tee(child_stdout, stderr(), |_| {});
+
+    wait(child)
+}
+
+/// Runs the plan like `run_plan`, while also passing each line of its output to `on_line`.
+/// The output is still written to the terminal as soon as it arrives.
+pub fn run_plan_tee(
+    plan: &ExecutionPlan,
+    on_line: &(dyn Fn(OutputStream, &str) + Sync)
+) -> Result<process::ExitStatus> {
+    let mut child = spawn(plan.to_command()
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+    )?;
+    let child_stdout = child.stdout.take().expect("stdout is piped");
+    let child_stderr = child.stderr.take().expect("stderr is piped");
+
+    thread::scope(|scope| {
+        scope.spawn(|| tee(child_stdout, stdout(), |line| on_line(OutputStream::Stdout, line)));
+        scope.spawn(|| tee(child_stderr, stderr(), |line| on_line(OutputStream::Stderr, line)));
+    });
+
+    wait(child)
+}
+
+fn tee(mut reader: impl Read, mut writer: impl Write, on_line: impl Fn(&str)) {
+    let mut buf = [0; 4096];
+    let mut line = vec![];
+
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(len) => len
+        };
+        let _ = writer.write_all(&buf[.. len]);
+        let _ = writer.flush();
+
+        line.extend_from_slice(&buf[.. len]);
+        while let Some(end) = line.iter().position(|byte| *byte == b'\n') {
+            let rest = line.split_off(end + 1);
+            on_line(String::from_utf8_lossy(&line).trim_end());
+            line = rest;
+        }
+    }
+
+    if ! line.is_empty() {
+        on_line(String::from_utf8_lossy(&line).trim_end());
+    }
+}
+
+/// Quotes the text as one argument for the shell commands are run with, unless it's safe as is.
+pub fn quote_arg(text: &str) -> String {
+    if is_safe_arg(text) {
+        text.to_string()
+    }
+    else if cfg!(target_os = "windows") {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    }
+    else {
+        format!("'{}'", text.replace('\'', r"'\''"))
+    }
+}
+
+/// Quotes the text as one argument in a command run with the shell, unless it's safe as is. PowerShell takes it in
+/// single quotes, doubling quotes in it. cmd takes it in double quotes, doubling quotes in it, though it still expands
+/// `%VAR%` in them.
+pub fn quote_arg_for(text: &str, shell: Option<CommandShell>) -> String {
+    match shell {
+        Some(CommandShell::Powershell | CommandShell::Pwsh) if ! is_safe_arg(text) => format!("'{}'", text.replace('\'', "''")),
+        _ => quote_arg(text)
+    }
+}
+
+fn is_safe_arg(text: &str) -> bool {
+    ! text.is_empty() && text.chars().all(|c| c.is_ascii_alphanumeric() || "_-.,:/@=+".contains(c))
+}
+
+/// Joins the args into one command run with the shell, quoting each, for `--escape`.
+pub fn join_args(args: &[String], shell: Option<CommandShell>) -> String {
+    args.iter().map(|arg| quote_arg_for(arg, shell)).collect::<Vec<_>>().join(" ")
+}
+
+/// Finds a likely quoting mistake in a command run with the shell: an unclosed quote, or an escape char escaping
+/// nothing at the end. Only a heuristic, since expansions and nested commands aren't parsed.
+pub fn find_quoting_problem(command: &str, shell: Option<CommandShell>) -> Option<String> {
+    // The escape char, and whether single quotes quote, in sh, PowerShell and cmd
+    let (escape, single_quotes) = match shell {
+        Some(CommandShell::Powershell | CommandShell::Pwsh) => ('`', true),
+        None if cfg!(windows) => ('^', false),
+        None => ('\\', true)
+    };
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some('\'') if c == '\'' => quote = None,
+            Some('\'') => {},
+            // cmd takes `^` literally in double quotes
+            Some('"') if c == escape && escape == '^' => {},
+            // Skips the escaped char
+            _ if c == escape && chars.next().is_none() => return Some(t!(QuotingEndsWithEscape, escape)),
+            Some(_) if c == '"' => quote = None,
+            None if c == '"' || (c == '\'' && single_quotes) => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote.map(|quote| t!(QuotingUnclosed, quote))
+}
+
+/// Returns the program a shell command starts with, skipping leading `VAR=value` assignments.
+pub fn get_program(command: &str) -> Option<&str> {
+    command
+        .split_whitespace()
+        .find(|word| ! word.contains('='))
+        .map(|word| word.trim_matches(|c| c == '"' || c == '\''))
+}
+
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    if program.contains(std::path::MAIN_SEPARATOR) || program.contains('/') {
+        let path = Path::new(program);
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let extensions: Vec<String> = if cfg!(target_os = "windows") {
+        env::var("PATHEXT")
+            .unwrap_or(".EXE;.CMD;.BAT;.COM".to_string())
+            .split(';')
+            .map(String::from)
+            .chain([ "".to_string() ])
+            .collect()
+    } else {
+        vec![ "".to_string() ]
+    };
+
+    env::split_paths(&env::var_os("PATH")?)
+        .flat_map(|dir| extensions
+            .iter()
+            .map(move |ext| dir.join(format!("{}{}", program, ext)))
+        )
+        .find(|path| path.is_file())
+}
+
+/// Returns the program of a shell command if it can't be found on PATH. Builtins are always found, and programs
+/// only known when the command runs, like `$EDITOR` or a subshell, are never missing.
+pub fn find_missing_program(command: &str) -> Option<&str> {
+    get_program(command)
+        .filter(|program| ! SHELL_BUILTINS.contains(program) && ! SHELL_KEYWORDS.contains(program))
+        .filter(|program| ! program.contains(['$', '`', '(']))
+        .filter(|program| find_executable(program).is_none())
+}
+
+/// Returns the program a PowerShell command starts with, or `None` if it starts with an expression like `& $exe`,
+/// `$env:X = 1` or `(Get-Date)`, or with a keyword.
+fn get_powershell_program(command: &str) -> Option<&str> {
+    let word = command.split_whitespace().next()?.trim_end_matches(';');
+    let is_keyword = POWERSHELL_KEYWORDS.iter().any(|keyword| keyword.eq_ignore_ascii_case(word));
+    (! word.starts_with(['&', '.', '$', '(', '[', '{', '@', '"', '\'']) && ! is_keyword).then_some(word)
+}
+
+/// Returns the program of a command if the shell running it can't find it, or the shell itself if it's not on PATH.
+/// sh commands are checked by `find_missing_program`. A PowerShell program not on PATH may still be a cmdlet, an alias
+/// or a function of a module, so it's looked up with `Get-Command` in the shell. The commands of cmd aren't checked,
+/// since its builtins aren't on PATH.
+pub fn find_missing_program_in(command: &str, shell: Option<CommandShell>) -> Option<&str> {
+    let shell_program = match shell {
+        Some(CommandShell::Powershell) => "powershell",
+        Some(CommandShell::Pwsh) => "pwsh",
+        None if cfg!(windows) => return None,
+        None => return find_missing_program(command)
+    };
+    if find_executable(shell_program).is_none() {
+        return Some(shell_program);
+    }
+    let program = get_powershell_program(command)?;
+    if find_executable(program).is_some() {
+        return None;
+    }
+    let lookup = format!("Get-Command -Name {} -ErrorAction Stop | Out-Null", quote_arg_for(program, shell));
+    let found = ExecutionPlan::new(&lookup, shell)
+        .to_command()
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::null())
+        .stderr(process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    (! found).then_some(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_safe_args_unquoted() {
+        for shell in [None, Some(CommandShell::Powershell), Some(CommandShell::Pwsh)] {
+            assert_eq!(quote_arg_for("socks5://127.0.0.1:1080", shell), "socks5://127.0.0.1:1080");
+            assert_eq!(quote_arg_for("KEY=value", shell), "KEY=value");
+        }
+    }
+
+    #[test]
+    fn powershell_doubles_single_quotes() {
+        for shell in [Some(CommandShell::Powershell), Some(CommandShell::Pwsh)] {
+            assert_eq!(quote_arg_for("it's", shell), "'it''s'");
+            assert_eq!(quote_arg_for("a b", shell), "'a b'");
+            assert_eq!(quote_arg_for("", shell), "''");
+            // Nothing but single quotes is special in single-quoted PowerShell strings
+            assert_eq!(quote_arg_for(r#"$HOME "x" `n"#, shell), r#"'$HOME "x" `n'"#);
+        }
+        assert_eq!(join_args(&["echo".into(), "don't".into()], Some(CommandShell::Pwsh)), "echo 'don''t'");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn sh_quotes_in_single_quotes() {
+        assert_eq!(quote_arg("it's"), r"'it'\''s'");
+        assert_eq!(quote_arg(r#"$HOME "x""#), r#"'$HOME "x"'"#);
+        assert_eq!(join_args(&["echo".into(), "a b".into()], None), "echo 'a b'");
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_doubles_double_quotes() {
+        assert_eq!(quote_arg(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(quote_arg("100%"), r#""100%""#);
+    }
+
+    #[test]
+    fn powershell_runs_with_command() {
+        let plan = ExecutionPlan::new("Write-Output 'a b'", Some(CommandShell::Pwsh));
+        assert_eq!(plan.program, "pwsh");
+        assert_eq!(plan.args, ["-NoProfile", "-Command", "Write-Output 'a b'"]);
+        assert!(! plan.raw_args);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_takes_the_command_line_raw() {
+        let command = r#"echo "a & b" 100% ^^"#;
+        let plan = ExecutionPlan::new(command, None);
+        assert_eq!(plan.program, env::var_os("COMSPEC").unwrap_or("cmd".into()));
+        assert_eq!(plan.args, [format!("/S /C \"{}\"", command)]);
+        assert!(plan.raw_args);
+        assert_eq!(plan.describe(), format!("{} /S /C \"{}\"", quote_arg(&plan.program.to_string_lossy()), command));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn cmd_keeps_quotes_in_commands() {
+        let output = run_command_output(r#"echo "a & b""#, None).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), r#""a & b""#);
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn sh_runs_with_c() {
+        let plan = ExecutionPlan::new("echo \"$HOME\"", None);
+        assert_eq!(plan.program, "sh");
+        assert_eq!(plan.args, ["-c", "echo \"$HOME\""]);
+        assert_eq!(plan.describe(), r#"sh -c 'echo "$HOME"'"#);
+    }
+
+    #[test]
+    fn finds_quoting_problems() {
+        assert_eq!(find_quoting_problem("echo 'it''s'", Some(CommandShell::Pwsh)), None);
+        assert!(find_quoting_problem("echo 'unclosed", Some(CommandShell::Powershell)).is_some());
+        assert!(find_quoting_problem("echo trailing`", Some(CommandShell::Pwsh)).is_some());
+    }
+
+    #[test]
+    fn powershell_programs_skip_expressions_and_keywords() {
+        assert_eq!(get_powershell_program("Get-Process -Name code"), Some("Get-Process"));
+        assert_eq!(get_powershell_program("Set-Proxy; Write-Output done"), Some("Set-Proxy"));
+        for command in ["& 'C:\\x.exe'", "$env:X = 1", "(Get-Date).Year", "If ($x) { 1 }", "  "] {
+            assert_eq!(get_powershell_program(command), None, "{}", command);
+        }
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn sh_programs_are_looked_up_on_path() {
+        assert_eq!(find_missing_program_in("surely-missing-program --on", None), Some("surely-missing-program"));
+        assert_eq!(find_missing_program_in("sh -c true", None), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+
+use crate::config::ConfigItem;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum InitShell {
+    Bash,
+    Zsh,
+    Fish
+}
+
+/// The wrapper function defined besides the per-item ones.
+const WRAPPER_NAME: &str = "sw";
+
+/// Turns an item name into a valid function name, by replacing anything but ASCII alphanumerics and `_` with `_`.
+pub fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+fn quote(shell: InitShell, text: &str) -> String {
+    match shell {
+        InitShell::Bash | InitShell::Zsh => format!("'{}'", text.replace('\'', r"'\''")),
+        InitShell::Fish => format!("'{}'", text.replace('\\', r"\\").replace('\'', r"\'"))
+    }
+}
+
+fn define_function(shell: InitShell, function_name: &str, args: &str) -> String {
+    match shell {
+        InitShell::Bash | InitShell::Zsh => format!("{}() {{ switchy {}\"$@\"; }}", function_name, args),
+        InitShell::Fish => format!("function {}; switchy {}$argv; end", function_name, args)
+    }
+}
+
+/// Generates the init snippet, and the collisions of sanitized names as (skipped item name, the name it collides with).
+pub fn generate(shell: InitShell, items: &[ConfigItem]) -> (String, Vec<(String, String)>) {
+    let mut lines = vec![
+        "# Generated by `switchy --shell-init`, regenerate after adding or removing items".to_string(),
+        define_function(shell, WRAPPER_NAME, "")
+    ];
+    let mut defined: HashMap<String, String> = HashMap::new();
+    defined.insert(WRAPPER_NAME.to_string(), WRAPPER_NAME.to_string());
+    let mut collisions = vec![];
+
+    for item in items {
+        let name = item.get_name();
+        let function_name = sanitize_name(name);
+
+        if let Some(existing) = defined.get(&function_name) {
+            collisions.push((name.to_string(), existing.clone()));
+            continue;
+        }
+
+        lines.push(define_function(shell, &function_name, &format!("{} ", quote(shell, name))));
+        defined.insert(function_name, name.to_string());
+    }
+
+    (lines.join("\n") + "\n", collisions)
+}
@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, fs_util, history::History, i18n::t};
+
+/// The version of the snapshot format, bumped on incompatible changes.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// The snapshot of current states in `state.json`, for external tools which shouldn't parse the config.
+/// See the "State snapshot" section of the ReadMe for the format.
+#[derive(Serialize)]
+pub struct Snapshot<'a> {
+    pub version: u32,
+    /// Item name => current state name.
+    pub items: BTreeMap<&'a str, &'a str>
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            items: config.items
+                .iter()
+                .map(|item| (item.get_name(), item.get_current_state()))
+                .collect()
+        }
+    }
+}
+
+/// The snapshot of current states with since when they're current, in `current.json`, sharing the version of
+/// `state.json`. See the "State snapshot" section of the ReadMe for the format.
+#[derive(Serialize, Deserialize)]
+pub struct CurrentSnapshot {
+    pub version: u32,
+    pub items: BTreeMap<String, CurrentState>
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CurrentState {
+    pub state: String,
+    /// When switchy switched the item to the state, or `None` if it's unknown, e.g. for states edited in by hand.
+    pub since: Option<DateTime<Local>>
+}
+
+impl CurrentSnapshot {
+    fn read(path: &Path) -> Option<Self> {
+        let snapshot_str = fs::read_to_string(path.join("current.json")).ok()?;
+        serde_json::from_str::<Self>(&snapshot_str).ok().filter(|snapshot| snapshot.version == SNAPSHOT_VERSION)
+    }
+
+    /// Snapshots the config. An item keeps its `since` if it's still in the state of the previous snapshot, and
+    /// otherwise gets the time of its last switch in the history, if that's to its current state.
+    fn new(path: &Path, config: &Config, previous: Option<Self>) -> Result<Self> {
+        let mut previous_items = previous.map(|previous| previous.items).unwrap_or_default();
+        let mut entries = None;
+        let mut items = BTreeMap::new();
+        for item in &config.items {
+            let (name, state) = (item.get_name(), item.get_current_state());
+            let since = match previous_items.remove(name) {
+                Some(previous) if previous.state == state => previous.since,
+                _ => {
+                    let entries = match &mut entries {
+                        Some(entries) => entries,
+                        None => entries.insert(History::new(path).read()?)
+                    };
+                    entries
+                        .iter()
+                        .rev()
+                        .find(|entry| entry.item == name && ! entry.reapply)
+                        .filter(|entry| entry.to == state)
+                        .map(|entry| entry.time)
+                }
+            };
+            items.insert(name.to_string(), CurrentState { state: state.to_string(), since });
+        }
+        Ok(Self { version: SNAPSHOT_VERSION, items })
+    }
+}
+
+/// Writes the snapshots into `state.json` and `current.json` under the config dir, each via a temporary file so
+/// readers never see partial content.
+pub fn write(path: &Path, config: &Config) -> Result<()> {
+    let snapshot_str = serde_json::to_string_pretty(&Snapshot::new(config))?;
+    fs_util::write_atomic(&path.join("state.json"), &(snapshot_str + "\n"))
+        .context(t!(FailedToWriteSnapshot))?;
+
+    let current = CurrentSnapshot::new(path, config, CurrentSnapshot::read(path))?;
+    let current_str = serde_json::to_string_pretty(&current)?;
+    fs_util::write_atomic(&path.join("current.json"), &(current_str + "\n"))
+        .context(t!(FailedToWriteCurrentSnapshot))?;
+
+    Ok(())
+}
+
+/// Rewrites the snapshots if `current.json` disagrees with the config about the item, e.g. when the config is edited
+/// by hand, or is missing.
+pub fn sync_item(path: &Path, config: &Config, item_name: &str, state: &str) -> Result<()> {
+    let in_sync = CurrentSnapshot::read(path)
+        .is_some_and(|snapshot| snapshot.items.get(item_name).is_some_and(|current| current.state == state));
+    if ! in_sync {
+        write(path, config)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use chrono::{DateTime, Local};
+
+use crate::{config::{ConfigItem, ItemSort}, history::HistoryEntry};
+
+/// Returns the indexes of the items in the sort order. The sort is stable, so items comparing equal keep their order
+/// in the config. `entries` are only needed for `ItemSort::Recent`.
+pub fn get_order(items: &[ConfigItem], sort: ItemSort, entries: &[HistoryEntry]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0 .. items.len()).collect();
+    match sort {
+        ItemSort::Manual => {},
+        // Case-folded byte order, the same in every locale
+        ItemSort::Name => order.sort_by_cached_key(|&index| items[index].get_name().to_lowercase()),
+        ItemSort::Type => order.sort_by_cached_key(|&index| items[index].get_type_string()),
+        ItemSort::Recent => {
+            let mut last_switched: HashMap<&str, DateTime<Local>> = HashMap::new();
+            for entry in entries {
+                last_switched
+                    .entry(&entry.item)
+                    .and_modify(|time| *time = (*time).max(entry.time))
+                    .or_insert(entry.time);
+            }
+            // The most recently switched first, and the never switched last
+            order.sort_by_key(|&index| Reverse(last_switched.get(items[index].get_name()).copied()));
+        }
+    }
+    order
+}
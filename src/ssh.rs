@@ -0,0 +1,69 @@
+use std::io::{stdin, IsTerminal};
+
+use crate::shell::{self, ExecutionPlan};
+
+/// The exit code of ssh failing itself, e.g. to connect or to authenticate. Remote commands exiting with it can't be
+/// told apart.
+pub const SSH_FAILURE_CODE: i32 = 255;
+
+/// Where the commands of a command item with `ssh_host` run.
+pub struct SshTarget<'a> {
+    /// `[user@]host`, without the port.
+    pub host: String,
+    pub port: Option<&'a str>,
+    /// Passed to ssh before anything else, e.g. `["-i", "~/.ssh/box"]`.
+    pub options: &'a [String]
+}
+
+impl<'a> SshTarget<'a> {
+    /// Parses `[user@]host[:port]`. An IPv6 address with a port goes in brackets, like `[::1]:2222`.
+    pub fn new(ssh_host: &'a str, options: &'a [String]) -> Self {
+        let (user, address) = match ssh_host.rsplit_once('@') {
+            Some((user, address)) => (Some(user), address),
+            None => (None, ssh_host)
+        };
+        let (address, port) = match address.strip_prefix('[').and_then(|address| address.split_once(']')) {
+            Some((address, rest)) => (address, rest.strip_prefix(':')),
+            None => match address.split_once(':') {
+                Some((address, port)) if ! port.contains(':') => (address, Some(port)),
+                _ => (address, None)
+            }
+        };
+        Self {
+            host: user.map_or(address.to_string(), |user| format!("{}@{}", user, address)),
+            port: port.filter(|port| ! port.is_empty()),
+            options
+        }
+    }
+
+    /// Returns the args of ssh running the command on the host. In batch mode ssh fails instead of prompting.
+    pub fn get_args(&self, command: &str, batch: bool) -> Vec<String> {
+        let mut args = self.options.to_vec();
+        if batch {
+            args.extend(["-o".to_string(), "BatchMode=yes".to_string()]);
+        }
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        args.extend([self.host.clone(), "--".to_string(), command.to_string()]);
+        args
+    }
+
+    /// Plans running the command with ssh. Without a terminal on stdin ssh runs in batch mode, failing instead of
+    /// waiting for a password no one can type. With one, password prompts work as usual.
+    pub fn plan(&self, command: &str) -> ExecutionPlan {
+        ExecutionPlan {
+            program: "ssh".into(),
+            args: self.get_args(command, ! stdin().is_terminal()),
+            raw_args: false,
+            dir: None,
+            vars: vec![]
+        }
+    }
+
+    /// Describes the ssh invocation running the command, for showing it.
+    pub fn describe(&self, command: &str) -> String {
+        let args: Vec<String> = self.get_args(command, ! stdin().is_terminal()).iter().map(|arg| shell::quote_arg(arg)).collect();
+        format!("ssh {}", args.join(" "))
+    }
+}
@@ -0,0 +1,79 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Serialize, Deserialize};
+
+use crate::{config::ConfigItem, fs_util, i18n::t};
+
+/// The current states of all items at some point, pushed by `--push` and restored by `--pop`.
+#[derive(Serialize, Deserialize)]
+pub struct StackEntry {
+    pub time: DateTime<Local>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Item name => current state.
+    pub states: BTreeMap<String, String>
+}
+
+impl StackEntry {
+    pub fn new(items: &[ConfigItem], label: Option<String>) -> Self {
+        StackEntry {
+            time: Local::now(),
+            label,
+            states: items
+                .iter()
+                .map(|item| (item.get_name().to_string(), item.get_current_state().to_string()))
+                .collect()
+        }
+    }
+}
+
+/// The snapshot stack, stored as JSON lines in `stack.jsonl` under the config dir, the most recent last.
+pub struct Stack {
+    file_path: PathBuf
+}
+
+impl Stack {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            file_path: path.join("stack.jsonl")
+        }
+    }
+
+    /// Reads all entries, oldest first. A missing file has no entries, and malformed lines are skipped.
+    pub fn read(&self) -> Result<Vec<StackEntry>> {
+        if ! self.file_path.exists() {
+            return Ok(vec![]);
+        }
+        let stack_str = fs::read_to_string(&self.file_path).context(t!(FailedToReadStack))?;
+        Ok(stack_str
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    fn write(&self, entries: &[StackEntry]) -> Result<()> {
+        let mut stack_str = String::new();
+        for entry in entries {
+            stack_str.push_str(&serde_json::to_string(entry)?);
+            stack_str.push('\n');
+        }
+        fs_util::write_atomic(&self.file_path, &stack_str).context(t!(FailedToWriteStack))
+    }
+
+    /// Pushes the entry, returning how many entries the stack has now.
+    pub fn push(&self, entry: StackEntry) -> Result<usize> {
+        let mut entries = self.read()?;
+        entries.push(entry);
+        self.write(&entries)?;
+        Ok(entries.len())
+    }
+
+    /// Removes the most recent entry.
+    pub fn drop_last(&self) -> Result<()> {
+        let mut entries = self.read()?;
+        entries.pop();
+        self.write(&entries)
+    }
+}
@@ -0,0 +1,64 @@
+use std::{fmt, time::Duration};
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::{config::ConfigItem, history::HistoryEntry, i18n::t, stats};
+
+/// An item whose current state has been current for longer than the `max_age` of the state.
+pub struct Overdue {
+    pub item: String,
+    pub state: String,
+    pub active_for: TimeDelta,
+    /// The `max_age` as written in the config.
+    pub max_age: String
+}
+
+impl fmt::Display for Overdue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", t!(OverdueState, self.state, stats::format_duration(self.active_for), self.max_age))
+    }
+}
+
+fn parse_max_age(max_age: &str) -> Option<Duration> {
+    humantime::parse_duration(max_age).ok()
+}
+
+/// Finds the states with a `max_age` which isn't a duration, as (item, state, max_age).
+pub fn find_invalid_max_ages(items: &[ConfigItem]) -> Vec<(&str, &str, &str)> {
+    items
+        .iter()
+        .flat_map(|item| item.get_state_names().into_iter().enumerate().filter_map(move |(index, state)| {
+            let max_age = item.get_state_max_age(index)?;
+            parse_max_age(max_age).is_none().then_some((item.get_name(), state, max_age))
+        }))
+        .collect()
+}
+
+/// Returns when the current state of the item became current, i.e. the time of the last switch of the item if it's to
+/// the current state. Reapplying a state doesn't make it current again. Without such a switch it's unknown.
+fn get_active_since(item: &ConfigItem, entries: &[HistoryEntry]) -> Option<DateTime<Local>> {
+    let last_entry = entries
+        .iter()
+        .rev()
+        .find(|entry| entry.item == item.get_name() && ! entry.reapply)?;
+    (last_entry.to == item.get_current_state()).then_some(last_entry.time)
+}
+
+/// Finds the overdue items, in the order of `items`. Items whose current state isn't in the history don't count.
+pub fn find_overdue(items: &[ConfigItem], entries: &[HistoryEntry], now: DateTime<Local>) -> Vec<Overdue> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let current_state = item.get_current_state();
+            let index = item.get_state_names().iter().position(|name| *name == current_state)?;
+            let max_age = item.get_state_max_age(index)?;
+            let active_for = now - get_active_since(item, entries)?;
+            (active_for > TimeDelta::from_std(parse_max_age(max_age)?).ok()?).then(|| Overdue {
+                item: item.get_name().to_string(),
+                state: current_state.to_string(),
+                active_for,
+                max_age: max_age.to_string()
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,222 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local, TimeDelta};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::{history::HistoryEntry, text};
+
+/// How many of the most recent transitions are reported.
+const RECENT_COUNT: usize = 10;
+
+#[derive(Serialize)]
+pub struct ItemStats {
+    pub item: String,
+    pub total: usize,
+    /// State name => how many times the item was switched to it.
+    pub per_state: BTreeMap<String, usize>,
+    pub current: String,
+    /// Since when the item has been in the current state, if the history knows.
+    pub current_since: Option<DateTime<Local>>,
+    /// The most recent transitions, newest first.
+    pub recent: Vec<HistoryEntry>
+}
+
+pub fn compute(entries: &[HistoryEntry], item_name: &str, current_state: &str) -> ItemStats {
+    // Reapplying the current state isn't a switch, so it neither counts nor breaks the streak
+    let item_entries: Vec<&HistoryEntry> = entries
+        .iter()
+        .filter(|entry| entry.item == item_name && ! entry.reapply)
+        .collect();
+
+    let mut per_state = BTreeMap::new();
+    for entry in &item_entries {
+        *per_state.entry(entry.to.clone()).or_insert(0) += 1;
+    }
+
+    // The streak is only known if the last switch led to the current state, which a hand edit may break
+    let current_since = item_entries
+        .last()
+        .filter(|entry| entry.to == current_state)
+        .map(|entry| entry.time);
+
+    ItemStats {
+        item: item_name.to_string(),
+        total: item_entries.len(),
+        per_state,
+        current: current_state.to_string(),
+        current_since,
+        recent: item_entries
+            .iter()
+            .rev()
+            .take(RECENT_COUNT)
+            .map(|entry| (*entry).clone())
+            .collect()
+    }
+}
+
+/// Formats a duration by its largest unit, e.g. "3 days".
+pub fn format_duration(duration: TimeDelta) -> String {
+    let (count, unit) = if duration.num_days() > 0 {
+        (duration.num_days(), "day")
+    }
+    else if duration.num_hours() > 0 {
+        (duration.num_hours(), "hour")
+    }
+    else if duration.num_minutes() > 0 {
+        (duration.num_minutes(), "minute")
+    }
+    else {
+        (duration.num_seconds().max(0), "second")
+    };
+
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+impl ItemStats {
+    pub fn render(&self, now: DateTime<Local>) -> String {
+        let mut lines = vec![format!(
+            "{}: {} switch(es), {}",
+            self.item.cyan(),
+            self.total,
+            match self.current_since {
+                Some(since) => format!(
+                    "in {} for {}",
+                    self.current.yellow(),
+                    format_duration(now - since)
+                ),
+                None => format!("in {}", self.current.yellow())
+            }
+        )];
+
+        let state_width = self.per_state.keys().map(|state| text::get_width(state)).max().unwrap_or(0);
+        for (state, count) in &self.per_state {
+            lines.push(format!(
+                "  {}{}  {:>5}",
+                state.yellow(),
+                text::get_padding(state, state_width),
+                count
+            ));
+        }
+
+        if ! self.recent.is_empty() {
+            lines.push("  Recent:".to_string());
+            for entry in &self.recent {
+                lines.push(format!(
+                    "  {}  {} => {}",
+                    entry.time.format("%Y-%m-%d %H:%M").to_string().dimmed(),
+                    entry.from.yellow(),
+                    entry.to.yellow()
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::history::History;
+
+    fn at(hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 1, hour, 0, 0).unwrap()
+    }
+
+    fn entry(hour: u32, item: &str, from: &str, to: &str) -> HistoryEntry {
+        HistoryEntry {
+            time: at(hour),
+            item: item.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            command_override: None,
+            reapply: false
+        }
+    }
+
+    #[test]
+    fn counts_switches_per_state() {
+        let entries = vec![
+            entry(1, "proxy", "off", "on"),
+            entry(2, "theme", "dark", "light"),
+            entry(3, "proxy", "on", "off"),
+            entry(4, "proxy", "off", "on")
+        ];
+        let stats = compute(&entries, "proxy", "on");
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.per_state, BTreeMap::from([("on".to_string(), 2), ("off".to_string(), 1)]));
+        assert_eq!(stats.current_since, Some(at(4)));
+    }
+
+    #[test]
+    fn reapplying_is_no_switch() {
+        let reapplied = HistoryEntry { reapply: true, ..entry(5, "proxy", "on", "on") };
+        let entries = vec![entry(1, "proxy", "off", "on"), reapplied];
+        let stats = compute(&entries, "proxy", "on");
+        assert_eq!(stats.total, 1);
+        assert_eq!(stats.current_since, Some(at(1)));
+        assert_eq!(stats.recent.len(), 1);
+    }
+
+    #[test]
+    fn streak_is_unknown_after_hand_edits() {
+        let entries = vec![entry(1, "proxy", "off", "on")];
+        assert_eq!(compute(&entries, "proxy", "off").current_since, None);
+        assert_eq!(compute(&[], "proxy", "off").current_since, None);
+        assert_eq!(compute(&[], "proxy", "off").total, 0);
+    }
+
+    #[test]
+    fn keeps_the_ten_most_recent_newest_first() {
+        let entries: Vec<HistoryEntry> = (0 .. 12)
+            .map(|hour| match hour % 2 {
+                0 => entry(hour, "proxy", "off", "on"),
+                _ => entry(hour, "proxy", "on", "off")
+            })
+            .collect();
+        let stats = compute(&entries, "proxy", "off");
+        assert_eq!(stats.total, 12);
+        assert_eq!(stats.recent.len(), RECENT_COUNT);
+        assert_eq!(stats.recent.first().unwrap().time, at(11));
+        assert_eq!(stats.recent.last().unwrap().time, at(2));
+    }
+
+    #[test]
+    fn reads_history_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines = [
+            serde_json::to_string(&entry(1, "proxy", "off", "on")).unwrap(),
+            "not json".to_string(),
+            serde_json::to_string(&entry(2, "proxy", "on", "off")).unwrap()
+        ];
+        fs::write(dir.path().join("history.jsonl"), lines.join("\n")).unwrap();
+        let entries = History::new(dir.path()).read().unwrap();
+        let stats = compute(&entries, "proxy", "off");
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.current_since, Some(at(2)));
+        assert!(History::new(&dir.path().join("missing")).read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn formats_durations_by_largest_unit() {
+        assert_eq!(format_duration(TimeDelta::days(3) + TimeDelta::hours(5)), "3 days");
+        assert_eq!(format_duration(TimeDelta::hours(1)), "1 hour");
+        assert_eq!(format_duration(TimeDelta::minutes(90)), "1 hour");
+        assert_eq!(format_duration(TimeDelta::seconds(59)), "59 seconds");
+        assert_eq!(format_duration(TimeDelta::seconds(-5)), "0 seconds");
+    }
+
+    #[test]
+    fn renders_the_streak() {
+        colored::control::set_override(false);
+        let entries = vec![entry(1, "proxy", "off", "on")];
+        let rendered = compute(&entries, "proxy", "on").render(at(1) + TimeDelta::days(3));
+        assert!(rendered.starts_with("proxy: 1 switch(es), in on for 3 days"), "{}", rendered);
+        assert!(rendered.contains("2026-10-01 01:00  off => on"), "{}", rendered);
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Error, Result};
+use chrono::Local;
+use colored::Colorize;
+
+use crate::{
+    config::{ConfigItem, RunDeclined, SwitchOptions},
+    history::{History, HistoryEntry},
+    i18n::t,
+    interrupt,
+    notify,
+    text
+};
+
+pub struct PlannedSwitch {
+    pub item_index: usize,
+    pub state: String
+}
+
+pub enum SwitchOutcome {
+    Switched,
+    DryRun,
+    Failed(Error),
+    Skipped(String),
+    NotRun,
+    /// The state is recorded without running anything, by `--pop --no-run`.
+    Recorded
+}
+
+impl SwitchOutcome {
+    /// Whether the new state is recorded in the config, by switching or by `--pop --no-run`.
+    pub fn is_recorded(&self) -> bool {
+        matches!(self, SwitchOutcome::Switched | SwitchOutcome::Recorded)
+    }
+
+    pub fn is_failed(&self) -> bool {
+        matches!(self, SwitchOutcome::Failed(_))
+    }
+}
+
+pub type SwitchResult = (PlannedSwitch, SwitchOutcome);
+
+/// Switches the item to the state, and records the switch in the history unless it's a dry run.
+pub fn switch(item: &mut ConfigItem, state: String, options: &SwitchOptions, history: &History) -> Result<()> {
+    let from = item.get_current_state().to_string();
+    let result = item.set_current_state(state.clone(), options);
+
+    if ! options.dry_run && options.resolve(item.get_options()).notify {
+        match &result {
+            Ok(()) => notify::send(&format!("{} → {}", item.get_name(), state)),
+            Err(err) => notify::send(&format!("{} → {} failed: {}", item.get_name(), state, err))
+        }
+    }
+    // An interrupted switch may still record the new state
+    let recorded = result.is_ok() || item.get_current_state() != from;
+
+    if ! options.dry_run && recorded {
+        let entry = HistoryEntry {
+            time: Local::now(),
+            item: item.get_name().to_string(),
+            reapply: from == state,
+            from,
+            to: state,
+            command_override: options.command_override.clone()
+        };
+        if let Err(err) = history.append(&entry) {
+            eprintln!("{} {:#}", "Warning:".yellow(), err);
+        }
+    }
+
+    result
+}
+
+/// Executes the planned switches in order. After a failure the rest are not run if `fail_fast` is set,
+/// and after an interrupt they are never run.
+pub fn execute(
+    items: &mut [ConfigItem],
+    plan: Vec<PlannedSwitch>,
+    options: &SwitchOptions,
+    history: &History,
+    fail_fast: bool
+) -> Vec<SwitchResult> {
+    let mut results = Vec::with_capacity(plan.len());
+    let mut failed = false;
+
+    for planned in plan {
+        let outcome = if (failed && fail_fast) || interrupt::is_interrupted() {
+            SwitchOutcome::NotRun
+        }
+        else {
+            match switch(&mut items[planned.item_index], planned.state.clone(), options, history) {
+                Ok(()) if options.dry_run => SwitchOutcome::DryRun,
+                Ok(()) => SwitchOutcome::Switched,
+                Err(err) if err.is::<RunDeclined>() => SwitchOutcome::Skipped(err.to_string()),
+                Err(err) => {
+                    failed = true;
+                    SwitchOutcome::Failed(err)
+                }
+            }
+        };
+        results.push((planned, outcome));
+    }
+
+    results
+}
+
+pub fn format_plan(items: &[ConfigItem], plan: &[PlannedSwitch]) -> String {
+    plan
+        .iter()
+        .map(|PlannedSwitch { item_index, state }| format!(
+            "  {} => {}",
+            items[*item_index].get_name().cyan(),
+            state.yellow()
+        ))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the results as a table, one line per item with the item and state names aligned.
+pub fn format_summary(items: &[ConfigItem], results: &[SwitchResult]) -> String {
+    let names: Vec<(String, String)> = results
+        .iter()
+        .map(|(PlannedSwitch { item_index, state }, _)| (
+            text::truncate(items[*item_index].get_name(), text::NAME_WIDTH),
+            text::truncate(state, text::NAME_WIDTH)
+        ))
+        .collect();
+    let name_width = names.iter().map(|(name, _)| text::get_width(name)).max().unwrap_or(0);
+    let state_width = names.iter().map(|(_, state)| text::get_width(state)).max().unwrap_or(0);
+
+    results
+        .iter()
+        .zip(&names)
+        .map(|((_, outcome), (name, state))| {
+            let switch = format!(
+                "{}{} => {}{}",
+                name.cyan(),
+                text::get_padding(name, name_width),
+                state.yellow(),
+                text::get_padding(state, state_width)
+            );
+            match outcome {
+                SwitchOutcome::Switched => format!("{} {}  {}", "✓".green(), switch, t!(SummarySwitched)),
+                SwitchOutcome::DryRun => format!("{} {}  {}", "~".blue(), switch, t!(SummaryWouldSwitch)),
+                SwitchOutcome::Failed(err) => format!("{} {}  {}", "✗".red(), switch, err.to_string().red()),
+                SwitchOutcome::Skipped(reason) => format!("{} {}  {}", "-".dimmed(), switch, t!(SummarySkipped, reason)),
+                SwitchOutcome::NotRun => format!("{} {}  {}", "-".dimmed(), switch, t!(SummaryNotRun)),
+                SwitchOutcome::Recorded => format!("{} {}  {}", "✓".green(), switch, t!(SummaryRecorded))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,89 @@
+use std::{io, path::Path, process::{Command, Stdio}};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Local};
+use colored::Colorize;
+
+use crate::{config::ConfigSync, history::HistoryEntry, i18n::t, shell::{self, CommandShell}};
+
+/// Runs git in the dir, returning its stdout. Unless `quiet`, the command is shown and its output goes to stderr instead,
+/// keeping stdout clean.
+fn run_git(dir: &Path, args: &[&str], quiet: bool) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir).args(args).stdin(Stdio::null());
+    let description = format!("git {}", args.join(" "));
+
+    if quiet {
+        let output = command.output().with_context(|| t!(FailedToRunSync, description))?;
+        if ! output.status.success() {
+            bail!(t!(SyncStepFailed, description, String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+    }
+
+    eprintln!("{}", t!(Running, "$".purple().bold(), description.purple()));
+    let status = command
+        .stdout(Stdio::from(io::stderr()))
+        .status()
+        .with_context(|| t!(FailedToRunSync, description))?;
+    if ! status.success() {
+        bail!(t!(SyncStepFailed, description, status));
+    }
+    Ok(String::new())
+}
+
+/// Sums up the switches since the time, keeping the last state of each item, like `proxy => on, theme => dark`.
+pub fn get_summary(entries: &[HistoryEntry], since: Option<DateTime<Local>>) -> String {
+    let mut switched: Vec<(&str, &str)> = vec![];
+    for entry in entries.iter().filter(|entry| since.is_none_or(|since| entry.time > since)) {
+        match switched.iter_mut().find(|(item, _)| *item == entry.item) {
+            Some((_, state)) => *state = &entry.to,
+            None => switched.push((&entry.item, &entry.to))
+        }
+    }
+
+    if switched.is_empty() {
+        return "update config".to_string();
+    }
+    switched
+        .iter()
+        .map(|(item, state)| format!("{} => {}", item, state))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Commits the changes in the config dir and syncs it with its remote, or runs the custom sync command.
+/// The commit message sums up the switches since the last commit.
+pub fn run(dir: &Path, sync: &ConfigSync, shell: Option<CommandShell>, entries: &[HistoryEntry], quiet: bool) -> Result<()> {
+    if run_git(dir, &["rev-parse", "--is-inside-work-tree"], true).is_err() {
+        bail!(t!(NotGitRepo, dir.display()));
+    }
+
+    let last_commit_time = run_git(dir, &["log", "-1", "--format=%cI"], true)
+        .ok()
+        .and_then(|time| DateTime::parse_from_rfc3339(time.trim()).ok())
+        .map(|time| time.with_timezone(&Local));
+    let message = format!("switchy: {}", get_summary(entries, last_commit_time));
+
+    if let Some(command) = &sync.command {
+        if ! quiet {
+            eprintln!("{}", t!(Running, "$".purple().bold(), command.purple()));
+        }
+        let status = shell::run_command_to_stderr_in(command, shell, dir, &[("SWITCHY_SYNC_MESSAGE", &message)])?;
+        if ! status.success() {
+            bail!(t!(SyncStepFailed, command, status));
+        }
+        return Ok(());
+    }
+
+    run_git(dir, &["add", "-A"], quiet)?;
+    if ! run_git(dir, &["status", "--porcelain"], true)?.trim().is_empty() {
+        run_git(dir, &["commit", "-m", &message], quiet)?;
+    }
+    // A repo without remotes is only committed
+    if ! run_git(dir, &["remote"], true)?.trim().is_empty() {
+        run_git(dir, &["pull", "--rebase"], quiet)?;
+        run_git(dir, &["push"], quiet)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Result};
+
+use crate::i18n::t;
+
+/// Substitutes every `{{var}}` placeholder in the template with the value of the var.
+/// Fails listing the placeholders without a var, so a half-rendered file is never written.
+pub fn render(template: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut missing: Vec<&str> = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[.. start]);
+        let after_start = &rest[start + 2 ..];
+        let Some(end) = after_start.find("}}") else {
+            bail!(t!(PlaceholderNotClosed, rest[start ..].lines().next().unwrap_or_default()));
+        };
+
+        let var = after_start[.. end].trim();
+        match vars.get(var) {
+            Some(value) => rendered.push_str(value),
+            None => if ! missing.contains(&var) {
+                missing.push(var);
+            }
+        }
+        rest = &after_start[end + 2 ..];
+    }
+    rendered.push_str(rest);
+
+    if ! missing.is_empty() {
+        bail!(t!(NoValueForPlaceholders, missing.join(", ")));
+    }
+
+    Ok(rendered)
+}
+
+/// Substitutes the `{{var}}` placeholders of only the var, leaving the rest of the text as it is, so commands keep
+/// other braces like the `{{.Name}}` of docker formats.
+pub fn substitute(text: &str, var: &str, value: &str) -> String {
+    let mut substituted = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let after_start = &rest[start + 2 ..];
+        let Some(end) = after_start.find("}}") else {
+            break;
+        };
+        substituted.push_str(&rest[.. start]);
+        if after_start[.. end].trim() == var {
+            substituted.push_str(value);
+        }
+        else {
+            substituted.push_str(&rest[start .. start + 2 + end + 2]);
+        }
+        rest = &after_start[end + 2 ..];
+    }
+    substituted.push_str(rest);
+
+    substituted
+}
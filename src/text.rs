@@ -0,0 +1,195 @@
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The display width budget of item and state names in listings. Longer names are truncated.
+pub const NAME_WIDTH: usize = 32;
+
+/// The width assumed when the output isn't a terminal.
+const FALLBACK_TERMINAL_WIDTH: usize = 80;
+
+/// The least width of a fitted line, so a deep indent on a narrow terminal still leaves some text.
+const MIN_LINE_WIDTH: usize = 20;
+
+/// Returns the number of terminal columns the text takes, counting CJK characters and emoji as 2.
+/// ANSI color codes take none, and so do the parts of an emoji joined to the one before.
+pub fn get_width(text: &str) -> usize {
+    if text.is_ascii() && ! text.contains('\x1b') {
+        return text.width();
+    }
+    split_visible(text).iter().map(|(_, width)| width).sum()
+}
+
+/// Returns the width of the terminal on stdout, or 80 if it's not a terminal.
+pub fn get_terminal_width() -> usize {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+        .unwrap_or(FALLBACK_TERMINAL_WIDTH)
+}
+
+/// Splits the text into its chars with their widths, keeping each ANSI escape sequence whole with no width.
+/// A char after a zero width joiner, or a skin tone modifier, is part of the emoji before it, so it has no width.
+fn split_visible(text: &str) -> Vec<(String, usize)> {
+    let mut pieces: Vec<(String, usize)> = vec![];
+    let mut chars = text.chars().peekable();
+    let mut joined = false;
+    while let Some(char) = chars.next() {
+        if char == '\x1b' && chars.peek() == Some(&'[') {
+            let mut sequence = String::from(char);
+            for char in chars.by_ref() {
+                sequence.push(char);
+                if char.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            pieces.push((sequence, 0));
+        }
+        else {
+            let is_part = joined || ('\u{1F3FB}' ..= '\u{1F3FF}').contains(&char);
+            pieces.push((char.to_string(), if is_part { 0 } else { char.width().unwrap_or(0) }));
+            joined = char == '\u{200D}';
+        }
+    }
+    pieces
+}
+
+/// Truncates the text to at most `max_width` columns, ending it with `…` if anything is cut.
+/// Colors cut off are reset, so they don't leak past the text.
+pub fn truncate(text: &str, max_width: usize) -> String {
+    if get_width(text) <= max_width {
+        return text.to_string();
+    }
+
+    // Leave 1 column for the ellipsis
+    let mut truncated = String::new();
+    let mut width = 0;
+    for (piece, piece_width) in split_visible(text) {
+        if width + piece_width + 1 > max_width {
+            break;
+        }
+        width += piece_width;
+        truncated.push_str(&piece);
+    }
+    truncated.push('…');
+    if text.contains('\x1b') {
+        truncated.push_str("\x1b[0m");
+    }
+    truncated
+}
+
+/// Wraps the text, which starts `indent` columns in, into lines fitting `width` columns.
+/// The lines after the first hang 2 columns further in.
+pub fn wrap(text: &str, indent: usize, width: usize) -> String {
+    let hanging_indent = indent + 2;
+    let mut wrapped = String::new();
+    let mut line_width = 0;
+    let mut max_line_width = width.saturating_sub(indent).max(MIN_LINE_WIDTH);
+    for (piece, piece_width) in split_visible(text) {
+        if line_width + piece_width > max_line_width && line_width > 0 {
+            wrapped.push('\n');
+            wrapped.push_str(&" ".repeat(hanging_indent));
+            line_width = 0;
+            max_line_width = width.saturating_sub(hanging_indent).max(MIN_LINE_WIDTH);
+        }
+        line_width += piece_width;
+        wrapped.push_str(&piece);
+    }
+    wrapped
+}
+
+/// Fits the text, which starts `indent` columns in, into the terminal width, by truncating it, or wrapping it if `full`.
+pub fn fit(text: &str, indent: usize, full: bool) -> String {
+    let width = get_terminal_width();
+    if full {
+        wrap(text, indent, width)
+    }
+    else {
+        truncate(text, width.saturating_sub(indent).max(MIN_LINE_WIDTH))
+    }
+}
+
+/// Returns the spaces padding the text to `width` columns. Colors are applied after measuring, so pad the plain text.
+pub fn get_padding(text: &str, width: usize) -> String {
+    " ".repeat(width.saturating_sub(get_width(text)))
+}
+
+/// The number of char insertions, deletions and substitutions turning one text into the other.
+pub fn get_edit_distance(text: &str, other: &str) -> usize {
+    let other: Vec<char> = other.chars().collect();
+    let mut previous: Vec<usize> = (0 ..= other.len()).collect();
+    for (i, char) in text.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, other_char) in other.iter().enumerate() {
+            let substitution = previous[j] + usize::from(char != *other_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[other.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_double_width_chars() {
+        assert_eq!(get_width("proxy"), 5);
+        assert_eq!(get_width("代理"), 4);
+        assert_eq!(get_width("开 on"), 5);
+        assert_eq!(get_width("🚀"), 2);
+        assert_eq!(get_width("カタカナ"), 8);
+    }
+
+    #[test]
+    fn measures_joined_emoji_as_one() {
+        // A family joined by zero width joiners, and a thumb with a skin tone
+        assert_eq!(get_width("👨\u{200D}👩\u{200D}👧"), 2);
+        assert_eq!(get_width("👍\u{1F3FD}"), 2);
+    }
+
+    #[test]
+    fn ignores_colors_in_width() {
+        assert_eq!(get_width("\x1b[36m代理\x1b[0m"), 4);
+        assert_eq!(get_width("\x1b[1;33mon\x1b[0m"), 2);
+    }
+
+    #[test]
+    fn truncates_by_columns() {
+        assert_eq!(truncate("代理服务器", 10), "代理服务器");
+        assert_eq!(truncate("代理服务器", 9), "代理服务…");
+        // A double width char never straddles the budget
+        assert_eq!(truncate("代理服务器", 6), "代理…");
+        assert_eq!(truncate("a代理", 4), "a代…");
+        assert_eq!(truncate("🚀🚀🚀", 5), "🚀🚀…");
+        assert!(get_width(&truncate("a代理服务器", 6)) <= 6);
+    }
+
+    #[test]
+    fn truncates_colored_text_and_resets() {
+        let truncated = truncate("\x1b[36m代理服务器\x1b[0m", 5);
+        assert_eq!(truncated, "\x1b[36m代理…\x1b[0m");
+        assert_eq!(get_width(&truncated), 5);
+    }
+
+    #[test]
+    fn pads_to_columns() {
+        assert_eq!(get_padding("代理", 6), "  ");
+        assert_eq!(get_padding("proxy", 6), " ");
+        assert_eq!(get_padding("🚀", 3), " ");
+        assert_eq!(get_padding("代理服务器", 6), "");
+        let names = ["代理", "proxy", "🚀🚀x"];
+        let width = names.iter().map(|name| get_width(name)).max().unwrap();
+        for name in names {
+            assert_eq!(get_width(&format!("{}{}|", name, get_padding(name, width))), width + 1);
+        }
+    }
+
+    #[test]
+    fn wraps_with_hanging_indent() {
+        let wrapped = wrap(&"代".repeat(30), 0, 40);
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(get_width(lines[0]), 40);
+        assert_eq!(lines[1], format!("  {}", "代".repeat(10)));
+    }
+}
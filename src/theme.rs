@@ -0,0 +1,130 @@
+use std::{env, io::{stderr, stdout, IsTerminal}, sync::OnceLock};
+
+use clap::ValueEnum;
+use colored::{Color, ColoredString, Colorize};
+use dialoguer::console;
+
+use crate::config::ConfigItem;
+
+const DEFAULT_CURRENT_MARKER: &str = "*";
+
+/// The marker after the default state of an item in listings.
+pub const DEFAULT_STATE_MARKER: &str = "↺";
+
+static CURRENT_MARKER: OnceLock<String> = OnceLock::new();
+
+/// Sets the marker of current states in listings, from `current_marker` in `[theme]`. Only the first call matters.
+pub fn set_current_marker(marker: Option<&str>) {
+    let _ = CURRENT_MARKER.set(marker.unwrap_or(DEFAULT_CURRENT_MARKER).to_string());
+}
+
+pub fn get_current_marker() -> &'static str {
+    CURRENT_MARKER.get().map_or(DEFAULT_CURRENT_MARKER, String::as_str)
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ColorWhen {
+    Auto,
+    Always,
+    Never
+}
+
+/// Whether to color a stream. `--color never` wins over everything, then `--color always`, then `NO_COLOR`, then
+/// `CLICOLOR_FORCE`, then `CLICOLOR=0`, and otherwise the stream is colored if it's a terminal. Env vars set to empty
+/// count as unset.
+pub fn should_color(when: ColorWhen, is_terminal: bool) -> bool {
+    should_color_with(when, is_terminal, |name| env::var(name).ok())
+}
+
+/// `should_color` with the env vars given by `get_env`.
+fn should_color_with(when: ColorWhen, is_terminal: bool, get_env: impl Fn(&str) -> Option<String>) -> bool {
+    let get_var = |name| get_env(name).filter(|value| ! value.is_empty());
+    match when {
+        ColorWhen::Never => false,
+        ColorWhen::Always => true,
+        ColorWhen::Auto if get_var("NO_COLOR").is_some() => false,
+        ColorWhen::Auto if get_var("CLICOLOR_FORCE").is_some_and(|value| value != "0") => true,
+        ColorWhen::Auto if get_var("CLICOLOR").is_some_and(|value| value == "0") => false,
+        ColorWhen::Auto => is_terminal
+    }
+}
+
+/// Sets whether to color, for colored by stdout, and for console, which colors the prompts of dialoguer, by each
+/// stream. Both would otherwise detect it each in their own way.
+pub fn set_colors(when: ColorWhen) {
+    let stdout_colored = should_color(when, stdout().is_terminal());
+    colored::control::set_override(stdout_colored);
+    console::set_colors_enabled(stdout_colored);
+    console::set_colors_enabled_stderr(should_color(when, stderr().is_terminal()));
+}
+
+/// Parses a color name like `red` or `bright blue`, or returns `None` if it's unknown.
+pub fn parse_color(name: &str) -> Option<Color> {
+    name.parse().ok()
+}
+
+/// Finds the states with unknown colors, as (item, state, color).
+pub fn find_unknown_colors(items: &[ConfigItem]) -> Vec<(&str, &str, &str)> {
+    items
+        .iter()
+        .flat_map(|item| item.get_state_names().into_iter().enumerate().filter_map(move |(index, state)| {
+            let color = item.get_state_color(index)?;
+            parse_color(color).is_none().then_some((item.get_name(), state, color))
+        }))
+        .collect()
+}
+
+/// Colors the state name in the color of the state, yellow if none or unknown.
+pub fn color_state(name: &str, color: Option<&str>) -> ColoredString {
+    match color.and_then(parse_color) {
+        Some(color) => name.color(color),
+        None => name.yellow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether a stream is colored with the env vars, for a terminal and for a pipe.
+    fn get_colored(when: ColorWhen, vars: &[(&str, &str)]) -> (bool, bool) {
+        let get_env = |name: &str| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string());
+        (should_color_with(when, true, get_env), should_color_with(when, false, get_env))
+    }
+
+    #[test]
+    fn auto_colors_terminals() {
+        assert_eq!(get_colored(ColorWhen::Auto, &[]), (true, false));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR", "1")]), (true, false));
+    }
+
+    #[test]
+    fn flags_win_over_env_vars() {
+        let all = [("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")];
+        assert_eq!(get_colored(ColorWhen::Always, &all), (true, true));
+        assert_eq!(get_colored(ColorWhen::Always, &[("NO_COLOR", "1")]), (true, true));
+        assert_eq!(get_colored(ColorWhen::Never, &all), (false, false));
+        assert_eq!(get_colored(ColorWhen::Never, &[("CLICOLOR_FORCE", "1")]), (false, false));
+    }
+
+    #[test]
+    fn no_color_wins_over_clicolor_force() {
+        assert_eq!(get_colored(ColorWhen::Auto, &[("NO_COLOR", "1")]), (false, false));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]), (false, false));
+    }
+
+    #[test]
+    fn clicolor_force_wins_over_clicolor() {
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR_FORCE", "1")]), (true, true));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR_FORCE", "1"), ("CLICOLOR", "0")]), (true, true));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR_FORCE", "0")]), (true, false));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR", "0")]), (false, false));
+    }
+
+    #[test]
+    fn empty_vars_count_as_unset() {
+        assert_eq!(get_colored(ColorWhen::Auto, &[("NO_COLOR", "")]), (true, false));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR_FORCE", "")]), (true, false));
+        assert_eq!(get_colored(ColorWhen::Auto, &[("CLICOLOR", ""), ("CLICOLOR_FORCE", "")]), (true, false));
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, TimeDelta};
+use serde::{Serialize, Deserialize};
+
+use crate::{config::{self, ConfigItem}, fs_util, i18n::t};
+
+/// How many days removed items stay in the trash without `trash_days` in `[defaults]`.
+pub const DEFAULT_TRASH_DAYS: u32 = 30;
+
+/// An item removed by `--remove` or `--prune`, kept until it's restored by `--restore-item` or purged.
+#[derive(Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub removed: DateTime<Local>,
+    pub item: ConfigItem
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct TrashFile {
+    #[serde(default)]
+    items: Vec<TrashEntry>
+}
+
+/// The removed items, stored in `trash.toml` under the config dir, the most recently removed last.
+pub struct Trash {
+    file_path: PathBuf
+}
+
+impl Trash {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            file_path: path.join("trash.toml")
+        }
+    }
+
+    /// Reads all entries. A missing file has none.
+    pub fn read(&self) -> Result<Vec<TrashEntry>> {
+        if ! self.file_path.exists() {
+            return Ok(vec![]);
+        }
+        let trash_str = fs::read_to_string(&self.file_path).context(t!(FailedToReadTrash))?;
+        let trash = toml::from_str::<TrashFile>(&trash_str).context(t!(FailedToReadTrash))?;
+        Ok(trash.items)
+    }
+
+    fn write(&self, items: Vec<TrashEntry>) -> Result<()> {
+        let trash_str = toml::to_string(&TrashFile { items })?;
+        fs_util::write_atomic(&self.file_path, &trash_str).context(t!(FailedToWriteTrash))
+    }
+
+    /// Moves the item into the trash.
+    pub fn add(&self, item: ConfigItem) -> Result<()> {
+        let mut entries = self.read()?;
+        entries.push(TrashEntry { removed: Local::now(), item });
+        self.write(entries)
+    }
+
+    /// Takes the most recently removed item of the name out of the entries, returning it with the rest to write back
+    /// by `finish_take` once it's restored.
+    pub fn take(&self, name: &str) -> Result<Option<(ConfigItem, Vec<TrashEntry>)>> {
+        let mut entries = self.read()?;
+        let Some(position) = entries.iter().rposition(|entry| config::is_same_name(entry.item.get_name(), name)) else {
+            return Ok(None);
+        };
+        let entry = entries.remove(position);
+        Ok(Some((entry.item, entries)))
+    }
+
+    /// Writes back the entries left by `take`.
+    pub fn finish_take(&self, entries: Vec<TrashEntry>) -> Result<()> {
+        self.write(entries)
+    }
+
+    /// Purges the entries removed more than `days` days before `now`, returning how many are purged.
+    /// Nothing is written if none are.
+    pub fn purge(&self, days: u32, now: DateTime<Local>) -> Result<usize> {
+        let entries = self.read()?;
+        let count = entries.len();
+        let kept: Vec<TrashEntry> = entries
+            .into_iter()
+            .filter(|entry| now - entry.removed <= TimeDelta::days(days.into()))
+            .collect();
+        let purged = count - kept.len();
+        if purged > 0 {
+            self.write(kept)?;
+        }
+        Ok(purged)
+    }
+}
@@ -0,0 +1,431 @@
+use std::{collections::HashMap, io::{self, IsTerminal}, time::Duration};
+
+use anyhow::{bail, Result};
+use ratatui::{
+    crossterm::{cursor, event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers}, execute, terminal},
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Style, Stylize},
+    text::{Line, Span, Text},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame
+};
+
+use crate::{
+    config::{ConfigItem, ConfigManager, MatchMode, RunDeclined, SwitchOptions},
+    history::{History, HistoryEntry},
+    i18n::t, interrupt, picker, switch, theme
+};
+
+/// How often the dashboard wakes up without input, to notice a termination signal.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(PartialEq)]
+enum Pane {
+    Items,
+    States
+}
+
+enum Status {
+    Done(String),
+    Declined(String),
+    Failed(String)
+}
+
+/// The full-screen dashboard of `--tui`, showing the items and the states of the selected one. It stays open across
+/// switches, which go through `switch::switch` like on the command line.
+struct Dashboard<'a, 'p> {
+    cm: &'a mut ConfigManager<'p>,
+    options: &'a SwitchOptions,
+    history: &'a History,
+    /// Runs after each successful switch, i.e. the `on_change` hook.
+    after_switch: &'a dyn Fn(&ConfigManager),
+    order: Vec<usize>,
+    /// The indices of the items shown, in `order` and matching `filter`.
+    shown: Vec<usize>,
+    filter: String,
+    filtering: bool,
+    focus: Pane,
+    showing_history: bool,
+    item_list: ListState,
+    state_list: ListState,
+    /// The state details by item index, since rendering those of plugin items runs the plugin.
+    details: HashMap<usize, Vec<Vec<String>>>,
+    entries: Vec<HistoryEntry>,
+    status: Option<Status>
+}
+
+/// Runs the dashboard until it's quit, switching items in `order`. The config is written after each switch.
+pub fn run(
+    cm: &mut ConfigManager,
+    options: &SwitchOptions,
+    history: &History,
+    order: Vec<usize>,
+    after_switch: &dyn Fn(&ConfigManager)
+) -> Result<()> {
+    if ! io::stdout().is_terminal() {
+        bail!(t!(TuiNeedsTerminal));
+    }
+
+    let mut dashboard = Dashboard {
+        entries: history.read().unwrap_or_default(),
+        cm,
+        options,
+        history,
+        after_switch,
+        shown: order.clone(),
+        order,
+        filter: String::new(),
+        filtering: false,
+        focus: Pane::Items,
+        showing_history: false,
+        item_list: ListState::default(),
+        state_list: ListState::default(),
+        details: HashMap::new(),
+        status: None
+    };
+    dashboard.select_item(0);
+
+    // Also restores the terminal on panics, before the crash message
+    let mut terminal = ratatui::init();
+    let result = dashboard.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+impl Dashboard<'_, '_> {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            // Resizes are picked up by the next draw
+            terminal.draw(|frame| self.draw(frame))?;
+            if interrupt::is_interrupted() {
+                return Ok(());
+            }
+            if ! event::poll(POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            // Windows also reports releases
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if ! self.handle_key(terminal, key)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Handles a key press, returning whether to keep running.
+    fn handle_key(&mut self, terminal: &mut DefaultTerminal, key: KeyEvent) -> Result<bool> {
+        // Raw mode turns Ctrl-C into a key instead of a signal
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(false);
+        }
+
+        if self.filtering {
+            match key.code {
+                KeyCode::Enter => self.filtering = false,
+                KeyCode::Esc => {
+                    self.filtering = false;
+                    self.filter.clear();
+                    self.apply_filter();
+                },
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.apply_filter();
+                },
+                KeyCode::Char(char) => {
+                    self.filter.push(char);
+                    self.apply_filter();
+                },
+                _ => {}
+            }
+            return Ok(true);
+        }
+
+        match key.code {
+            KeyCode::Esc if self.focus == Pane::States => self.focus = Pane::Items,
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.focus = if self.focus == Pane::Items && ! self.showing_history { Pane::States } else { Pane::Items };
+            },
+            KeyCode::Enter if self.focus == Pane::Items && ! self.showing_history => self.focus = Pane::States,
+            KeyCode::Enter if self.focus == Pane::States => {
+                if let (Some(item_index), Some(state_index)) = (self.get_selected_item(), self.state_list.selected()) {
+                    self.switch(terminal, item_index, Some(state_index))?;
+                }
+            },
+            KeyCode::Char('t') => {
+                if let Some(item_index) = self.get_selected_item() {
+                    self.switch(terminal, item_index, None)?;
+                }
+            },
+            KeyCode::Char('/') => {
+                self.filtering = true;
+                self.focus = Pane::Items;
+            },
+            KeyCode::Char('h') => {
+                self.showing_history = ! self.showing_history;
+                self.focus = Pane::Items;
+            },
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    fn get_selected_item(&self) -> Option<usize> {
+        self.item_list.selected().and_then(|position| self.shown.get(position).copied())
+    }
+
+    /// Selects the item at the position in the shown ones, and its current state.
+    fn select_item(&mut self, position: usize) {
+        if self.shown.is_empty() {
+            self.item_list.select(None);
+            self.state_list.select(None);
+            return;
+        }
+        self.item_list.select(Some(position.min(self.shown.len() - 1)));
+        let item = &self.cm.config.items[self.get_selected_item().unwrap()];
+        let current_state = item.get_current_state();
+        self.state_list.select(Some(item.get_state_names().iter().position(|name| *name == current_state).unwrap_or(0)));
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        match self.focus {
+            Pane::Items => {
+                let Some(position) = self.item_list.selected() else {
+                    return;
+                };
+                self.select_item(position.saturating_add_signed(offset));
+            },
+            Pane::States => {
+                let Some(item_index) = self.get_selected_item() else {
+                    return;
+                };
+                let state_count = self.cm.config.items[item_index].get_state_names().len();
+                if let Some(position) = self.state_list.selected() {
+                    self.state_list.select(Some(position.saturating_add_signed(offset).min(state_count.saturating_sub(1))));
+                }
+            }
+        }
+    }
+
+    fn apply_filter(&mut self) {
+        let names: Vec<&str> = self.order.iter().map(|&index| self.cm.config.items[index].get_name()).collect();
+        let matched = match self.cm.config.defaults.match_mode {
+            // Like the fuzzy item picker, the typed chars match in order, but the order of items is kept
+            MatchMode::Fuzzy => {
+                let filter = self.filter.to_lowercase();
+                (0 .. names.len())
+                    .filter(|&index| {
+                        let mut name_chars = names[index].chars().flat_map(char::to_lowercase);
+                        filter.chars().all(|char| name_chars.any(|name_char| name_char == char))
+                    })
+                    .collect()
+            },
+            match_mode => picker::filter(&names, &self.filter, match_mode)
+        };
+        self.shown = matched.into_iter().map(|index| self.order[index]).collect();
+        self.select_item(0);
+    }
+
+    /// Switches the item to the state at the index, or to its next state. The dashboard steps aside meanwhile, so
+    /// commands, prompts and the hook have the terminal. Returns an error only if the terminal can't be restored.
+    fn switch(&mut self, terminal: &mut DefaultTerminal, item_index: usize, state_index: Option<usize>) -> Result<()> {
+        ratatui::restore();
+        execute!(io::stdout(), cursor::Show)?;
+
+        let result = self.switch_item(item_index, state_index);
+        self.status = Some(match result {
+            Ok(message) => Status::Done(message),
+            Err(err) if err.is::<RunDeclined>() => Status::Declined(err.to_string()),
+            Err(err) => Status::Failed(format!("{:#}", err))
+        });
+        self.entries = self.history.read().unwrap_or_default();
+        let position = self.item_list.selected().unwrap_or(0);
+        self.select_item(position);
+
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+        // Resizing redraws everything, also picking up a resize meanwhile, without querying the cursor like clearing
+        let size = terminal.size()?;
+        terminal.resize(Rect::new(0, 0, size.width, size.height))?;
+        Ok(())
+    }
+
+    fn switch_item(&mut self, item_index: usize, state_index: Option<usize>) -> Result<String> {
+        let item = &self.cm.config.items[item_index];
+        let state = match state_index {
+            Some(index) => {
+                let state = item.get_state_names()[index].to_string();
+                if let (false, Some(reason)) = (self.options.force, item.get_state_unavailable_reason(index, self.options)) {
+                    bail!(t!(StateUnavailableNow, state, item.get_name(), reason));
+                }
+                // States are switched to by name, so a duplicate one can't be told apart
+                item.check_unique_state(&state)?;
+                state
+            },
+            None => match item.get_next_state(self.options) {
+                Some(state) => state.to_string(),
+                None => bail!(t!(NoAvailableState, item.get_name()))
+            }
+        };
+
+        let item = &mut self.cm.config.items[item_index];
+        let item_name = item.get_name().to_string();
+        let result = switch::switch(item, state.clone(), self.options, self.history);
+        if ! self.options.dry_run && (result.is_ok() || interrupt::is_interrupted()) {
+            // An interrupted switch may still record the new state
+            self.cm.write_switched()?;
+        }
+        result?;
+        if self.options.dry_run {
+            return Ok(t!(WouldSwitch, item_name, state));
+        }
+        (self.after_switch)(self.cm);
+        Ok(t!(TuiSwitched, item_name, state))
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [body, status_line] = Layout::vertical([Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [items_pane, right_pane] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(body);
+
+        self.draw_items(frame, items_pane);
+        match self.get_selected_item() {
+            Some(item_index) if self.showing_history => self.draw_history(frame, right_pane, item_index),
+            Some(item_index) => self.draw_states(frame, right_pane, item_index),
+            None => frame.render_widget(Block::bordered(), right_pane)
+        }
+        self.draw_status(frame, status_line);
+    }
+
+    fn get_block(&self, title: String, focused: bool) -> Block<'static> {
+        let block = Block::bordered().title(format!(" {} ", title));
+        if focused {
+            block.border_style(Style::new().cyan())
+        }
+        else {
+            block
+        }
+    }
+
+    fn draw_items(&mut self, frame: &mut Frame, area: Rect) {
+        let entries: Vec<ListItem> = self.shown
+            .iter()
+            .map(|&index| {
+                let item = &self.cm.config.items[index];
+                ListItem::new(Line::from(vec![
+                    Span::styled(item.get_name().to_string(), Style::new().cyan()),
+                    Span::raw("  "),
+                    get_current_state_span(item)
+                ]))
+            })
+            .collect();
+        let title = if self.filter.is_empty() { t!(TuiItems) } else { format!("{} /{}", t!(TuiItems), self.filter) };
+        let list = List::new(entries)
+            .block(self.get_block(title, self.focus == Pane::Items))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, area, &mut self.item_list);
+    }
+
+    fn draw_states(&mut self, frame: &mut Frame, area: Rect, item_index: usize) {
+        let item = &self.cm.config.items[item_index];
+        let details = self.details.entry(item_index).or_insert_with(|| {
+            (0 .. item.get_state_names().len())
+                .map(|index| item.get_state_details(index, true)
+                    .iter()
+                    .map(|detail| console::strip_ansi_codes(detail).to_string())
+                    .collect())
+                .collect()
+        });
+
+        let current_state = item.get_current_state();
+        let marker = theme::get_current_marker();
+        let blank_marker = " ".repeat(unicode_width::UnicodeWidthStr::width(marker));
+        let entries: Vec<ListItem> = item.get_state_names()
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let mut lines = vec![Line::from(vec![
+                    if *name == current_state { Span::styled(marker, Style::new().green()) } else { Span::raw(blank_marker.clone()) },
+                    Span::raw(" "),
+                    Span::styled(name.to_string(), get_state_style(item.get_state_color(index)))
+                ])];
+                lines.extend(details[index].iter().map(|detail| Line::from(format!("    {}", detail)).dim()));
+                ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let list = List::new(entries)
+            .block(self.get_block(t!(TuiStatesOf, item.get_name()), self.focus == Pane::States))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, area, &mut self.state_list);
+    }
+
+    fn draw_history(&self, frame: &mut Frame, area: Rect, item_index: usize) {
+        let item = &self.cm.config.items[item_index];
+        let lines: Vec<Line> = self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.item == item.get_name())
+            .map(|entry| Line::from(vec![
+                Span::raw(entry.time.format("%Y-%m-%d %H:%M:%S").to_string()).dim(),
+                Span::raw("  "),
+                Span::raw(format!("{} → {}", entry.from, entry.to))
+            ]))
+            .collect();
+        let text = if lines.is_empty() { Text::from(Line::from(t!(TuiNoHistory)).dim()) } else { Text::from(lines) };
+        let paragraph = Paragraph::new(text).block(self.get_block(t!(TuiHistoryOf, item.get_name()), false));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_status(&self, frame: &mut Frame, area: Rect) {
+        let line = if self.filtering {
+            Line::from(format!("/{}▏", self.filter))
+        }
+        else {
+            match &self.status {
+                Some(Status::Done(message)) => Line::from(message.as_str()).green(),
+                Some(Status::Declined(message)) => Line::from(message.as_str()).yellow(),
+                Some(Status::Failed(message)) => Line::from(message.as_str()).red(),
+                None => Line::from(t!(TuiKeys)).dim()
+            }
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+fn get_current_state_span(item: &ConfigItem) -> Span<'static> {
+    let current_state = item.get_current_state();
+    let color = item.get_state_names()
+        .iter()
+        .position(|name| *name == current_state)
+        .and_then(|index| item.get_state_color(index));
+    Span::styled(current_state.to_string(), get_state_style(color))
+}
+
+/// The style of a state name in the color of the state, yellow if none or unknown, like in listings.
+fn get_state_style(color: Option<&str>) -> Style {
+    let color = match color.and_then(theme::parse_color) {
+        Some(colored::Color::Black) => Color::Black,
+        Some(colored::Color::Red) => Color::Red,
+        Some(colored::Color::Green) => Color::Green,
+        Some(colored::Color::Blue) => Color::Blue,
+        Some(colored::Color::Magenta) => Color::Magenta,
+        Some(colored::Color::Cyan) => Color::Cyan,
+        Some(colored::Color::White) => Color::Gray,
+        Some(colored::Color::BrightBlack) => Color::DarkGray,
+        Some(colored::Color::BrightRed) => Color::LightRed,
+        Some(colored::Color::BrightGreen) => Color::LightGreen,
+        Some(colored::Color::BrightYellow) => Color::LightYellow,
+        Some(colored::Color::BrightBlue) => Color::LightBlue,
+        Some(colored::Color::BrightMagenta) => Color::LightMagenta,
+        Some(colored::Color::BrightCyan) => Color::LightCyan,
+        Some(colored::Color::BrightWhite) => Color::White,
+        Some(colored::Color::TrueColor { r, g, b }) => Color::Rgb(r, g, b),
+        Some(colored::Color::Yellow) | None => Color::Yellow
+    };
+    Style::new().fg(color)
+}
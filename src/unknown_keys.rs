@@ -0,0 +1,108 @@
+use std::fmt::Write;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+#[derive(Clone)]
+enum PathSegment {
+    Key(String),
+    Index(usize)
+}
+
+fn format_path(path: &[PathSegment]) -> String {
+    let mut formatted = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) if formatted.is_empty() => formatted.push_str(key),
+            PathSegment::Key(key) => write!(formatted, ".{}", key).unwrap(),
+            PathSegment::Index(index) => write!(formatted, "[{}]", index).unwrap()
+        }
+    }
+    formatted
+}
+
+/// Collects the paths of the keys in `original` which are missing in `round_trip`.
+fn find_missing(
+    original: &toml::Value,
+    round_trip: Option<&toml::Value>,
+    path: &mut Vec<PathSegment>,
+    missing: &mut Vec<Vec<PathSegment>>
+) {
+    match original {
+        toml::Value::Table(table) => for (key, value) in table {
+            path.push(PathSegment::Key(key.clone()));
+            match round_trip.and_then(|round_trip| round_trip.get(key)) {
+                Some(round_trip_value) => find_missing(value, Some(round_trip_value), path, missing),
+                // Keys under a table dropped whole, e.g. `[defaults]` with only default values, are checked too
+                None => {
+                    missing.push(path.clone());
+                    find_missing(value, None, path, missing);
+                }
+            }
+            path.pop();
+        },
+        toml::Value::Array(array) => for (index, value) in array.iter().enumerate() {
+            path.push(PathSegment::Index(index));
+            find_missing(value, round_trip.and_then(|round_trip| round_trip.get(index)), path, missing);
+            path.pop();
+        },
+        _ => {}
+    }
+}
+
+/// Replaces the value at the path, if the path exists.
+fn set_at_path(value: &mut toml::Value, path: &[PathSegment], new_value: toml::Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut current = value;
+    for segment in parents {
+        let next = match segment {
+            PathSegment::Key(key) => current.get_mut(key.as_str()),
+            PathSegment::Index(index) => current.get_mut(*index)
+        };
+        let Some(next) = next else {
+            return;
+        };
+        current = next;
+    }
+    if let (PathSegment::Key(key), Some(table)) = (last, current.as_table_mut()) {
+        table.insert(key.clone(), new_value);
+    }
+}
+
+/// Finds the keys of the TOML value which deserializing into `T` ignores, so writing `T` back would drop them.
+/// A key skipped when serializing isn't necessarily unknown, e.g. `log = false` equals the default, so each missing key
+/// is set to a value no field accepts: an unknown key still deserializes to the same, a known one doesn't.
+pub fn find<T: Serialize + DeserializeOwned>(original: &toml::Value) -> Vec<String> {
+    let Ok(parsed) = original.clone().try_into::<T>() else {
+        return vec![];
+    };
+    let (Ok(round_trip), Ok(round_trip_str)) = (toml::Value::try_from(&parsed), toml::to_string(&parsed)) else {
+        return vec![];
+    };
+
+    let mut missing: Vec<Vec<PathSegment>> = vec![];
+    find_missing(original, Some(&round_trip), &mut vec![], &mut missing);
+
+    let sentinel = toml::Value::Array(vec![toml::Value::Boolean(true)]);
+    missing
+        .into_iter()
+        .filter(|path| {
+            let mut perturbed = original.clone();
+            set_at_path(&mut perturbed, path, sentinel.clone());
+            perturbed
+                .try_into::<T>()
+                .is_ok_and(|perturbed| toml::to_string(&perturbed).is_ok_and(|perturbed_str| perturbed_str == round_trip_str))
+        })
+        .map(|path| format_path(&path))
+        .fold(vec![], |mut unknown: Vec<String>, path| {
+            // Only report the outermost unknown key
+            let is_nested = unknown.last().is_some_and(|parent| {
+                path.strip_prefix(parent.as_str()).is_some_and(|rest| rest.starts_with('.') || rest.starts_with('['))
+            });
+            if ! is_nested {
+                unknown.push(path);
+            }
+            unknown
+        })
+}
@@ -0,0 +1,168 @@
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
+#[cfg(feature = "watch")]
+use std::{collections::BTreeSet, sync::mpsc, time::Duration};
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use colored::Colorize;
+#[cfg(feature = "watch")]
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode};
+
+use crate::{config::{ConfigItem, SwitchOptions}, fs_util, history::History, i18n::t, switch};
+#[cfg(feature = "watch")]
+use crate::interrupt;
+
+/// How long changes of a watched file settle before it's checked, since tools often write a file in several steps.
+#[cfg(feature = "watch")]
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How often waiting for changes wakes up, to notice a Ctrl-C.
+#[cfg(feature = "watch")]
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An item with `watch`, and the file it watches.
+pub struct WatchedItem {
+    pub item_index: usize,
+    /// With its dir canonicalized if it exists, so it's comparable to the paths of file events.
+    pub path: PathBuf
+}
+
+/// Finds the items with `watch`.
+pub fn find_watched(items: &[ConfigItem]) -> Vec<WatchedItem> {
+    items
+        .iter()
+        .enumerate()
+        .filter_map(|(item_index, item)| {
+            let path = fs_util::expand_home(item.get_options().watch.as_deref()?);
+            let path = match (path.parent().and_then(|dir| dir.canonicalize().ok()), path.file_name()) {
+                (Some(dir), Some(file_name)) => dir.join(file_name),
+                _ => path
+            };
+            Some(WatchedItem { item_index, path })
+        })
+        .collect()
+}
+
+/// The fingerprints of the watched files as switchy last left them, stored in `watch.json` under the config dir, so
+/// `--watch --once` notices changes made between runs.
+pub struct Fingerprints {
+    file_path: PathBuf,
+    /// Item name => fingerprint, `None` for a missing file.
+    entries: BTreeMap<String, Option<String>>
+}
+
+impl Fingerprints {
+    /// Reads the fingerprints. A missing or malformed file has none, so every watched item is checked as changed.
+    pub fn read(path: &Path) -> Self {
+        let file_path = path.join("watch.json");
+        let entries = fs::read_to_string(&file_path)
+            .ok()
+            .and_then(|fingerprints_str| serde_json::from_str(&fingerprints_str).ok())
+            .unwrap_or_default();
+        Self { file_path, entries }
+    }
+
+    fn write(&self) -> Result<()> {
+        let fingerprints_str = serde_json::to_string_pretty(&self.entries)?;
+        fs_util::write_atomic(&self.file_path, &(fingerprints_str + "\n")).context(t!(FailedToWriteFingerprints))
+    }
+}
+
+/// Fingerprints the content of the file, or returns `None` if it's missing.
+fn get_fingerprint(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|content| fs_util::fingerprint(&content))
+}
+
+/// Reapplies the current state of each of the watched items whose file changed since switchy last left it, and
+/// records the fingerprints afterwards. Returns how many reapplies failed, which are retried on the next check.
+pub fn check(
+    items: &mut [ConfigItem],
+    watched: &[&WatchedItem],
+    fingerprints: &mut Fingerprints,
+    options: &SwitchOptions,
+    history: &History
+) -> Result<usize> {
+    let mut failed = 0;
+    let mut changed = false;
+    for watched_item in watched {
+        let item = &mut items[watched_item.item_index];
+        let fingerprint = get_fingerprint(&watched_item.path);
+        if fingerprints.entries.get(item.get_name()) == Some(&fingerprint) {
+            continue;
+        }
+
+        let state = item.get_current_state().to_string();
+        eprintln!(
+            "{} {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S").to_string().dimmed(),
+            t!(WatchedFileChanged, watched_item.path.display(), item.get_name().cyan(), state.yellow())
+        );
+        match switch::switch(item, state, options, history) {
+            Ok(()) => {
+                // Reapplying may well write the file itself
+                fingerprints.entries.insert(item.get_name().to_string(), get_fingerprint(&watched_item.path));
+                changed = true;
+            },
+            Err(err) => {
+                eprintln!("{} {}", t!(Warning).yellow(), t!(ReapplyFailed, item.get_name().cyan(), format!("{:#}", err)));
+                failed += 1;
+            }
+        }
+    }
+    if changed && ! options.dry_run {
+        fingerprints.write()?;
+    }
+    Ok(failed)
+}
+
+/// Checks the watched items, then checks each again whenever its file changes, until interrupted.
+/// The dirs of the files are watched, so files replaced by renaming are still noticed.
+#[cfg(feature = "watch")]
+pub fn run(
+    items: &mut [ConfigItem],
+    watched: &[WatchedItem],
+    fingerprints: &mut Fingerprints,
+    options: &SwitchOptions,
+    history: &History
+) -> Result<()> {
+    check(items, &watched.iter().collect::<Vec<_>>(), fingerprints, options, history)?;
+
+    let (sender, receiver) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE, sender).context(t!(FailedToWatch))?;
+    let dirs: BTreeSet<&Path> = watched.iter().filter_map(|watched_item| watched_item.path.parent()).collect();
+    for dir in dirs {
+        debouncer.watcher()
+            .watch(dir, RecursiveMode::NonRecursive)
+            .with_context(|| t!(FailedToWatchDir, dir.display()))?;
+    }
+    eprintln!("{}", t!(Watching, watched.len()).dimmed());
+
+    while ! interrupt::is_interrupted() {
+        let events = match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(events)) => events,
+            Ok(Err(err)) => {
+                eprintln!("{} {}", t!(Warning).yellow(), t!(WatchError, err));
+                continue;
+            },
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break
+        };
+        let changed: Vec<&WatchedItem> = watched
+            .iter()
+            .filter(|watched_item| events.iter().any(|event| event.path == watched_item.path))
+            .collect();
+        check(items, &changed, fingerprints, options, history)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "watch"))]
+pub fn run(
+    _items: &mut [ConfigItem],
+    _watched: &[WatchedItem],
+    _fingerprints: &mut Fingerprints,
+    _options: &SwitchOptions,
+    _history: &History
+) -> Result<()> {
+    anyhow::bail!(t!(WatchUnsupported));
+}
@@ -0,0 +1,649 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use colored::Colorize;
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, Select};
+
+use crate::{
+    config::{
+        self, ConfigBlockItem, ConfigBlockItemState, ConfigCommandItem, ConfigCommandItemState, ConfigDirItem, ConfigDirItemState,
+        ConfigGitConfigItem, ConfigGitConfigItemState, ConfigItem,
+        ConfigKeyValueItem, ConfigKeyValueItemState, ConfigTemplateItem, ConfigTemplateItemState, ItemOptions,
+        StateCommand
+    },
+    editor,
+    fs_util,
+    git_config,
+    i18n::t,
+    key_value::KeyValueFormat,
+    recipe::{self, Recipe, RecipeParam},
+    shell::{self, CommandShell, ExecutionPlan}
+};
+
+/// Walks through adding an item of any type. Returns `None` if the user discards the item.
+/// `shell` is the default shell, for testing commands.
+pub fn add_item(theme: &ColorfulTheme, name: String, shell: Option<CommandShell>) -> Result<Option<ConfigItem>> {
+    let item_type = Select::with_theme(theme)
+        .with_prompt(t!(WizardItemType))
+        .default(0)
+        .items(&[
+            t!(ItemTypeCommand),
+            t!(ItemTypeKeyValue),
+            t!(ItemTypeBlock),
+            t!(ItemTypeGitConfig),
+            t!(ItemTypeDir),
+            t!(ItemTypeTemplate)
+        ])
+        .interact()?;
+
+    match item_type {
+        0 => add_command_item(theme, name, shell),
+        1 => add_key_value_item(theme, name),
+        2 => add_block_item(theme, name),
+        3 => add_git_config_item(theme, name),
+        4 => add_dir_item(theme, name),
+        5 => add_template_item(theme, name),
+        _ => unreachable!()
+    }
+}
+
+/// Walks through generating an item from the recipe, prompting only for its params.
+/// Returns `None` if the user discards the item.
+pub fn add_item_from_recipe(theme: &ColorfulTheme, name: String, recipe: &Recipe) -> Result<Option<ConfigItem>> {
+    eprintln!("{}", recipe.description);
+
+    let Some(vars) = prompt_recipe_params(theme, &recipe.params, None)? else {
+        return Ok(None);
+    };
+
+    let mut each_states = vec![];
+    if let Some(each_state) = &recipe.each_state {
+        let discovered = each_state.discover.as_deref().map(recipe::discover).unwrap_or_default();
+        let initial_text = if discovered.is_empty() { each_state.default.clone().unwrap_or_default() } else { discovered.join(", ") };
+
+        let Some(state_names) = prompt_non_empty_with(theme, &each_state.prompt, &t!(WizardStateNames), &initial_text)? else {
+            return Ok(None);
+        };
+        let mut seen_names: Vec<&str> = vec![];
+        for state_name in state_names.split(',').map(str::trim).filter(|name| ! name.is_empty()) {
+            if seen_names.contains(&state_name) {
+                continue;
+            }
+            seen_names.push(state_name);
+
+            let Some(state_vars) = prompt_recipe_params(theme, &each_state.params, Some(state_name))? else {
+                return Ok(None);
+            };
+            each_states.push((state_name.to_string(), state_vars));
+        }
+    }
+
+    Ok(Some(recipe.generate(name, &vars, &each_states)?))
+}
+
+/// Asks which of the entered states is currently active, instead of assuming the first one, and whether to apply it
+/// now so the item starts consistent with the system. Returns whether to apply it.
+pub fn prompt_current_state(theme: &ColorfulTheme, item: &mut ConfigItem) -> Result<bool> {
+    let state_names: Vec<String> = item.get_state_names().iter().map(|name| name.to_string()).collect();
+    if state_names.len() > 1 {
+        let index = Select::with_theme(theme)
+            .with_prompt(t!(WizardCurrentState))
+            .default(0)
+            .items(&state_names)
+            .interact()?;
+        *item.get_current_state_mut() = state_names[index].clone();
+    }
+
+    // Applying a dir state only prints its path
+    if matches!(item, ConfigItem::DirItem(_)) {
+        return Ok(false);
+    }
+    Ok(Confirm::with_theme(theme)
+        .with_prompt(t!(WizardApplyNow, item.get_current_state().yellow()))
+        .default(false)
+        .interact()?)
+}
+
+/// Prompts for the default state of an item with several states, which `--reset` switches back to.
+pub fn prompt_default_state(theme: &ColorfulTheme, item: &mut ConfigItem) -> Result<()> {
+    let state_names: Vec<String> = item.get_state_names().iter().map(|name| name.to_string()).collect();
+    if state_names.len() < 2 {
+        return Ok(());
+    }
+    let mut choices = state_names.clone();
+    choices.push(t!(WizardNoDefault));
+    let index = Select::with_theme(theme)
+        .with_prompt(t!(WizardDefaultState))
+        .default(0)
+        .items(&choices)
+        .interact()?;
+    item.get_options_mut().default_state = state_names.get(index).cloned();
+    Ok(())
+}
+
+/// Prompts for the value of each recipe param, pre-filled by its discovered or default value.
+fn prompt_recipe_params(
+    theme: &ColorfulTheme,
+    params: &[RecipeParam],
+    state_name: Option<&str>
+) -> Result<Option<BTreeMap<String, String>>> {
+    let mut vars = BTreeMap::new();
+    for param in params {
+        let prompt = match state_name {
+            Some(state_name) => t!(WizardParamOfState, param.prompt, state_name),
+            None => param.prompt.clone()
+        };
+        let Some(value) = prompt_non_empty_with(theme, &prompt, &param.prompt, &param.get_default().unwrap_or_default())? else {
+            return Ok(None);
+        };
+        vars.insert(param.name.clone(), value);
+    }
+    Ok(Some(vars))
+}
+
+/// Reorders the states of the item interactively, moving one picked state at a time up or down.
+/// Returns `false` if the user discards the new order.
+pub fn reorder_states(theme: &ColorfulTheme, item: &mut ConfigItem) -> Result<bool> {
+    let mut picked = 0;
+    loop {
+        let mut entries: Vec<String> = item.get_state_names().iter().map(|name| name.to_string()).collect();
+        let state_count = entries.len();
+        entries.push(t!(WizardSaveOrder).green().to_string());
+
+        let Some(index) = Select::with_theme(theme)
+            .with_prompt(t!(WizardStateToMove))
+            .default(picked)
+            .items(&entries)
+            .interact_opt()?
+        else {
+            return Ok(false);
+        };
+        if index == state_count {
+            return Ok(true);
+        }
+
+        picked = index;
+        let mut action = 0;
+        loop {
+            let order = item.get_state_names()
+                .iter()
+                .enumerate()
+                .map(|(index, name)| if index == picked { format!("[{}]", name) } else { name.to_string() })
+                .collect::<Vec<_>>()
+                .join(", ");
+            action = match Select::with_theme(theme)
+                .with_prompt(t!(WizardMoving, order))
+                .default(action)
+                .items(&[t!(WizardMoveUp), t!(WizardMoveDown), t!(WizardDone)])
+                .interact_opt()?
+            {
+                Some(action) => action,
+                None => break
+            };
+            match action {
+                0 if picked > 0 => {
+                    item.move_state(picked, picked - 1);
+                    picked -= 1;
+                },
+                1 if picked + 1 < state_count => {
+                    item.move_state(picked, picked + 1);
+                    picked += 1;
+                },
+                2 => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn add_command_item(theme: &ColorfulTheme, name: String, shell: Option<CommandShell>) -> Result<Option<ConfigItem>> {
+    let states = prompt_states(
+        theme,
+        |state: &ConfigCommandItemState| &state.name,
+        |state_name| Ok(prompt_command(theme, shell)?.map(|command| ConfigCommandItemState {
+            name: state_name,
+            color: None,
+            available_if: None,
+            max_age: None,
+            value: None,
+            verify_command: None,
+            verify_retries: None,
+            verify_delay_ms: None,
+            command: StateCommand::Plain(command)
+        }))
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::CommandItem(ConfigCommandItem {
+        name,
+        current: states[0].name.clone(),
+        ssh_host: None,
+        ssh_options: vec![],
+        rollback_on_failure: false,
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+fn add_key_value_item(theme: &ColorfulTheme, name: String) -> Result<Option<ConfigItem>> {
+    let Some(file) = prompt_non_empty(theme, &t!(WizardFilePath), &t!(WizardFilePath))? else {
+        return Ok(None);
+    };
+
+    let format_names: Vec<&str> = KeyValueFormat::ALL.iter().map(KeyValueFormat::get_name).collect();
+    let format_index = Select::with_theme(theme)
+        .with_prompt(t!(WizardFileFormat))
+        .default(0)
+        .items(&format_names)
+        .interact()?;
+    let format = KeyValueFormat::ALL[format_index];
+
+    let key_prompt = if format == KeyValueFormat::Env { t!(WizardKey) } else { t!(WizardSectionKey) };
+    let Some(key) = prompt_non_empty(theme, &key_prompt, &t!(WizardKey))? else {
+        return Ok(None);
+    };
+
+    let create_missing = Confirm::with_theme(theme)
+        .with_prompt(t!(WizardCreateMissing))
+        .default(false)
+        .interact()?;
+
+    let states = prompt_states(
+        theme,
+        |state: &ConfigKeyValueItemState| &state.name,
+        |state_name| Ok(prompt_non_empty(theme, &t!(WizardStateValue), &t!(WizardStateValue))?
+            .map(|value| ConfigKeyValueItemState { name: state_name, color: None, available_if: None, max_age: None, value })
+        )
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::KeyValueItem(ConfigKeyValueItem {
+        name,
+        current: states[0].name.clone(),
+        file,
+        format,
+        key,
+        create_missing,
+        mode: None,
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+fn add_block_item(theme: &ColorfulTheme, name: String) -> Result<Option<ConfigItem>> {
+    let Some(file) = prompt_non_empty(theme, &t!(WizardFilePath), &t!(WizardFilePath))? else {
+        return Ok(None);
+    };
+    let Some(marker) = prompt_non_empty(theme, &t!(WizardMarker), &t!(WizardMarkerName))? else {
+        return Ok(None);
+    };
+
+    let states = prompt_states(
+        theme,
+        |state: &ConfigBlockItemState| &state.name,
+        |state_name| prompt_block_content(theme, state_name)
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::BlockItem(ConfigBlockItem {
+        name,
+        current: states[0].name.clone(),
+        file,
+        marker,
+        comment: "#".to_string(),
+        mode: None,
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+/// Prompts for the block content of a state, typed in the editor or read from a snippet file.
+/// Returns `None` if the user discards the item instead.
+fn prompt_block_content(theme: &ColorfulTheme, state_name: String) -> Result<Option<ConfigBlockItemState>> {
+    let source = Select::with_theme(theme)
+        .with_prompt(t!(WizardBlockContent))
+        .default(0)
+        .items(&[
+            t!(WizardTypeInEditor),
+            t!(WizardReadSnippet)
+        ])
+        .interact()?;
+
+    if source == 1 {
+        return Ok(prompt_non_empty(theme, &t!(WizardSnippetPath), &t!(WizardSnippetPath))?
+            .map(|content_file| ConfigBlockItemState { name: state_name, color: None, available_if: None, max_age: None, value: None, content: None, content_file: Some(content_file) })
+        );
+    }
+
+    loop {
+        if let Some(content) = editor::edit_text("", "txt")? {
+            return Ok(Some(ConfigBlockItemState { name: state_name, color: None, available_if: None, max_age: None, value: None, content: Some(content), content_file: None }));
+        }
+        eprintln!("{}", t!(WizardContentNotSaved));
+        if confirm_discard(theme)? {
+            return Ok(None);
+        }
+    }
+}
+
+fn add_git_config_item(theme: &ColorfulTheme, name: String) -> Result<Option<ConfigItem>> {
+    git_config::ensure_git()?;
+
+    let scope = Input::<String>::with_theme(theme)
+        .with_prompt(t!(WizardGitScope))
+        .default(git_config::GLOBAL_SCOPE.to_string())
+        .interact_text()?
+        .trim()
+        .to_string();
+
+    let states = prompt_states(
+        theme,
+        |state: &ConfigGitConfigItemState| &state.name,
+        |state_name| prompt_git_config_values(theme, state_name)
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::GitConfigItem(ConfigGitConfigItem {
+        name,
+        current: states[0].name.clone(),
+        scope,
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+/// Prompts for the identity of a git config state, pre-filled from the current global config.
+/// Returns `None` if the user discards the item instead.
+fn prompt_git_config_values(theme: &ColorfulTheme, state_name: String) -> Result<Option<ConfigGitConfigItemState>> {
+    let mut values = BTreeMap::new();
+
+    for key in ["user.name", "user.email"] {
+        let current_value = git_config::get_value(git_config::GLOBAL_SCOPE, key)?.unwrap_or_default();
+        let Some(value) = prompt_non_empty_with(theme, key, key, &current_value)? else {
+            return Ok(None);
+        };
+        values.insert(key.to_string(), value);
+    }
+
+    let ssh_command = Input::<String>::with_theme(theme)
+        .with_prompt(t!(WizardSshCommand))
+        .allow_empty(true)
+        .interact_text()?
+        .trim()
+        .to_string();
+    if ! ssh_command.is_empty() {
+        values.insert("core.sshCommand".to_string(), ssh_command);
+    }
+
+    Ok(Some(ConfigGitConfigItemState { name: state_name, color: None, available_if: None, max_age: None, value: None, values }))
+}
+
+fn add_dir_item(theme: &ColorfulTheme, name: String) -> Result<Option<ConfigItem>> {
+    let states = prompt_states(
+        theme,
+        |state: &ConfigDirItemState| &state.name,
+        |state_name| {
+            let Some(path) = prompt_non_empty(theme, &t!(WizardDirPath), &t!(WizardDirPath))? else {
+                return Ok(None);
+            };
+            if ! fs_util::expand_home(&path).is_dir() {
+                eprintln!("{} {}", t!(Warning).yellow(), t!(NotADirectory, path));
+            }
+            Ok(Some(ConfigDirItemState { name: state_name, color: None, available_if: None, max_age: None, value: None, path }))
+        }
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::DirItem(ConfigDirItem {
+        name,
+        current: states[0].name.clone(),
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+fn add_template_item(theme: &ColorfulTheme, name: String) -> Result<Option<ConfigItem>> {
+    let Some(template) = prompt_non_empty(theme, &t!(WizardTemplatePath), &t!(WizardTemplatePath))? else {
+        return Ok(None);
+    };
+    let Some(output) = prompt_non_empty(theme, &t!(WizardOutputPath), &t!(WizardOutputPath))? else {
+        return Ok(None);
+    };
+
+    let post_command = Input::<String>::with_theme(theme)
+        .with_prompt(t!(WizardPostCommand))
+        .allow_empty(true)
+        .interact_text()?
+        .trim()
+        .to_string();
+
+    let states = prompt_states(
+        theme,
+        |state: &ConfigTemplateItemState| &state.name,
+        |state_name| Ok(Some(ConfigTemplateItemState { name: state_name, color: None, available_if: None, max_age: None, value: None, vars: prompt_vars(theme)? }))
+    )?;
+    let Some(states) = states else {
+        return Ok(None);
+    };
+
+    Ok(Some(ConfigItem::TemplateItem(ConfigTemplateItem {
+        name,
+        current: states[0].name.clone(),
+        template,
+        output,
+        post_command: (! post_command.is_empty()).then_some(post_command),
+        mode: None,
+        options: ItemOptions::default(),
+        states
+    })))
+}
+
+/// Prompts for `key=value` template vars until an empty input.
+fn prompt_vars(theme: &ColorfulTheme) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    loop {
+        let var = Input::<String>::with_theme(theme)
+            .with_prompt(t!(WizardVar))
+            .allow_empty(true)
+            .validate_with(|var: &String| {
+                if var.trim().is_empty() || var.contains('=') {
+                    Ok(())
+                }
+                else {
+                    Err(t!(WizardVarMissingEquals))
+                }
+            })
+            .interact_text()?;
+
+        let Some((key, value)) = var.split_once('=') else {
+            return Ok(vars);
+        };
+        vars.insert(key.trim().to_string(), value.trim().to_string());
+    }
+}
+
+/// Prompts for states until the user stops adding more, starting with the default state.
+/// `prompt_state` completes a state given its name. Returns `None` if the user discards the item.
+fn prompt_states<S>(
+    theme: &ColorfulTheme,
+    get_name: impl Fn(&S) -> &str,
+    mut prompt_state: impl FnMut(String) -> Result<Option<S>>
+) -> Result<Option<Vec<S>>> {
+    let mut states: Vec<S> = vec![];
+    loop {
+        if states.is_empty() {
+            eprintln!("{}", t!(WizardAddingDefaultState));
+        }
+        else if ! Confirm::with_theme(theme)
+            .with_prompt(t!(WizardAddAnotherState))
+            .interact()?
+        {
+            break;
+        }
+
+        let used_names: Vec<&str> = states.iter().map(&get_name).collect();
+        let Some(state_name) = prompt_state_name(theme, &used_names)? else {
+            return Ok(None);
+        };
+        let Some(state) = prompt_state(state_name)? else {
+            return Ok(None);
+        };
+        states.push(state);
+    }
+
+    Ok(Some(states))
+}
+
+/// Prompts for a state name not in `used_names`, re-prompting on empty input.
+/// Returns `None` if the user discards the item instead.
+fn prompt_state_name(theme: &ColorfulTheme, used_names: &[&str]) -> Result<Option<String>> {
+    loop {
+        let state_name = Input::<String>::with_theme(theme)
+            .with_prompt(t!(WizardStateName))
+            .allow_empty(true)
+            .validate_with(|state_name: &String| {
+                let state_name = state_name.trim();
+                match used_names.iter().find(|used_name| config::is_same_name(used_name, state_name)) {
+                    Some(used_name) => Err(t!(WizardStateNameUsed, used_name)),
+                    None => Ok(())
+                }
+            })
+            .interact_text()?
+            .trim()
+            .to_string();
+
+        if ! state_name.is_empty() {
+            return Ok(Some(state_name));
+        }
+        eprintln!("{}", t!(WizardEmpty, t!(WizardStateName)));
+        if confirm_discard(theme)? {
+            return Ok(None);
+        }
+    }
+}
+
+/// Prompts for a non-empty answer, re-prompting on empty input. Returns `None` if the user discards the item instead.
+fn prompt_non_empty(theme: &ColorfulTheme, prompt: &str, what: &str) -> Result<Option<String>> {
+    prompt_non_empty_with(theme, prompt, what, "")
+}
+
+/// Like `prompt_non_empty`, with the answer pre-filled.
+fn prompt_non_empty_with(theme: &ColorfulTheme, prompt: &str, what: &str, initial_text: &str) -> Result<Option<String>> {
+    loop {
+        let answer = Input::<String>::with_theme(theme)
+            .with_prompt(prompt)
+            .with_initial_text(initial_text)
+            .allow_empty(true)
+            .interact_text()?
+            .trim()
+            .to_string();
+
+        if ! answer.is_empty() {
+            return Ok(Some(answer));
+        }
+        eprintln!("{}", t!(WizardEmpty, what));
+        if confirm_discard(theme)? {
+            return Ok(None);
+        }
+    }
+}
+
+/// Prompts for a state command, warning about programs missing on PATH and offering a test run.
+/// Returns `None` if the user discards the item instead. An empty command is accepted once confirmed.
+fn prompt_command(theme: &ColorfulTheme, shell: Option<CommandShell>) -> Result<Option<String>> {
+    let mut command = String::new();
+    loop {
+        let previous_command = command;
+        command = Input::<String>::with_theme(theme)
+            .with_prompt(t!(WizardStateCommand))
+            .with_initial_text(&previous_command)
+            .allow_empty(true)
+            .interact_text()?
+            .trim()
+            .to_string();
+
+        if command == "!e" {
+            let Some(edited) = editor::edit_text(&previous_command, if cfg!(windows) { "cmd" } else { "sh" })? else {
+                eprintln!("{}", t!(WizardCommandNotSaved));
+                command = previous_command;
+                continue;
+            };
+            command = join_command_lines(&edited);
+            eprintln!("{} {}", "$".purple().bold(), command.purple());
+        }
+
+        if command.is_empty() {
+            if Confirm::with_theme(theme)
+                .with_prompt(t!(WizardNoCommand))
+                .default(false)
+                .interact()?
+            {
+                return Ok(Some(command));
+            }
+            eprintln!("{}", t!(WizardEmpty, t!(WizardStateCommandName)));
+            if confirm_discard(theme)? {
+                return Ok(None);
+            }
+            continue;
+        }
+
+        // Shown with the shell it runs with, since quoting differs between them
+        eprintln!("{} {}", t!(WizardWillRun).dimmed(), ExecutionPlan::new(&command, shell).describe());
+        if let Some(problem) = shell::find_quoting_problem(&command, shell) {
+            eprintln!("{} {}", t!(Warning).yellow(), t!(WizardQuotingProblem, problem));
+        }
+        if let Some(program) = shell::find_missing_program(&command) {
+            eprintln!("{} {}", t!(Warning).yellow(), t!(ProgramNotFound, program));
+        }
+
+        if ! Confirm::with_theme(theme)
+            .with_prompt(t!(WizardTestCommand))
+            .default(false)
+            .interact()?
+        {
+            return Ok(Some(command));
+        }
+
+        eprintln!("{}", t!(Running, "$".purple().bold(), command.purple()));
+        let status = shell::run_command(&command, shell)?;
+        match status.code() {
+            Some(code) => eprintln!("{}", t!(CommandExited, code)),
+            None => eprintln!("{}", t!(CommandKilled))
+        }
+
+        if status.success() || ! Confirm::with_theme(theme)
+            .with_prompt(t!(WizardEditFailedCommand))
+            .default(true)
+            .interact()?
+        {
+            return Ok(Some(command));
+        }
+    }
+}
+
+/// Joins the non-empty lines of a command typed in the editor with ` && `, so they run in order until one fails.
+fn join_command_lines(text: &str) -> String {
+    text
+        .lines()
+        .map(str::trim)
+        .filter(|line| ! line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" && ")
+}
+
+fn confirm_discard(theme: &ColorfulTheme) -> Result<bool> {
+    Ok(Confirm::with_theme(theme)
+        .with_prompt(t!(WizardDiscardItem))
+        .default(false)
+        .interact()?)
+}
@@ -0,0 +1,134 @@
+//! Runs the built switchy against a scratch config dir, so tests never touch the real config, the cache or the
+//! history of whoever runs them.
+
+#![allow(dead_code)]
+
+use std::{env, fs, path::PathBuf, process::{Command, Output}};
+#[cfg(unix)]
+use std::{thread, time::Duration};
+
+use tempfile::TempDir;
+
+/// How long a prompt may take to show before an interactive test fails, in milliseconds.
+#[cfg(unix)]
+const PROMPT_TIMEOUT: u64 = 10_000;
+
+/// A scratch home with its own config dir, removed when dropped.
+pub struct Switchy {
+    home: TempDir
+}
+
+impl Switchy {
+    pub fn new() -> Self {
+        Self {
+            home: TempDir::new().expect("failed to create the scratch home")
+        }
+    }
+
+    /// Starts with the config file holding `config`.
+    pub fn with_config(config: &str) -> Self {
+        let switchy = Self::new();
+        switchy.write_config(config);
+        switchy
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.home.path().join("switchy")
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.config_dir().join("config.toml")
+    }
+
+    pub fn write_config(&self, config: &str) {
+        fs::create_dir_all(self.config_dir()).unwrap();
+        fs::write(self.config_path(), config).unwrap();
+    }
+
+    pub fn read_config(&self) -> String {
+        fs::read_to_string(self.config_path()).unwrap()
+    }
+
+    pub fn read_file(&self, name: &str) -> String {
+        fs::read_to_string(self.config_dir().join(name)).unwrap()
+    }
+
+    /// The command running switchy with args, in English and without colors. Every `SWITCHY_*` var of the test
+    /// process is left out, and the dirs switchy could fall back to are under the scratch home.
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_switchy"));
+        for (name, _) in env::vars_os() {
+            if name.to_string_lossy().starts_with("SWITCHY_") {
+                command.env_remove(name);
+            }
+        }
+        command
+            .args(args)
+            .env("SWITCHY_CONFIG_DIR", self.config_dir())
+            .env("HOME", self.home.path())
+            .env("XDG_CONFIG_HOME", self.home.path().join(".config"))
+            .env("XDG_CACHE_HOME", self.home.path().join(".cache"))
+            .env("XDG_DATA_HOME", self.home.path().join(".local/share"))
+            .env("LC_ALL", "C")
+            .env("NO_COLOR", "1")
+            .env_remove("CLICOLOR_FORCE")
+            .env_remove("LANG")
+            .env_remove("LC_MESSAGES");
+        command
+    }
+
+    /// Runs switchy with args, with stdin closed so nothing is prompted for.
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).stdin(std::process::Stdio::null()).output().unwrap()
+    }
+
+    /// Runs switchy with args, failing the test unless it succeeds, and returns its stdout.
+    pub fn run_ok(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(
+            output.status.success(),
+            "switchy {:?} failed with {}\nstdout:\n{}\nstderr:\n{}",
+            args,
+            output.status,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Runs switchy with args, failing the test if it succeeds, and returns its stderr.
+    pub fn run_err(&self, args: &[&str]) -> String {
+        let output = self.run(args);
+        assert!(! output.status.success(), "switchy {:?} succeeded", args);
+        String::from_utf8(output.stderr).unwrap()
+    }
+
+    /// Runs switchy with args in a pseudo terminal, for answering its prompts. The escape codes of the output are
+    /// stripped.
+    #[cfg(unix)]
+    pub fn spawn(&self, args: &[&str]) -> rexpect::session::PtySession {
+        let options = rexpect::reader::Options { timeout_ms: Some(PROMPT_TIMEOUT), strip_ansi_escape_codes: true };
+        rexpect::session::spawn_with_options(self.command(args), options).expect("failed to spawn switchy")
+    }
+}
+
+/// How long to wait before sending keys, see `send_keys`.
+#[cfg(unix)]
+const KEY_DELAY: Duration = Duration::from_millis(300);
+
+/// Sends keys to the prompt shown last. Keys sent before the prompt switches the terminal to raw mode are lost, so
+/// they're sent after a delay. The output is read as Latin-1, so only ASCII is matched reliably after them.
+#[cfg(unix)]
+pub fn send_keys(session: &mut rexpect::session::PtySession, keys: &str) {
+    thread::sleep(KEY_DELAY);
+    session.send(keys).unwrap();
+    session.flush().unwrap();
+}
+
+/// Keys sent to prompts.
+#[cfg(unix)]
+pub mod keys {
+    pub const ENTER: &str = "\r";
+    pub const ESC: &str = "\x1b";
+    pub const DOWN: &str = "\x1b[B";
+}
@@ -0,0 +1,85 @@
+//! `confirm_run = "always"`, asking before anything runs.
+
+mod common;
+
+use std::process::Command;
+
+use common::Switchy;
+
+/// A git config item setting a key in a repo of its own under the scratch home, with every run confirmed.
+fn git_config_item(switchy: &Switchy) -> String {
+    let repo = switchy.config_dir().join("repo");
+    std::fs::create_dir_all(&repo).unwrap();
+    assert!(Command::new("git").arg("init").arg("-q").arg(&repo).status().unwrap().success());
+    format!(r#"
+[defaults]
+confirm_run = "always"
+
+[[items]]
+type = "GitConfigItem"
+name = "identity"
+current = "home"
+scope = '{}'
+
+[[items.states]]
+name = "home"
+values = {{ "user.email" = "me@home.example" }}
+
+[[items.states]]
+name = "work"
+values = {{ "user.email" = "me@work.example", "user.name" = "Me at Work" }}
+"#, repo.display())
+}
+
+fn get_email(switchy: &Switchy) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(switchy.config_dir().join("repo"))
+        .args(["config", "--local", "--get", "user.email"])
+        .output()
+        .unwrap();
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn yes_skips_confirming_git_config() {
+    let switchy = Switchy::new();
+    switchy.write_config(&git_config_item(&switchy));
+    switchy.run_ok(&["identity", "work", "-y"]);
+    assert_eq!(get_email(&switchy), "me@work.example\n");
+    assert_eq!(switchy.run_ok(&["--current", "identity"]), "work\n");
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::{common::{send_keys, Switchy}, get_email, git_config_item};
+
+    #[test]
+    fn declining_git_config_sets_nothing() {
+        let switchy = Switchy::new();
+        let config = git_config_item(&switchy);
+        switchy.write_config(&config);
+        let mut session = switchy.spawn(&["identity", "work"]);
+        session.exp_string("git -C").unwrap();
+        session.exp_string("user.email me@work.example").unwrap();
+        session.exp_string("user.name 'Me at Work'").unwrap();
+        session.exp_string("Run them?").unwrap();
+        send_keys(&mut session, "n");
+        session.exp_string("Cancelled, nothing is switched").unwrap();
+        session.exp_eof().unwrap();
+        assert_eq!(get_email(&switchy), "");
+        assert_eq!(switchy.read_config(), config);
+    }
+
+    #[test]
+    fn confirming_git_config_sets_the_keys() {
+        let switchy = Switchy::new();
+        switchy.write_config(&git_config_item(&switchy));
+        let mut session = switchy.spawn(&["identity", "work"]);
+        session.exp_string("Run them?").unwrap();
+        send_keys(&mut session, "y");
+        session.exp_eof().unwrap();
+        assert_eq!(get_email(&switchy), "me@work.example\n");
+        assert_eq!(switchy.run_ok(&["--current", "identity"]), "work\n");
+    }
+}
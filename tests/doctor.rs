@@ -0,0 +1,117 @@
+//! `--doctor` reports the problems of the config without failing to read it.
+
+mod common;
+
+use common::Switchy;
+
+/// A command item, and a template item whose problem is filled in for `{}`.
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+default_state = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "TemplateItem"
+name = "theme"
+current = "dark"
+template = "~/theme.tpl"
+output = "~/theme.conf"
+{}
+
+[[items.states]]
+name = "dark"
+vars = { color = "black" }
+
+[[items.states]]
+name = "light"
+vars = { color = "white" }
+"#;
+
+/// Starts with the template item having the problem, which only fails switching it.
+fn with_problem(problem: &str) -> Switchy {
+    let switchy = Switchy::with_config(&CONFIG.replace("{}", problem));
+    let home = switchy.config_dir().parent().unwrap().to_path_buf();
+    std::fs::write(home.join("theme.tpl"), "color = {{color}}\n").unwrap();
+    switchy
+}
+
+/// Runs `--doctor`, returning its report, whether or not a check fails.
+fn run_doctor(switchy: &Switchy) -> String {
+    String::from_utf8(switchy.run(&["--doctor"]).stdout).unwrap()
+}
+
+#[test]
+fn warns_about_identical_commands() {
+    let switchy = Switchy::with_config(r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy"
+
+[[items.states]]
+name = "off"
+command = " echo proxy "
+"#);
+    let report = run_doctor(&switchy);
+    let warning = "! The states of proxy run different commands  States on and off of proxy run the same command";
+    assert!(report.contains(warning), "{}", report);
+}
+
+#[test]
+fn invalid_modes_only_fail_switching_the_item() {
+    let switchy = with_problem(r#"mode = "0999""#);
+    assert!(switchy.run_ok(&["--list"]).contains("theme"));
+    assert_eq!(switchy.run_ok(&["proxy", "on"]), "proxy-on\n");
+
+    let stderr = switchy.run_err(&["theme", "light"]);
+    assert!(stderr.contains("Config item theme has an invalid mode `0999`"), "{}", stderr);
+    assert!(! switchy.config_dir().parent().unwrap().join("theme.conf").exists());
+    assert_eq!(switchy.run_ok(&["--current", "theme"]), "dark\n");
+
+    let report = run_doctor(&switchy);
+    assert!(report.contains("✗ The config item theme is valid  Config item theme has an invalid mode"), "{}", report);
+}
+
+#[test]
+fn missing_default_states_only_fail_resetting_the_item() {
+    let switchy = with_problem(r#"default_state = "sepia""#);
+    assert_eq!(switchy.run_ok(&["proxy", "on"]), "proxy-on\n");
+    switchy.run_ok(&["theme", "light"]);
+    assert!(switchy.read_file("../theme.conf").contains("white"));
+
+    let stderr = switchy.run_err(&["--reset", "theme"]);
+    assert!(stderr.contains("The default state sepia of theme isn't one of its states"), "{}", stderr);
+
+    let stderr = switchy.run_err(&["--reset-all"]);
+    assert!(stderr.contains("The default state sepia of theme isn't one of its states"), "{}", stderr);
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    assert_eq!(switchy.run_ok(&["--current", "theme"]), "light\n");
+
+    let report = run_doctor(&switchy);
+    assert!(report.contains("✗ The config item theme is valid  The default state sepia of theme"), "{}", report);
+}
+
+#[test]
+fn unknown_os_hides_the_item_wherever_it_is() {
+    let switchy = with_problem(r#"only_on = { os = ["linx"] }"#);
+    let listing = switchy.run_ok(&["--list"]);
+    assert!(listing.contains("proxy") && ! listing.contains("theme"), "{}", listing);
+    assert_eq!(switchy.run_ok(&["proxy", "on"]), "proxy-on\n");
+
+    let report = run_doctor(&switchy);
+    assert!(report.contains("✗ The config item theme is valid  Item theme has unknown os `linx` in only_on"), "{}", report);
+}
@@ -0,0 +1,40 @@
+//! `switchy examples`, and switching an item of the same name.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "examples"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo examples-on"
+
+[[items.states]]
+name = "off"
+command = "echo examples-off"
+"#;
+
+#[test]
+fn prints_the_examples() {
+    let switchy = Switchy::new();
+    let examples = switchy.run_ok(&["examples"]);
+    assert!(examples.starts_with("Getting started\n"), "{}", examples);
+    assert!(examples.contains("  $ switchy proxy on\n"), "{}", examples);
+    assert_eq!(switchy.run_ok(&["--examples"]), examples);
+    assert!(switchy.run_ok(&["--help"]).contains("Run `switchy examples` for more."));
+}
+
+#[test]
+fn items_named_examples_are_switched_after_dashes() {
+    let switchy = Switchy::with_config(CONFIG);
+    assert!(switchy.run_ok(&["examples"]).contains("Getting started"));
+    assert_eq!(switchy.run_ok(&["--current", "examples"]), "off\n");
+
+    assert_eq!(switchy.run_ok(&["--", "examples", "on"]), "examples-on\n");
+    assert_eq!(switchy.run_ok(&["--current", "examples"]), "on\n");
+}
@@ -0,0 +1,105 @@
+//! The item filters `--type`, `--tag`, `--in-state` and `--has-state`, of listings and the item picker.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "on"
+tags = ["net"]
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "CommandItem"
+name = "vpn"
+current = "off"
+tags = ["net"]
+
+[[items.states]]
+name = "on"
+command = "echo vpn-on"
+
+[[items.states]]
+name = "off"
+command = "echo vpn-off"
+
+[[items]]
+type = "DirItem"
+name = "project"
+current = "work"
+
+[[items.states]]
+name = "work"
+path = "~/work"
+
+[[items.states]]
+name = "home"
+path = "~/home"
+"#;
+
+/// The names of the items listed with the filter flags.
+fn list_names(switchy: &Switchy, filters: &[&str]) -> Vec<String> {
+    let listing = switchy.run_ok(&[&["--list"], filters].concat());
+    listing
+        .lines()
+        .filter(|line| ! line.starts_with([' ', '*']) && line.ends_with(']'))
+        .map(|line| line.split(' ').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn lists_the_matching_items() {
+    let switchy = Switchy::with_config(CONFIG);
+    assert_eq!(list_names(&switchy, &[]), ["proxy", "vpn", "project"]);
+    assert_eq!(list_names(&switchy, &["--type", "command"]), ["proxy", "vpn"]);
+    assert_eq!(list_names(&switchy, &["--type", "dir-item"]), ["project"]);
+    assert_eq!(list_names(&switchy, &["--tag", "net", "--in-state", "on"]), ["proxy"]);
+    assert_eq!(list_names(&switchy, &["--has-state", "home"]), ["project"]);
+}
+
+#[test]
+fn unmatched_filters_are_echoed() {
+    let switchy = Switchy::with_config(CONFIG);
+    let output = switchy.run(&["--list", "--tag", "net", "--has-state", "home"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No items matched --tag net --has-state home."), "{}", stderr);
+    assert!(output.stdout.is_empty());
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::{common::{keys, send_keys, Switchy}, CONFIG};
+
+    #[test]
+    fn picker_lists_only_matching_items() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&["--type", "dir", "--has-state", "home"]);
+        session.exp_string("project").unwrap();
+        // The other items are filtered out, so moving down stays on project
+        send_keys(&mut session, keys::DOWN);
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("currently: work").unwrap();
+        send_keys(&mut session, keys::ESC);
+        send_keys(&mut session, keys::ESC);
+        session.exp_eof().unwrap();
+        assert_eq!(switchy.read_config(), CONFIG);
+    }
+
+    #[test]
+    fn unmatched_filters_fail_before_prompting() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&["--type", "git-config"]);
+        session.exp_string("No items matched --type git-config.").unwrap();
+        session.exp_eof().unwrap();
+    }
+}
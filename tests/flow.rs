@@ -0,0 +1,219 @@
+//! The main flows end to end: adding, listing, switching and removing items.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "CommandItem"
+name = "theme"
+current = "dark"
+
+[[items.states]]
+name = "dark"
+command = "echo dark"
+
+[[items.states]]
+name = "light"
+command = "echo light"
+"#;
+
+#[test]
+fn switch_then_list() {
+    let switchy = Switchy::new();
+    switchy.run_ok(&["--add", "proxy", "--bool", "--on", "echo proxy-on", "--off", "echo proxy-off"]);
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+
+    let output = switchy.run_ok(&["proxy", "on"]);
+    assert_eq!(output, "proxy-on\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "on\n");
+    let listing = switchy.run_ok(&["--list", "proxy"]);
+    assert!(listing.contains("* on"), "{}", listing);
+    assert!(listing.contains("  off"), "{}", listing);
+
+    // A boolean item is toggled without a state
+    switchy.run_ok(&["proxy"]);
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    assert!(switchy.read_config().contains(r#"current = "off""#));
+}
+
+#[test]
+fn switch_records_history() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["theme", "light"]);
+    let history = switchy.read_file("history.jsonl");
+    assert_eq!(history.lines().count(), 1);
+    assert!(history.contains(r#""item":"theme""#), "{}", history);
+    assert!(history.contains(r#""to":"light""#), "{}", history);
+}
+
+#[test]
+fn unknown_state_fails_without_writing() {
+    let switchy = Switchy::with_config(CONFIG);
+    let stderr = switchy.run_err(&["theme", "sepia"]);
+    assert!(stderr.contains("sepia"), "{}", stderr);
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn remove_moves_item_to_trash() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--remove", "theme"]);
+    let listing = switchy.run_ok(&["--list"]);
+    assert!(! listing.contains("theme"), "{}", listing);
+    assert!(switchy.run_ok(&["--trash"]).contains("theme"));
+
+    switchy.run_ok(&["--restore-item", "theme"]);
+    assert!(switchy.run_ok(&["--list"]).contains("theme"));
+}
+
+#[test]
+fn remove_keeps_the_item_if_the_trash_fails() {
+    let switchy = Switchy::with_config(CONFIG);
+    std::fs::create_dir(switchy.config_dir().join("trash.toml")).unwrap();
+    let stderr = switchy.run_err(&["--remove", "theme"]);
+    assert!(stderr.contains("trash"), "{}", stderr);
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn unwritten_remove_takes_the_item_out_of_the_trash() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--remove", "proxy"]);
+    let config = switchy.read_config();
+
+    // Confirming the write fails without a terminal
+    switchy.run_err(&["--remove", "theme", "--confirm-writes"]);
+    assert_eq!(switchy.read_config(), config);
+    assert_eq!(switchy.run_ok(&["--trash"]).lines().count(), 1);
+    assert!(switchy.run_ok(&["--trash"]).starts_with("proxy [Command]"));
+}
+
+#[test]
+fn purged_items_skip_the_trash() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--remove", "theme", "--purge"]);
+    assert!(! switchy.run_ok(&["--list"]).contains("theme"));
+    assert_eq!(switchy.run_ok(&["--trash"]), "");
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::{common::{keys, send_keys, Switchy}, CONFIG};
+
+    #[test]
+    fn add_in_wizard() {
+        let switchy = Switchy::new();
+        let mut session = switchy.spawn(&["--add", "theme"]);
+        session.exp_string("The type of the item").unwrap();
+        send_keys(&mut session, keys::ENTER);
+        for (index, state) in ["dark", "light"].into_iter().enumerate() {
+            session.exp_string("State name").unwrap();
+            send_keys(&mut session, &format!("{}{}", state, keys::ENTER));
+            session.exp_string("State command").unwrap();
+            send_keys(&mut session, &format!("echo {}{}", state, keys::ENTER));
+            session.exp_string("Test this command now?").unwrap();
+            send_keys(&mut session, "n");
+            session.exp_string("To add another state?").unwrap();
+            send_keys(&mut session, if index == 0 { "y" } else { "n" });
+        }
+        session.exp_string("Which state is the default").unwrap();
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Which state is currently active?").unwrap();
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Apply dark now?").unwrap();
+        send_keys(&mut session, "n");
+        session.exp_eof().unwrap();
+
+        let listing = switchy.run_ok(&["--list", "theme"]);
+        assert!(listing.contains("* dark"), "{}", listing);
+        assert!(listing.contains("  light"), "{}", listing);
+    }
+
+    #[test]
+    fn pick_item_and_state_by_typing() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&[]);
+        session.exp_string("proxy").unwrap();
+        send_keys(&mut session, "the");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("currently: dark").unwrap();
+        send_keys(&mut session, "lig");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Switching theme => light").unwrap();
+        session.exp_eof().unwrap();
+
+        assert_eq!(switchy.run_ok(&["--current", "theme"]), "light\n");
+        assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    }
+
+    #[test]
+    fn esc_in_state_picker_keeps_the_typed_filter() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&[]);
+        session.exp_string("proxy").unwrap();
+        send_keys(&mut session, "the");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("currently: dark").unwrap();
+        send_keys(&mut session, keys::ESC);
+        // Only theme is left by the filter, so moving down keeps it selected
+        send_keys(&mut session, keys::DOWN);
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("currently: dark").unwrap();
+        send_keys(&mut session, "lig");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Switching theme => light").unwrap();
+        session.exp_eof().unwrap();
+        assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    }
+
+    #[test]
+    fn declined_remove_takes_the_item_out_of_the_trash() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&["--remove", "theme", "--confirm-writes"]);
+        session.exp_string("Write the changes?").unwrap();
+        send_keys(&mut session, "n");
+        session.exp_eof().unwrap();
+        assert_eq!(switchy.read_config(), CONFIG);
+        assert_eq!(switchy.run_ok(&["--trash"]), "");
+    }
+
+    #[test]
+    fn pruned_items_go_to_the_trash() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&["--prune"]);
+        session.exp_string("To remove").unwrap();
+        send_keys(&mut session, " ");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Moved proxy to the trash").unwrap();
+        session.exp_eof().unwrap();
+        assert!(! switchy.read_config().contains("proxy"));
+        assert!(switchy.run_ok(&["--trash"]).starts_with("proxy [Command]"));
+        switchy.run_ok(&["--restore-item", "proxy"]);
+        assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    }
+
+    #[test]
+    fn esc_in_item_picker_exits() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&[]);
+        session.exp_string("theme").unwrap();
+        send_keys(&mut session, keys::ESC);
+        session.exp_eof().unwrap();
+        assert_eq!(switchy.read_config(), CONFIG);
+    }
+}
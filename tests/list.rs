@@ -0,0 +1,84 @@
+//! `--list` and the other listings.
+
+mod common;
+
+use common::Switchy;
+
+const MIXED_WIDTH: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "代理"
+current = "开"
+
+[[items.states]]
+name = "开"
+command = "echo on"
+
+[[items.states]]
+name = "off"
+command = "echo off"
+
+[[items.states]]
+name = "🚀🚀"
+command = "echo rocket"
+
+[[items]]
+type = "CommandItem"
+name = "editor"
+current = "work"
+
+[[items.states]]
+name = "work"
+command = "echo work"
+
+[[items.states]]
+name = "开"
+command = "echo kai"
+"#;
+
+#[test]
+fn marks_current_states_in_one_column() {
+    let switchy = Switchy::with_config(MIXED_WIDTH);
+    let listing = switchy.run_ok(&["--list", "代理"]);
+    let lines: Vec<&str> = listing.lines().collect();
+    assert_eq!(lines, ["代理 [Command]", "* 开", "  off", "  🚀🚀"]);
+}
+
+#[test]
+fn aligns_summaries_of_mixed_width_names() {
+    let switchy = Switchy::with_config(MIXED_WIDTH);
+    let output = switchy.run(&["--all", "开", "--dry-run"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("~ 代理   => 开  would switch"), "{}", stderr);
+    assert!(stderr.contains("~ editor => 开  would switch"), "{}", stderr);
+}
+
+#[test]
+fn truncates_long_names() {
+    let name = "超长的配置项名称超长的配置项名称超长的配置项名称";
+    let switchy = Switchy::with_config(&MIXED_WIDTH.replace("代理", name));
+    let listing = switchy.run_ok(&["--list"]);
+    assert!(listing.contains("超长的配置项名称超长的配置项名… [Command]"), "{}", listing);
+    assert!(! listing.contains(name), "{}", listing);
+}
+
+/// Whether `--list` into a pipe is colored with the args and env vars. The `NO_COLOR` set by the harness is removed
+/// first.
+fn is_list_colored(args: &[&str], vars: &[(&str, &str)]) -> bool {
+    let switchy = Switchy::with_config(MIXED_WIDTH);
+    let mut command = switchy.command(&[&["--list"], args].concat());
+    command.env_remove("NO_COLOR").envs(vars.iter().copied());
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap().contains('\x1b')
+}
+
+#[test]
+fn colors_pipes_only_on_request() {
+    assert!(! is_list_colored(&[], &[]));
+    assert!(is_list_colored(&[], &[("CLICOLOR_FORCE", "1")]));
+    assert!(is_list_colored(&["--color", "always"], &[("NO_COLOR", "1")]));
+    assert!(is_list_colored(&[], &[("SWITCHY_COLOR", "always")]));
+    assert!(! is_list_colored(&[], &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]));
+    assert!(! is_list_colored(&["--color", "never"], &[("CLICOLOR_FORCE", "1"), ("SWITCHY_COLOR", "always")]));
+}
@@ -0,0 +1,45 @@
+//! Migrating from other setups with `--import-from`, without running anything.
+
+mod common;
+
+use common::Switchy;
+
+#[test]
+fn toggle_json_adds_command_items() {
+    let switchy = Switchy::new();
+    let path = switchy.config_dir().parent().unwrap().join("toggle.json");
+    std::fs::write(&path, r#"{ "proxy": { "on": "echo proxy-on", "off": "echo proxy-off" }, "version": 1 }"#).unwrap();
+
+    let output = switchy.command(&["--import-from", "toggle-json", &path.to_string_lossy(), "--yes"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    assert_eq!(switchy.run_ok(&["proxy", "on"]), "proxy-on\n");
+}
+
+#[test]
+fn invalid_toggle_json_fails_without_writing() {
+    let switchy = Switchy::with_config("items = []\n");
+    let path = switchy.config_dir().parent().unwrap().join("toggle.json");
+    std::fs::write(&path, "{ proxy: on }").unwrap();
+    let stderr = switchy.run_err(&["--import-from", "toggle-json", &path.to_string_lossy()]);
+    assert!(stderr.contains("is not valid JSON"), "{}", stderr);
+    assert_eq!(switchy.read_config(), "items = []\n");
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::common::{keys, send_keys, Switchy};
+
+    #[test]
+    fn candidates_are_labeled_with_where_they_came_from() {
+        let switchy = Switchy::new();
+        let path = switchy.config_dir().parent().unwrap().join("toggle.json");
+        std::fs::write(&path, r#"{ "proxy": { "on": "echo proxy-on", "off": "echo proxy-off" } }"#).unwrap();
+
+        let mut session = switchy.spawn(&["--import-from", "toggle-json", &path.to_string_lossy()]);
+        session.exp_string("toggle proxy as proxy, with 2 state(s) from").unwrap();
+        send_keys(&mut session, keys::ESC);
+        session.exp_eof().unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! Switching several items at once: `--all` and `--pop`.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "CommandItem"
+name = "editor"
+current = "work"
+
+[[items.states]]
+name = "work"
+command = "echo editor-work"
+
+[[items.states]]
+name = "home"
+command = "echo editor-home"
+"#;
+
+fn stderr_of(switchy: &Switchy, args: &[&str]) -> String {
+    let output = switchy.run(args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stderr).unwrap()
+}
+
+#[test]
+fn all_skips_items_without_the_state() {
+    let switchy = Switchy::with_config(CONFIG);
+    let stderr = stderr_of(&switchy, &["--all", "home"]);
+    assert!(stderr.contains("- proxy  => home  skipped: no such state"), "{}", stderr);
+    assert!(stderr.contains("✓ editor => home  switched"), "{}", stderr);
+    assert_eq!(switchy.run_ok(&["--current", "editor"]), "home\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+}
+
+#[test]
+fn all_summary_is_translated() {
+    let switchy = Switchy::with_config(CONFIG);
+    let output = switchy.command(&["--all", "home", "--dry-run"]).env("LC_ALL", "zh_CN.UTF-8").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("已跳过：没有此状态"), "{}", stderr);
+    assert!(! stderr.contains("no such state"), "{}", stderr);
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn pop_restores_the_pushed_states() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--push", "demo"]);
+    switchy.run_ok(&["editor", "home"]);
+    switchy.run_ok(&["proxy", "on"]);
+    assert!(switchy.run_ok(&["--stack"]).contains("demo"));
+
+    let stderr = stderr_of(&switchy, &["--pop"]);
+    assert!(stderr.contains("✓ editor => work  switched"), "{}", stderr);
+    assert!(stderr.contains("✓ proxy  => off   switched"), "{}", stderr);
+    assert_eq!(switchy.run_ok(&["--current", "editor"]), "work\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+}
+
+#[test]
+fn pop_skips_current_states() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--push"]);
+    switchy.run_ok(&["editor", "home"]);
+
+    let stderr = stderr_of(&switchy, &["--pop", "--no-run"]);
+    assert!(stderr.contains("- proxy  => off   skipped: already current"), "{}", stderr);
+    assert!(stderr.contains("✓ editor => work  recorded"), "{}", stderr);
+    assert_eq!(switchy.run_ok(&["--current", "editor"]), "work\n");
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::{common::{keys, send_keys, Switchy}, CONFIG};
+
+    #[test]
+    fn batch_with_yes_skips_the_confirmation() {
+        let switchy = Switchy::with_config(CONFIG);
+        let mut session = switchy.spawn(&["--batch", "--yes"]);
+        session.exp_string("proxy").unwrap();
+        send_keys(&mut session, " ");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("currently: off").unwrap();
+        send_keys(&mut session, "on");
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("Planned switches").unwrap();
+        session.exp_string("proxy-on").unwrap();
+        session.exp_eof().unwrap();
+        assert_eq!(switchy.run_ok(&["--current", "proxy"]), "on\n");
+    }
+}
@@ -0,0 +1,136 @@
+//! Normalizing new names: trimming them and refusing names differing from existing ones only in ASCII case.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "CommandItem"
+name = "dämon"
+current = "off"
+
+[[items.states]]
+name = "On"
+command = "echo dämon-on"
+
+[[items.states]]
+name = "off"
+command = "echo dämon-off"
+"#;
+
+fn add_bool(switchy: &Switchy, name: &str) -> std::process::Output {
+    switchy.run(&["--add", name, "--bool", "--on", "echo on", "--off", "echo off"])
+}
+
+fn add_bool_renamed(switchy: &Switchy, new_name: &str) -> std::process::Output {
+    switchy.run(&["--add", "proxy", "--rename-to", new_name, "--bool", "--on", "echo on", "--off", "echo off"])
+}
+
+#[test]
+fn added_names_are_trimmed() {
+    let switchy = Switchy::with_config(CONFIG);
+    assert!(add_bool(&switchy, "  vpn\t").status.success());
+    assert!(switchy.read_config().contains(r#"name = "vpn""#));
+    assert_eq!(switchy.run_ok(&["--current", "vpn"]), "off\n");
+}
+
+#[test]
+fn empty_names_are_refused() {
+    let switchy = Switchy::with_config(CONFIG);
+    let output = add_bool(&switchy, "   ");
+    assert!(! output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("The item name is empty"));
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn names_collide_ignoring_ascii_case() {
+    let switchy = Switchy::with_config(CONFIG);
+    for name in ["Proxy", " PROXY "] {
+        let output = add_bool(&switchy, name);
+        assert!(! output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(&format!("Config item {} collides with proxy", name.trim())), "{}", stderr);
+    }
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn other_letters_keep_their_case() {
+    let switchy = Switchy::with_config(CONFIG);
+    assert!(add_bool(&switchy, "DÄMON").status.success());
+    assert_eq!(switchy.run_ok(&["--current", "DÄMON"]), "off\n");
+}
+
+#[test]
+fn case_sensitive_names_allow_both() {
+    let switchy = Switchy::with_config(&format!("[defaults]\ncase_sensitive_names = true\n{}", CONFIG));
+    assert!(add_bool(&switchy, "Proxy").status.success());
+    assert_eq!(switchy.run_ok(&["Proxy", "on"]), "on\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+}
+
+#[test]
+fn new_names_of_conflicts_are_normalized() {
+    let switchy = Switchy::with_config(CONFIG);
+    let stderr = String::from_utf8_lossy(&add_bool_renamed(&switchy, "PROXY").stderr).into_owned();
+    assert!(stderr.contains("Config item PROXY collides with proxy"), "{}", stderr);
+    assert!(! add_bool_renamed(&switchy, "  ").status.success());
+
+    assert!(add_bool_renamed(&switchy, " proxy-2 ").status.success());
+    assert!(switchy.read_config().contains(r#"name = "proxy-2""#));
+}
+
+#[test]
+fn renamed_states_are_normalized() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--rename-state-all", "off", " idle ", "--yes"]);
+    assert_eq!(switchy.run_ok(&["--current", "dämon"]), "idle\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "idle\n");
+
+    // proxy has `on` and dämon has `On`
+    let stderr = switchy.run_err(&["--rename-state-all", "idle", "ON", "--yes"]);
+    assert!(stderr.contains("Skipping proxy, which already has a state ON"), "{}", stderr);
+    assert!(stderr.contains("Skipping dämon, which already has a state ON"), "{}", stderr);
+
+    let stderr = switchy.run_err(&["--rename-state-all", "idle", "  ", "--yes"]);
+    assert!(stderr.contains("empty"), "{}", stderr);
+}
+
+#[cfg(unix)]
+mod interactive {
+    use super::common::{keys, send_keys, Switchy};
+
+    #[test]
+    fn wizard_state_names_collide_ignoring_case() {
+        let switchy = Switchy::new();
+        let mut session = switchy.spawn(&["--add", "theme"]);
+        session.exp_string("The type of the item").unwrap();
+        send_keys(&mut session, keys::ENTER);
+        session.exp_string("State name").unwrap();
+        send_keys(&mut session, &format!(" Dark {}", keys::ENTER));
+        session.exp_string("State command").unwrap();
+        send_keys(&mut session, &format!("echo dark{}", keys::ENTER));
+        session.exp_string("Test this command now?").unwrap();
+        send_keys(&mut session, "n");
+        session.exp_string("To add another state?").unwrap();
+        send_keys(&mut session, "y");
+        session.exp_string("State name").unwrap();
+        send_keys(&mut session, &format!("dark{}", keys::ENTER));
+        session.exp_string("State name 'Dark' is used").unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! Only the data asked for goes to stdout, and progress goes to stderr, so stdout can be consumed by scripts.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+value = "socks5://127.0.0.1:1080"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "DirItem"
+name = "project"
+current = "a"
+
+[[items.states]]
+name = "a"
+path = "~/a"
+
+[[items.states]]
+name = "b"
+path = "~/b"
+"#;
+
+fn run_split(switchy: &Switchy, args: &[&str]) -> (String, String) {
+    let output = switchy.run(args);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+fn parse_json(stdout: &str) -> serde_json::Value {
+    serde_json::from_str(stdout).unwrap_or_else(|err| panic!("{}: {}", err, stdout))
+}
+
+#[test]
+fn switching_prints_only_the_command_output() {
+    let switchy = Switchy::with_config(CONFIG);
+    let (stdout, stderr) = run_split(&switchy, &["proxy", "on"]);
+    assert_eq!(stdout, "proxy-on\n");
+    assert!(stderr.contains("Switching proxy => on"), "{}", stderr);
+    assert!(stderr.contains("Running $ echo proxy-on"), "{}", stderr);
+}
+
+#[test]
+fn queries_print_only_the_data() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["proxy", "on"]);
+    assert_eq!(run_split(&switchy, &["--current", "proxy"]), ("on\n".into(), String::new()));
+    assert_eq!(run_split(&switchy, &["--value", "proxy"]), ("socks5://127.0.0.1:1080\n".into(), String::new()));
+    let home = switchy.config_dir().parent().unwrap().to_path_buf();
+    assert_eq!(run_split(&switchy, &["--print-path", "project"]).0, format!("{}\n", home.join("a").display()));
+}
+
+#[test]
+fn print_path_switches_quietly() {
+    let switchy = Switchy::with_config(CONFIG);
+    let home = switchy.config_dir().parent().unwrap().to_path_buf();
+    std::fs::create_dir(home.join("b")).unwrap();
+    let (stdout, stderr) = run_split(&switchy, &["--print-path", "project", "b"]);
+    assert_eq!(stdout, format!("{}\n", home.join("b").display()));
+    assert_eq!(stderr, "");
+    assert_eq!(switchy.run_ok(&["--current", "project"]), "b\n");
+}
+
+#[test]
+fn export_script_prints_only_the_script() {
+    let switchy = Switchy::with_config(CONFIG);
+    let (stdout, _) = run_split(&switchy, &["--export-script", "--items", "proxy"]);
+    assert!(stdout.starts_with("#!"), "{}", stdout);
+    assert!(stdout.contains("echo proxy-off"), "{}", stdout);
+    assert!(! stdout.contains("Switching") && ! stdout.contains("Running"), "{}", stdout);
+}
+
+#[test]
+fn json_modes_print_only_json() {
+    let switchy = Switchy::with_config(CONFIG);
+    let (stdout, _) = run_split(&switchy, &["--list", "--format", "json"]);
+    assert_eq!(parse_json(&stdout).as_array().unwrap().len(), 2);
+    let (stdout, _) = run_split(&switchy, &["--list", "proxy", "--format", "json"]);
+    assert_eq!(parse_json(&stdout)["name"], "proxy");
+
+    let (stdout, stderr) = run_split(&switchy, &["proxy", "on", "--dry-run", "--format", "json"]);
+    let plan = parse_json(&stdout);
+    assert!(plan.to_string().contains("echo proxy-on"), "{}", plan);
+    assert!(! stderr.is_empty());
+    assert_eq!(switchy.read_config(), CONFIG);
+
+    let output = switchy.run(&["--doctor", "--format", "json"]);
+    assert!(parse_json(&String::from_utf8(output.stdout).unwrap()).is_array());
+}
+
+#[test]
+fn adding_prints_nothing() {
+    let switchy = Switchy::new();
+    let (stdout, stderr) = run_split(&switchy, &["--add", "vpn", "--bool", "--on", "echo on", "--off", "echo off"]);
+    assert_eq!(stdout, "");
+    assert!(! stderr.is_empty());
+}
+
+#[test]
+fn mangen_reports_on_stderr() {
+    let switchy = Switchy::new();
+    let dir = switchy.config_dir().parent().unwrap().join("man");
+    let (stdout, stderr) = run_split(&switchy, &["--mangen", &dir.to_string_lossy()]);
+    assert_eq!(stdout, "");
+    assert!(stderr.contains("Wrote"), "{}", stderr);
+    assert!(dir.join("switchy.1").is_file());
+}
+
+#[test]
+fn errors_are_translated() {
+    let switchy = Switchy::with_config(r#"
+[[items]]
+type = "TemplateItem"
+name = "theme"
+current = "dark"
+template = "~/theme.tpl"
+output = "~/theme.conf"
+
+[[items.states]]
+name = "dark"
+vars = {}
+
+[[items.states]]
+name = "light"
+vars = {}
+"#);
+    let home = switchy.config_dir().parent().unwrap().to_path_buf();
+    std::fs::write(home.join("theme.tpl"), "color = {{color}}\n").unwrap();
+    let output = switchy.command(&["theme", "light"]).env("LC_ALL", "zh_CN.UTF-8").output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(! output.status.success());
+    assert!(stderr.contains("这些占位符没有值：color"), "{}", stderr);
+    assert!(! home.join("theme.conf").exists());
+}
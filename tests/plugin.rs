@@ -0,0 +1,122 @@
+//! The plugin protocol end to end, with the example plugin and a few broken ones.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+use common::Switchy;
+
+/// A scratch home with the JDKs `11` and `17` under `jdks`, and `java` switched by the example plugin pointing
+/// `jdk` at one of them.
+fn with_jdks() -> Switchy {
+    let switchy = Switchy::new();
+    let home = switchy.config_dir().parent().unwrap().to_path_buf();
+    for version in ["11", "17"] {
+        fs::create_dir_all(home.join("jdks").join(version)).unwrap();
+    }
+    switchy.write_config(&format!(
+        r#"
+[[items]]
+type = "ExternalItem"
+name = "java"
+current = "11"
+plugin = "{}"
+settings = {{ dir = "{}", link = "~/jdk" }}
+"#,
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("plugins/symlink.sh").display(),
+        home.join("jdks").display()
+    ));
+    switchy
+}
+
+/// Configures `broken` switched by a plugin script with the body, under `plugins` in the config dir.
+fn with_plugin(body: &str) -> Switchy {
+    let switchy = Switchy::with_config(
+        r#"
+[[items]]
+type = "ExternalItem"
+name = "broken"
+current = "on"
+plugin = "plugins/broken.sh"
+"#
+    );
+    let path = switchy.config_dir().join("plugins/broken.sh");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    switchy
+}
+
+fn get_link(switchy: &Switchy) -> String {
+    let link = switchy.config_dir().parent().unwrap().join("jdk");
+    fs::read_link(link).unwrap().file_name().unwrap().to_string_lossy().into_owned()
+}
+
+#[test]
+fn lists_the_states_of_the_plugin() {
+    let switchy = with_jdks();
+    let listing = switchy.run_ok(&["--list", "java"]);
+    assert!(listing.contains("java [External]"), "{}", listing);
+    assert!(listing.contains("* 11"), "{}", listing);
+    assert!(listing.contains("  17"), "{}", listing);
+
+    let listing = switchy.run_ok(&["--list", "java", "--verbose"]);
+    assert!(listing.contains("jdks/17"), "{}", listing);
+}
+
+#[test]
+fn switches_and_detects_with_the_plugin() {
+    let switchy = with_jdks();
+    switchy.run_ok(&["java", "17"]);
+    assert_eq!(get_link(&switchy), "17");
+    assert_eq!(switchy.run_ok(&["--current", "java"]), "17\n");
+    assert!(switchy.run_ok(&["--detect", "java"]).contains("17"));
+
+    switchy.run_ok(&["java", "11"]);
+    assert_eq!(get_link(&switchy), "11");
+}
+
+#[test]
+fn unknown_states_are_refused_before_the_plugin_switches() {
+    let switchy = with_jdks();
+    let stderr = switchy.run_err(&["java", "21"]);
+    assert!(stderr.contains("21"), "{}", stderr);
+    assert!(! switchy.config_dir().parent().unwrap().join("jdk").exists());
+}
+
+#[test]
+fn errors_of_the_plugin_name_it() {
+    let switchy = with_plugin(r#"printf '{"protocol":1,"error":"no backend"}'; exit 1"#);
+    let stderr = switchy.run_err(&["broken", "on"]);
+    assert!(stderr.contains("Plugin `plugins/broken.sh` failed at `switch`: no backend"), "{}", stderr);
+}
+
+#[test]
+fn failed_plugins_keep_the_recorded_state() {
+    let switchy = with_plugin("exit 3");
+    let output = switchy.run(&["--list", "broken"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Failed to list the states of broken"), "{}", stderr);
+    assert!(stderr.contains("Plugin `plugins/broken.sh` exited with code 3 at `states`"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("* on"));
+}
+
+#[test]
+fn malformed_replies_name_the_plugin() {
+    let switchy = with_plugin(
+        r#"case $1 in states) printf '{"protocol":1,"states":[{"name":"on"}]}' ;; *) echo nope ;; esac"#
+    );
+    let stderr = switchy.run_err(&["broken", "on"]);
+    assert!(stderr.contains("Plugin `plugins/broken.sh` replied to `switch` with invalid JSON"), "{}", stderr);
+}
+
+#[test]
+fn other_protocol_versions_are_refused() {
+    let switchy = with_plugin(r#"printf '{"protocol":2,"states":[{"name":"on"}]}'"#);
+    let output = switchy.run(&["--list", "broken"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let refused = "Plugin `plugins/broken.sh` speaks protocol version 2, but this switchy speaks 1";
+    assert!(stderr.contains(refused), "{}", stderr);
+}
@@ -0,0 +1,89 @@
+//! The check that the program of a command can be found before running it, under each shell.
+
+#![cfg(unix)]
+
+mod common;
+
+use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf};
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "Known-Cmdlet -Port 1080"
+
+[[items.states]]
+name = "off"
+command = "Missing-Cmdlet"
+"#;
+
+/// A bin dir under the scratch home with a fake `pwsh`, which only knows the cmdlet `Known-Cmdlet`.
+fn with_fake_pwsh() -> (Switchy, PathBuf) {
+    let switchy = Switchy::with_config(CONFIG);
+    let bin = switchy.config_dir().parent().unwrap().join("bin");
+    fs::create_dir(&bin).unwrap();
+    let pwsh = bin.join("pwsh");
+    fs::write(
+        &pwsh,
+        r#"#!/bin/sh
+case "$3" in
+    "Get-Command -Name Known-Cmdlet "*) exit 0 ;;
+    Get-Command*) exit 1 ;;
+    *) echo "ran: $3" ;;
+esac
+"#
+    ).unwrap();
+    fs::set_permissions(&pwsh, fs::Permissions::from_mode(0o755)).unwrap();
+    (switchy, bin)
+}
+
+fn run_under(switchy: &Switchy, path: &str, args: &[&str]) -> (bool, String, String) {
+    let output = switchy.command(args).env("PATH", path).env("SWITCHY_SHELL", "pwsh").output().unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap()
+    )
+}
+
+#[test]
+fn cmdlets_known_to_the_shell_pass() {
+    let (switchy, bin) = with_fake_pwsh();
+    let path = format!("{}:/usr/bin:/bin", bin.display());
+    let (success, stdout, stderr) = run_under(&switchy, &path, &["proxy", "on"]);
+    assert!(success, "{}", stderr);
+    assert_eq!(stdout, "ran: Known-Cmdlet -Port 1080\n");
+}
+
+#[test]
+fn programs_unknown_to_the_shell_fail_before_running() {
+    let (switchy, bin) = with_fake_pwsh();
+    switchy.write_config(&CONFIG.replace(r#"current = "off""#, r#"current = "on""#));
+    let config = switchy.read_config();
+    let path = format!("{}:/usr/bin:/bin", bin.display());
+    let (success, stdout, stderr) = run_under(&switchy, &path, &["proxy", "off"]);
+    assert!(! success);
+    assert!(stderr.contains("`Missing-Cmdlet` is not found on PATH"), "{}", stderr);
+    assert_eq!(stdout, "");
+    assert_eq!(switchy.read_config(), config);
+
+    let (success, stdout, stderr) = run_under(&switchy, &path, &["proxy", "off", "--no-preflight"]);
+    assert!(success, "{}", stderr);
+    assert_eq!(stdout, "ran: Missing-Cmdlet\n");
+}
+
+#[test]
+fn a_missing_shell_is_named() {
+    let (switchy, bin) = with_fake_pwsh();
+    fs::remove_file(bin.join("pwsh")).unwrap();
+    let (success, _, stderr) = run_under(&switchy, &bin.display().to_string(), &["proxy", "on"]);
+    assert!(! success);
+    assert!(stderr.contains("`pwsh` is not found on PATH"), "{}", stderr);
+    assert_eq!(switchy.read_config(), CONFIG);
+}
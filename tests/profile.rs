@@ -0,0 +1,78 @@
+//! Profiles, config dirs of their own under `profiles`, picked by `--profile` or `SWITCHY_PROFILE`.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+"#;
+
+/// Starts with the main config and a `work` profile, where proxy is on.
+fn with_work_profile() -> Switchy {
+    let switchy = Switchy::with_config(CONFIG);
+    let dir = switchy.config_dir().join("profiles/work");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("config.toml"), CONFIG.replace(r#"current = "off""#, r#"current = "on""#)).unwrap();
+    switchy
+}
+
+fn run_with_profile(switchy: &Switchy, profile: &str, args: &[&str]) -> (String, String) {
+    let output = switchy.command(args).env("SWITCHY_PROFILE", profile).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn the_profile_has_its_own_config() {
+    let switchy = with_work_profile();
+    assert_eq!(switchy.run_ok(&["--current", "proxy"]), "off\n");
+    assert_eq!(switchy.run_ok(&["--current", "proxy", "--profile", "work"]), "on\n");
+    assert_eq!(run_with_profile(&switchy, "work", &["--current", "proxy"]).0, "on\n");
+
+    run_with_profile(&switchy, "work", &["proxy", "off"]);
+    assert_eq!(switchy.run_ok(&["--current", "proxy", "--profile", "work"]), "off\n");
+    assert!(std::fs::read_to_string(switchy.config_dir().join("profiles/work/history.jsonl")).is_ok());
+    assert_eq!(switchy.read_config(), CONFIG);
+}
+
+#[test]
+fn the_flag_wins_over_the_env_var() {
+    let switchy = with_work_profile();
+    assert_eq!(run_with_profile(&switchy, "work", &["--current", "proxy", "--profile", ""]).0, "off\n");
+    let add = ["--add", "vpn", "--bool", "--on", "echo on", "--off", "echo off", "--profile", "home"];
+    run_with_profile(&switchy, "work", &add);
+    assert!(switchy.read_file("profiles/home/config.toml").contains("vpn"));
+    assert!(! switchy.read_file("profiles/work/config.toml").contains("vpn"));
+}
+
+#[test]
+fn paths_are_not_profiles() {
+    let switchy = with_work_profile();
+    let stderr = switchy.run_err(&["--current", "proxy", "--profile", "../work"]);
+    assert!(stderr.contains("Invalid value `../work` of `--profile`"), "{}", stderr);
+
+    // A bad env var is only warned about, like any other
+    let (stdout, stderr) = run_with_profile(&switchy, "../work", &["--current", "proxy"]);
+    assert_eq!(stdout, "off\n");
+    assert!(stderr.contains("SWITCHY_PROFILE"), "{}", stderr);
+}
+
+#[test]
+fn doctor_reports_the_profile() {
+    let switchy = with_work_profile();
+    let output = switchy.command(&["--doctor"]).env("SWITCHY_PROFILE", "work").output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("SWITCHY_PROFILE=work"), "{}", stdout);
+}
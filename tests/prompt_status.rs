@@ -0,0 +1,89 @@
+//! `--prompt-status`, which must print the status and nothing else, and never fail.
+
+mod common;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "on"
+prompt = true
+label = "px"
+
+[[items.states]]
+name = "on"
+command = "true"
+
+[[items.states]]
+name = "off"
+command = "true"
+
+[[items]]
+type = "CommandItem"
+name = "vpn"
+current = "off"
+prompt = true
+
+[[items.states]]
+name = "on"
+command = "true"
+
+[[items.states]]
+name = "off"
+command = "true"
+
+[[items]]
+type = "CommandItem"
+name = "theme"
+current = "dark"
+
+[[items.states]]
+name = "dark"
+command = "true"
+"#;
+
+/// Runs `--prompt-status`, asserting it succeeds with nothing on stderr, and returns its stdout.
+fn prompt_status(command: &mut std::process::Command) -> String {
+    let output = command.output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn prints_flagged_items() {
+    let switchy = Switchy::with_config(CONFIG);
+    let stdout = prompt_status(&mut switchy.command(&["--prompt-status"]));
+    assert_eq!(stdout, "px:on vpn:off");
+}
+
+#[test]
+fn prints_nothing_without_config() {
+    let switchy = Switchy::new();
+    assert_eq!(prompt_status(&mut switchy.command(&["--prompt-status"])), "");
+}
+
+#[test]
+fn prints_nothing_for_unparsable_config() {
+    let switchy = Switchy::with_config("[[items]\n");
+    assert_eq!(prompt_status(&mut switchy.command(&["--prompt-status"])), "");
+}
+
+#[test]
+fn ignores_bad_env_vars() {
+    let switchy = Switchy::with_config(CONFIG);
+    let mut command = switchy.command(&["--prompt-status"]);
+    command.env("SWITCHY_TYPO", "1").env("SWITCHY_YES", "maybe").env("SWITCHY_LOG", "debug");
+    assert_eq!(prompt_status(&mut command), "px:on vpn:off");
+}
+
+#[test]
+fn ignores_unusable_log_file() {
+    let switchy = Switchy::new();
+    let mut command = switchy.command(&["--prompt-status", "--log-file"]);
+    // The log file would be under a dir which can't be created, since a file is in its way
+    std::fs::write(switchy.config_dir(), "").unwrap();
+    assert_eq!(prompt_status(&mut command), "");
+}
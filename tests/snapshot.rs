@@ -0,0 +1,126 @@
+//! The state snapshots `state.json` and `current.json`, which must agree with the config after every change.
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use common::Switchy;
+
+const CONFIG: &str = r#"
+[[items]]
+type = "CommandItem"
+name = "proxy"
+current = "off"
+default_state = "off"
+
+[[items.states]]
+name = "on"
+command = "echo proxy-on"
+
+[[items.states]]
+name = "off"
+command = "echo proxy-off"
+
+[[items]]
+type = "CommandItem"
+name = "theme"
+current = "dark"
+
+[[items.states]]
+name = "dark"
+command = "echo dark"
+
+[[items.states]]
+name = "light"
+command = "echo light"
+"#;
+
+/// The current state of each item in the config.
+fn get_config_states(switchy: &Switchy) -> BTreeMap<String, String> {
+    let config: toml::Value = toml::from_str(&switchy.read_config()).unwrap();
+    config["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| (item["name"].as_str().unwrap().to_string(), item["current"].as_str().unwrap().to_string()))
+        .collect()
+}
+
+fn read_json(switchy: &Switchy, name: &str) -> serde_json::Value {
+    serde_json::from_str(&switchy.read_file(name)).unwrap_or_else(|err| panic!("{} is invalid: {}", name, err))
+}
+
+/// Fails unless both snapshots hold exactly the current states of the config.
+fn assert_in_sync(switchy: &Switchy, after: &[&str]) {
+    let expected = get_config_states(switchy);
+    let state = read_json(switchy, "state.json");
+    let current = read_json(switchy, "current.json");
+    assert_eq!(state["version"], 1);
+    assert_eq!(current["version"], 1);
+
+    let state_items: BTreeMap<String, String> = state["items"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(name, state)| (name.clone(), state.as_str().unwrap().to_string()))
+        .collect();
+    let current_items: BTreeMap<String, String> = current["items"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(name, current)| (name.clone(), current["state"].as_str().unwrap().to_string()))
+        .collect();
+    assert_eq!(state_items, expected, "state.json after {:?}", after);
+    assert_eq!(current_items, expected, "current.json after {:?}", after);
+}
+
+#[test]
+fn mutating_commands_keep_the_snapshots_in_sync() {
+    let switchy = Switchy::with_config(CONFIG);
+    let commands: &[&[&str]] = &[
+        &["--snapshot"],
+        &["proxy", "on"],
+        &["theme", "light", "--no-run"],
+        &["--add", "vpn", "--bool", "--on", "echo vpn-on", "--off", "echo vpn-off"],
+        &["vpn"],
+        &["--push"],
+        &["--all", "off"],
+        &["--pop"],
+        &["--reset", "proxy"],
+        &["--rename-state-all", "off", "idle", "--yes"],
+        &["--move-state", "theme", "light", "theme", "bright"],
+        &["--copy-state", "theme", "dark", "vpn", "dark"],
+        &["vpn", "dark"],
+        &["--remove", "theme"],
+        &["--restore-item", "theme"],
+        &["--remove", "vpn", "--purge"]
+    ];
+    for args in commands {
+        switchy.run_ok(args);
+        assert_in_sync(&switchy, args);
+    }
+}
+
+#[test]
+fn since_is_kept_until_the_state_changes() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["proxy", "on"]);
+    let since = read_json(&switchy, "current.json")["items"]["proxy"]["since"].clone();
+    assert!(since.is_string(), "{}", since);
+    assert!(read_json(&switchy, "current.json")["items"]["theme"]["since"].is_null());
+
+    switchy.run_ok(&["theme", "light"]);
+    assert_eq!(read_json(&switchy, "current.json")["items"]["proxy"]["since"], since);
+    switchy.run_ok(&["--snapshot"]);
+    assert_eq!(read_json(&switchy, "current.json")["items"]["proxy"]["since"], since);
+}
+
+#[test]
+fn current_resyncs_snapshots_edited_behind_its_back() {
+    let switchy = Switchy::with_config(CONFIG);
+    switchy.run_ok(&["--snapshot"]);
+    switchy.write_config(&CONFIG.replace(r#"current = "dark""#, r#"current = "light""#));
+
+    assert_eq!(switchy.run_ok(&["--current", "theme"]), "light\n");
+    assert_in_sync(&switchy, &["--current", "theme"]);
+}